@@ -14,7 +14,7 @@
 
 #![allow(missing_docs)]
 
-use std::ptr;
+use std::{cmp, mem, ptr};
 use winapi::{FLOAT, INT, UINT, UINT8, DXGI_FORMAT,
              DXGI_FORMAT_R16_UINT, DXGI_FORMAT_R32_UINT,
              D3D11_CLEAR_FLAG, D3D11_PRIMITIVE_TOPOLOGY, D3D11_VIEWPORT, D3D11_RECT,
@@ -23,8 +23,8 @@ use core::{command, pso, shade, state, target, texture as tex};
 use core::{IndexType, VertexCount};
 use core::{MAX_VERTEX_ATTRIBUTES, MAX_CONSTANT_BUFFERS,
            MAX_RESOURCE_VIEWS, MAX_UNORDERED_VIEWS,
-           MAX_SAMPLERS, MAX_COLOR_TARGETS};
-use {native, Resources, InputLayout, Buffer, Texture, Pipeline, Program};
+           MAX_SAMPLERS, MAX_COLOR_TARGETS, MAX_VIEWPORTS};
+use {data, native, Resources, InputLayout, Buffer, Texture, Pipeline, Program};
 
 /// The place of some data in the data buffer.
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -71,11 +71,14 @@ pub enum Command {
     BindPixelTargets([native::Rtv; MAX_COLOR_TARGETS], native::Dsv),
     SetPrimitive(D3D11_PRIMITIVE_TOPOLOGY),
     SetViewport(D3D11_VIEWPORT),
+    SetViewports([D3D11_VIEWPORT; MAX_VIEWPORTS], UINT),
     SetScissor(D3D11_RECT),
     SetRasterizer(*const ID3D11RasterizerState),
     SetDepthStencil(*const ID3D11DepthStencilState, UINT),
     SetBlend(*const ID3D11BlendState, [FLOAT; 4], UINT),
     CopyBuffer(Buffer, Buffer, UINT, UINT, UINT),
+    CopyTexture(tex::TextureCopyRegion<Texture>, tex::TextureCopyRegion<Texture>),
+    ResolveTexture(tex::TextureCopyRegion<Texture>, tex::TextureCopyRegion<Texture>, DXGI_FORMAT),
     // resource updates
     UpdateBuffer(Buffer, DataPointer, usize),
     UpdateTexture(Texture, tex::Kind, Option<tex::CubeFace>, DataPointer, tex::RawImageInfo),
@@ -87,6 +90,9 @@ pub enum Command {
     DrawInstanced(UINT, UINT, UINT, UINT),
     DrawIndexed(UINT, UINT, INT),
     DrawIndexedInstanced(UINT, UINT, UINT, INT, UINT),
+    DrawInstancedIndirect(Buffer, UINT),
+    DrawIndexedInstancedIndirect(Buffer, UINT),
+    Dispatch(UINT, UINT, UINT),
 }
 
 unsafe impl Send for Command {}
@@ -98,6 +104,7 @@ struct Cache {
     stencil_ref: UINT,
     blend: *const ID3D11BlendState,
     blend_ref: [FLOAT; 4],
+    sample_mask: UINT,
 }
 unsafe impl Send for Cache {}
 
@@ -108,6 +115,7 @@ impl Cache {
             rasterizer: ptr::null(),
             depth_stencil: ptr::null(),
             stencil_ref: 0,
+            sample_mask: !0,
             blend: ptr::null(),
             blend_ref: [0.0; 4],
         }
@@ -121,6 +129,7 @@ pub struct CommandBuffer<P> {
 
 pub trait Parser: Sized + Send {
     fn reset(&mut self);
+    fn append(&mut self, &Self);
     fn parse(&mut self, Command);
     fn update_buffer(&mut self, Buffer, &[u8], usize);
     fn update_texture(&mut self, Texture, tex::Kind, Option<tex::CubeFace>, &[u8], tex::RawImageInfo);
@@ -137,9 +146,8 @@ impl<P: Parser> From<P> for CommandBuffer<P> {
 
 impl<P: Parser> CommandBuffer<P> {
     fn flush(&mut self) {
-        let sample_mask = !0; //TODO
         self.parser.parse(Command::SetDepthStencil(self.cache.depth_stencil, self.cache.stencil_ref));
-        self.parser.parse(Command::SetBlend(self.cache.blend, self.cache.blend_ref, sample_mask));
+        self.parser.parse(Command::SetBlend(self.cache.blend, self.cache.blend_ref, self.cache.sample_mask));
     }
 }
 
@@ -149,6 +157,12 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         self.cache = Cache::new();
     }
 
+    fn append(&mut self, other: &CommandBuffer<P>) -> Result<(), command::AppendError> {
+        self.parser.append(&other.parser);
+        self.cache = Cache::new();
+        Ok(())
+    }
+
     fn bind_pipeline_state(&mut self, pso: Pipeline) {
         self.parser.parse(Command::SetPrimitive(pso.topology));
         for (stride, ad_option) in self.cache.attrib_strides.iter_mut().zip(pso.attributes.iter()) {
@@ -166,6 +180,7 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         }
         self.cache.depth_stencil = pso.depth_stencil;
         self.cache.blend = pso.blend;
+        self.cache.sample_mask = pso.sample_mask;
         self.parser.parse(Command::BindInputLayout(pso.layout));
         self.parser.parse(Command::BindProgram(pso.program));
     }
@@ -198,7 +213,13 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
             let mut count = 0;
             for cbuf in cbs.iter() {
                 if cbuf.1.contains(mask) {
-                    buffers[cbuf.2 as usize] = (cbuf.0).0;
+                    // TODO: bind the ranges with `*SetConstantBuffers1` (11.1), until then
+                    // `constant_buffer_range_supported` keeps the rings of constants from being created
+                    if cbuf.3.is_some() {
+                        error!("Constant buffer ranges are not supported, leaving slot {} unbound", cbuf.2);
+                    } else {
+                        buffers[cbuf.2 as usize] = (cbuf.0).0;
+                    }
                     count += 1;
                 }
             }
@@ -287,6 +308,13 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         self.parser.parse(Command::SetViewport(viewport));
     }
 
+    fn bind_stream_output(&mut self, so: Option<pso::StreamOutputParam<Resources>>) {
+        //TODO: needs the geometry shader to be created with the stream output declaration
+        if so.is_some() {
+            error!("Stream output is not supported by the D3D11 backend yet");
+        }
+    }
+
     fn bind_index(&mut self, buf: Buffer, itype: IndexType) {
         let format = match itype {
             IndexType::U16 => DXGI_FORMAT_R16_UINT,
@@ -304,6 +332,36 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         }));
     }
 
+    fn set_viewports(&mut self, viewports: &[command::Viewport]) {
+        if viewports.is_empty() {
+            // the default viewport is set by `bind_pixel_targets`
+            return;
+        }
+        if viewports.len() > MAX_VIEWPORTS {
+            error!("Too many viewports: {}, only the first {} are used", viewports.len(), MAX_VIEWPORTS);
+        }
+        let mut list = [D3D11_VIEWPORT {
+            TopLeftX: 0.0,
+            TopLeftY: 0.0,
+            Width: 0.0,
+            Height: 0.0,
+            MinDepth: 0.0,
+            MaxDepth: 0.0,
+        }; MAX_VIEWPORTS];
+        let count = cmp::min(viewports.len(), MAX_VIEWPORTS);
+        for (out, vp) in list.iter_mut().zip(viewports.iter()) {
+            *out = D3D11_VIEWPORT {
+                TopLeftX: vp.rect.x as FLOAT,
+                TopLeftY: vp.rect.y as FLOAT,
+                Width: vp.rect.w as FLOAT,
+                Height: vp.rect.h as FLOAT,
+                MinDepth: vp.near,
+                MaxDepth: vp.far,
+            };
+        }
+        self.parser.parse(Command::SetViewports(list, count as UINT));
+    }
+
     fn set_ref_values(&mut self, rv: state::RefValues) {
         if rv.stencil.0 != rv.stencil.1 {
             error!("Unable to set different stencil ref values for front ({}) and back ({})",
@@ -341,6 +399,25 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         unimplemented!()
     }
 
+    fn copy_texture(&mut self, src: tex::TextureCopyRegion<Texture>,
+                    dst: tex::TextureCopyRegion<Texture>) {
+        self.parser.parse(Command::CopyTexture(src, dst));
+    }
+
+    fn resolve_texture(&mut self, src: tex::TextureCopyRegion<Texture>,
+                       dst: tex::TextureCopyRegion<Texture>) {
+        match data::map_format(dst.info.format, true) {
+            Some(format) => self.parser.parse(Command::ResolveTexture(src, dst, format)),
+            None => error!("Unable to resolve into format {:?}", dst.info.format),
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn blit_texture(&mut self, src: tex::TextureCopyRegion<Texture>,
+                    dst: tex::TextureCopyRegion<Texture>, filter: tex::FilterMethod) {
+        error!("Texture blits are not supported by the DX11 backend");
+    }
+
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
         self.parser.update_buffer(buf, data, offset);
     }
@@ -395,4 +472,30 @@ impl<P: Parser> command::Buffer<Resources> for CommandBuffer<P> {
         });
     }
 
+    fn call_draw_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        self.flush();
+        // D3D11 has no multi-draw, so each record is a separate call
+        let stride = mem::size_of::<command::DrawIndirectArgs>();
+        for i in 0 .. count {
+            self.parser.parse(Command::DrawInstancedIndirect(buf, (offset + i * stride) as UINT));
+        }
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        self.flush();
+        let stride = mem::size_of::<command::DrawIndexedIndirectArgs>();
+        for i in 0 .. count {
+            self.parser.parse(Command::DrawIndexedInstancedIndirect(buf, (offset + i * stride) as UINT));
+        }
+    }
+
+    fn call_dispatch(&mut self, x: command::ThreadGroupCount, y: command::ThreadGroupCount,
+                     z: command::ThreadGroupCount) {
+        self.parser.parse(Command::Dispatch(x as UINT, y as UINT, z as UINT));
+    }
+
+    // `create_query` refuses every kind, so there are no queries to record
+    fn begin_query(&mut self, _: ()) {}
+    fn end_query(&mut self, _: ()) {}
+    fn write_timestamp(&mut self, _: ()) {}
 }
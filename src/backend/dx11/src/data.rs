@@ -142,6 +142,47 @@ pub fn map_format(format: Format, is_target: bool) -> Option<DXGI_FORMAT> {
             (false, Float) => DXGI_FORMAT_R32_FLOAT,
             _ => return None,
         },
+        BC1_R8_G8_B8_A1 => match format.1 {
+            Unorm => DXGI_FORMAT_BC1_UNORM,
+            Srgb  => DXGI_FORMAT_BC1_UNORM_SRGB,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC2_UNORM,
+            Srgb  => DXGI_FORMAT_BC2_UNORM_SRGB,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC3_UNORM,
+            Srgb  => DXGI_FORMAT_BC3_UNORM_SRGB,
+            _ => return None,
+        },
+        BC4_R8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC4_SNORM,
+            Unorm => DXGI_FORMAT_BC4_UNORM,
+            _ => return None,
+        },
+        BC5_R8_G8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC5_SNORM,
+            Unorm => DXGI_FORMAT_BC5_UNORM,
+            _ => return None,
+        },
+        BC6_R16_G16_B16 => match format.1 {
+            Float => DXGI_FORMAT_BC6H_UF16,
+            _ => return None,
+        },
+        BC6S_R16_G16_B16 => match format.1 {
+            Float => DXGI_FORMAT_BC6H_SF16,
+            _ => return None,
+        },
+        BC7_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC7_UNORM,
+            Srgb  => DXGI_FORMAT_BC7_UNORM_SRGB,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 |
+        ASTC_4x4 | ASTC_5x4 | ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 | ASTC_8x6 | ASTC_8x8 |
+        ASTC_10x5 | ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => return None,
     })
 }
 
@@ -166,6 +207,16 @@ pub fn map_surface(surface: SurfaceType) -> Option<DXGI_FORMAT> {
         D16             => DXGI_FORMAT_R16_TYPELESS,
         D24 | D24_S8    => DXGI_FORMAT_R24G8_TYPELESS,
         D32             => DXGI_FORMAT_R32_TYPELESS,
+        BC1_R8_G8_B8_A1 => DXGI_FORMAT_BC1_TYPELESS,
+        BC2_R8_G8_B8_A8 => DXGI_FORMAT_BC2_TYPELESS,
+        BC3_R8_G8_B8_A8 => DXGI_FORMAT_BC3_TYPELESS,
+        BC4_R8          => DXGI_FORMAT_BC4_TYPELESS,
+        BC5_R8_G8       => DXGI_FORMAT_BC5_TYPELESS,
+        BC6_R16_G16_B16 | BC6S_R16_G16_B16 => DXGI_FORMAT_BC6H_TYPELESS,
+        BC7_R8_G8_B8_A8 => DXGI_FORMAT_BC7_TYPELESS,
+        ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 |
+        ASTC_4x4 | ASTC_5x4 | ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 | ASTC_8x6 | ASTC_8x8 |
+        ASTC_10x5 | ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => return None,
     })
 }
 
@@ -217,7 +268,7 @@ pub fn map_usage(usage: Usage, bind: Bind) -> (D3D11_USAGE, D3D11_CPU_ACCESS_FLA
         } else {
             (D3D11_USAGE_IMMUTABLE, D3D11_CPU_ACCESS_FLAG(0))
         },
-        Usage::Dynamic => (D3D11_USAGE_DYNAMIC, D3D11_CPU_ACCESS_WRITE),
+        Usage::Dynamic | Usage::Persistent => (D3D11_USAGE_DYNAMIC, D3D11_CPU_ACCESS_WRITE),
         Usage::Upload => (D3D11_USAGE_STAGING, D3D11_CPU_ACCESS_WRITE),
         Usage::Download => (D3D11_USAGE_STAGING, D3D11_CPU_ACCESS_READ),
     }
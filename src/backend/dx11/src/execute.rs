@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use winapi::{self, UINT};
 use core::{self, texture as tex};
 use command;
@@ -59,7 +59,7 @@ pub fn update_buffer(context: *mut winapi::ID3D11DeviceContext, buffer: &Buffer,
 
 pub fn update_texture(context: *mut winapi::ID3D11DeviceContext, texture: &Texture, kind: tex::Kind,
                       face: Option<tex::CubeFace>, data: &[u8], image: &tex::RawImageInfo) {
-    let subres = texture_subres(face, image);
+    let subres = texture_subres(texture, kind, face, image.mipmap, 0);
     let dst_resource = texture.as_resource();
     let (width, height, _, _) = kind.get_level_dimensions(image.mipmap);
     let stride = image.format.0.get_total_bits() as usize;
@@ -84,20 +84,94 @@ pub fn update_texture(context: *mut winapi::ID3D11DeviceContext, texture: &Textu
     }
 }
 
-fn texture_subres(face: Option<tex::CubeFace>, image: &tex::RawImageInfo) -> winapi::UINT {
-    use core::texture::CubeFace::*;
+pub fn copy_texture(context: *mut winapi::ID3D11DeviceContext,
+                    src: &tex::TextureCopyRegion<Texture>,
+                    dst: &tex::TextureCopyRegion<Texture>) {
+    let (si, di) = (src.info, dst.info);
+    // the layers of arrays are separate subresources, copied one by one,
+    // while the depth of volume textures is part of the box
+    let (layers, depth) = match src.kind {
+        tex::Kind::D3(..) => (1, cmp::max(1, si.depth)),
+        _ => (cmp::max(1, si.depth), 1),
+    };
+    for layer in 0 .. layers {
+        let (src_z, dst_z) = match src.kind {
+            tex::Kind::D3(..) => (si.zoffset, di.zoffset),
+            _ => (0, 0),
+        };
+        let src_box = winapi::D3D11_BOX {
+            left: si.xoffset as UINT,
+            right: (si.xoffset + si.width) as UINT,
+            top: si.yoffset as UINT,
+            bottom: (si.yoffset + cmp::max(1, si.height)) as UINT,
+            front: src_z as UINT,
+            back: (src_z + depth) as UINT,
+        };
+        let src_subres = texture_subres(&src.texture, src.kind, src.cube_face, si.mipmap, si.zoffset + layer);
+        let dst_subres = texture_subres(&dst.texture, dst.kind, dst.cube_face, di.mipmap, di.zoffset + layer);
+        unsafe {
+            (*context).CopySubresourceRegion(dst.texture.as_resource(), dst_subres,
+                                             di.xoffset as UINT, di.yoffset as UINT, dst_z as UINT,
+                                             src.texture.as_resource(), src_subres,
+                                             &src_box)
+        };
+    }
+}
+
+pub fn resolve_texture(context: *mut winapi::ID3D11DeviceContext,
+                       src: &tex::TextureCopyRegion<Texture>,
+                       dst: &tex::TextureCopyRegion<Texture>,
+                       format: winapi::DXGI_FORMAT) {
+    for layer in 0 .. cmp::max(1, src.info.depth) {
+        let src_subres = texture_subres(&src.texture, src.kind, src.cube_face, src.info.mipmap,
+                                        src.info.zoffset + layer);
+        let dst_subres = texture_subres(&dst.texture, dst.kind, dst.cube_face, dst.info.mipmap,
+                                        dst.info.zoffset + layer);
+        unsafe {
+            (*context).ResolveSubresource(dst.texture.as_resource(), dst_subres,
+                                          src.texture.as_resource(), src_subres, format);
+        }
+    }
+}
+
+/// Get the number of mipmap levels the texture was created with.
+fn texture_levels(texture: &Texture) -> UINT {
+    use native::Texture::*;
+    unsafe {
+        match texture.0 {
+            D1(t) => {
+                let mut desc = mem::zeroed();
+                (*t).GetDesc(&mut desc);
+                desc.MipLevels
+            },
+            D2(t) => {
+                let mut desc = mem::zeroed();
+                (*t).GetDesc(&mut desc);
+                desc.MipLevels
+            },
+            D3(t) => {
+                let mut desc = mem::zeroed();
+                (*t).GetDesc(&mut desc);
+                desc.MipLevels
+            },
+        }
+    }
+}
 
-    let array_slice = match face {
-        Some(PosX) => 0,
-        Some(NegX) => 1,
-        Some(PosY) => 2,
-        Some(NegY) => 3,
-        Some(PosZ) => 4,
-        Some(NegZ) => 5,
-        None => 0,
+/// Get the subresource index of a level of a layer, as `D3D11CalcSubresource` does.
+/// The faces of a cube map are counted as layers.
+fn texture_subres(texture: &Texture, kind: tex::Kind, face: Option<tex::CubeFace>,
+                  level: tex::Level, layer: tex::Size) -> UINT {
+    let face_index = face.map_or(0, |face| {
+        tex::CUBE_FACES.iter().position(|&f| f == face).unwrap()
+    }) as UINT;
+    let array_slice = match kind {
+        tex::Kind::D3(..) => 0,
+        tex::Kind::Cube(_) => face_index,
+        tex::Kind::CubeArray(..) => layer as UINT * 6 + face_index,
+        _ => layer as UINT,
     };
-    let num_mipmap_levels = 1; //TODO
-    array_slice * num_mipmap_levels + (image.mipmap as UINT)
+    level as UINT + array_slice * texture_levels(texture)
 }
 
 pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command, data_buf: &command::DataBuffer) {
@@ -116,6 +190,7 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             (*ctx).DSSetShader(prog.ds, ptr::null_mut(), 0);
             (*ctx).GSSetShader(prog.gs, ptr::null_mut(), 0);
             (*ctx).PSSetShader(prog.ps, ptr::null_mut(), 0);
+            (*ctx).CSSetShader(prog.cs, ptr::null_mut(), 0);
         },
         BindInputLayout(layout) => unsafe {
             (*ctx).IASetInputLayout(layout);
@@ -143,6 +218,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetConstantBuffers(0, max_cb, &buffers[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetConstantBuffers(0, max_cb, &buffers[0].0);
+            },
         },
         BindShaderResources(stage, ref views) => match stage {
             Stage::Vertex => unsafe {
@@ -160,6 +238,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetShaderResources(0, max_srv, &views[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetShaderResources(0, max_srv, &views[0].0);
+            },
         },
         BindSamplers(stage, ref samplers) => match stage {
             Stage::Vertex => unsafe {
@@ -177,6 +258,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
             Stage::Pixel => unsafe {
                 (*ctx).PSSetSamplers(0, max_sm, &samplers[0].0);
             },
+            Stage::Compute => unsafe {
+                (*ctx).CSSetSamplers(0, max_sm, &samplers[0].0);
+            },
         },
         BindPixelTargets(ref colors, ds) => unsafe {
             (*ctx).OMSetRenderTargets(core::MAX_COLOR_TARGETS as UINT,
@@ -188,6 +272,9 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
         SetViewport(ref viewport) => unsafe {
             (*ctx).RSSetViewports(1, viewport);
         },
+        SetViewports(ref viewports, count) => unsafe {
+            (*ctx).RSSetViewports(count, viewports.as_ptr());
+        },
         SetScissor(ref rect) => unsafe {
             (*ctx).RSSetScissorRects(1, rect);
         },
@@ -203,6 +290,12 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
         CopyBuffer(ref src, ref dst, src_offset, dst_offset, size) => {
             copy_buffer(ctx, src, dst, src_offset, dst_offset, size);
         },
+        CopyTexture(ref src, ref dst) => {
+            copy_texture(ctx, src, dst);
+        },
+        ResolveTexture(ref src, ref dst, format) => {
+            resolve_texture(ctx, src, dst, format);
+        },
         UpdateBuffer(ref buffer, pointer, offset) => {
             let data = data_buf.get(pointer);
             update_buffer(ctx, buffer, data, offset);
@@ -232,5 +325,14 @@ pub fn process(ctx: *mut winapi::ID3D11DeviceContext, command: &command::Command
         DrawIndexedInstanced(nind, ninst, sind, base, sinst) => unsafe {
             (*ctx).DrawIndexedInstanced(nind, ninst, sind, base, sinst);
         },
+        DrawInstancedIndirect(ref buf, offset) => unsafe {
+            (*ctx).DrawInstancedIndirect((buf.0).0, offset);
+        },
+        DrawIndexedInstancedIndirect(ref buf, offset) => unsafe {
+            (*ctx).DrawIndexedInstancedIndirect((buf.0).0, offset);
+        },
+        Dispatch(x, y, z) => unsafe {
+            (*ctx).Dispatch(x, y, z);
+        },
     }
 }
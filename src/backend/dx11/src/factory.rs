@@ -14,10 +14,11 @@
 
 use std::{cmp, ptr, slice};
 use std::collections::BTreeMap as Map;
+use std::ops::Range;
 use std::os::raw::c_void;
 use std::sync::Arc;
 use winapi;
-use core::{self, factory as f, buffer, texture, mapping};
+use core::{self, factory as f, buffer, texture, mapping, query};
 use core::memory::{self, Bind, Typed};
 use core::handle::{self as h, Producer};
 use {Resources as R, Share, Buffer, Texture, Pipeline, Program, Shader};
@@ -126,6 +127,11 @@ impl Factory {
         use winapi::d3d11::*;
         use data::{map_bind, map_usage};
 
+        if info.usage == memory::Usage::Persistent {
+            error!("Persistent buffer mapping is not supported");
+            return Err(buffer::CreationError::UnsupportedUsage(info.usage));
+        }
+
         let (subind, size) = match info.role {
             buffer::Role::Vertex   =>
                 (D3D11_BIND_VERTEX_BUFFER, info.size),
@@ -138,9 +144,13 @@ impl Factory {
             },
             buffer::Role::Constant  => // 16 bit alignment
                 (D3D11_BIND_CONSTANT_BUFFER, (info.size + 0xF) & !0xF),
-            buffer::Role::Staging =>
+            buffer::Role::Staging | buffer::Role::Indirect =>
                 (D3D11_BIND_FLAG(0), info.size)
         };
+        let misc = match info.role {
+            buffer::Role::Indirect => D3D11_RESOURCE_MISC_DRAWINDIRECT_ARGS,
+            _ => D3D11_RESOURCE_MISC_FLAG(0),
+        };
 
         assert!(size >= info.size);        
         let (usage, cpu) = map_usage(info.usage, info.bind);
@@ -153,7 +163,7 @@ impl Factory {
             Usage: usage,
             BindFlags: bind.0,
             CPUAccessFlags: cpu.0,
-            MiscFlags: 0,
+            MiscFlags: misc.0,
             StructureByteStride: 0, //TODO
         };
         let mut sub = D3D11_SUBRESOURCE_DATA {
@@ -181,6 +191,7 @@ impl Factory {
             let mapping = match info.usage {
                 Data | Dynamic => None,
                 Upload | Download => Some(MappingGate { pointer: ptr::null_mut() }),
+                Persistent => unreachable!(),
             };
 
             Ok(self.share.handles.borrow_mut().make_buffer(buf, info, mapping))
@@ -337,6 +348,65 @@ impl core::Factory<R> for Factory {
         &self.share.capabilities
     }
 
+    fn set_debug_name_raw(&mut self, handle: h::Ref<R>, name: &str) {
+        //TODO: SetPrivateData with WKPDID_D3DDebugObjectName
+        self.share.handles.borrow_mut().set_label(handle, name);
+    }
+
+    fn get_statistics(&self) -> h::Statistics {
+        self.share.handles.borrow().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<h::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: core::format::Format) -> core::format::Properties {
+        use core::format as fm;
+        use data::map_format;
+        let query = |dxgi: Option<winapi::DXGI_FORMAT>| match dxgi {
+            Some(dxgi) => {
+                let mut support = 0;
+                let hr = unsafe { (*self.device).CheckFormatSupport(dxgi, &mut support) };
+                if winapi::SUCCEEDED(hr) { support } else { 0 }
+            },
+            None => 0,
+        };
+        let target_format = map_format(format, true);
+        let view = query(map_format(format, false));
+        let target = query(target_format);
+        let mut support = fm::Support::empty();
+        if view & winapi::D3D11_FORMAT_SUPPORT_SHADER_SAMPLE.0 != 0 {
+            support |= fm::SAMPLE;
+        }
+        if target & (winapi::D3D11_FORMAT_SUPPORT_RENDER_TARGET.0 | winapi::D3D11_FORMAT_SUPPORT_DEPTH_STENCIL.0) != 0 {
+            support |= fm::RENDER;
+        }
+        if target & winapi::D3D11_FORMAT_SUPPORT_BLENDABLE.0 != 0 {
+            support |= fm::BLEND;
+        }
+        if self.share.capabilities.unordered_access_view_supported &&
+           view & winapi::D3D11_FORMAT_SUPPORT_TYPED_UNORDERED_ACCESS_VIEW.0 != 0 {
+            support |= fm::UNORDERED;
+        }
+        let max_samples = match target_format {
+            Some(dxgi) if support.contains(fm::RENDER) => {
+                [32u8, 16, 8, 4, 2].iter().cloned().find(|&count| {
+                    let mut levels = 0;
+                    let hr = unsafe {
+                        (*self.device).CheckMultisampleQualityLevels(dxgi, count as winapi::UINT, &mut levels)
+                    };
+                    winapi::SUCCEEDED(hr) && levels != 0
+                }).unwrap_or(1)
+            },
+            _ => 0,
+        };
+        fm::Properties {
+            support: support,
+            max_samples: max_samples,
+        }
+    }
+
     fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<h::RawBuffer<R>, buffer::CreationError> {
         self.create_buffer_internal(info, None)
     }
@@ -397,7 +467,13 @@ impl core::Factory<R> for Factory {
                 };
                 (hr, ret as *mut ID3D11DeviceChild)
             },
-            //_ => return Err(CreateShaderError::StageNotSupported(stage))
+            Stage::Compute => {
+                let mut ret = ptr::null_mut();
+                let hr = unsafe {
+                    (*dev).CreateComputeShader(code.as_ptr() as *const c_void, len, ptr::null_mut(), &mut ret)
+                };
+                (hr, ret as *mut ID3D11DeviceChild)
+            },
         };
 
         if winapi::SUCCEEDED(hr) {
@@ -425,7 +501,8 @@ impl core::Factory<R> for Factory {
 
     fn create_program(&mut self, shader_set: &core::ShaderSet<R>)
                       -> Result<h::Program<R>, core::shade::CreateProgramError> {
-        use winapi::{ID3D11VertexShader, ID3D11HullShader, ID3D11DomainShader, ID3D11GeometryShader, ID3D11PixelShader};
+        use winapi::{ID3D11VertexShader, ID3D11HullShader, ID3D11DomainShader, ID3D11GeometryShader, ID3D11PixelShader,
+                     ID3D11ComputeShader};
         use core::shade::{ProgramInfo, Stage};
         use mirror::populate_info;
 
@@ -437,6 +514,7 @@ impl core::Factory<R> for Factory {
             unordereds: Vec::new(),
             samplers: Vec::new(),
             outputs: Vec::new(),
+            stream_outputs: Vec::new(),
             output_depth: false,
             knows_outputs: true,
         };
@@ -453,6 +531,7 @@ impl core::Factory<R> for Factory {
                     ds: ptr::null_mut(),
                     gs: ptr::null_mut(),
                     ps: ps.object as *mut ID3D11PixelShader,
+                    cs: ptr::null_mut(),
                     vs_hash: vs.code_hash,
                 }
             },
@@ -468,6 +547,7 @@ impl core::Factory<R> for Factory {
                     ds: ptr::null_mut(),
                     gs: gs.object as *mut ID3D11GeometryShader,
                     ps: ps.object as *mut ID3D11PixelShader,
+                    cs: ptr::null_mut(),
                     vs_hash: vs.code_hash,
                 }
             },
@@ -485,9 +565,27 @@ impl core::Factory<R> for Factory {
                     ds: ds.object as *mut ID3D11DomainShader,
                     gs: ptr::null_mut(),
                     ps: ps.object as *mut ID3D11PixelShader,
+                    cs: ptr::null_mut(),
                     vs_hash: vs.code_hash,
                 }
-            }
+            },
+            &core::ShaderSet::Compute(ref cs) => {
+                let cs = cs.reference(fh);
+                populate_info(&mut info, Stage::Compute, cs.reflection);
+                unsafe { (*cs.object).AddRef(); }
+                Program {
+                    vs: ptr::null_mut(),
+                    hs: ptr::null_mut(),
+                    ds: ptr::null_mut(),
+                    gs: ptr::null_mut(),
+                    ps: ptr::null_mut(),
+                    cs: cs.object as *mut ID3D11ComputeShader,
+                    vs_hash: 0,
+                }
+            },
+            &core::ShaderSet::TransformFeedback(..) => {
+                return Err("Stream output programs are not supported by this backend".into())
+            },
         };
         Ok(self.share.handles.borrow_mut().make_program(prog, info))
     }
@@ -542,25 +640,27 @@ impl core::Factory<R> for Factory {
         }
 
         let prog = *self.frame_handles.ref_program(program);
-        let vs_bin = match self.vs_cache.get(&prog.vs_hash) {
-            Some(ref code) => &code[..],
-            None => {
-                error!("VS hash {} is not found in the factory cache", prog.vs_hash);
-                return Err(core::pso::CreationError);
-            }
-        };
-
         let dev = self.device;
         let mut vertex_layout = ptr::null_mut();
-        let hr = unsafe {
-            (*dev).CreateInputLayout(
-                layouts.as_ptr(), layouts.len() as winapi::UINT,
-                vs_bin.as_ptr() as *const c_void, vs_bin.len() as winapi::SIZE_T,
-                &mut vertex_layout)
-        };
-        if !winapi::SUCCEEDED(hr) {
-            error!("Failed to create input layout from {:#?}, error {:x}", layouts, hr);
-            return Err(core::pso::CreationError);
+        // compute programs have no vertex input
+        if prog.cs == ptr::null_mut() {
+            let vs_bin = match self.vs_cache.get(&prog.vs_hash) {
+                Some(ref code) => &code[..],
+                None => {
+                    error!("VS hash {} is not found in the factory cache", prog.vs_hash);
+                    return Err(core::pso::CreationError);
+                }
+            };
+            let hr = unsafe {
+                (*dev).CreateInputLayout(
+                    layouts.as_ptr(), layouts.len() as winapi::UINT,
+                    vs_bin.as_ptr() as *const c_void, vs_bin.len() as winapi::SIZE_T,
+                    &mut vertex_layout)
+            };
+            if !winapi::SUCCEEDED(hr) {
+                error!("Failed to create input layout from {:#?}, error {:x}", layouts, hr);
+                return Err(core::pso::CreationError);
+            }
         }
         let dummy_dsi = core::pso::DepthStencilInfo { depth: None, front: None, back: None };
         //TODO: cache rasterizer, depth-stencil, and blend states
@@ -588,12 +688,14 @@ impl core::Factory<R> for Factory {
             vertex_buffers: desc.vertex_buffers,
             attributes: desc.attributes,
             program: prog,
-            rasterizer: state::make_rasterizer(dev, &desc.rasterizer, desc.scissor),
+            rasterizer: state::make_rasterizer(dev, &desc.rasterizer, desc.depth_bias,
+                                               desc.depth_clamp, desc.scissor),
             depth_stencil: state::make_depth_stencil(dev, match desc.depth_stencil {
                 Some((_, ref dsi)) => dsi,
                 None => &dummy_dsi,
             }),
-            blend: state::make_blend(dev, &desc.color_targets),
+            blend: state::make_blend(dev, &desc.color_targets, desc.multisample.alpha_to_coverage),
+            sample_mask: desc.multisample.sample_mask,
         };
         Ok(self.share.handles.borrow_mut().make_pso(pso, program))
     }
@@ -733,7 +835,7 @@ impl core::Factory<R> for Factory {
         Ok(self.share.handles.borrow_mut().make_texture_srv(native::Srv(raw_view), htex))
     }
 
-    fn view_texture_as_unordered_access_raw(&mut self, _htex: &h::RawTexture<R>)
+    fn view_texture_as_unordered_access_raw(&mut self, _htex: &h::RawTexture<R>, _desc: texture::UnorderedDesc)
                                         -> Result<h::RawUnorderedAccessView<R>, f::ResourceViewError> {
         Err(f::ResourceViewError::Unsupported) //TODO
     }
@@ -900,30 +1002,54 @@ impl core::Factory<R> for Factory {
         }
     }
 
-    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>)
-                               -> Result<mapping::Reader<'b, R, T>,
-                                         mapping::Error>
+    fn create_query(&mut self, kind: query::Kind) -> Result<h::Query<R>, query::CreationError> {
+        Err(query::CreationError::Unsupported(kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
         where T: Copy
     {
         unsafe {
-            mapping::read(buf.raw(), |mut m| {
+            mapping::read(buf.raw(), range, |mut m, _| {
                 ensure_mapped(&mut m, buf.raw(), winapi::d3d11::D3D11_MAP_READ, self)
             })
         }
     }
 
-    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>)
-                               -> Result<mapping::Writer<'b, R, T>,
-                                         mapping::Error>
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
         where T: Copy
     {
         unsafe {
-            mapping::write(buf.raw(), |mut m| {
+            mapping::write(buf.raw(), range, |mut m, _| {
                 // not MAP_WRITE_DISCARD because we are STAGING
                 ensure_mapped(&mut m, buf.raw(), winapi::d3d11::D3D11_MAP_WRITE, self)
             })
         }
     }
+
+    fn flush_mapping<T>(&mut self, buf: &h::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        // staging buffers get flushed when unmapped before the submission
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::WRITE, |_, _| ())
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &h::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        // mapping a staging buffer for reading waits for the GPU already
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::READ, |_, _| ())
+        }
+    }
 }
 
 pub fn ensure_mapped(mapping: &mut MappingGate,
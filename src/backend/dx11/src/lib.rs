@@ -120,6 +120,7 @@ pub struct Program {
     ds: *mut winapi::ID3D11DomainShader,
     gs: *mut winapi::ID3D11GeometryShader,
     ps: *mut winapi::ID3D11PixelShader,
+    cs: *mut winapi::ID3D11ComputeShader,
     vs_hash: u64,
 }
 unsafe impl Send for Program {}
@@ -137,6 +138,7 @@ pub struct Pipeline {
     rasterizer: *const winapi::ID3D11RasterizerState,
     depth_stencil: *const winapi::ID3D11DepthStencilState,
     blend: *const winapi::ID3D11BlendState,
+    sample_mask: winapi::UINT,
 }
 unsafe impl Send for Pipeline {}
 unsafe impl Sync for Pipeline {}
@@ -156,6 +158,7 @@ impl core::Resources for Resources {
     type UnorderedAccessView = ();
     type Sampler             = native::Sampler;
     type Fence               = Fence;
+    type Query               = ();
     type Mapping             = factory::MappingGate;
 }
 
@@ -197,9 +200,36 @@ pub fn create(driver_type: winapi::D3D_DRIVER_TYPE, desc: &winapi::DXGI_SWAP_CHA
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: true,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            compute_supported: true,
+            draw_indirect_supported: true,
+            persistent_mapping_supported: false,
+            alpha_to_coverage_supported: true,
+            sample_mask_supported: true,
+            depth_clamp_supported: true,
+            depth_bias_clamp_supported: true,
+            limits: core::Limits {
+                // feature level 11.0 requirements, UAVs are not exposed yet
+                max_texture_3d_size: 2048,
+                max_texture_cube_size: 16384,
+                max_texture_array_layers: 2048,
+                max_anisotropy: 16,
+                max_samples: 4,
+                max_color_targets: 8,
+                max_vertex_attributes: 32,
+                max_viewports: 16,
+                max_constant_buffers: 14,
+                max_constant_buffer_size: 4096 * 16,
+                constant_buffer_offset_alignment: 256,
+                max_resource_views: 128,
+                max_compute_group_count: [65535; 3],
+                max_compute_group_size: [1024, 1024, 64],
+                max_compute_invocations: 1024,
+                .. core::Limits::default()
+            },
         },
         handles: RefCell::new(h::Manager::new()),
     };
@@ -271,6 +301,16 @@ impl command::Parser for CommandList {
         self.0.clear();
         self.1.reset();
     }
+    fn append(&mut self, other: &CommandList) {
+        let data = &mut self.1;
+        self.0.extend(other.0.iter().map(|com| match *com {
+            command::Command::UpdateBuffer(buf, ptr, offset_bytes) =>
+                command::Command::UpdateBuffer(buf, data.add(other.1.get(ptr)), offset_bytes),
+            command::Command::UpdateTexture(tex, kind, face, ptr, image) =>
+                command::Command::UpdateTexture(tex, kind, face, data.add(other.1.get(ptr)), image),
+            com => com,
+        }));
+    }
     fn parse(&mut self, com: command::Command) {
         self.0.push(com);
     }
@@ -306,6 +346,29 @@ impl command::Parser for DeferredContext {
             (*self.0).ClearState()
         };
     }
+    fn append(&mut self, other: &DeferredContext) {
+        // the other context may have been finished already by a submission
+        let (cl, finished) = match other.1 {
+            Some(cl) => (cl, false),
+            None => {
+                let mut cl = ptr::null_mut();
+                let hr = unsafe {
+                    (*other.0).FinishCommandList(winapi::FALSE, &mut cl)
+                };
+                if !winapi::SUCCEEDED(hr) {
+                    error!("Failed to finish the appended command list, error {:x}", hr);
+                    return
+                }
+                (cl, true)
+            },
+        };
+        unsafe {
+            (*self.0).ExecuteCommandList(cl, winapi::FALSE);
+            if finished {
+                (*cl).Release();
+            }
+        }
+    }
     fn parse(&mut self, com: command::Command) {
         let db = command::DataBuffer::new(); //not used
         execute::process(self.0, &com, &db);
@@ -363,6 +426,11 @@ impl core::Device for Device {
         unimplemented!()
     }
 
+    fn get_query_result(&mut self, _query: &h::Query<Self::Resources>) -> Option<u64> {
+        // `create_query` refuses every kind, so there is no query to get the result of
+        None
+    }
+
     fn cleanup(&mut self) {
         use core::handle::Producer;
 
@@ -387,10 +455,11 @@ impl core::Device for Device {
                 if p.ds != ptr::null_mut() { (*p.ds).Release(); }
                 if p.gs != ptr::null_mut() { (*p.gs).Release(); }
                 if p.ps != ptr::null_mut() { (*p.ps).Release(); }
+                if p.cs != ptr::null_mut() { (*p.cs).Release(); }
             },
             |_, v| unsafe { //PSO
                 type Child = *mut winapi::ID3D11DeviceChild;
-                (*v.layout).Release();
+                if v.layout != ptr::null_mut() { (*v.layout).Release(); }
                 (*(v.rasterizer as Child)).Release();
                 (*(v.depth_stencil as Child)).Release();
                 (*(v.blend as Child)).Release();
@@ -402,6 +471,7 @@ impl core::Device for Device {
             |_, v| unsafe { (*v.0).Release(); }, //DSV
             |_, v| unsafe { (*v.0).Release(); }, //sampler
             |_, _fence| {},
+            |_, _query| {},
         );
     }
 }
@@ -473,6 +543,11 @@ impl core::Device for Deferred {
         unimplemented!()
     }
 
+    fn get_query_result(&mut self, _query: &h::Query<Self::Resources>) -> Option<u64> {
+        // `create_query` refuses every kind, so there is no query to get the result of
+        None
+    }
+
     fn cleanup(&mut self) {
         self.0.cleanup();
     }
@@ -256,6 +256,7 @@ pub fn populate_info(info: &mut s::ProgramInfo, stage: s::Stage,
                                 count: mem_desc.Elements as usize,
                                 base_type: btype,
                                 container: container,
+                                layout: None,
                             })
                         }
                     }
@@ -266,6 +267,7 @@ pub fn populate_info(info: &mut s::ProgramInfo, stage: s::Stage,
                         count: vtype_desc.Elements as usize,
                         base_type: map_base_type(vtype_desc.Type),
                         container: map_container(&vtype_desc),
+                        layout: None,
                     })
                 }
             }
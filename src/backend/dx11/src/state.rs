@@ -17,7 +17,8 @@ use winapi::*;
 use core::{pso, state};
 use data::map_function;
 
-pub fn make_rasterizer(device: *mut ID3D11Device, rast: &state::Rasterizer, use_scissor: bool)
+pub fn make_rasterizer(device: *mut ID3D11Device, rast: &state::Rasterizer,
+                       bias: Option<pso::DepthBias>, depth_clamp: bool, use_scissor: bool)
                        -> *const ID3D11RasterizerState {
     let desc = D3D11_RASTERIZER_DESC {
         FillMode: match rast.method {
@@ -37,16 +38,22 @@ pub fn make_rasterizer(device: *mut ID3D11Device, rast: &state::Rasterizer, use_
             state::FrontFace::Clockwise => FALSE,
             state::FrontFace::CounterClockwise => TRUE,
         },
-        DepthBias: match rast.offset {
-            Some(ref o) => o.1 as INT,
-            None => 0,
+        DepthBias: match (bias, rast.offset) {
+            // D3D11 only takes an integer number of depth units
+            (Some(ref b), _) => b.constant().round() as INT,
+            (None, Some(ref o)) => o.1 as INT,
+            (None, None) => 0,
         },
-        DepthBiasClamp: 16.0,
-        SlopeScaledDepthBias: match rast.offset {
-            Some(ref o) => o.0 as FLOAT,
-            None => 0.0,
+        DepthBiasClamp: match bias {
+            Some(ref b) => b.clamp(),
+            None => 16.0,
         },
-        DepthClipEnable: TRUE,
+        SlopeScaledDepthBias: match (bias, rast.offset) {
+            (Some(ref b), _) => b.slope_scaled(),
+            (None, Some(ref o)) => o.0 as FLOAT,
+            (None, None) => 0.0,
+        },
+        DepthClipEnable: if depth_clamp {FALSE} else {TRUE},
         ScissorEnable: if use_scissor {TRUE} else {FALSE},
         MultisampleEnable: match rast.samples {
             Some(_) => TRUE,
@@ -170,8 +177,8 @@ fn map_blend_op(equation: state::Equation) -> D3D11_BLEND_OP {
     }
 }
 
-pub fn make_blend(device: *mut ID3D11Device, targets: &[Option<pso::ColorTargetDesc>])
-                  -> *const ID3D11BlendState {
+pub fn make_blend(device: *mut ID3D11Device, targets: &[Option<pso::ColorTargetDesc>],
+                  alpha_to_coverage: bool) -> *const ID3D11BlendState {
     let dummy_target = D3D11_RENDER_TARGET_BLEND_DESC {
         BlendEnable: FALSE,
         SrcBlend: D3D11_BLEND_ZERO,
@@ -183,7 +190,7 @@ pub fn make_blend(device: *mut ID3D11Device, targets: &[Option<pso::ColorTargetD
         RenderTargetWriteMask: 0xF,
     };
     let mut desc = D3D11_BLEND_DESC {
-        AlphaToCoverageEnable: FALSE, //TODO
+        AlphaToCoverageEnable: if alpha_to_coverage {TRUE} else {FALSE},
         IndependentBlendEnable: match targets[1..].iter().find(|t| t.is_some()) {
             Some(_) => TRUE,
             None => FALSE,
@@ -16,9 +16,9 @@
 
 use gl;
 use core::{self as c, command, state as s};
-use core::target::{ColorValue, Depth, Mirror, Rect, Stencil};
+use core::target::{ColorValue, Depth, Rect, Stencil};
 use {Buffer, BufferElement, Program, FrameBuffer, Texture,
-     NewTexture, Resources, PipelineState, ResourceView, TargetView};
+     NewTexture, Resources, PipelineState, Query, ResourceView, TargetView, UnorderedView};
 
 
 fn primitive_to_gl(primitive: c::Primitive) -> gl::types::GLenum {
@@ -73,7 +73,7 @@ impl DataBuffer {
 
 
 /// Serialized device command.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Command {
     // states
     BindProgram(Program),
@@ -82,6 +82,7 @@ pub enum Command {
     BindUnorderedView(c::pso::UnorderedViewParam<Resources>),
     BindSampler(c::pso::SamplerParam<Resources>, Option<gl::types::GLenum>),
     BindPixelTargets(c::pso::PixelTargetSet<Resources>),
+    BindStreamOutput(c::pso::StreamOutputParam<Resources>),
     BindVao,
     BindAttribute(c::AttributeSlot, Buffer, BufferElement),
     UnbindAttribute(c::AttributeSlot),
@@ -89,8 +90,10 @@ pub enum Command {
     BindFrameBuffer(Access, FrameBuffer),
     BindUniform(c::shade::Location, c::shade::UniformValue),
     SetDrawColorBuffers(c::ColorSlot),
-    SetRasterizer(s::Rasterizer),
-    SetViewport(Rect),
+    SetRasterizer(s::Rasterizer, Option<c::pso::DepthBias>, bool),
+    SetMultisample(c::pso::MultisampleInfo),
+    SetViewport(command::Viewport),
+    SetViewportIndexed(gl::types::GLuint, command::Viewport),
     SetScissor(Option<Rect>),
     SetDepthState(Option<s::Depth>),
     SetStencilState(Option<s::Stencil>, (Stencil, Stencil), s::CullFace),
@@ -110,6 +113,11 @@ pub enum Command {
                         Option<c::texture::CubeFace>,
                         c::texture::RawImageInfo,
                         Buffer, gl::types::GLintptr),
+    CopyTexture(c::texture::TextureCopyRegion<NewTexture>,
+                c::texture::TextureCopyRegion<NewTexture>),
+    BlitTexture(c::texture::TextureCopyRegion<NewTexture>,
+                c::texture::TextureCopyRegion<NewTexture>,
+                gl::types::GLenum),
     // resource updates
     UpdateBuffer(Buffer, DataPointer, usize),
     UpdateTexture(Texture,
@@ -125,9 +133,24 @@ pub enum Command {
                 gl::types::GLenum,
                 RawOffset,
                 c::VertexCount,
-                c::VertexCount,
+                gl::types::GLint,
                 Option<command::InstanceParams>),
-    _Blit(Rect, Rect, Mirror, usize),
+    DrawIndirect(gl::types::GLenum, Buffer, RawOffset, gl::types::GLsizei),
+    DrawIndexedIndirect(gl::types::GLenum,
+                        gl::types::GLenum,
+                        Buffer,
+                        RawOffset,
+                        gl::types::GLsizei),
+    Dispatch(command::ThreadGroupCount, command::ThreadGroupCount, command::ThreadGroupCount,
+             gl::types::GLbitfield),
+    BeginCapture(gl::types::GLenum),
+    EndCapture,
+    BeginQuery(Query),
+    EndQuery(Query),
+    WriteTimestamp(Query),
+    PushDebugGroup(DataPointer),
+    PopDebugGroup,
+    InsertDebugMarker(DataPointer),
 }
 
 pub const COLOR_DEFAULT: s::Color = s::Color {
@@ -135,7 +158,7 @@ pub const COLOR_DEFAULT: s::Color = s::Color {
     blend: None,
 };
 
-pub const RESET: [Command; 14] = [
+pub const RESET: [Command; 15] = [
     Command::BindProgram(0),
     Command::BindVao,
     // Command::UnbindAttribute, //not needed, handled by the cache
@@ -147,12 +170,20 @@ pub const RESET: [Command; 14] = [
         method: s::RasterMethod::Fill,
         offset: None,
         samples: None,
+    }, None, false),
+    Command::SetMultisample(c::pso::MultisampleInfo {
+        alpha_to_coverage: false,
+        sample_mask: !0,
     }),
-    Command::SetViewport(Rect {
-        x: 0,
-        y: 0,
-        w: 0,
-        h: 0,
+    Command::SetViewport(command::Viewport {
+        rect: Rect {
+            x: 0,
+            y: 0,
+            w: 0,
+            h: 0,
+        },
+        near: 0.0,
+        far: 1.0,
     }),
     Command::SetScissor(None),
     Command::SetDepthState(None),
@@ -176,6 +207,11 @@ struct Cache {
     // blend: Option<s::Blend>,
     cull_face: s::CullFace,
     draw_mask: u32,
+    capture: Option<gl::types::GLenum>,
+    capturing: bool,
+    stream_output: Option<c::pso::StreamOutputParam<Resources>>,
+    /// Memory barriers needed after the bound unordered views are written.
+    unordered_barriers: gl::types::GLbitfield,
 
     program: Program,
     constant_buffer: Option<c::pso::ConstantBufferParam<Resources>>,
@@ -184,8 +220,9 @@ struct Cache {
     depth_state: Option<s::Depth>,
     blend_state: Option<(c::ColorSlot, s::Color)>,
     blend_color: Option<ColorValue>,
-    viewport: Option<Rect>,
-    rasterizer: Option<s::Rasterizer>,
+    viewports: [Option<command::Viewport>; c::MAX_VIEWPORTS],
+    rasterizer: Option<(s::Rasterizer, Option<c::pso::DepthBias>, bool)>,
+    multisample: Option<c::pso::MultisampleInfo>,
     framebuffer: Option<(Access, FrameBuffer)>,
     index: Buffer,
 }
@@ -204,6 +241,10 @@ impl Cache {
             cull_face: s::CullFace::Nothing,
             // blend: None,
             draw_mask: 0,
+            capture: None,
+            capturing: false,
+            stream_output: None,
+            unordered_barriers: 0,
 
             program: 0,
             constant_buffer: None,
@@ -212,8 +253,9 @@ impl Cache {
             depth_state: None,
             blend_state: None,
             blend_color: None,
-            viewport: None,
+            viewports: [None; c::MAX_VIEWPORTS],
             rasterizer: None,
+            multisample: None,
             framebuffer: None,
             index: 0,
         }
@@ -260,20 +302,38 @@ impl Cache {
         Some(Command::BindFrameBuffer(access, fb))
     }
 
-    fn set_rasterizer(&mut self, rasterizer: s::Rasterizer) -> Option<Command> {
-        if self.rasterizer == Some(rasterizer) {
+    fn set_rasterizer(&mut self, rasterizer: s::Rasterizer, bias: Option<c::pso::DepthBias>,
+                      clamp: bool) -> Option<Command> {
+        if self.rasterizer == Some((rasterizer, bias, clamp)) {
             return None;
         }
-        self.rasterizer = Some(rasterizer);
-        Some(Command::SetRasterizer(rasterizer))
+        self.rasterizer = Some((rasterizer, bias, clamp));
+        Some(Command::SetRasterizer(rasterizer, bias, clamp))
     }
 
-    fn set_viewport(&mut self, rect: Rect) -> Option<Command> {
-        if self.viewport == Some(rect) {
+    fn set_multisample(&mut self, multisample: c::pso::MultisampleInfo) -> Option<Command> {
+        if self.multisample == Some(multisample) {
             return None;
         }
-        self.viewport = Some(rect);
-        Some(Command::SetViewport(rect))
+        self.multisample = Some(multisample);
+        Some(Command::SetMultisample(multisample))
+    }
+
+    fn set_viewport(&mut self, viewport: command::Viewport) -> Option<Command> {
+        if self.viewports.iter().all(|v| *v == Some(viewport)) {
+            return None;
+        }
+        // `glViewport` and `glDepthRange` affect all the viewports
+        self.viewports = [Some(viewport); c::MAX_VIEWPORTS];
+        Some(Command::SetViewport(viewport))
+    }
+
+    fn set_viewport_indexed(&mut self, index: usize, viewport: command::Viewport) -> Option<Command> {
+        if self.viewports[index] == Some(viewport) {
+            return None;
+        }
+        self.viewports[index] = Some(viewport);
+        Some(Command::SetViewportIndexed(index as gl::types::GLuint, viewport))
     }
 
     fn set_scissor(&mut self, rect: Option<Rect>) -> Option<Command> {
@@ -361,6 +421,41 @@ impl CommandBuffer {
             Some(_) => false,
         }
     }
+    /// Push a draw command, starting a transform feedback first
+    /// if the bound pipeline captures the stream output.
+    /// The capture goes on with the following draws, appending to the buffer,
+    /// until the pipeline or the stream output changes.
+    fn push_draw(&mut self, cmd: Command) {
+        match (self.cache.capture, self.cache.stream_output) {
+            (Some(mode), Some(_)) => if !self.cache.capturing {
+                self.cache.capturing = true;
+                self.buf.push(Command::BeginCapture(mode));
+            },
+            (Some(_), None) => {
+                error!("No stream output buffer is bound for the capturing pipeline");
+                self.end_capture();
+            },
+            (None, _) => self.end_capture(),
+        }
+        self.buf.push(cmd);
+    }
+    /// End the transform feedback in progress, if any. It has to be done
+    /// before any command that doesn't draw, since the rasterizer is disabled.
+    fn end_capture(&mut self) {
+        if self.cache.capturing {
+            self.cache.capturing = false;
+            self.buf.push(Command::EndCapture);
+        }
+    }
+    /// Check if a transform feedback is left in progress at the end of the buffer.
+    pub fn is_capturing(&self) -> bool {
+        self.cache.capturing
+    }
+    /// Forget the states that texture copies and blits change behind the cache.
+    fn invalidate_blit_state(&mut self) {
+        self.cache.framebuffer = None;
+        self.cache.blend_state = None;
+    }
 }
 
 impl command::Buffer<Resources> for CommandBuffer {
@@ -371,6 +466,34 @@ impl command::Buffer<Resources> for CommandBuffer {
         self.active_attribs = (1 << c::MAX_VERTEX_ATTRIBUTES) - 1;
     }
 
+    fn append(&mut self, other: &CommandBuffer) -> Result<(), command::AppendError> {
+        self.end_capture();
+        // the other commands expect the state left by the device reset
+        // before a submission, with no vertex attribute enabled
+        for i in 0 .. c::MAX_VERTEX_ATTRIBUTES {
+            if self.active_attribs & (1 << i) != 0 {
+                self.buf.push(Command::UnbindAttribute(i as c::AttributeSlot));
+            }
+        }
+        self.buf.extend_from_slice(&RESET);
+        let data = &mut self.data;
+        self.buf.extend(other.buf.iter().map(|com| match *com {
+            Command::UpdateBuffer(buf, ptr, offset_bytes) =>
+                Command::UpdateBuffer(buf, data.add(other.data.get(ptr)), offset_bytes),
+            Command::UpdateTexture(tex, kind, face, ptr, image) =>
+                Command::UpdateTexture(tex, kind, face, data.add(other.data.get(ptr)), image),
+            Command::PushDebugGroup(ptr) => Command::PushDebugGroup(data.add(other.data.get(ptr))),
+            Command::InsertDebugMarker(ptr) => Command::InsertDebugMarker(data.add(other.data.get(ptr))),
+            ref com => com.clone(),
+        }));
+        if other.is_capturing() {
+            self.buf.push(Command::EndCapture);
+        }
+        self.cache = Cache::new();
+        self.active_attribs = other.active_attribs;
+        Ok(())
+    }
+
     fn bind_pipeline_state(&mut self, pso: PipelineState) {
         let cull = pso.rasterizer.cull_face;
         self.cache.primitive = primitive_to_gl(pso.primitive);
@@ -378,9 +501,14 @@ impl command::Buffer<Resources> for CommandBuffer {
         self.cache.stencil = pso.output.stencil;
         self.cache.cull_face = cull;
         self.cache.draw_mask = pso.output.draw_mask;
+        if self.cache.program != pso.program || self.cache.capture != pso.capture {
+            self.end_capture();
+        }
+        self.cache.capture = pso.capture;
         self.buf.extend(self.cache.bind_program(pso.program));
         self.cache.scissor = pso.scissor;
-        self.buf.extend(self.cache.set_rasterizer(pso.rasterizer));
+        self.buf.extend(self.cache.set_rasterizer(pso.rasterizer, pso.depth_bias, pso.depth_clamp));
+        self.buf.extend(self.cache.set_multisample(pso.multisample));
         self.buf.extend(self.cache.set_depth_state(pso.output.depth));
         self.buf.extend(self.cache.set_stencil_state(pso.output.stencil, (0, 0), cull));
         for i in 0..c::MAX_COLOR_TARGETS {
@@ -442,7 +570,19 @@ impl command::Buffer<Resources> for CommandBuffer {
     }
 
     fn bind_unordered_views(&mut self, uavs: &[c::pso::UnorderedViewParam<Resources>]) {
+        self.cache.unordered_barriers = 0;
         for param in uavs.iter() {
+            self.cache.unordered_barriers |= match param.0 {
+                // the written buffer may be consumed by anything reading from buffers
+                UnorderedView::Buffer(_) =>
+                    gl::SHADER_STORAGE_BARRIER_BIT | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT |
+                    gl::ELEMENT_ARRAY_BARRIER_BIT | gl::UNIFORM_BARRIER_BIT | gl::COMMAND_BARRIER_BIT |
+                    gl::BUFFER_UPDATE_BARRIER_BIT | gl::PIXEL_BUFFER_BARRIER_BIT |
+                    gl::TRANSFORM_FEEDBACK_BARRIER_BIT,
+                UnorderedView::Image { .. } =>
+                    gl::SHADER_IMAGE_ACCESS_BARRIER_BIT | gl::TEXTURE_FETCH_BARRIER_BIT |
+                    gl::TEXTURE_UPDATE_BARRIER_BIT | gl::FRAMEBUFFER_BARRIER_BIT,
+            };
             self.buf.push(Command::BindUnorderedView(param.clone()));
         }
     }
@@ -473,12 +613,21 @@ impl command::Buffer<Resources> for CommandBuffer {
         let view = pts.get_view();
         self.cache.target_dim = view;
         self.buf.extend(
-            self.cache.set_viewport(Rect {
+            self.cache.set_viewport(command::Viewport::new(Rect {
                                     x: 0,
                                     y: 0,
                                     w: view.0,
                                     h: view.1,
-                                }));
+                                })));
+    }
+
+    fn bind_stream_output(&mut self, so: Option<c::pso::StreamOutputParam<Resources>>) {
+        if self.cache.stream_output == so {
+            return;
+        }
+        self.end_capture();
+        self.cache.stream_output = so;
+        self.buf.extend(so.map(Command::BindStreamOutput));
     }
 
     fn bind_index(&mut self, buf: Buffer, itype: c::IndexType) {
@@ -503,6 +652,43 @@ impl command::Buffer<Resources> for CommandBuffer {
         self.buf.extend(self.cache.set_scissor(scissor_rect));
     }
 
+    fn set_viewports(&mut self, viewports: &[command::Viewport]) {
+        use std::cmp;
+        let target_dim = self.cache.target_dim;
+        let flip = |vp: &command::Viewport| command::Viewport {
+            rect: Rect {
+                // inverting the Y axis in order to match D3D11
+                y: cmp::max(target_dim.1, vp.rect.y + vp.rect.h) -
+                    vp.rect.y -
+                    vp.rect.h,
+                ..vp.rect
+            },
+            ..*vp
+        };
+        match viewports.len() {
+            0 => {
+                let full = command::Viewport::new(Rect {
+                    x: 0,
+                    y: 0,
+                    w: target_dim.0,
+                    h: target_dim.1,
+                });
+                self.buf.extend(self.cache.set_viewport(full));
+            }
+            1 => {
+                self.buf.extend(self.cache.set_viewport(flip(&viewports[0])));
+            }
+            num => {
+                if num > c::MAX_VIEWPORTS {
+                    error!("Too many viewports: {}, only the first {} are used", num, c::MAX_VIEWPORTS);
+                }
+                for (i, vp) in viewports.iter().take(c::MAX_VIEWPORTS).enumerate() {
+                    self.buf.extend(self.cache.set_viewport_indexed(i, flip(vp)));
+                }
+            }
+        }
+    }
+
     fn set_ref_values(&mut self, rv: s::RefValues) {
         let stencil = self.cache.stencil;
         let cull_face = self.cache.cull_face;
@@ -518,6 +704,7 @@ impl command::Buffer<Resources> for CommandBuffer {
                    src_offset_bytes: usize,
                    dst_offset_bytes: usize,
                    size_bytes: usize) {
+        self.end_capture();
         self.buf.push(Command::CopyBuffer(src, dst,
                                           src_offset_bytes as gl::types::GLintptr,
                                           dst_offset_bytes as gl::types::GLintptr,
@@ -530,6 +717,7 @@ impl command::Buffer<Resources> for CommandBuffer {
                               kind: c::texture::Kind,
                               face: Option<c::texture::CubeFace>,
                               img: c::texture::RawImageInfo) {
+        self.end_capture();
         match dst {
             NewTexture::Texture(t) =>
                 self.buf.push(Command::CopyBufferToTexture(
@@ -547,13 +735,48 @@ impl command::Buffer<Resources> for CommandBuffer {
                               face: Option<c::texture::CubeFace>,
                               img: c::texture::RawImageInfo,
                               dst: Buffer, dst_offset_bytes: usize) {
+        self.end_capture();
         self.buf.push(Command::CopyTextureToBuffer(
             src, kind, face, img,
             dst, dst_offset_bytes as gl::types::GLintptr
         ));
     }
 
+    fn copy_texture(&mut self,
+                    src: c::texture::TextureCopyRegion<NewTexture>,
+                    dst: c::texture::TextureCopyRegion<NewTexture>) {
+        self.end_capture();
+        // the copy may fall back to a blit, which is affected by the scissor
+        self.buf.extend(self.cache.set_scissor(None));
+        self.buf.push(Command::CopyTexture(src, dst));
+        self.invalidate_blit_state();
+    }
+
+    fn resolve_texture(&mut self,
+                       src: c::texture::TextureCopyRegion<NewTexture>,
+                       dst: c::texture::TextureCopyRegion<NewTexture>) {
+        self.end_capture();
+        self.buf.extend(self.cache.set_scissor(None));
+        self.buf.push(Command::BlitTexture(src, dst, gl::NEAREST));
+        self.invalidate_blit_state();
+    }
+
+    fn blit_texture(&mut self,
+                    src: c::texture::TextureCopyRegion<NewTexture>,
+                    dst: c::texture::TextureCopyRegion<NewTexture>,
+                    filter: c::texture::FilterMethod) {
+        self.end_capture();
+        let filter = match filter {
+            c::texture::FilterMethod::Scale => gl::NEAREST,
+            _ => gl::LINEAR,
+        };
+        self.buf.extend(self.cache.set_scissor(None));
+        self.buf.push(Command::BlitTexture(src, dst, filter));
+        self.invalidate_blit_state();
+    }
+
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset_bytes: usize) {
+        self.end_capture();
         let ptr = self.data.add(data);
         self.buf.push(Command::UpdateBuffer(buf, ptr, offset_bytes));
     }
@@ -564,6 +787,7 @@ impl command::Buffer<Resources> for CommandBuffer {
                       face: Option<c::texture::CubeFace>,
                       data: &[u8],
                       img: c::texture::RawImageInfo) {
+        self.end_capture();
         let ptr = self.data.add(data);
         match ntex {
             NewTexture::Texture(t) => {
@@ -576,10 +800,12 @@ impl command::Buffer<Resources> for CommandBuffer {
     }
 
     fn generate_mipmap(&mut self, srv: ResourceView) {
+        self.end_capture();
         self.buf.push(Command::GenerateMipmap(srv));
     }
 
     fn clear_color(&mut self, target: TargetView, value: command::ClearColor) {
+        self.end_capture();
         // this could be optimized by deferring the actual clear call
         let mut pts = c::pso::PixelTargetSet::new();
         pts.colors[0] = Some(target);
@@ -591,6 +817,7 @@ impl command::Buffer<Resources> for CommandBuffer {
                            target: TargetView,
                            depth: Option<Depth>,
                            stencil: Option<Stencil>) {
+        self.end_capture();
         let mut pts = c::pso::PixelTargetSet::new();
         if depth.is_some() {
             pts.depth = Some(target);
@@ -606,7 +833,8 @@ impl command::Buffer<Resources> for CommandBuffer {
                  start: c::VertexCount,
                  count: c::VertexCount,
                  instances: Option<command::InstanceParams>) {
-        self.buf.push(Command::Draw(self.cache.primitive, start, count, instances));
+        let cmd = Command::Draw(self.cache.primitive, start, count, instances);
+        self.push_draw(cmd);
     }
 
     fn call_draw_indexed(&mut self,
@@ -618,13 +846,127 @@ impl command::Buffer<Resources> for CommandBuffer {
             c::IndexType::U16 => (start * 2u32, gl::UNSIGNED_SHORT),
             c::IndexType::U32 => (start * 4u32, gl::UNSIGNED_INT),
         };
-        self.buf.push(
-                  Command::DrawIndexed(
+        let cmd = Command::DrawIndexed(
                       self.cache.primitive,
                       gl_index,
                       RawOffset(offset as *const gl::types::GLvoid),
                       count,
-                      base,
-                      instances));
+                      base as gl::types::GLint,
+                      instances);
+        self.push_draw(cmd);
+    }
+
+    fn call_draw_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        let cmd = Command::DrawIndirect(
+            self.cache.primitive,
+            buf,
+            RawOffset(offset as *const gl::types::GLvoid),
+            count as gl::types::GLsizei);
+        self.push_draw(cmd);
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        let gl_index = match self.cache.index_type {
+            c::IndexType::U16 => gl::UNSIGNED_SHORT,
+            c::IndexType::U32 => gl::UNSIGNED_INT,
+        };
+        let cmd = Command::DrawIndexedIndirect(
+            self.cache.primitive,
+            gl_index,
+            buf,
+            RawOffset(offset as *const gl::types::GLvoid),
+            count as gl::types::GLsizei);
+        self.push_draw(cmd);
+    }
+
+    fn call_dispatch(&mut self,
+                     x: command::ThreadGroupCount,
+                     y: command::ThreadGroupCount,
+                     z: command::ThreadGroupCount) {
+        self.end_capture();
+        self.buf.push(Command::Dispatch(x, y, z, self.cache.unordered_barriers));
+    }
+
+    fn begin_query(&mut self, query: Query) {
+        self.buf.push(Command::BeginQuery(query));
+    }
+
+    fn end_query(&mut self, query: Query) {
+        self.buf.push(Command::EndQuery(query));
+    }
+
+    fn write_timestamp(&mut self, query: Query) {
+        self.buf.push(Command::WriteTimestamp(query));
+    }
+
+    fn push_debug_group(&mut self, name: &str) {
+        let ptr = self.data.add(name.as_bytes());
+        self.buf.push(Command::PushDebugGroup(ptr));
+    }
+
+    fn pop_debug_group(&mut self) {
+        self.buf.push(Command::PopDebugGroup);
+    }
+
+    fn insert_debug_marker(&mut self, name: &str) {
+        let ptr = self.data.add(name.as_bytes());
+        self.buf.push(Command::InsertDebugMarker(ptr));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gl;
+    use core::{self as c, state as s};
+    use core::command::Buffer;
+    use {OutputMerger, PipelineState, Program, TargetView};
+    use super::{Command, CommandBuffer, COLOR_DEFAULT};
+
+    fn capturing_pipeline(program: Program) -> PipelineState {
+        PipelineState {
+            program: program,
+            primitive: c::Primitive::PointList,
+            input: [None; c::MAX_VERTEX_ATTRIBUTES],
+            scissor: false,
+            rasterizer: s::Rasterizer::new_fill(),
+            depth_bias: None,
+            depth_clamp: false,
+            multisample: c::pso::MultisampleInfo::default(),
+            output: OutputMerger {
+                draw_mask: 0,
+                stencil: None,
+                depth: None,
+                colors: [COLOR_DEFAULT; c::MAX_COLOR_TARGETS],
+            },
+            capture: Some(gl::POINTS),
+        }
+    }
+
+    fn captures(cb: &CommandBuffer) -> Vec<&'static str> {
+        cb.buf.iter().filter_map(|com| match *com {
+            Command::BeginCapture(_) => Some("begin"),
+            Command::EndCapture => Some("end"),
+            Command::Draw(..) => Some("draw"),
+            Command::Clear(..) => Some("clear"),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn consecutive_draws_append() {
+        let mut cb = CommandBuffer::new(1);
+        cb.bind_pipeline_state(capturing_pipeline(2));
+        cb.bind_stream_output(Some(c::pso::StreamOutputParam(3, 16)));
+        cb.call_draw(0, 3, None);
+        cb.bind_pipeline_state(capturing_pipeline(2));
+        cb.call_draw(3, 3, None);
+        assert!(cb.is_capturing());
+        cb.clear_color(TargetView::Surface(0), c::command::ClearColor::Float([0.0; 4]));
+        cb.call_draw(6, 3, None);
+        cb.bind_stream_output(Some(c::pso::StreamOutputParam(4, 0)));
+        cb.call_draw(0, 3, None);
+        assert_eq!(captures(&cb),
+                   vec!["begin", "draw", "draw", "end", "clear",
+                        "begin", "draw", "end", "begin", "draw"]);
     }
 }
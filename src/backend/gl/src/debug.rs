@@ -0,0 +1,162 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Driver messages of `GL_KHR_debug`.
+
+use std::cell::RefCell;
+use std::os::raw::c_void;
+use std::{ptr, slice};
+use gl;
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+
+/// Severity of a driver message.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+/// Part of the system that produced a driver message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+/// A message reported by the driver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub source: Source,
+    pub severity: Severity,
+    /// The message reports an error, as opposed to a warning or a performance hint.
+    pub is_error: bool,
+    pub id: u32,
+    pub text: String,
+}
+
+/// Handling of the driver messages and of the errors raised by the commands.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// Messages of a lower severity are not reported by the driver.
+    pub min_severity: Severity,
+    /// Panic as soon as a command raises an error, instead of returning
+    /// `SubmissionError::DeviceError` from the submission.
+    pub panic_on_error: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            min_severity: Severity::Low,
+            panic_on_error: false,
+        }
+    }
+}
+
+/// Receiver of the messages, owned by the device while the callback is installed.
+pub struct Output {
+    errors: RefCell<Vec<Message>>,
+}
+
+impl Output {
+    pub fn new() -> Output {
+        Output {
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Take the errors reported since the last call.
+    pub fn take_errors(&self) -> Vec<Message> {
+        self.errors.borrow_mut().drain(..).collect()
+    }
+}
+
+fn map_severity(severity: GLenum) -> Severity {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH   => Severity::High,
+        gl::DEBUG_SEVERITY_MEDIUM => Severity::Medium,
+        gl::DEBUG_SEVERITY_LOW    => Severity::Low,
+        _                         => Severity::Notification,
+    }
+}
+
+fn map_source(source: GLenum) -> Source {
+    match source {
+        gl::DEBUG_SOURCE_API             => Source::Api,
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM   => Source::WindowSystem,
+        gl::DEBUG_SOURCE_SHADER_COMPILER => Source::ShaderCompiler,
+        gl::DEBUG_SOURCE_THIRD_PARTY     => Source::ThirdParty,
+        gl::DEBUG_SOURCE_APPLICATION     => Source::Application,
+        _                                => Source::Other,
+    }
+}
+
+extern "system" fn callback(source: GLenum, gltype: GLenum, id: GLuint, severity: GLenum,
+                            length: GLsizei, message: *const GLchar, user_param: *mut c_void) {
+    let output = unsafe { &*(user_param as *const Output) };
+    let bytes = unsafe { slice::from_raw_parts(message as *const u8, length as usize) };
+    let message = Message {
+        source: map_source(source),
+        severity: map_severity(severity),
+        is_error: gltype == gl::DEBUG_TYPE_ERROR,
+        id: id,
+        text: String::from_utf8_lossy(bytes).into_owned(),
+    };
+    match message.severity {
+        Severity::High => error!("[{:?} {}] {}", message.source, message.id, message.text),
+        Severity::Medium => warn!("[{:?} {}] {}", message.source, message.id, message.text),
+        Severity::Low => info!("[{:?} {}] {}", message.source, message.id, message.text),
+        Severity::Notification => debug!("[{:?} {}] {}", message.source, message.id, message.text),
+    }
+    // unwinding out of the driver is not allowed, so the errors are
+    // only collected here and raised once the GL call returns
+    if message.is_error {
+        output.errors.borrow_mut().push(message);
+    }
+}
+
+/// Route the driver messages to the output. Messages are delivered synchronously,
+/// so that the errors can be traced back to the GL call that raised them.
+pub fn install(gl: &gl::Gl, output: &Output, config: Config) {
+    let severities = [
+        (Severity::Notification, gl::DEBUG_SEVERITY_NOTIFICATION),
+        (Severity::Low, gl::DEBUG_SEVERITY_LOW),
+        (Severity::Medium, gl::DEBUG_SEVERITY_MEDIUM),
+        (Severity::High, gl::DEBUG_SEVERITY_HIGH),
+    ];
+    unsafe {
+        gl.Enable(gl::DEBUG_OUTPUT);
+        gl.Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl.DebugMessageCallback(Some(callback), output as *const Output as *const c_void);
+        for &(severity, gl_severity) in severities.iter() {
+            let enabled = if severity >= config.min_severity { gl::TRUE } else { gl::FALSE };
+            gl.DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl_severity, 0, ptr::null(), enabled);
+        }
+    }
+}
+
+/// Stop routing the driver messages.
+pub fn uninstall(gl: &gl::Gl) {
+    unsafe {
+        gl.DebugMessageCallback(None, ptr::null());
+        gl.Disable(gl::DEBUG_OUTPUT);
+    }
+}
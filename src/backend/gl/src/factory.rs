@@ -13,19 +13,20 @@
 // limitations under the License.
 
 use std::rc::Rc;
-use std::{slice, ptr};
+use std::{cmp, slice, ptr};
+use std::ops::Range;
 
-use {gl, tex};
-use core::{self as d, factory as f, texture as t, buffer, mapping};
+use {gl, shade, tex};
+use core::{self as d, factory as f, texture as t, buffer, mapping, query};
 use core::memory::{self, Bind, SHADER_RESOURCE, UNORDERED_ACCESS, Typed};
-use core::format::ChannelType;
+use core::format::{ChannelType, Format, Swizzle};
 use core::handle::{self, Producer};
 use core::target::{Layer, Level};
 
 use command::{CommandBuffer, COLOR_DEFAULT};
 use {Resources as R, Share, OutputMerger};
 use {Buffer, BufferElement, FatSampler, NewTexture,
-     PipelineState, ResourceView, TargetView, Fence};
+     PipelineState, ResourceView, TargetView, UnorderedView, Fence, Query};
 
 
 pub fn role_to_target(role: buffer::Role) -> gl::types::GLenum {
@@ -34,6 +35,7 @@ pub fn role_to_target(role: buffer::Role) -> gl::types::GLenum {
         buffer::Role::Index    => gl::ELEMENT_ARRAY_BUFFER,
         buffer::Role::Constant => gl::UNIFORM_BUFFER,
         buffer::Role::Staging  => gl::ARRAY_BUFFER,
+        buffer::Role::Indirect => gl::DRAW_INDIRECT_BUFFER,
     }
 }
 
@@ -53,6 +55,35 @@ fn access_to_gl(access: memory::Access) -> gl::types::GLenum {
     }
 }
 
+// Tokens of `GL_ARB_pipeline_statistics_query`, not present in the generated bindings.
+const VERTICES_SUBMITTED: gl::types::GLenum                 = 0x82EE;
+const PRIMITIVES_SUBMITTED: gl::types::GLenum               = 0x82EF;
+const VERTEX_SHADER_INVOCATIONS: gl::types::GLenum          = 0x82F0;
+const TESS_CONTROL_SHADER_PATCHES: gl::types::GLenum        = 0x82F1;
+const TESS_EVALUATION_SHADER_INVOCATIONS: gl::types::GLenum = 0x82F2;
+const GEOMETRY_SHADER_PRIMITIVES_EMITTED: gl::types::GLenum = 0x82F3;
+const FRAGMENT_SHADER_INVOCATIONS: gl::types::GLenum        = 0x82F4;
+const COMPUTE_SHADER_INVOCATIONS: gl::types::GLenum         = 0x82F5;
+const CLIPPING_INPUT_PRIMITIVES: gl::types::GLenum          = 0x82F6;
+const CLIPPING_OUTPUT_PRIMITIVES: gl::types::GLenum         = 0x82F7;
+
+fn statistic_to_target(stat: query::PipelineStatistic) -> gl::types::GLenum {
+    use core::query::PipelineStatistic::*;
+    match stat {
+        InputVertices => VERTICES_SUBMITTED,
+        InputPrimitives => PRIMITIVES_SUBMITTED,
+        VertexShaderInvocations => VERTEX_SHADER_INVOCATIONS,
+        HullShaderInvocations => TESS_CONTROL_SHADER_PATCHES,
+        DomainShaderInvocations => TESS_EVALUATION_SHADER_INVOCATIONS,
+        GeometryShaderInvocations => gl::GEOMETRY_SHADER_INVOCATIONS,
+        GeometryShaderPrimitives => GEOMETRY_SHADER_PRIMITIVES_EMITTED,
+        ClippingInvocations => CLIPPING_INPUT_PRIMITIVES,
+        ClippingPrimitives => CLIPPING_OUTPUT_PRIMITIVES,
+        PixelShaderInvocations => FRAGMENT_SHADER_INVOCATIONS,
+        ComputeShaderInvocations => COMPUTE_SHADER_INVOCATIONS,
+    }
+}
+
 pub fn update_sub_buffer(gl: &gl::Gl, buffer: Buffer, address: *const u8,
                          size: usize, offset: usize, role: buffer::Role) {
     let target = role_to_target(role);
@@ -113,7 +144,7 @@ impl Factory {
     fn init_buffer(&mut self,
                    buffer: Buffer,
                    info: &buffer::Info,
-                   data_opt: Option<&[u8]>) -> Option<MappingGate> {
+                   data_opt: Option<&[u8]>) -> Result<Option<MappingGate>, buffer::CreationError> {
         use core::memory::Usage::*;
 
         let gl = &self.share.context;
@@ -132,6 +163,8 @@ impl Factory {
                 Dynamic => gl::DYNAMIC_STORAGE_BIT,
                 Upload => access_to_map_bits(memory::WRITE) | gl::MAP_PERSISTENT_BIT,
                 Download => access_to_map_bits(memory::READ) | gl::MAP_PERSISTENT_BIT,
+                Persistent => access_to_map_bits(memory::RW) | gl::MAP_PERSISTENT_BIT |
+                              gl::MAP_COHERENT_BIT,
             };
             let size = if info.size == 0 {
                 // we are not allowed to pass size=0 into `glBufferStorage`
@@ -155,6 +188,10 @@ impl Factory {
                 Dynamic => gl::DYNAMIC_DRAW,
                 Upload => gl::STREAM_DRAW,
                 Download => gl::STREAM_READ,
+                Persistent => {
+                    error!("Persistent buffer mapping is not supported by this GL version");
+                    return Err(buffer::CreationError::UnsupportedUsage(info.usage));
+                },
             };
             unsafe {
                 gl.BindBuffer(target, buffer);
@@ -173,13 +210,17 @@ impl Factory {
             Data | Dynamic => None,
             Upload => Some(memory::WRITE),
             Download => Some(memory::READ),
+            Persistent => Some(memory::RW),
         };
 
-        mapping_access.map(|access| {
+        Ok(mapping_access.map(|access| {
             let (kind, ptr) = if self.share.private_caps.buffer_storage_supported {
+                let coherent = info.usage == Persistent;
                 let mut gl_access = access_to_map_bits(access) |
                                     gl::MAP_PERSISTENT_BIT;
-                if access.contains(memory::WRITE) {
+                if coherent {
+                    gl_access |= gl::MAP_COHERENT_BIT;
+                } else if access.contains(memory::WRITE) {
                     gl_access |= gl::MAP_FLUSH_EXPLICIT_BIT;
                 }
                 let size = info.size as isize;
@@ -187,7 +228,13 @@ impl Factory {
                     gl.BindBuffer(target, buffer);
                     gl.MapBufferRange(target, 0, size, gl_access)
                 } as *mut ::std::os::raw::c_void;
-                (MappingKind::Persistent(mapping::Status::clean()), ptr)
+                let status = mapping::Status::clean();
+                let kind = if coherent {
+                    MappingKind::Coherent(status)
+                } else {
+                    MappingKind::Persistent(status)
+                };
+                (kind, ptr)
             } else {
                 (MappingKind::Temporary, ptr::null_mut())
             };
@@ -200,7 +247,7 @@ impl Factory {
                 kind: kind,
                 pointer: ptr,
             }
-        })
+        }))
     }
 
     fn create_program_raw(&mut self, shader_set: &d::ShaderSet<R>)
@@ -208,6 +255,7 @@ impl Factory {
         use shade::create_program;
         let frame_handles = &mut self.frame_handles;
         let mut shaders = [0; 5];
+        let mut stream_outputs: &[String] = &[];
         let usage = shader_set.get_usage();
         let shader_slice = match shader_set {
             &d::ShaderSet::Simple(ref vs, ref ps) => {
@@ -228,9 +276,24 @@ impl Factory {
                 shaders[3] = *ps.reference(frame_handles);
                 &shaders[..4]
             },
+            &d::ShaderSet::Compute(ref cs) => {
+                shaders[0] = *cs.reference(frame_handles);
+                &shaders[..1]
+            },
+            &d::ShaderSet::TransformFeedback(ref vs, ref gs, ref outputs) => {
+                stream_outputs = outputs;
+                shaders[0] = *vs.reference(frame_handles);
+                match *gs {
+                    Some(ref gs) => {
+                        shaders[1] = *gs.reference(frame_handles);
+                        &shaders[..2]
+                    },
+                    None => &shaders[..1],
+                }
+            },
         };
         let result = create_program(&self.share.context, &self.share.capabilities,
-                                    &self.share.private_caps, shader_slice, usage);
+                                    &self.share.private_caps, shader_slice, stream_outputs, usage);
         if let Err(err) = self.share.check() {
             panic!("Error {:?} creating program: {:?}", err, shader_set)
         }
@@ -252,6 +315,8 @@ impl Factory {
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum MappingKind {
     Persistent(mapping::Status<R>),
+    /// Persistent mapping which is neither flushed nor synchronized implicitly.
+    Coherent(mapping::Status<R>),
     Temporary,
 }
 
@@ -312,13 +377,146 @@ impl f::Factory<R> for Factory {
         &self.share.capabilities
     }
 
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<R>, name: &str) {
+        use core::handle::Ref;
+        self.share.handles.borrow_mut().set_label(handle, name);
+        if !self.share.private_caps.debug_output_supported {
+            return
+        }
+        let gl = &self.share.context;
+        let label = |identifier, object| unsafe {
+            gl.ObjectLabel(identifier, object, name.len() as gl::types::GLsizei,
+                           name.as_ptr() as *const gl::types::GLchar);
+        };
+        match handle {
+            Ref::Buffer(h) => label(gl::BUFFER, *self.frame_handles.ref_buffer(h)),
+            Ref::Shader(h) => label(gl::SHADER, *self.frame_handles.ref_shader(h)),
+            Ref::Program(h) => label(gl::PROGRAM, *self.frame_handles.ref_program(h)),
+            Ref::Texture(h) => match *self.frame_handles.ref_texture(h) {
+                NewTexture::Surface(0) | NewTexture::Texture(0) => (),
+                NewTexture::Surface(surface) => label(gl::RENDERBUFFER, surface),
+                NewTexture::Texture(texture) => label(gl::TEXTURE, texture),
+            },
+            Ref::ShaderResourceView(h) => {
+                // the views that don't own their object would rename the viewed texture
+                let view = *self.frame_handles.ref_srv(h);
+                if view.owned {
+                    label(gl::TEXTURE, view.object);
+                }
+            },
+            Ref::Sampler(h) => {
+                if self.share.private_caps.sampler_objects_supported {
+                    label(gl::SAMPLER, self.frame_handles.ref_sampler(h).object);
+                }
+            },
+            Ref::Fence(h) => unsafe {
+                let fence = self.frame_handles.ref_fence(h);
+                gl.ObjectPtrLabel(fence.0 as *const _, name.len() as gl::types::GLsizei,
+                                  name.as_ptr() as *const gl::types::GLchar);
+            },
+            Ref::Query(h) => {
+                // the query object only comes to existence once it has been used
+                let object = self.frame_handles.ref_query(h).object;
+                if unsafe { gl.IsQuery(object) } == gl::TRUE {
+                    label(gl::QUERY, object);
+                }
+            },
+            // these are states and attachment points, with no GL object of their own
+            Ref::PipelineState(_) | Ref::UnorderedAccessView(_) |
+            Ref::RenderTargetView(_) | Ref::DepthStencilView(_) => (),
+        }
+    }
+
+    fn get_statistics(&self) -> handle::Statistics {
+        self.share.handles.borrow().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: Format) -> d::format::Properties {
+        use core::format as fm;
+        let gl = &self.share.context;
+        let caps = &self.share.capabilities;
+        let internal_format = match tex::format_to_glfull(format) {
+            Ok(f) => f,
+            Err(_) => return fm::Properties::unsupported(),
+        };
+        if format.0.is_compressed() {
+            return if tex::is_compressed_format_supported(gl, internal_format) {
+                fm::Properties { support: fm::SAMPLE, max_samples: 0 }
+            } else {
+                fm::Properties::unsupported()
+            };
+        }
+        let is_depth = match format.0 {
+            fm::SurfaceType::D16 | fm::SurfaceType::D24 |
+            fm::SurfaceType::D24_S8 | fm::SurfaceType::D32 => true,
+            _ => false,
+        };
+        let private_caps = &self.share.private_caps;
+        let support = if private_caps.internal_format_query2_supported {
+            let query = |pname| {
+                let mut value = 0;
+                unsafe {
+                    gl.GetInternalformativ(gl::TEXTURE_2D, internal_format, pname, 1, &mut value);
+                }
+                value as gl::types::GLenum
+            };
+            let mut support = fm::Support::empty();
+            if query(gl::INTERNALFORMAT_SUPPORTED) == gl::TRUE as gl::types::GLenum {
+                if query(gl::FRAGMENT_TEXTURE) != gl::NONE {
+                    support |= fm::SAMPLE;
+                }
+                if query(gl::FRAMEBUFFER_RENDERABLE) != gl::NONE {
+                    support |= fm::RENDER;
+                }
+                if query(gl::FRAMEBUFFER_BLEND) != gl::NONE {
+                    support |= fm::BLEND;
+                }
+                if caps.unordered_access_view_supported && query(gl::SHADER_IMAGE_STORE) != gl::NONE {
+                    support |= fm::UNORDERED;
+                }
+            }
+            support
+        } else {
+            let mut support = tex::get_required_format_support(format, private_caps.is_embedded);
+            if !is_depth && caps.unordered_access_view_supported && tex::is_image_format(format) {
+                support |= fm::UNORDERED;
+            }
+            support
+        };
+        let max_samples = if !support.contains(fm::RENDER) {
+            1
+        } else if private_caps.internal_format_query_supported {
+            let mut samples = 0;
+            unsafe {
+                gl.GetInternalformativ(gl::RENDERBUFFER, internal_format, gl::SAMPLES, 1, &mut samples);
+            }
+            cmp::min(samples, 0xFF) as u8
+        } else {
+            caps.limits.max_samples
+        };
+        fm::Properties {
+            support: support,
+            max_samples: cmp::max(max_samples, 1),
+        }
+    }
+
     fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<handle::RawBuffer<R>, buffer::CreationError> {
         if !self.share.capabilities.constant_buffer_supported && info.role == buffer::Role::Constant {
             error!("Constant buffers are not supported by this GL version");
             return Err(buffer::CreationError::Other);
         }
         let name = self.create_buffer_internal();
-        let mapping = self.init_buffer(name, &info, None);
+        let mapping = match self.init_buffer(name, &info, None) {
+            Ok(mapping) => mapping,
+            Err(err) => {
+                unsafe { self.share.context.DeleteBuffers(1, &name) };
+                return Err(err);
+            }
+        };
         Ok(self.share.handles.borrow_mut().make_buffer(name, info, mapping))
     }
 
@@ -332,12 +530,15 @@ impl f::Factory<R> for Factory {
             size: data.len(),
             stride: stride,
         };
-        let mapping = self.init_buffer(name, &info, Some(data));
+        let mapping = try!(self.init_buffer(name, &info, Some(data)));
         Ok(self.share.handles.borrow_mut().make_buffer(name, info, mapping))
     }
 
     fn create_shader(&mut self, stage: d::shade::Stage, code: &[u8])
                      -> Result<handle::Shader<R>, d::shade::CreateShaderError> {
+        if stage == d::shade::Stage::Compute && !self.share.capabilities.compute_supported {
+            return Err(d::shade::CreateShaderError::StageNotSupported(stage));
+        }
         ::shade::create_shader(&self.share.context, stage, code)
                 .map(|sh| self.share.handles.borrow_mut().make_shader(sh))
     }
@@ -357,6 +558,13 @@ impl f::Factory<R> for Factory {
                 return Err(d::pso::CreationError),
             _ => ()
         }
+        if (desc.multisample.alpha_to_coverage && !caps.alpha_to_coverage_supported) ||
+           (desc.multisample.sample_mask != !0 && !caps.sample_mask_supported) ||
+           (desc.depth_clamp && !caps.depth_clamp_supported) ||
+           (desc.depth_bias.map_or(false, |b| b.clamp() != 0.0) && !caps.depth_bias_clamp_supported) {
+            error!("Unsupported multisample or depth controls requested by {:?}", desc);
+            return Err(d::pso::CreationError)
+        }
         let mut output = OutputMerger {
             draw_mask: 0,
             stencil: match desc.depth_stencil {
@@ -388,13 +596,25 @@ impl f::Factory<R> for Factory {
                 elem: at.1,
             });
         }
+        let program_name = *self.frame_handles.ref_program(program);
+        let capture = match desc.stream_output {
+            Some(_) => match shade::get_capture_mode(&self.share.context, program_name, desc.primitive) {
+                Some(mode) => Some(mode),
+                None => return Err(d::pso::CreationError),
+            },
+            None => None,
+        };
         let pso = PipelineState {
-            program: *self.frame_handles.ref_program(program),
+            program: program_name,
             primitive: desc.primitive,
             input: inputs,
             scissor: desc.scissor,
             rasterizer: desc.rasterizer,
+            depth_bias: desc.depth_bias,
+            depth_clamp: desc.depth_clamp,
+            multisample: desc.multisample,
             output: output,
+            capture: capture,
         };
         Ok(self.share.handles.borrow_mut().make_pso(pso, program))
     }
@@ -454,28 +674,122 @@ impl f::Factory<R> for Factory {
         Ok(self.share.handles.borrow_mut().make_buffer_srv(view, hbuf))
     }
 
-    fn view_buffer_as_unordered_access_raw(&mut self, _hbuf: &handle::RawBuffer<R>)
+    fn view_buffer_as_unordered_access_raw(&mut self, hbuf: &handle::RawBuffer<R>)
                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
-        Err(f::ResourceViewError::Unsupported) //TODO
+        if !self.share.private_caps.storage_buffer_supported {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+        if !hbuf.get_info().bind.contains(UNORDERED_ACCESS) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        let view = UnorderedView::Buffer(*self.frame_handles.ref_buffer(hbuf));
+        Ok(self.share.handles.borrow_mut().make_buffer_uav(view, hbuf))
     }
 
-    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<R>, _desc: t::ResourceDesc)
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::ResourceDesc)
                                        -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        let name = match self.frame_handles.ref_texture(htex) {
+            &NewTexture::Surface(_) => return Err(f::ResourceViewError::NoBindFlag),
+            &NewTexture::Texture(t) => t,
+        };
+        let info = *htex.get_info();
+        let caps = &self.share.private_caps;
+        let gl = &self.share.context;
+        // cube faces are addressed as layers
+        let num_layers = match info.kind {
+            t::Kind::Cube(_) => Some(6),
+            t::Kind::CubeArray(_, n) => Some(6 * n),
+            kind => kind.get_num_slices(),
+        };
+        let layers = match (desc.layer, num_layers) {
+            (None, n) => (0, n.unwrap_or(1)),
+            (Some(l), Some(n)) if l < n => (l, 1),
+            (Some(l), Some(n)) => return Err(f::ResourceViewError::Layer(t::LayerError::OutOfBounds(l, n))),
+            (Some(_), None) => return Err(f::ResourceViewError::Layer(t::LayerError::NotExpected(info.kind))),
+        };
+        let levels = (desc.min, cmp::min(desc.max, info.levels - 1));
+        if levels.0 > levels.1 {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+        if desc.swizzle != Swizzle::new() && !caps.texture_swizzle_supported {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+
+        let view = if caps.texture_view_supported && caps.immutable_storage_supported {
+            let target = match (info.kind, desc.layer) {
+                (t::Kind::D1Array(..), Some(_)) => gl::TEXTURE_1D,
+                (t::Kind::D2Array(_, _, _, t::AaMode::Single), Some(_)) => gl::TEXTURE_2D,
+                (t::Kind::D2Array(..), Some(_)) => gl::TEXTURE_2D_MULTISAMPLE,
+                (t::Kind::Cube(_), Some(_)) | (t::Kind::CubeArray(..), Some(_)) => gl::TEXTURE_2D,
+                (kind, _) => tex::kind_to_gl(kind),
+            };
+            let format = match tex::format_to_glfull(Format(info.format, desc.channel)) {
+                Ok(format) => format,
+                Err(_) => return Err(f::ResourceViewError::Channel(desc.channel)),
+            };
+            let object = tex::make_view(gl, name, target, format, levels, layers);
+            if desc.swizzle != Swizzle::new() {
+                tex::set_swizzle(gl, target, desc.swizzle);
+            }
+            ResourceView {
+                object: object,
+                bind: target,
+                owned: true,
+                params: None,
+            }
+        } else {
+            // without views, the level range and the swizzle are set on the texture
+            // when binding the view, but single layers can't be selected
+            if desc.layer.is_some() {
+                return Err(f::ResourceViewError::Unsupported)
+            }
+            ResourceView::new_texture(name, info.kind, levels, desc.swizzle)
+        };
+        if let Err(err) = self.share.check() {
+            panic!("Error {:?} creating texture SRV: {:?}, desc: {:?}", err, info, desc)
+        }
+        Ok(self.share.handles.borrow_mut().make_texture_srv(view, htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::UnorderedDesc)
+                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        if !self.share.capabilities.unordered_access_view_supported {
+            return Err(f::ResourceViewError::Unsupported)
+        }
+        let info = htex.get_info();
+        if !info.bind.contains(UNORDERED_ACCESS) {
+            return Err(f::ResourceViewError::NoBindFlag)
+        }
+        if desc.level >= info.levels {
+            return Err(f::ResourceViewError::Level(desc.level))
+        }
+        // the image unit reinterprets the texels with the format of the view
+        let format = Format(info.format, desc.channel);
+        let gl_format = match tex::format_to_glfull(format) {
+            Ok(gl_format) if tex::is_image_format(format) => gl_format,
+            _ => return Err(f::ResourceViewError::Channel(desc.channel)),
+        };
         match self.frame_handles.ref_texture(htex) {
             &NewTexture::Surface(_) => Err(f::ResourceViewError::NoBindFlag),
             &NewTexture::Texture(t) => {
-                //TODO: use the view descriptor
-                let view = ResourceView::new_texture(t, htex.get_info().kind);
-                Ok(self.share.handles.borrow_mut().make_texture_srv(view, htex))
+                let layered = match info.kind {
+                    t::Kind::D1(_) | t::Kind::D2(..) => false,
+                    _ => true,
+                };
+                let view = UnorderedView::Image {
+                    object: t,
+                    level: desc.level,
+                    layered: layered,
+                    format: gl_format,
+                };
+                if let Err(err) = self.share.check() {
+                    panic!("Error {:?} creating texture UAV: {:?}", err, info)
+                }
+                Ok(self.share.handles.borrow_mut().make_texture_uav(view, htex))
             },
         }
     }
 
-    fn view_texture_as_unordered_access_raw(&mut self, _htex: &handle::RawTexture<R>)
-                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
-        Err(f::ResourceViewError::Unsupported) //TODO
-    }
-
     fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::RenderDesc)
                                          -> Result<handle::RawRenderTargetView<R>, f::TargetViewError> {
         self.view_texture_as_target(htex, desc.level, desc.layer)
@@ -510,17 +824,40 @@ impl f::Factory<R> for Factory {
         self.share.handles.borrow_mut().make_sampler(sam, info)
     }
 
-    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
-                               -> Result<mapping::Reader<'b, R, T>,
-                                         mapping::Error>
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<R>, query::CreationError> {
+        let caps = &self.share.private_caps;
+        let target = match kind {
+            query::Kind::Occlusion if caps.occlusion_query_supported => gl::SAMPLES_PASSED,
+            query::Kind::Timestamp if caps.timer_query_supported => gl::TIMESTAMP,
+            query::Kind::PipelineStatistics(stat) if caps.pipeline_statistics_query_supported =>
+                statistic_to_target(stat),
+            query::Kind::PrimitivesWritten if caps.transform_feedback_supported =>
+                gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN,
+            _ => return Err(query::CreationError::Unsupported(kind)),
+        };
+        let mut name = 0 as gl::types::GLuint;
+        unsafe {
+            self.share.context.GenQueries(1, &mut name);
+        }
+        let query = Query {
+            object: name,
+            target: target,
+        };
+        Ok(self.share.handles.borrow_mut().make_query(query, kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
         where T: Copy
     {
         let gl = &self.share.context;
         let handles = &mut self.frame_handles;
         unsafe {
-            mapping::read(buf.raw(), |mapping| match mapping.kind {
+            mapping::read(buf.raw(), range, |mapping, _| match mapping.kind {
                 MappingKind::Persistent(ref mut status) =>
                     status.cpu_access(|fence| wait_fence(&handles.ref_fence(&fence), gl)),
+                MappingKind::Coherent(_) => (),
                 MappingKind::Temporary =>
                     temporary_ensure_mapped(&mut mapping.pointer,
                                             role_to_target(buf.get_info().role),
@@ -531,17 +868,18 @@ impl f::Factory<R> for Factory {
         }
     }
 
-    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
-                                -> Result<mapping::Writer<'b, R, T>,
-                                          mapping::Error>
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
         where T: Copy
     {
         let gl = &self.share.context;
         let handles = &mut self.frame_handles;
         unsafe {
-            mapping::write(buf.raw(), |mapping| match mapping.kind {
+            mapping::write(buf.raw(), range, |mapping, bytes| match mapping.kind {
                 MappingKind::Persistent(ref mut status) =>
-                    status.cpu_write_access(|fence| wait_fence(&handles.ref_fence(&fence), gl)),
+                    status.cpu_write_access(bytes, |fence| wait_fence(&handles.ref_fence(&fence), gl)),
+                MappingKind::Coherent(_) => (),
                 MappingKind::Temporary =>
                     temporary_ensure_mapped(&mut mapping.pointer,
                                             role_to_target(buf.get_info().role),
@@ -551,6 +889,43 @@ impl f::Factory<R> for Factory {
             })
         }
     }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let gl = &self.share.context;
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::WRITE, |mapping, bytes| match mapping.kind {
+                MappingKind::Persistent(ref mut status) => {
+                    gl.BindBuffer(role_to_target(buf.get_info().role), *buf.raw().resource());
+                    gl.FlushMappedBufferRange(role_to_target(buf.get_info().role),
+                                              bytes.start as gl::types::GLintptr,
+                                              (bytes.end - bytes.start) as gl::types::GLsizeiptr);
+                    status.flushed(bytes);
+                },
+                // coherent writes are visible without flushing,
+                // and temporary mappings are flushed when unmapped
+                MappingKind::Coherent(_) | MappingKind::Temporary => (),
+            })
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let gl = &self.share.context;
+        let handles = &mut self.frame_handles;
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::READ, |mapping, _| match mapping.kind {
+                MappingKind::Persistent(ref mut status) |
+                MappingKind::Coherent(ref mut status) =>
+                    status.cpu_access(|fence| wait_fence(&handles.ref_fence(&fence), gl)),
+                MappingKind::Temporary => (),
+            })
+        }
+    }
 }
 
 pub fn wait_fence(fence: &Fence, gl: &gl::Gl) {
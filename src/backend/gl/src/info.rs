@@ -13,9 +13,9 @@
 // limitations under the License.
 
 use std::collections::HashSet;
-use std::{ffi, fmt, mem, str};
+use std::{cmp, ffi, fmt, mem, str};
 use gl;
-use core::Capabilities;
+use core::{Capabilities, Limits};
 
 
 /// A version number for a specific component of an OpenGL implementation
@@ -138,6 +138,14 @@ fn get_usize(gl: &gl::Gl, name: gl::types::GLenum) -> usize {
     value as usize
 }
 
+fn get_indexed_u32(gl: &gl::Gl, name: gl::types::GLenum) -> [u32; 3] {
+    let mut values = [0 as gl::types::GLint; 3];
+    for (i, value) in values.iter_mut().enumerate() {
+        unsafe { gl.GetIntegeri_v(name, i as gl::types::GLuint, value) };
+    }
+    [values[0] as u32, values[1] as u32, values[2] as u32]
+}
+
 unsafe fn c_str_as_static_str(c_str: *const i8) -> &'static str {
     mem::transmute(str::from_utf8(ffi::CStr::from_ptr(c_str as *const _).to_bytes()).unwrap())
 }
@@ -171,6 +179,21 @@ pub struct PrivateCaps {
     pub buffer_storage_supported: bool,
     pub clear_buffer_supported: bool,
     pub frag_data_location_supported: bool,
+    pub multi_draw_indirect_supported: bool,
+    pub map_buffer_range_supported: bool,
+    pub occlusion_query_supported: bool,
+    pub timer_query_supported: bool,
+    pub pipeline_statistics_query_supported: bool,
+    pub viewport_array_supported: bool,
+    pub copy_image_supported: bool,
+    pub storage_buffer_supported: bool,
+    pub texture_view_supported: bool,
+    pub texture_swizzle_supported: bool,
+    pub transform_feedback_supported: bool,
+    pub internal_format_query_supported: bool,
+    pub internal_format_query2_supported: bool,
+    pub debug_output_supported: bool,
+    pub is_embedded: bool,
 }
 
 /// OpenGL implementation information
@@ -231,7 +254,8 @@ impl Info {
 pub fn get(gl: &gl::Gl) -> (Info, Capabilities, PrivateCaps) {
     let info = Info::get(gl);
     let tessellation_supported =           info.is_version_or_extension_supported(4, 0, "GL_ARB_tessellation_shader");
-    let caps = Capabilities {
+    let buffer_storage_supported =         info.is_version_or_extension_supported(4, 4, "GL_ARB_buffer_storage");
+    let mut caps = Capabilities {
         max_vertex_count: get_usize(gl, gl::MAX_ELEMENTS_VERTICES),
         max_index_count:  get_usize(gl, gl::MAX_ELEMENTS_INDICES),
         max_texture_size: get_usize(gl, gl::MAX_TEXTURE_SIZE),
@@ -243,26 +267,112 @@ pub fn get(gl: &gl::Gl) -> (Info, Capabilities, PrivateCaps) {
         vertex_base_supported:             info.is_version_or_extension_supported(3, 2, "GL_ARB_draw_elements_base_vertex"),
         srgb_color_supported:              info.is_version_or_extension_supported(3, 2, "GL_ARB_framebuffer_sRGB"),
         constant_buffer_supported:         info.is_version_or_extension_supported(3, 1, "GL_ARB_uniform_buffer_object"),
-        unordered_access_view_supported:   info.is_version_supported(4, 0), //TODO: extension
+        constant_buffer_range_supported:   info.is_version_or_extension_supported(3, 1, "GL_ARB_uniform_buffer_object"),
+        unordered_access_view_supported:   info.is_version_or_extension_supported(4, 2, "GL_ARB_shader_image_load_store") |
+                                           info.is_embedded_version_supported(3, 1),
         separate_blending_slots_supported: info.is_version_or_extension_supported(4, 0, "GL_ARB_draw_buffers_blend"),
         copy_buffer_supported:             info.is_version_or_extension_supported(3, 1, "GL_ARB_copy_buffer") |
                                            info.is_embedded_version_supported(3, 0) |
                                           (info.is_embedded_version_supported(2, 0) & info.is_extension_supported("GL_NV_copy_buffer")),
+        compute_supported:                 info.is_version_or_extension_supported(4, 3, "GL_ARB_compute_shader") |
+                                           info.is_embedded_version_supported(3, 1),
+        draw_indirect_supported:           info.is_version_or_extension_supported(4, 0, "GL_ARB_draw_indirect") |
+                                           info.is_embedded_version_supported(3, 1),
+        persistent_mapping_supported:      buffer_storage_supported,
+        alpha_to_coverage_supported:       true,
+        sample_mask_supported:             info.is_version_or_extension_supported(3, 2, "GL_ARB_texture_multisample") |
+                                           info.is_embedded_version_supported(3, 1),
+        depth_clamp_supported:             info.is_version_or_extension_supported(3, 2, "GL_ARB_depth_clamp"),
+        depth_bias_clamp_supported:        info.is_version_or_extension_supported(4, 6, "GL_ARB_polygon_offset_clamp"),
+        limits: Limits::default(),
     };
+    caps.limits = get_limits(gl, &info, &caps);
     let private = PrivateCaps {
         array_buffer_supported:            info.is_version_or_extension_supported(3, 0, "GL_ARB_vertex_array_object"),
         frame_buffer_supported:            info.is_version_or_extension_supported(3, 0, "GL_ARB_framebuffer_object") |
                                            info.is_embedded_version_supported(2, 0),
         immutable_storage_supported:       info.is_version_or_extension_supported(4, 2, "GL_ARB_texture_storage"),
         sampler_objects_supported:         info.is_version_or_extension_supported(3, 3, "GL_ARB_sampler_objects"),
-        program_interface_supported:       info.is_version_or_extension_supported(4, 3, "GL_ARB_program_interface_query"),
-        buffer_storage_supported:          info.is_version_or_extension_supported(4, 4, "GL_ARB_buffer_storage"),
+        program_interface_supported:       info.is_version_or_extension_supported(4, 3, "GL_ARB_program_interface_query") |
+                                           info.is_embedded_version_supported(3, 1),
+        buffer_storage_supported:          buffer_storage_supported,
         clear_buffer_supported:            info.is_version_supported(3, 0) | info.is_embedded_version_supported(3, 0),
         frag_data_location_supported:      !info.version.is_embedded,
+        multi_draw_indirect_supported:     info.is_version_or_extension_supported(4, 3, "GL_ARB_multi_draw_indirect"),
+        map_buffer_range_supported:        info.is_version_or_extension_supported(3, 0, "GL_ARB_map_buffer_range") |
+                                           info.is_embedded_version_supported(3, 0),
+        occlusion_query_supported:         !info.version.is_embedded,
+        timer_query_supported:             info.is_version_or_extension_supported(3, 3, "GL_ARB_timer_query"),
+        pipeline_statistics_query_supported: info.is_version_or_extension_supported(4, 6, "GL_ARB_pipeline_statistics_query"),
+        viewport_array_supported:          info.is_version_or_extension_supported(4, 1, "GL_ARB_viewport_array"),
+        copy_image_supported:              info.is_version_or_extension_supported(4, 3, "GL_ARB_copy_image") |
+                                           info.is_embedded_version_supported(3, 2),
+        storage_buffer_supported:          info.is_version_or_extension_supported(4, 3, "GL_ARB_shader_storage_buffer_object") |
+                                           info.is_embedded_version_supported(3, 1),
+        texture_view_supported:            info.is_version_or_extension_supported(4, 3, "GL_ARB_texture_view"),
+        texture_swizzle_supported:         info.is_version_or_extension_supported(3, 3, "GL_ARB_texture_swizzle") |
+                                           info.is_embedded_version_supported(3, 0),
+        transform_feedback_supported:      info.is_version_or_extension_supported(3, 0, "GL_EXT_transform_feedback") |
+                                           info.is_embedded_version_supported(3, 0),
+        internal_format_query_supported:   info.is_version_or_extension_supported(4, 2, "GL_ARB_internalformat_query") |
+                                           info.is_embedded_version_supported(3, 0),
+        internal_format_query2_supported:  info.is_version_or_extension_supported(4, 3, "GL_ARB_internalformat_query2"),
+        debug_output_supported:            info.is_version_or_extension_supported(4, 3, "GL_KHR_debug") |
+                                           info.is_embedded_version_supported(3, 2),
+        is_embedded:                       info.version.is_embedded,
     };
     (info, caps, private)
 }
 
+/// Query the limits of the device, leaving zeros for the unsupported features.
+fn get_limits(gl: &gl::Gl, info: &Info, caps: &Capabilities) -> Limits {
+    let mut limits = Limits {
+        max_texture_cube_size: get_usize(gl, gl::MAX_CUBE_MAP_TEXTURE_SIZE),
+        max_anisotropy: 1,
+        max_samples: 1,
+        max_color_targets: 1,
+        max_vertex_attributes: get_usize(gl, gl::MAX_VERTEX_ATTRIBS),
+        max_viewports: 1,
+        max_resource_views: get_usize(gl, gl::MAX_TEXTURE_IMAGE_UNITS),
+        .. Limits::default()
+    };
+    if !info.version.is_embedded | info.is_embedded_version_supported(3, 0) {
+        limits.max_texture_3d_size = get_usize(gl, gl::MAX_3D_TEXTURE_SIZE);
+        limits.max_color_targets = get_usize(gl, gl::MAX_DRAW_BUFFERS);
+    }
+    if info.is_version_or_extension_supported(3, 0, "GL_EXT_texture_array") |
+       info.is_embedded_version_supported(3, 0) {
+        limits.max_texture_array_layers = get_usize(gl, gl::MAX_ARRAY_TEXTURE_LAYERS);
+    }
+    if info.is_version_or_extension_supported(3, 0, "GL_ARB_framebuffer_object") |
+       info.is_embedded_version_supported(3, 0) {
+        limits.max_samples = cmp::min(get_usize(gl, gl::MAX_SAMPLES), 0xFF) as u8;
+    }
+    if info.is_extension_supported("GL_EXT_texture_filter_anisotropic") {
+        let mut value = 1.0 as gl::types::GLfloat;
+        unsafe { gl.GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut value) };
+        limits.max_anisotropy = value.min(255.0) as u8;
+    }
+    if info.is_version_or_extension_supported(4, 1, "GL_ARB_viewport_array") {
+        limits.max_viewports = get_usize(gl, gl::MAX_VIEWPORTS);
+    }
+    if caps.constant_buffer_supported {
+        limits.max_constant_buffers = cmp::min(get_usize(gl, gl::MAX_VERTEX_UNIFORM_BLOCKS),
+                                               get_usize(gl, gl::MAX_FRAGMENT_UNIFORM_BLOCKS));
+        limits.max_constant_buffer_size = get_usize(gl, gl::MAX_UNIFORM_BLOCK_SIZE);
+        limits.constant_buffer_offset_alignment = get_usize(gl, gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT);
+    }
+    if caps.unordered_access_view_supported {
+        limits.max_unordered_views = get_usize(gl, gl::MAX_IMAGE_UNITS);
+    }
+    if caps.compute_supported {
+        limits.max_compute_group_count = get_indexed_u32(gl, gl::MAX_COMPUTE_WORK_GROUP_COUNT);
+        limits.max_compute_group_size = get_indexed_u32(gl, gl::MAX_COMPUTE_WORK_GROUP_SIZE);
+        limits.max_compute_invocations = get_usize(gl, gl::MAX_COMPUTE_WORK_GROUP_INVOCATIONS) as u32;
+    }
+    limits
+}
+
 #[cfg(test)]
 mod tests {
     use super::Version;
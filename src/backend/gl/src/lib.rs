@@ -24,17 +24,23 @@ extern crate gfx_gl as gl;
 extern crate gfx_core as core;
 
 use std::cell::RefCell;
+use std::cmp;
+use std::mem;
 use std::rc::Rc;
+use std::slice;
 use core::{self as c, handle, state as s, format, pso, texture, memory, command as com, buffer};
 use core::target::{Layer, Level};
-use command::{Command, DataBuffer};
+use command::{Command, DataBuffer, RawOffset};
 use factory::MappingKind;
 
 pub use self::command::CommandBuffer;
 pub use self::factory::Factory;
+pub use self::debug::{Config as DebugConfig, Message as DebugMessage,
+                      Severity as DebugSeverity, Source as DebugSource};
 pub use self::info::{Info, PlatformName, Version};
 
 mod command;
+mod debug;
 mod factory;
 mod info;
 mod shade;
@@ -56,6 +62,12 @@ pub struct Fence(gl::types::GLsync);
 unsafe impl Send for Fence {}
 unsafe impl Sync for Fence {}
 
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Query {
+    object: gl::types::GLuint,
+    target: gl::types::GLenum,
+}
+
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Resources {}
 
@@ -68,9 +80,10 @@ impl c::Resources for Resources {
     type RenderTargetView    = TargetView;
     type DepthStencilView    = TargetView;
     type ShaderResourceView  = ResourceView;
-    type UnorderedAccessView = ();
+    type UnorderedAccessView = UnorderedView;
     type Sampler             = FatSampler;
     type Fence               = Fence;
+    type Query               = Query;
     type Mapping             = factory::MappingGate;
 }
 
@@ -95,7 +108,12 @@ pub struct PipelineState {
     input: [Option<BufferElement>; c::MAX_VERTEX_ATTRIBUTES],
     scissor: bool,
     rasterizer: s::Rasterizer,
+    depth_bias: Option<c::pso::DepthBias>,
+    depth_clamp: bool,
+    multisample: c::pso::MultisampleInfo,
     output: OutputMerger,
+    /// Primitive mode of the transform feedback, for the programs capturing their output.
+    capture: Option<gl::types::GLenum>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
@@ -109,14 +127,19 @@ pub struct ResourceView {
     object: Texture,
     bind: gl::types::GLenum,
     owned: bool,
+    /// Level range and swizzle to set on the texture when binding it,
+    /// if the view isn't a separate texture view.
+    params: Option<((Level, Level), format::Swizzle)>,
 }
 
 impl ResourceView {
-    pub fn new_texture(t: Texture, kind: texture::Kind) -> ResourceView {
+    pub fn new_texture(t: Texture, kind: texture::Kind, levels: (Level, Level),
+                       swizzle: format::Swizzle) -> ResourceView {
         ResourceView {
             object: t,
             bind: tex::kind_to_gl(kind),
             owned: false,
+            params: Some((levels, swizzle)),
         }
     }
     pub fn new_buffer(b: Texture) -> ResourceView {
@@ -124,10 +147,25 @@ impl ResourceView {
             object: b,
             bind: gl::TEXTURE_BUFFER,
             owned: true,
+            params: None,
         }
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum UnorderedView {
+    /// A shader storage buffer.
+    Buffer(Buffer),
+    /// An image of a texture level, with all of its layers if `layered`,
+    /// and the internal format to access it with.
+    Image {
+        object: Texture,
+        level: Level,
+        layered: bool,
+        format: gl::types::GLenum,
+    },
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct FatSampler {
     object: Sampler,
@@ -214,11 +252,27 @@ pub struct Share {
     capabilities: c::Capabilities,
     private_caps: info::PrivateCaps,
     handles: RefCell<handle::Manager<Resources>>,
+    /// Receiver of the driver messages, shared with the factories
+    /// so that their errors are not blamed on the next submission.
+    debug_output: RefCell<Option<Box<debug::Output>>>,
 }
 
 impl Share {
-    /// Fails during a debug build if the implementation's error flag was set.
+    /// Take the texts of the error messages reported by the driver since the last call.
+    pub fn take_debug_errors(&self) -> Vec<String> {
+        match *self.debug_output.borrow() {
+            Some(ref output) => output.take_errors().into_iter().map(|m| m.text).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Fails if the driver reported an error message since the last check,
+    /// or during a debug build if the implementation's error flag was set.
     pub fn check(&self) -> Result<(), Error> {
+        // the messages are logged as soon as they are reported
+        if !self.take_debug_errors().is_empty() {
+            return Err(Error::UnknownError)
+        }
         if cfg!(debug_assertions) {
             let gl = &self.context;
             let err = Error::from_error_code(unsafe { gl.GetError() });
@@ -235,8 +289,11 @@ pub struct Device {
     info: Info,
     share: Rc<Share>,
     vao: ArrayBuffer,
+    blit_fbos: [FrameBuffer; 2],
     frame_handles: handle::Manager<Resources>,
     max_resource_count: Option<usize>,
+    /// How the errors of the commands are handled, once the debug output is enabled.
+    debug_config: Option<DebugConfig>,
 }
 
 impl Device {
@@ -278,6 +335,13 @@ impl Device {
                 gl.BindVertexArray(vao);
             }
         }
+        // create the frame buffers for texture blits
+        let mut blit_fbos = [0; 2];
+        if private.frame_buffer_supported {
+            unsafe {
+                gl.GenFramebuffers(2, blit_fbos.as_mut_ptr());
+            }
+        }
         // create the shared context
         let handles = handle::Manager::new();
         let share = Share {
@@ -285,6 +349,7 @@ impl Device {
             capabilities: caps,
             private_caps: private,
             handles: RefCell::new(handles),
+            debug_output: RefCell::new(None),
         };
         if let Err(err) = share.check() {
             panic!("Error {:?} after initialization", err)
@@ -294,8 +359,33 @@ impl Device {
             info: info,
             share: Rc::new(share),
             vao: vao,
+            blit_fbos: blit_fbos,
             frame_handles: handle::Manager::new(),
             max_resource_count: Some(999999),
+            debug_config: None,
+        }
+    }
+
+    /// Log the driver messages with `GL_KHR_debug`, and configure how the errors
+    /// raised by the submitted commands are handled. Returns `false` if the debug
+    /// output is not supported, in which case only `glGetError` is checked, in
+    /// debug builds. Until then, the errors are only logged.
+    pub fn enable_debug_output(&mut self, config: DebugConfig) -> bool {
+        self.debug_config = Some(config);
+        if !self.share.private_caps.debug_output_supported {
+            return false
+        }
+        let mut debug_output = self.share.debug_output.borrow_mut();
+        let output = debug_output.take().unwrap_or_else(|| Box::new(debug::Output::new()));
+        debug::install(&self.share.context, &output, config);
+        *debug_output = Some(output);
+        true
+    }
+
+    /// Stop logging the driver messages.
+    pub fn disable_debug_output(&mut self) {
+        if self.share.debug_output.borrow_mut().take().is_some() {
+            debug::uninstall(&self.share.context);
         }
     }
 
@@ -397,10 +487,165 @@ impl Device {
         unsafe { gl.FramebufferTexture(point, attachment, 0, 0) };
     }
 
+    fn bind_blit_region(&self, point: gl::types::GLenum, fbo: FrameBuffer, attachment: gl::types::GLenum,
+                        region: &texture::TextureCopyRegion<NewTexture>, layer: texture::Size) {
+        let gl = &self.share.context;
+        let level = region.info.mipmap as gl::types::GLint;
+        let texture = match region.texture {
+            NewTexture::Surface(0) => {
+                // the main targets live in the default frame buffer
+                unsafe { gl.BindFramebuffer(point, 0) };
+                return
+            },
+            NewTexture::Surface(surface) => unsafe {
+                gl.BindFramebuffer(point, fbo);
+                gl.FramebufferRenderbuffer(point, attachment, gl::RENDERBUFFER, surface);
+                return
+            },
+            NewTexture::Texture(texture) => texture,
+        };
+        unsafe { gl.BindFramebuffer(point, fbo) };
+        let layer = region.info.zoffset + layer;
+        match (region.kind, region.cube_face) {
+            (texture::Kind::D1(_), _) | (texture::Kind::D2(..), _) => unsafe {
+                gl.FramebufferTexture(point, attachment, texture, level);
+            },
+            (texture::Kind::Cube(_), Some(face)) => unsafe {
+                gl.FramebufferTexture2D(point, attachment, tex::cube_face_to_gl(face), texture, level);
+            },
+            (_, face) => {
+                let layer = match face {
+                    Some(face) => {
+                        let index = texture::CUBE_FACES.iter().position(|&f| f == face).unwrap();
+                        layer as usize * 6 + index
+                    },
+                    None => layer as usize,
+                };
+                unsafe {
+                    gl.FramebufferTextureLayer(point, attachment, texture, level,
+                                               layer as gl::types::GLint);
+                }
+            },
+        }
+    }
+
+    fn blit_texture(&mut self, src: &texture::TextureCopyRegion<NewTexture>,
+                    dst: &texture::TextureCopyRegion<NewTexture>, filter: gl::types::GLenum) {
+        use core::format::SurfaceType as S;
+        type GLint = gl::types::GLint;
+        if !self.share.private_caps.frame_buffer_supported {
+            error!("Texture blits require frame buffer objects");
+            return
+        }
+        let (attachment, mask) = match src.info.format.0 {
+            S::D16 | S::D24 | S::D32 => (gl::DEPTH_ATTACHMENT, gl::DEPTH_BUFFER_BIT),
+            S::D24_S8 => (gl::DEPTH_STENCIL_ATTACHMENT, gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT),
+            _ => (gl::COLOR_ATTACHMENT0, gl::COLOR_BUFFER_BIT),
+        };
+        let (read_fbo, draw_fbo) = (self.blit_fbos[0], self.blit_fbos[1]);
+        let (si, di) = (src.info, dst.info);
+        state::unlock_color_mask(&self.share.context);
+        for layer in 0 .. cmp::max(1, cmp::min(si.depth, di.depth)) {
+            self.bind_blit_region(gl::READ_FRAMEBUFFER, read_fbo, attachment, src, layer);
+            self.bind_blit_region(gl::DRAW_FRAMEBUFFER, draw_fbo, attachment, dst, layer);
+            let gl = &self.share.context;
+            unsafe {
+                gl.BlitFramebuffer(
+                    si.xoffset as GLint,
+                    si.yoffset as GLint,
+                    (si.xoffset + si.width) as GLint,
+                    (si.yoffset + cmp::max(1, si.height)) as GLint,
+                    di.xoffset as GLint,
+                    di.yoffset as GLint,
+                    (di.xoffset + di.width) as GLint,
+                    (di.yoffset + cmp::max(1, di.height)) as GLint,
+                    mask,
+                    filter
+                );
+            }
+        }
+        // detach the textures, so that they don't outlive their handles
+        let gl = &self.share.context;
+        for &(point, fbo) in &[(gl::READ_FRAMEBUFFER, read_fbo), (gl::DRAW_FRAMEBUFFER, draw_fbo)] {
+            unsafe {
+                gl.BindFramebuffer(point, fbo);
+                gl.FramebufferTexture(point, attachment, 0, 0);
+            }
+        }
+    }
+
+    fn copy_texture(&mut self, src: &texture::TextureCopyRegion<NewTexture>,
+                    dst: &texture::TextureCopyRegion<NewTexture>) {
+        fn target_layer(region: &texture::TextureCopyRegion<NewTexture>)
+                        -> Option<(gl::types::GLuint, gl::types::GLenum, gl::types::GLint)> {
+            let face = region.cube_face.map_or(0, |face| {
+                texture::CUBE_FACES.iter().position(|&f| f == face).unwrap()
+            });
+            match region.texture {
+                NewTexture::Surface(0) => None,
+                NewTexture::Surface(surface) => Some((surface, gl::RENDERBUFFER, 0)),
+                NewTexture::Texture(texture) => {
+                    let layer = match region.kind {
+                        texture::Kind::CubeArray(..) => region.info.zoffset as usize * 6 + face,
+                        texture::Kind::Cube(_) if region.cube_face.is_some() => face,
+                        _ => region.info.zoffset as usize,
+                    };
+                    Some((texture, tex::kind_to_gl(region.kind), layer as gl::types::GLint))
+                },
+            }
+        }
+        type GLint = gl::types::GLint;
+        type GLsizei = gl::types::GLsizei;
+        if self.share.private_caps.copy_image_supported {
+            if let (Some(s), Some(d)) = (target_layer(src), target_layer(dst)) {
+                let (si, di) = (src.info, dst.info);
+                let gl = &self.share.context;
+                unsafe {
+                    gl.CopyImageSubData(
+                        s.0, s.1, si.mipmap as GLint,
+                        si.xoffset as GLint, si.yoffset as GLint, s.2,
+                        d.0, d.1, di.mipmap as GLint,
+                        di.xoffset as GLint, di.yoffset as GLint, d.2,
+                        si.width as GLsizei,
+                        cmp::max(1, si.height) as GLsizei,
+                        cmp::max(1, si.depth) as GLsizei
+                    );
+                }
+                return
+            }
+        }
+        self.blit_texture(src, dst, gl::NEAREST);
+    }
+
     fn reset_state(&mut self) {
+        // the errors raised outside of the submissions, like by direct GL calls,
+        // were logged when they occurred and don't concern the following commands
+        if let Err(err) = self.check_errors() {
+            warn!("Error {} raised outside of the submissions", err);
+        }
         let data = DataBuffer::new();
         for com in command::RESET.iter() {
             self.process(com, &data);
+            if let Err(err) = self.check_errors() {
+                let message = format!("Error {} resetting the state with command: {:?}", err, com);
+                if self.debug_config.map_or(false, |config| config.panic_on_error) {
+                    panic!("{}", message)
+                }
+                error!("{}", message);
+            }
+        }
+    }
+
+    /// Collect the errors raised since the last check.
+    fn check_errors(&self) -> Result<(), String> {
+        let mut errors = self.share.take_debug_errors();
+        if let Err(err) = self.share.check() {
+            errors.push(format!("{:?}", err));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
         }
     }
 
@@ -474,14 +719,58 @@ impl Device {
             Command::BindProgram(program) => unsafe {
                 self.share.context.UseProgram(program);
             },
-            Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot)) => unsafe {
-                self.share.context.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer);
+            Command::BindConstantBuffer(pso::ConstantBufferParam(buffer, _, slot, range)) => unsafe {
+                let gl = &self.share.context;
+                match range {
+                    Some(pso::BufferRange { offset, size }) =>
+                        gl.BindBufferRange(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer,
+                                           offset as gl::types::GLintptr, size as gl::types::GLsizeiptr),
+                    None =>
+                        gl.BindBufferBase(gl::UNIFORM_BUFFER, slot as gl::types::GLuint, buffer),
+                }
             },
             Command::BindResourceView(pso::ResourceViewParam(view, _, slot)) => unsafe {
-                self.share.context.ActiveTexture(gl::TEXTURE0 + slot as gl::types::GLenum);
-                self.share.context.BindTexture(view.bind, view.object);
+                let gl = &self.share.context;
+                gl.ActiveTexture(gl::TEXTURE0 + slot as gl::types::GLenum);
+                gl.BindTexture(view.bind, view.object);
+                // the views of the same texture share its parameters, so they are set with every bind
+                if let Some((levels, swizzle)) = view.params {
+                    tex::set_mipmap_range(gl, view.bind, levels);
+                    if self.share.private_caps.texture_swizzle_supported {
+                        tex::set_swizzle(gl, view.bind, swizzle);
+                    }
+                }
+            },
+            Command::BindUnorderedView(pso::UnorderedViewParam(view, _, slot)) => unsafe {
+                let gl = &self.share.context;
+                match view {
+                    UnorderedView::Buffer(buffer) =>
+                        gl.BindBufferBase(gl::SHADER_STORAGE_BUFFER, slot as gl::types::GLuint, buffer),
+                    UnorderedView::Image { object, level, layered, format } =>
+                        gl.BindImageTexture(slot as gl::types::GLuint, object, level as gl::types::GLint,
+                                            if layered { gl::TRUE } else { gl::FALSE }, 0,
+                                            gl::READ_WRITE, format),
+                }
+            },
+            Command::BindStreamOutput(pso::StreamOutputParam(buffer, offset)) => unsafe {
+                let gl = &self.share.context;
+                if offset == 0 {
+                    gl.BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, 0, buffer);
+                } else {
+                    let mut size = 0;
+                    gl.BindBuffer(gl::TRANSFORM_FEEDBACK_BUFFER, buffer);
+                    gl.GetBufferParameteriv(gl::TRANSFORM_FEEDBACK_BUFFER, gl::BUFFER_SIZE, &mut size);
+                    if offset < size as usize {
+                        gl.BindBufferRange(gl::TRANSFORM_FEEDBACK_BUFFER, 0, buffer,
+                                           offset as gl::types::GLintptr,
+                                           (size as usize - offset) as gl::types::GLsizeiptr);
+                    } else {
+                        error!("Stream output offset {} is past the end of Buffer({}) of {} bytes",
+                               offset, buffer, size);
+                        gl.BindBufferBase(gl::TRANSFORM_FEEDBACK_BUFFER, 0, 0);
+                    }
+                }
             },
-            Command::BindUnorderedView(_uav) => unimplemented!(),
             Command::BindSampler(pso::SamplerParam(sampler, _, slot), bind_opt) => {
                 let gl = &self.share.context;
                 if self.share.private_caps.sampler_objects_supported {
@@ -537,7 +826,7 @@ impl Device {
                     error!("Tried to bind FBO {} without FBO support!", frame_buffer);
                 }
             },
-            Command::BindUniform(loc, uniform) => {
+            Command::BindUniform(loc, ref uniform) => {
                 let gl = &self.share.context;
                 shade::bind_uniform(gl, loc as gl::types::GLint, uniform);
             },
@@ -545,11 +834,30 @@ impl Device {
                 let mask = (1 << (num as usize)) - 1;
                 state::bind_draw_color_buffers(&self.share.context, mask);
             },
-            Command::SetRasterizer(rast) => {
-                state::bind_rasterizer(&self.share.context, &rast, self.info.version.is_embedded);
+            Command::SetRasterizer(rast, bias, clamp) => {
+                let gl = &self.share.context;
+                state::bind_rasterizer(gl, &rast, self.info.version.is_embedded);
+                if let Some(bias) = bias {
+                    state::bind_depth_bias(gl, rast.method, bias, self.info.version.is_embedded);
+                }
+                if self.share.capabilities.depth_clamp_supported {
+                    state::bind_depth_clamp(gl, clamp);
+                }
+            },
+            Command::SetMultisample(ms) => {
+                state::bind_multisample(&self.share.context, ms, self.share.capabilities.sample_mask_supported);
+            },
+            Command::SetViewport(vp) => {
+                state::bind_viewport(&self.share.context, vp, self.info.version.is_embedded);
             },
-            Command::SetViewport(rect) => {
-                state::bind_viewport(&self.share.context, rect);
+            Command::SetViewportIndexed(index, vp) => {
+                if self.share.private_caps.viewport_array_supported {
+                    state::bind_viewport_indexed(&self.share.context, index, vp);
+                } else if index == 0 {
+                    state::bind_viewport(&self.share.context, vp, self.info.version.is_embedded);
+                } else {
+                    error!("Viewport arrays are not supported, ignoring viewport {}", index);
+                }
             },
             Command::SetScissor(rect) => {
                 state::bind_scissor(&self.share.context, rect);
@@ -603,6 +911,12 @@ impl Device {
                     Err(e) => error!("GL: {:?} failed: {:?}", cmd, e)
                 }
             },
+            Command::CopyTexture(ref src, ref dst) => {
+                self.copy_texture(src, dst);
+            },
+            Command::BlitTexture(ref src, ref dst, filter) => {
+                self.blit_texture(src, dst, filter);
+            },
             Command::UpdateBuffer(buffer, pointer, offset) => {
                 let data = data_buf.get(pointer);
                 factory::update_sub_buffer(&self.share.context, buffer,
@@ -665,7 +979,7 @@ impl Device {
                                 index_type,
                                 offset.0,
                                 num as gl::types::GLsizei,
-                                base_vertex as gl::types::GLint,
+                                base_vertex,
                             );
                         } else if base_vertex == 0 && base_instance != 0 {
                             gl.DrawElementsInstancedBaseInstance(
@@ -683,7 +997,7 @@ impl Device {
                                 index_type,
                                 offset.0,
                                 num as gl::types::GLsizei,
-                                base_vertex as gl::types::GLint,
+                                base_vertex,
                                 base_instance as gl::types::GLuint,
                             );
                         }
@@ -708,70 +1022,160 @@ impl Device {
                                 count as gl::types::GLsizei,
                                 index_type,
                                 offset.0,
-                                base_vertex as gl::types::GLint,
+                                base_vertex,
                             );
                         }
                     },
                 }
             },
-            Command::_Blit(mut s_rect, d_rect, mirror, _) => {
-                type GLint = gl::types::GLint;
-                // mirror
-                let mut s_end_x = s_rect.x + s_rect.w;
-                let mut s_end_y = s_rect.y + s_rect.h;
-                if mirror.intersects(c::target::MIRROR_X) {
-                    s_end_x = s_rect.x;
-                    s_rect.x += s_rect.w;
-                }
-                if mirror.intersects(c::target::MIRROR_Y) {
-                    s_end_y = s_rect.y;
-                    s_rect.y += s_rect.h;
-                }
-                // build mask
-                let flags = 0;
-                error!("Blit mask setup is not implemented");
-                /*TODO
-                if mask.intersects(d::target::COLOR) {
-                    flags |= gl::COLOR_BUFFER_BIT;
-                }
-                if mask.intersects(d::target::DEPTH) {
-                    flags |= gl::DEPTH_BUFFER_BIT;
-                }
-                if mask.intersects(d::target::STENCIL) {
-                    flags |= gl::STENCIL_BUFFER_BIT;
-                }*/
-                // build filter
-                let filter = if s_rect.w == d_rect.w && s_rect.h == d_rect.h {
-                    gl::NEAREST
-                }else {
-                    gl::LINEAR
-                };
-                // blit
+            Command::DrawIndirect(primitive, buffer, offset, count) => {
+                if !self.share.capabilities.draw_indirect_supported {
+                    // draw the records one by one, reading them back from the buffer
+                    let args = self.read_indirect_args::<com::DrawIndirectArgs>(buffer, offset.0 as usize, count as usize);
+                    for a in args {
+                        let instances = match (a.instance_count, a.first_instance) {
+                            (1, 0) => None,
+                            (num, base) => Some((num, base)),
+                        };
+                        self.process(&Command::Draw(primitive, a.first_vertex, a.vertex_count, instances), data_buf);
+                    }
+                    return
+                }
                 let gl = &self.share.context;
-                unsafe { gl.BlitFramebuffer(
-                    s_rect.x as GLint,
-                    s_rect.y as GLint,
-                    s_end_x as GLint,
-                    s_end_y as GLint,
-                    d_rect.x as GLint,
-                    d_rect.y as GLint,
-                    (d_rect.x + d_rect.w) as GLint,
-                    (d_rect.y + d_rect.h) as GLint,
-                    flags,
-                    filter
-                ) };
+                unsafe { gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer) };
+                if self.share.private_caps.multi_draw_indirect_supported {
+                    unsafe { gl.MultiDrawArraysIndirect(primitive, offset.0, count, 0) };
+                } else {
+                    let stride = mem::size_of::<com::DrawIndirectArgs>();
+                    for i in 0 .. count as usize {
+                        let ptr = (offset.0 as usize + i * stride) as *const gl::types::GLvoid;
+                        unsafe { gl.DrawArraysIndirect(primitive, ptr) };
+                    }
+                }
+            },
+            Command::DrawIndexedIndirect(primitive, index_type, buffer, offset, count) => {
+                if !self.share.capabilities.draw_indirect_supported {
+                    let index_size = if index_type == gl::UNSIGNED_INT { 4 } else { 2 };
+                    let args = self.read_indirect_args::<com::DrawIndexedIndirectArgs>(buffer, offset.0 as usize, count as usize);
+                    for a in args {
+                        let instances = match (a.instance_count, a.first_instance) {
+                            (1, 0) => None,
+                            (num, base) => Some((num, base)),
+                        };
+                        let index_offset = RawOffset((a.first_index as usize * index_size) as *const gl::types::GLvoid);
+                        self.process(&Command::DrawIndexed(primitive, index_type, index_offset, a.index_count,
+                                                           a.base_vertex, instances), data_buf);
+                    }
+                    return
+                }
+                let gl = &self.share.context;
+                unsafe { gl.BindBuffer(gl::DRAW_INDIRECT_BUFFER, buffer) };
+                if self.share.private_caps.multi_draw_indirect_supported {
+                    unsafe { gl.MultiDrawElementsIndirect(primitive, index_type, offset.0, count, 0) };
+                } else {
+                    let stride = mem::size_of::<com::DrawIndexedIndirectArgs>();
+                    for i in 0 .. count as usize {
+                        let ptr = (offset.0 as usize + i * stride) as *const gl::types::GLvoid;
+                        unsafe { gl.DrawElementsIndirect(primitive, index_type, ptr) };
+                    }
+                }
+            },
+            Command::Dispatch(x, y, z, barriers) => unsafe {
+                let gl = &self.share.context;
+                gl.DispatchCompute(x, y, z);
+                // make the writes to the bound unordered views visible to the next commands
+                if barriers != 0 {
+                    gl.MemoryBarrier(barriers);
+                }
+            },
+            Command::BeginCapture(mode) => unsafe {
+                let gl = &self.share.context;
+                // the capturing programs have no pixel shader to rasterize with
+                gl.Enable(gl::RASTERIZER_DISCARD);
+                gl.BeginTransformFeedback(mode);
+            },
+            Command::EndCapture => unsafe {
+                let gl = &self.share.context;
+                gl.EndTransformFeedback();
+                gl.Disable(gl::RASTERIZER_DISCARD);
+            },
+            Command::BeginQuery(query) => unsafe {
+                self.share.context.BeginQuery(query.target, query.object);
+            },
+            Command::EndQuery(query) => unsafe {
+                self.share.context.EndQuery(query.target);
+            },
+            Command::WriteTimestamp(query) => unsafe {
+                self.share.context.QueryCounter(query.object, query.target);
+            },
+            Command::PushDebugGroup(ptr) => unsafe {
+                if self.share.private_caps.debug_output_supported {
+                    let name = data_buf.get(ptr);
+                    self.share.context.PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0,
+                        name.len() as gl::types::GLsizei, name.as_ptr() as *const gl::types::GLchar);
+                }
+            },
+            Command::PopDebugGroup => unsafe {
+                if self.share.private_caps.debug_output_supported {
+                    self.share.context.PopDebugGroup();
+                }
+            },
+            Command::InsertDebugMarker(ptr) => unsafe {
+                if self.share.private_caps.debug_output_supported {
+                    let name = data_buf.get(ptr);
+                    self.share.context.DebugMessageInsert(gl::DEBUG_SOURCE_APPLICATION, gl::DEBUG_TYPE_MARKER, 0,
+                        gl::DEBUG_SEVERITY_NOTIFICATION, name.len() as gl::types::GLsizei,
+                        name.as_ptr() as *const gl::types::GLchar);
+                }
             },
         }
-        if let Err(err) = self.share.check() {
-            panic!("Error {:?} executing command: {:?}", err, cmd)
+    }
+
+    /// Read the records of an indirect draw back from the argument buffer,
+    /// for the devices that can't draw from it.
+    fn read_indirect_args<T: Copy>(&self, buffer: Buffer, offset: usize, count: usize) -> Vec<T> {
+        let gl = &self.share.context;
+        let size = count * mem::size_of::<T>();
+        let mut args = Vec::with_capacity(count);
+        if !self.share.private_caps.map_buffer_range_supported ||
+           !self.share.capabilities.copy_buffer_supported {
+            error!("Indirect draws are not supported, skipping the draws of Buffer({})", buffer);
+            return args
+        }
+        unsafe {
+            gl.BindBuffer(gl::COPY_READ_BUFFER, buffer);
+            let ptr = gl.MapBufferRange(gl::COPY_READ_BUFFER, offset as gl::types::GLintptr,
+                                        size as gl::types::GLsizeiptr, gl::MAP_READ_BIT);
+            if ptr.is_null() {
+                error!("Failed to map the indirect arguments of Buffer({})", buffer);
+                return args
+            }
+            args.extend_from_slice(slice::from_raw_parts(ptr as *const T, count));
+            gl.UnmapBuffer(gl::COPY_READ_BUFFER);
         }
+        args
     }
 
-    fn no_fence_submit(&mut self, cb: &mut command::CommandBuffer) {
+    fn no_fence_submit(&mut self, cb: &mut command::CommandBuffer) -> c::SubmissionResult<()> {
         self.reset_state();
-        for com in &cb.buf {
+        let mut result = Ok(());
+        for (i, com) in cb.buf.iter().enumerate() {
             self.process(com, &cb.data);
+            if let Err(err) = self.check_errors() {
+                let message = format!("Error {} executing command #{}: {:?}", err, i, com);
+                if self.debug_config.map_or(false, |config| config.panic_on_error) {
+                    panic!("{}", message)
+                }
+                error!("{}", message);
+                if self.debug_config.is_some() && result.is_ok() {
+                    result = Err(c::SubmissionError::DeviceError(message));
+                }
+            }
         }
+        if cb.is_capturing() {
+            self.process(&Command::EndCapture, &cb.data);
+        }
+        result
     }
 
     fn before_submit<'a>(&mut self, gpu_access: &'a com::AccessInfo<Resources>)
@@ -794,13 +1198,15 @@ impl Device {
             let target = factory::role_to_target(buffer.get_info().role);
             let status = match &mut mapping.kind {
                 &mut MappingKind::Persistent(ref mut status) => status,
+                &mut MappingKind::Coherent(_) => continue,
                 _ => unreachable!(),
             };
 
-            status.ensure_flushed(|| unsafe {
+            status.ensure_flushed(|range| unsafe {
                 gl.BindBuffer(target, *buffer.resource());
-                let size = buffer.get_info().size as isize;
-                gl.FlushMappedBufferRange(target, 0, size);
+                gl.FlushMappedBufferRange(target,
+                                          range.start as gl::types::GLintptr,
+                                          (range.end - range.start) as gl::types::GLsizeiptr);
             });
         }
     }
@@ -860,7 +1266,8 @@ impl Device {
                                fence: &handle::Fence<Resources>) {
         for (_, mapping) in gpu_access.access_mapped() {
             let status = match &mut mapping.kind {
-                &mut MappingKind::Persistent(ref mut status) => status,
+                &mut MappingKind::Persistent(ref mut status) |
+                &mut MappingKind::Coherent(ref mut status) => status,
                 _ => unreachable!(),
             };
             status.gpu_access(fence.clone());
@@ -868,6 +1275,13 @@ impl Device {
     }
 }
 
+impl Drop for Device {
+    fn drop(&mut self) {
+        // the callback would point to the freed output otherwise
+        self.disable_debug_output();
+    }
+}
+
 impl c::Device for Device {
     type Resources = Resources;
     type CommandBuffer = command::CommandBuffer;
@@ -892,9 +1306,9 @@ impl c::Device for Device {
               access: &com::AccessInfo<Resources>) -> c::SubmissionResult<()>
     {
         let mut access = try!(self.before_submit(access));
-        self.no_fence_submit(cb);
+        let result = self.no_fence_submit(cb);
         self.after_submit(&mut access);
-        Ok(())
+        result
     }
 
     fn fenced_submit(&mut self,
@@ -912,8 +1326,9 @@ impl c::Device for Device {
         }
 
         let mut access = try!(self.before_submit(access));
-        self.no_fence_submit(cb);
+        let result = self.no_fence_submit(cb);
         let fence_opt = self.after_submit(&mut access);
+        try!(result);
         Ok(fence_opt.unwrap_or_else(|| self.place_fence()))
     }
 
@@ -922,6 +1337,31 @@ impl c::Device for Device {
                             &self.share.context);
     }
 
+    fn get_query_result(&mut self, query: &handle::Query<Resources>) -> Option<u64> {
+        let gl = &self.share.context;
+        let object = self.frame_handles.ref_query(query).object;
+        let mut available = 0;
+        unsafe {
+            // the query object only comes to existence once it has been used
+            if gl.IsQuery(object) == gl::FALSE {
+                return None
+            }
+            gl.GetQueryObjectuiv(object, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        if available == 0 {
+            return None
+        }
+        if self.share.private_caps.timer_query_supported {
+            let mut result = 0;
+            unsafe { gl.GetQueryObjectui64v(object, gl::QUERY_RESULT, &mut result) };
+            Some(result)
+        } else {
+            let mut result = 0;
+            unsafe { gl.GetQueryObjectuiv(object, gl::QUERY_RESULT, &mut result) };
+            Some(result as u64)
+        }
+    }
+
     fn cleanup(&mut self) {
         use core::handle::Producer;
         self.frame_handles.clear();
@@ -931,7 +1371,7 @@ impl c::Device for Device {
                     // we have exclusive access because it's the last reference
                     let mapping = unsafe { raw.use_access() };
                     match mapping.kind {
-                        MappingKind::Persistent(_) => (),
+                        MappingKind::Persistent(_) | MappingKind::Coherent(_) => (),
                         MappingKind::Temporary => {
                             let target = factory::role_to_target(buffer.get_info().role);
                             factory::temporary_ensure_unmapped(&mut mapping.pointer,
@@ -958,6 +1398,7 @@ impl c::Device for Device {
             |_, _| {}, //DSV
             |gl, v| unsafe { if v.object != 0 { gl.DeleteSamplers(1, &v.object) }},
             |gl, fence| unsafe { gl.DeleteSync(fence.0) },
+            |gl, query| unsafe { gl.DeleteQueries(1, &query.object) },
         );
     }
 }
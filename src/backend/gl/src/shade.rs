@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::ffi::CString;
 use std::iter::repeat;
 use core::{self as c, shade as s};
 use info::PrivateCaps;
@@ -37,6 +38,13 @@ fn get_block_iv(gl: &gl::Gl, name: super::Program, index: gl::types::GLuint,
     iv
 }
 
+fn get_uniform_iv(gl: &gl::Gl, name: super::Program, index: gl::types::GLuint,
+                  query: gl::types::GLenum) -> gl::types::GLint {
+    let mut iv = 0;
+    unsafe { gl.GetActiveUniformsiv(name, 1, &index, query, &mut iv) };
+    iv
+}
+
 pub fn get_shader_log(gl: &gl::Gl, name: super::Shader) -> String {
     let mut length = get_shader_iv(gl, name, gl::INFO_LOG_LENGTH);
     if length > 0 {
@@ -61,6 +69,7 @@ pub fn create_shader(gl: &gl::Gl, stage: s::Stage, data: &[u8])
         s::Stage::Domain => gl::TESS_EVALUATION_SHADER,
         s::Stage::Geometry => gl::GEOMETRY_SHADER,
         s::Stage::Pixel => gl::FRAGMENT_SHADER,
+        s::Stage::Compute => gl::COMPUTE_SHADER,
     };
     let name = unsafe { gl.CreateShader(target) };
     unsafe {
@@ -87,6 +96,7 @@ pub fn create_shader(gl: &gl::Gl, stage: s::Stage, data: &[u8])
 enum StorageType {
     Var(s::BaseType, s::ContainerType),
     Sampler(s::BaseType, s::TextureType, s::SamplerType),
+    Image(s::BaseType, s::TextureType),
     Unknown,
 }
 
@@ -165,6 +175,34 @@ impl StorageType {
             gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY       => Sampler(BaseType::U32, TextureType::Cube(Array),   SamplerType(NoCompare, NoRect)),
             gl::UNSIGNED_INT_SAMPLER_BUFFER               => Sampler(BaseType::U32, TextureType::Buffer,        SamplerType(NoCompare, NoRect)),
 
+            gl::IMAGE_1D                     => Image(BaseType::F32, TextureType::D1(NoArray)),
+            gl::IMAGE_1D_ARRAY               => Image(BaseType::F32, TextureType::D1(Array)),
+            gl::IMAGE_2D                     => Image(BaseType::F32, TextureType::D2(NoArray, NoMultiSample)),
+            gl::IMAGE_2D_RECT                => Image(BaseType::F32, TextureType::D2(NoArray, NoMultiSample)),
+            gl::IMAGE_2D_ARRAY               => Image(BaseType::F32, TextureType::D2(Array,   NoMultiSample)),
+            gl::IMAGE_2D_MULTISAMPLE         => Image(BaseType::F32, TextureType::D2(NoArray, MultiSample)),
+            gl::IMAGE_2D_MULTISAMPLE_ARRAY   => Image(BaseType::F32, TextureType::D2(Array,   MultiSample)),
+            gl::IMAGE_3D                     => Image(BaseType::F32, TextureType::D3),
+            gl::IMAGE_CUBE                   => Image(BaseType::F32, TextureType::Cube(NoArray)),
+            gl::IMAGE_CUBE_MAP_ARRAY         => Image(BaseType::F32, TextureType::Cube(Array)),
+            gl::IMAGE_BUFFER                 => Image(BaseType::F32, TextureType::Buffer),
+
+            gl::INT_IMAGE_1D                 => Image(BaseType::I32, TextureType::D1(NoArray)),
+            gl::INT_IMAGE_1D_ARRAY           => Image(BaseType::I32, TextureType::D1(Array)),
+            gl::INT_IMAGE_2D                 => Image(BaseType::I32, TextureType::D2(NoArray, NoMultiSample)),
+            gl::INT_IMAGE_2D_ARRAY           => Image(BaseType::I32, TextureType::D2(Array,   NoMultiSample)),
+            gl::INT_IMAGE_3D                 => Image(BaseType::I32, TextureType::D3),
+            gl::INT_IMAGE_CUBE               => Image(BaseType::I32, TextureType::Cube(NoArray)),
+            gl::INT_IMAGE_BUFFER             => Image(BaseType::I32, TextureType::Buffer),
+
+            gl::UNSIGNED_INT_IMAGE_1D        => Image(BaseType::U32, TextureType::D1(NoArray)),
+            gl::UNSIGNED_INT_IMAGE_1D_ARRAY  => Image(BaseType::U32, TextureType::D1(Array)),
+            gl::UNSIGNED_INT_IMAGE_2D        => Image(BaseType::U32, TextureType::D2(NoArray, NoMultiSample)),
+            gl::UNSIGNED_INT_IMAGE_2D_ARRAY  => Image(BaseType::U32, TextureType::D2(Array,   NoMultiSample)),
+            gl::UNSIGNED_INT_IMAGE_3D        => Image(BaseType::U32, TextureType::D3),
+            gl::UNSIGNED_INT_IMAGE_CUBE      => Image(BaseType::U32, TextureType::Cube(NoArray)),
+            gl::UNSIGNED_INT_IMAGE_BUFFER    => Image(BaseType::U32, TextureType::Buffer),
+
             _ => Unknown,
         }
     }
@@ -210,6 +248,50 @@ fn query_attributes(gl: &gl::Gl, prog: super::Program) -> Vec<s::AttributeVar> {
     .collect()
 }
 
+fn query_stream_outputs(gl: &gl::Gl, prog: super::Program) -> Vec<s::StreamOutputVar> {
+    let num = get_program_iv(gl, prog, gl::TRANSFORM_FEEDBACK_VARYINGS);
+    let max_len = get_program_iv(gl, prog, gl::TRANSFORM_FEEDBACK_VARYING_MAX_LENGTH);
+    let mut name = String::with_capacity(max_len as usize);
+    name.extend(repeat('\0').take(max_len as usize));
+    let mut offset = 0;
+    (0..num as gl::types::GLuint).map(|i| {
+        let mut length = 0 as gl::types::GLint;
+        let mut size = 0 as gl::types::GLint;
+        let mut storage = 0 as gl::types::GLenum;
+        unsafe {
+            let raw = (&name[..]).as_ptr() as *mut gl::types::GLchar;
+            gl.GetTransformFeedbackVarying(prog, i, max_len, &mut length, &mut size, &mut storage, raw);
+        }
+        let real_name = name[..length as usize].to_string();
+        let (base, container) = match StorageType::new(storage) {
+            StorageType::Var(b, c) => (b, c),
+            _ => {
+                error!("Unrecognized varying storage: {}", storage);
+                (s::BaseType::F32, s::ContainerType::Single)
+            }
+        };
+        info!("\t\tVarying[{}] = {:?}\t{:?}\t{:?}", offset, real_name, base, container);
+        let var = s::StreamOutputVar {
+            name: real_name,
+            offset: offset,
+            base_type: base,
+            container: container,
+        };
+        // varyings are tightly packed in the interleaved mode
+        let scalar_size = match base {
+            s::BaseType::F64 => 8,
+            _ => 4,
+        };
+        let num_scalars = match container {
+            s::ContainerType::Single => 1,
+            s::ContainerType::Vector(n) => n as u32,
+            s::ContainerType::Matrix(_, r, c) => r as u32 * c as u32,
+        };
+        offset += size as u32 * num_scalars * scalar_size;
+        var
+    }).collect()
+}
+
 fn query_blocks(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program,
                 block_indices: &[gl::types::GLint], block_offsets: &[gl::types::GLint])
                 -> Vec<s::ConstantBufferVar> {
@@ -252,9 +334,12 @@ fn query_blocks(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program,
                 (s::VERTEX,   gl::UNIFORM_BLOCK_REFERENCED_BY_VERTEX_SHADER),
                 (s::GEOMETRY, gl::UNIFORM_BLOCK_REFERENCED_BY_GEOMETRY_SHADER),
                 (s::PIXEL,    gl::UNIFORM_BLOCK_REFERENCED_BY_FRAGMENT_SHADER),
+                (s::COMPUTE,  gl::UNIFORM_BLOCK_REFERENCED_BY_COMPUTE_SHADER),
             ];
             let mut usage = s::Usage::empty();
-            for &(stage, eval) in usage_list.iter() {
+            // the compute enum is not known to pre-4.3 implementations
+            let num_stages = if caps.compute_supported { 4 } else { 3 };
+            for &(stage, eval) in usage_list[..num_stages].iter() {
                 if get_block_iv(gl, prog, idx, eval) != 0 {
                     usage = usage | stage;
                 }
@@ -289,22 +374,28 @@ fn query_blocks(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program,
                         gl.GetActiveUniform(prog, i as gl::types::GLuint, max_len, &mut length, &mut size, &mut storage, raw);
                     };
                     let real_name = el_name[..length as usize].to_string();
+                    let layout = s::ConstLayout {
+                        array_stride: get_uniform_iv(gl, prog, i as gl::types::GLuint, gl::UNIFORM_ARRAY_STRIDE) as usize,
+                        matrix_stride: get_uniform_iv(gl, prog, i as gl::types::GLuint, gl::UNIFORM_MATRIX_STRIDE) as usize,
+                    };
                     let (base, container) = match StorageType::new(storage) {
-                        StorageType::Var(base, cont) => {
-                            info!("\t\t\tElement at {}\t= '{}'\t{:?}\t{:?}", *offset, real_name, base, cont);
-                            (base, cont)
-                        },
+                        StorageType::Var(base, s::ContainerType::Matrix(_, rows, cols))
+                        if get_uniform_iv(gl, prog, i as gl::types::GLuint, gl::UNIFORM_IS_ROW_MAJOR) != 0 =>
+                            (base, s::ContainerType::Matrix(s::MatrixFormat::RowMajor, rows, cols)),
+                        StorageType::Var(base, cont) => (base, cont),
                         _ => {
                             error!("Unrecognized element storage: {}", storage);
                             (s::BaseType::F32, s::ContainerType::Single)
                         },
                     };
+                    info!("\t\t\tElement at {}\t= '{}'\t{:?}\t{:?}\t{:?}", *offset, real_name, base, container, layout);
                     Some(s::ConstVar {
                         name: real_name,
                         location: *offset as s::Location,
                         count: size as usize,
                         base_type: base,
                         container: container,
+                        layout: Some(layout),
                     })
                 } else { None }
             }).collect()
@@ -313,9 +404,11 @@ fn query_blocks(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program,
 }
 
 fn query_parameters(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program, usage: s::Usage)
-                    -> (Vec<s::ConstVar>, Vec<s::TextureVar>, Vec<s::SamplerVar>, Vec<gl::types::GLint>, Vec<gl::types::GLint>) {
+                    -> Result<(Vec<s::ConstVar>, Vec<s::TextureVar>, Vec<s::UnorderedVar>, Vec<s::SamplerVar>,
+                               Vec<gl::types::GLint>, Vec<gl::types::GLint>), s::CreateProgramError> {
     let mut uniforms = Vec::new();
     let mut textures = Vec::new();
+    let mut unordereds = Vec::new();
     let mut samplers = Vec::new();
     let total_num = get_program_iv(gl, prog, gl::ACTIVE_UNIFORMS);
     let indices: Vec<_> = (0..total_num as gl::types::GLuint).collect();
@@ -330,13 +423,13 @@ fn query_parameters(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program, u
                 (&indices[..]).as_ptr(), gl::UNIFORM_OFFSET,
                 block_offsets.as_mut_ptr());
         }
-        //TODO: UNIFORM_IS_ROW_MAJOR
     }
     // prepare the name string
     let max_len = get_program_iv(gl, prog, gl::ACTIVE_UNIFORM_MAX_LENGTH);
     let mut name = String::with_capacity(max_len as usize);
     name.extend(repeat('\0').take(max_len as usize));
     let mut texture_slot = 0;
+    let mut image_slot = 0;
     unsafe { gl.UseProgram(prog); } //TODO: passive mode
     // walk the indices
     for (&i, _) in indices.iter().zip(block_indices.iter()).filter(|&(_, &b)| b<0) {
@@ -361,38 +454,127 @@ fn query_parameters(gl: &gl::Gl, caps: &c::Capabilities, prog: super::Program, u
                     count: size as usize,
                     base_type: base,
                     container: container,
+                    layout: None,
                 });
             },
             StorageType::Sampler(base, tex_type, samp_type) => {
-                let slot = texture_slot;
-                texture_slot += 1;
+                // arrays of samplers take consecutive slots, one per element
+                let max_slots = if tex_type.can_sample() { c::MAX_SAMPLERS } else { c::MAX_RESOURCE_VIEWS };
+                if (texture_slot + size) as usize > max_slots {
+                    return Err(format!("Sampler '{}' of {} elements exceeds the {} texture slots",
+                                       real_name, size, max_slots).into())
+                }
+                let slots: Vec<_> = (texture_slot .. texture_slot + size).collect();
+                texture_slot += size;
                 unsafe {
-                    gl.Uniform1i(loc, slot as gl::types::GLint);
+                    gl.Uniform1iv(loc, size, slots.as_ptr());
                 }
                 //TODO: detect the texture slot instead of trying to set it up
-                info!("\t\tSampler[{}] = '{}'\t{:?}\t{:?}", slot, real_name, base, tex_type);
-                textures.push(s::TextureVar {
-                    name: real_name.clone(),
-                    slot: slot as c::ResourceViewSlot,
-                    base_type: base,
-                    ty: tex_type,
-                    usage: usage,
-                });
-                if tex_type.can_sample() {
-                    samplers.push(s::SamplerVar {
-                        name: real_name,
-                        slot: slot as c::SamplerSlot,
-                        ty: samp_type,
+                for (k, &slot) in slots.iter().enumerate() {
+                    let el_name = if size > 1 {
+                        format!("{}[{}]", real_name.trim_right_matches("[0]"), k)
+                    } else {
+                        real_name.clone()
+                    };
+                    info!("\t\tSampler[{}] = '{}'\t{:?}\t{:?}", slot, el_name, base, tex_type);
+                    textures.push(s::TextureVar {
+                        name: el_name.clone(),
+                        slot: slot as c::ResourceViewSlot,
+                        base_type: base,
+                        ty: tex_type,
                         usage: usage,
                     });
+                    if tex_type.can_sample() {
+                        samplers.push(s::SamplerVar {
+                            name: el_name,
+                            slot: slot as c::SamplerSlot,
+                            ty: samp_type,
+                            usage: usage,
+                        });
+                    }
                 }
             },
+            StorageType::Image(base, tex_type) => {
+                if image_slot >= c::MAX_UNORDERED_VIEWS {
+                    return Err(format!("Image '{}' exceeds the {} unordered view slots",
+                                       real_name, c::MAX_UNORDERED_VIEWS).into())
+                }
+                let slot = image_slot;
+                image_slot += 1;
+                unsafe {
+                    gl.Uniform1i(loc, slot as gl::types::GLint);
+                }
+                info!("\t\tImage[{}] = '{}'\t{:?}\t{:?}", slot, real_name, base, tex_type);
+                unordereds.push(s::UnorderedVar {
+                    name: real_name,
+                    slot: slot as c::UnorderedViewSlot,
+                    usage: usage,
+                });
+            },
             StorageType::Unknown => {
                 error!("Unrecognized uniform storage: {}", storage);
             },
         }
     }
-    (uniforms, textures, samplers, block_indices, block_offsets)
+    Ok((uniforms, textures, unordereds, samplers, block_indices, block_offsets))
+}
+
+/// Query the shader storage blocks, assigning them the unordered slots
+/// starting at `first_slot`, after the ones taken by images.
+fn query_storage_blocks(gl: &gl::Gl, prog: super::Program, first_slot: usize)
+                        -> Result<Vec<s::UnorderedVar>, s::CreateProgramError> {
+    use std::ptr;
+
+    let mut num = 0;
+    unsafe {
+        gl.GetProgramInterfaceiv(prog, gl::SHADER_STORAGE_BLOCK, gl::ACTIVE_RESOURCES, &mut num);
+    }
+    if first_slot + num as usize > c::MAX_UNORDERED_VIEWS {
+        return Err(format!("{} images and {} storage blocks exceed the {} unordered view slots",
+                           first_slot, num, c::MAX_UNORDERED_VIEWS).into())
+    }
+    Ok((0 .. num as gl::types::GLuint).map(|idx| {
+        let mut length = 0;
+        unsafe {
+            gl.GetProgramResourceiv(prog, gl::SHADER_STORAGE_BLOCK, idx, 1, &gl::NAME_LENGTH, 1,
+                                    ptr::null_mut(), &mut length);
+        }
+        let mut name = String::with_capacity(length as usize);
+        name.extend(repeat('\0').take(length as usize));
+        unsafe {
+            gl.GetProgramResourceName(prog, gl::SHADER_STORAGE_BLOCK, idx, length, ptr::null_mut(),
+                                      (&name[..]).as_ptr() as *mut gl::types::GLchar);
+        }
+        // remove the \0
+        name.pop();
+
+        let usage_list = [
+            (s::VERTEX,   gl::REFERENCED_BY_VERTEX_SHADER),
+            (s::GEOMETRY, gl::REFERENCED_BY_GEOMETRY_SHADER),
+            (s::PIXEL,    gl::REFERENCED_BY_FRAGMENT_SHADER),
+            (s::COMPUTE,  gl::REFERENCED_BY_COMPUTE_SHADER),
+        ];
+        let mut usage = s::Usage::empty();
+        for &(stage, prop) in usage_list.iter() {
+            let mut referenced = 0;
+            unsafe {
+                gl.GetProgramResourceiv(prog, gl::SHADER_STORAGE_BLOCK, idx, 1, &prop, 1,
+                                        ptr::null_mut(), &mut referenced);
+            }
+            if referenced != 0 {
+                usage = usage | stage;
+            }
+        }
+
+        let slot = first_slot + idx as usize;
+        unsafe { gl.ShaderStorageBlockBinding(prog, idx, slot as gl::types::GLuint); }
+        info!("\t\tStorage[{}] = '{}'", slot, name);
+        s::UnorderedVar {
+            name: name,
+            slot: slot as c::UnorderedViewSlot,
+            usage: usage,
+        }
+    }).collect())
 }
 
 fn query_outputs(gl: &gl::Gl, prog: super::Program) -> (Vec<s::OutputVar>, bool) {
@@ -475,13 +657,27 @@ pub fn get_program_log(gl: &gl::Gl, name: super::Program) -> String {
 }
 
 pub fn create_program(gl: &gl::Gl, caps: &c::Capabilities, private: &PrivateCaps,
-                      shaders: &[super::Shader], usage: s::Usage)
+                      shaders: &[super::Shader], stream_outputs: &[String], usage: s::Usage)
                       -> Result<(::Program, s::ProgramInfo), s::CreateProgramError> {
+    if !stream_outputs.is_empty() && !private.transform_feedback_supported {
+        return Err("Transform feedback is not supported by the device".into())
+    }
     let name = unsafe { gl.CreateProgram() };
     for &sh in shaders {
         unsafe { gl.AttachShader(name, sh) };
     }
 
+    if !stream_outputs.is_empty() {
+        let names: Vec<_> = stream_outputs.iter()
+                                          .map(|n| CString::new(&n[..]).unwrap())
+                                          .collect();
+        let ptrs: Vec<_> = names.iter().map(|n| n.as_ptr()).collect();
+        unsafe {
+            gl.TransformFeedbackVaryings(name, ptrs.len() as gl::types::GLsizei, ptrs.as_ptr(),
+                                         gl::INTERLEAVED_ATTRIBS);
+        }
+    }
+
     if !private.program_interface_supported && private.frag_data_location_supported {
         for i in 0..c::MAX_COLOR_TARGETS {
             let color_name = format!("Target{}\0", i);
@@ -501,16 +697,25 @@ pub fn create_program(gl: &gl::Gl, caps: &c::Capabilities, private: &PrivateCaps
             warn!("\tLog: {}", log);
         }
 
-        let (uniforms, textures, samplers, block_indices, block_offsets) =
-            query_parameters(gl, caps, name, usage);
+        let (uniforms, textures, mut unordereds, samplers, block_indices, block_offsets) =
+            try!(query_parameters(gl, caps, name, usage));
+        if private.storage_buffer_supported && private.program_interface_supported {
+            let first_slot = unordereds.len();
+            unordereds.extend(try!(query_storage_blocks(gl, name, first_slot)));
+        }
         let mut info = s::ProgramInfo {
             vertex_attributes: query_attributes(gl, name),
             globals: uniforms,
             constant_buffers: query_blocks(gl, caps, name, &block_indices, &block_offsets),
             textures: textures,
-            unordereds: Vec::new(), //TODO
+            unordereds: unordereds,
             samplers: samplers,
             outputs: Vec::new(),
+            stream_outputs: if stream_outputs.is_empty() {
+                Vec::new()
+            } else {
+                query_stream_outputs(gl, name)
+            },
             output_depth: false,
             knows_outputs: false,
         };
@@ -528,22 +733,87 @@ pub fn create_program(gl: &gl::Gl, caps: &c::Capabilities, private: &PrivateCaps
     }
 }
 
-pub fn bind_uniform(gl: &gl::Gl, loc: gl::types::GLint, uniform: s::UniformValue) {
+/// Get the primitive mode of the transform feedback for a program drawn with
+/// the given primitive, which is defined by the geometry shader output if any.
+pub fn get_capture_mode(gl: &gl::Gl, prog: super::Program, primitive: c::Primitive)
+                        -> Option<gl::types::GLenum> {
+    use core::Primitive::*;
+    let mut shaders = [0; 5];
+    let mut count = 0;
+    unsafe {
+        gl.GetAttachedShaders(prog, shaders.len() as gl::types::GLsizei, &mut count, shaders.as_mut_ptr());
+    }
+    let has_geometry = shaders[..count as usize].iter().any(|&sh|
+        get_shader_iv(gl, sh, gl::SHADER_TYPE) as gl::types::GLenum == gl::GEOMETRY_SHADER
+    );
+    if has_geometry {
+        match get_program_iv(gl, prog, gl::GEOMETRY_OUTPUT_TYPE) as gl::types::GLenum {
+            gl::POINTS => Some(gl::POINTS),
+            gl::LINE_STRIP => Some(gl::LINES),
+            gl::TRIANGLE_STRIP => Some(gl::TRIANGLES),
+            _ => None,
+        }
+    } else {
+        match primitive {
+            PointList => Some(gl::POINTS),
+            LineList | LineStrip => Some(gl::LINES),
+            TriangleList | TriangleStrip => Some(gl::TRIANGLES),
+            _ => None,
+        }
+    }
+}
+
+pub fn bind_uniform(gl: &gl::Gl, loc: gl::types::GLint, uniform: &s::UniformValue) {
     use core::shade::UniformValue;
-    match uniform {
+    match *uniform {
         UniformValue::I32(val) => unsafe { gl.Uniform1i(loc, val) },
+        UniformValue::U32(val) => unsafe { gl.Uniform1ui(loc, val) },
         UniformValue::F32(val) => unsafe { gl.Uniform1f(loc, val) },
-
-        UniformValue::I32Vector2(val) => unsafe { gl.Uniform2iv(loc, 1, val.as_ptr()) },
-        UniformValue::I32Vector3(val) => unsafe { gl.Uniform3iv(loc, 1, val.as_ptr()) },
-        UniformValue::I32Vector4(val) => unsafe { gl.Uniform4iv(loc, 1, val.as_ptr()) },
-
-        UniformValue::F32Vector2(val) => unsafe { gl.Uniform2fv(loc, 1, val.as_ptr()) },
-        UniformValue::F32Vector3(val) => unsafe { gl.Uniform3fv(loc, 1, val.as_ptr()) },
-        UniformValue::F32Vector4(val) => unsafe { gl.Uniform4fv(loc, 1, val.as_ptr()) },
-
-        UniformValue::F32Matrix2(val) => unsafe{ gl.UniformMatrix2fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
-        UniformValue::F32Matrix3(val) => unsafe{ gl.UniformMatrix3fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
-        UniformValue::F32Matrix4(val) => unsafe{ gl.UniformMatrix4fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F64(val) => unsafe { gl.Uniform1d(loc, val) },
+        UniformValue::Bool(val) => unsafe { gl.Uniform1i(loc, val as gl::types::GLint) },
+
+        UniformValue::I32Vector2(ref val) => unsafe { gl.Uniform2iv(loc, 1, val.as_ptr()) },
+        UniformValue::I32Vector3(ref val) => unsafe { gl.Uniform3iv(loc, 1, val.as_ptr()) },
+        UniformValue::I32Vector4(ref val) => unsafe { gl.Uniform4iv(loc, 1, val.as_ptr()) },
+
+        UniformValue::U32Vector2(ref val) => unsafe { gl.Uniform2uiv(loc, 1, val.as_ptr()) },
+        UniformValue::U32Vector3(ref val) => unsafe { gl.Uniform3uiv(loc, 1, val.as_ptr()) },
+        UniformValue::U32Vector4(ref val) => unsafe { gl.Uniform4uiv(loc, 1, val.as_ptr()) },
+
+        UniformValue::F32Vector2(ref val) => unsafe { gl.Uniform2fv(loc, 1, val.as_ptr()) },
+        UniformValue::F32Vector3(ref val) => unsafe { gl.Uniform3fv(loc, 1, val.as_ptr()) },
+        UniformValue::F32Vector4(ref val) => unsafe { gl.Uniform4fv(loc, 1, val.as_ptr()) },
+
+        UniformValue::F64Vector2(ref val) => unsafe { gl.Uniform2dv(loc, 1, val.as_ptr()) },
+        UniformValue::F64Vector3(ref val) => unsafe { gl.Uniform3dv(loc, 1, val.as_ptr()) },
+        UniformValue::F64Vector4(ref val) => unsafe { gl.Uniform4dv(loc, 1, val.as_ptr()) },
+
+        UniformValue::F32Matrix2(ref val) => unsafe{ gl.UniformMatrix2fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix3(ref val) => unsafe{ gl.UniformMatrix3fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix4(ref val) => unsafe{ gl.UniformMatrix4fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+
+        UniformValue::F32Matrix2x3(ref val) => unsafe{ gl.UniformMatrix2x3fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix2x4(ref val) => unsafe{ gl.UniformMatrix2x4fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix3x2(ref val) => unsafe{ gl.UniformMatrix3x2fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix3x4(ref val) => unsafe{ gl.UniformMatrix3x4fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix4x2(ref val) => unsafe{ gl.UniformMatrix4x2fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+        UniformValue::F32Matrix4x3(ref val) => unsafe{ gl.UniformMatrix4x3fv(loc, 1, gl::FALSE, val[0].as_ptr()) },
+
+        UniformValue::I32Array(ref val) => unsafe { gl.Uniform1iv(loc, val.len() as gl::types::GLsizei, val.as_ptr()) },
+        UniformValue::U32Array(ref val) => unsafe { gl.Uniform1uiv(loc, val.len() as gl::types::GLsizei, val.as_ptr()) },
+        UniformValue::F32Array(ref val) => unsafe { gl.Uniform1fv(loc, val.len() as gl::types::GLsizei, val.as_ptr()) },
+        UniformValue::F32Vector2Array(ref val) => unsafe {
+            gl.Uniform2fv(loc, val.len() as gl::types::GLsizei, val.as_ptr() as *const gl::types::GLfloat)
+        },
+        UniformValue::F32Vector3Array(ref val) => unsafe {
+            gl.Uniform3fv(loc, val.len() as gl::types::GLsizei, val.as_ptr() as *const gl::types::GLfloat)
+        },
+        UniformValue::F32Vector4Array(ref val) => unsafe {
+            gl.Uniform4fv(loc, val.len() as gl::types::GLsizei, val.as_ptr() as *const gl::types::GLfloat)
+        },
+        UniformValue::F32Matrix4Array(ref val) => unsafe {
+            gl.UniformMatrix4fv(loc, val.len() as gl::types::GLsizei, gl::FALSE,
+                                val.as_ptr() as *const gl::types::GLfloat)
+        },
     }
 }
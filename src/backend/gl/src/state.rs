@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use core::{MAX_COLOR_TARGETS, ColorSlot};
+use core::command::Viewport;
+use core::pso::{DepthBias, MultisampleInfo};
 use core::state as s;
 use core::state::{BlendValue, Comparison, CullFace, Equation,
                   Offset, RasterMethod, StencilOp, FrontFace};
@@ -73,6 +75,50 @@ pub fn bind_rasterizer(gl: &gl::Gl, r: &s::Rasterizer, is_embedded: bool) {
     }
 }
 
+pub fn bind_depth_bias(gl: &gl::Gl, method: s::RasterMethod, bias: DepthBias, is_embedded: bool) {
+    let gl_offset = match method {
+        _ if is_embedded => gl::POLYGON_OFFSET_FILL,
+        RasterMethod::Point => gl::POLYGON_OFFSET_POINT,
+        RasterMethod::Line(_) => gl::POLYGON_OFFSET_LINE,
+        RasterMethod::Fill => gl::POLYGON_OFFSET_FILL,
+    };
+    unsafe {
+        gl.Enable(gl_offset);
+        if bias.clamp() != 0.0 {
+            gl.PolygonOffsetClamp(bias.slope_scaled(), bias.constant(), bias.clamp());
+        } else {
+            gl.PolygonOffset(bias.slope_scaled(), bias.constant());
+        }
+    }
+}
+
+pub fn bind_depth_clamp(gl: &gl::Gl, clamp: bool) {
+    if clamp {
+        unsafe { gl.Enable(gl::DEPTH_CLAMP) };
+    } else {
+        unsafe { gl.Disable(gl::DEPTH_CLAMP) };
+    }
+}
+
+pub fn bind_multisample(gl: &gl::Gl, ms: MultisampleInfo, sample_mask_supported: bool) {
+    if ms.alpha_to_coverage {
+        unsafe { gl.Enable(gl::SAMPLE_ALPHA_TO_COVERAGE) };
+    } else {
+        unsafe { gl.Disable(gl::SAMPLE_ALPHA_TO_COVERAGE) };
+    }
+    if !sample_mask_supported {
+        return;
+    }
+    if ms.sample_mask != !0 {
+        unsafe {
+            gl.Enable(gl::SAMPLE_MASK);
+            gl.SampleMaski(0, ms.sample_mask);
+        }
+    } else {
+        unsafe { gl.Disable(gl::SAMPLE_MASK) };
+    }
+}
+
 pub fn bind_draw_color_buffers(gl: &gl::Gl, mask: usize) {
     let attachments = [
         gl::COLOR_ATTACHMENT0,  gl::COLOR_ATTACHMENT1,  gl::COLOR_ATTACHMENT2,
@@ -94,13 +140,29 @@ pub fn bind_draw_color_buffers(gl: &gl::Gl, mask: usize) {
     unsafe { gl.DrawBuffers(count as gl::types::GLint, targets.as_ptr()) };
 }
 
-pub fn bind_viewport(gl: &gl::Gl, rect: Rect) {
+pub fn bind_viewport(gl: &gl::Gl, vp: Viewport, is_embedded: bool) {
     unsafe { gl.Viewport(
-        rect.x as gl::types::GLint,
-        rect.y as gl::types::GLint,
-        rect.w as gl::types::GLint,
-        rect.h as gl::types::GLint
+        vp.rect.x as gl::types::GLint,
+        vp.rect.y as gl::types::GLint,
+        vp.rect.w as gl::types::GLint,
+        vp.rect.h as gl::types::GLint
     )};
+    if is_embedded {
+        unsafe { gl.DepthRangef(vp.near, vp.far) };
+    } else {
+        unsafe { gl.DepthRange(vp.near as gl::types::GLdouble, vp.far as gl::types::GLdouble) };
+    }
+}
+
+pub fn bind_viewport_indexed(gl: &gl::Gl, index: gl::types::GLuint, vp: Viewport) {
+    unsafe {
+        gl.ViewportIndexedf(index,
+            vp.rect.x as gl::types::GLfloat,
+            vp.rect.y as gl::types::GLfloat,
+            vp.rect.w as gl::types::GLfloat,
+            vp.rect.h as gl::types::GLfloat);
+        gl.DepthRangeIndexed(index, vp.near as gl::types::GLdouble, vp.far as gl::types::GLdouble);
+    }
 }
 
 pub fn bind_scissor(gl: &gl::Gl, rect: Option<Rect>) {
@@ -16,11 +16,12 @@ use {gl, Surface, Texture, NewTexture, Buffer, Sampler};
 use gl::types::{GLenum, GLuint, GLint, GLfloat, GLsizei, GLvoid};
 use state;
 use core::memory::SHADER_RESOURCE;
-use core::format::{Format as NewFormat, ChannelType};
+use core::format::{Format as NewFormat, ChannelType, ChannelSource, Swizzle};
+use core::format::{Support, SAMPLE, RENDER, BLEND};
 use core::texture as t;
 
 
-fn cube_face_to_gl(face: t::CubeFace) -> GLenum {
+pub fn cube_face_to_gl(face: t::CubeFace) -> GLenum {
     match face {
         t::CubeFace::PosZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
         t::CubeFace::NegZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
@@ -52,6 +53,12 @@ fn kind_face_to_gl(kind: t::Kind, face: Option<t::CubeFace>) -> GLenum {
     }
 }
 
+// Tokens of `GL_KHR_texture_compression_astc_ldr`, not present in the generated bindings.
+#[allow(non_upper_case_globals)]
+const COMPRESSED_RGBA_ASTC_4x4_KHR: GLenum         = 0x93B0;
+#[allow(non_upper_case_globals)]
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR: GLenum = 0x93D0;
+
 fn format_to_glpixel(format: NewFormat) -> GLenum {
     use core::format::SurfaceType as S;
     use core::format::ChannelType as C;
@@ -68,6 +75,13 @@ fn format_to_glpixel(format: NewFormat) -> GLenum {
         S::D24_S8 => gl::DEPTH_STENCIL,
         S::D16 | S::D24 | S::D32 => gl::DEPTH,
         S::B8_G8_R8_A8 => bgra,
+        // compressed formats are uploaded without specifying the pixel layout
+        S::BC1_R8_G8_B8_A1 | S::BC2_R8_G8_B8_A8 | S::BC3_R8_G8_B8_A8 | S::BC4_R8 |
+        S::BC5_R8_G8 | S::BC6_R16_G16_B16 | S::BC6S_R16_G16_B16 | S::BC7_R8_G8_B8_A8 |
+        S::ETC2_R8_G8_B8 | S::ETC2_R8_G8_B8_A1 | S::ETC2_R8_G8_B8_A8 | S::EAC_R11 |
+        S::EAC_R11_G11 | S::ASTC_4x4 | S::ASTC_5x4 | S::ASTC_5x5 | S::ASTC_6x5 |
+        S::ASTC_6x6 | S::ASTC_8x5 | S::ASTC_8x6 | S::ASTC_8x8 | S::ASTC_10x5 |
+        S::ASTC_10x6 | S::ASTC_10x8 | S::ASTC_10x10 | S::ASTC_12x10 | S::ASTC_12x12 => rgba,
     }
 }
 
@@ -96,10 +110,18 @@ fn format_to_gltype(format: NewFormat) -> Result<GLenum, ()> {
         S::D24 => gl::UNSIGNED_INT,
         S::D24_S8 => gl::UNSIGNED_INT_24_8,
         S::D32 => gl::FLOAT,
+        // compressed formats have no pixel type
+        S::BC1_R8_G8_B8_A1 | S::BC2_R8_G8_B8_A8 | S::BC3_R8_G8_B8_A8 | S::BC4_R8 |
+        S::BC5_R8_G8 | S::BC6_R16_G16_B16 | S::BC6S_R16_G16_B16 | S::BC7_R8_G8_B8_A8 |
+        S::ETC2_R8_G8_B8 | S::ETC2_R8_G8_B8_A1 | S::ETC2_R8_G8_B8_A8 | S::EAC_R11 |
+        S::EAC_R11_G11 | S::ASTC_4x4 | S::ASTC_5x4 | S::ASTC_5x5 | S::ASTC_6x5 |
+        S::ASTC_6x6 | S::ASTC_8x5 | S::ASTC_8x6 | S::ASTC_8x8 | S::ASTC_10x5 |
+        S::ASTC_10x6 | S::ASTC_10x8 | S::ASTC_10x10 | S::ASTC_12x10 | S::ASTC_12x12 =>
+            return Err(()),
     })
 }
 
-fn format_to_glfull(format: NewFormat) -> Result<GLenum, ()> {
+pub fn format_to_glfull(format: NewFormat) -> Result<GLenum, ()> {
     use core::format::SurfaceType as S;
     use core::format::ChannelType as C;
     let cty = format.1;
@@ -216,14 +238,186 @@ fn format_to_glfull(format: NewFormat) -> Result<GLenum, ()> {
         S::D24 => gl::DEPTH_COMPONENT24,
         S::D24_S8 => gl::DEPTH24_STENCIL8,
         S::D32 => gl::DEPTH_COMPONENT32F,
+        // block-compressed
+        S::BC1_R8_G8_B8_A1 => match cty {
+            C::Unorm => gl::COMPRESSED_RGBA_S3TC_DXT1_EXT,
+            C::Srgb => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+            _ => return Err(()),
+        },
+        S::BC2_R8_G8_B8_A8 => match cty {
+            C::Unorm => gl::COMPRESSED_RGBA_S3TC_DXT3_EXT,
+            C::Srgb => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+            _ => return Err(()),
+        },
+        S::BC3_R8_G8_B8_A8 => match cty {
+            C::Unorm => gl::COMPRESSED_RGBA_S3TC_DXT5_EXT,
+            C::Srgb => gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+            _ => return Err(()),
+        },
+        S::BC4_R8 => match cty {
+            C::Inorm => gl::COMPRESSED_SIGNED_RED_RGTC1,
+            C::Unorm => gl::COMPRESSED_RED_RGTC1,
+            _ => return Err(()),
+        },
+        S::BC5_R8_G8 => match cty {
+            C::Inorm => gl::COMPRESSED_SIGNED_RG_RGTC2,
+            C::Unorm => gl::COMPRESSED_RG_RGTC2,
+            _ => return Err(()),
+        },
+        S::BC6_R16_G16_B16 => match cty {
+            C::Float => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+            _ => return Err(()),
+        },
+        S::BC6S_R16_G16_B16 => match cty {
+            C::Float => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+            _ => return Err(()),
+        },
+        S::BC7_R8_G8_B8_A8 => match cty {
+            C::Unorm => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            C::Srgb => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            _ => return Err(()),
+        },
+        S::ETC2_R8_G8_B8 => match cty {
+            C::Unorm => gl::COMPRESSED_RGB8_ETC2,
+            C::Srgb => gl::COMPRESSED_SRGB8_ETC2,
+            _ => return Err(()),
+        },
+        S::ETC2_R8_G8_B8_A1 => match cty {
+            C::Unorm => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            C::Srgb => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            _ => return Err(()),
+        },
+        S::ETC2_R8_G8_B8_A8 => match cty {
+            C::Unorm => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            C::Srgb => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            _ => return Err(()),
+        },
+        S::EAC_R11 => match cty {
+            C::Inorm => gl::COMPRESSED_SIGNED_R11_EAC,
+            C::Unorm => gl::COMPRESSED_R11_EAC,
+            _ => return Err(()),
+        },
+        S::EAC_R11_G11 => match cty {
+            C::Inorm => gl::COMPRESSED_SIGNED_RG11_EAC,
+            C::Unorm => gl::COMPRESSED_RG11_EAC,
+            _ => return Err(()),
+        },
+        S::ASTC_4x4 | S::ASTC_5x4 | S::ASTC_5x5 | S::ASTC_6x5 | S::ASTC_6x6 |
+        S::ASTC_8x5 | S::ASTC_8x6 | S::ASTC_8x8 | S::ASTC_10x5 | S::ASTC_10x6 |
+        S::ASTC_10x8 | S::ASTC_10x10 | S::ASTC_12x10 | S::ASTC_12x12 => {
+            // the ASTC formats of each color space are enumerated consecutively,
+            // in the same order as the surface types
+            let index = format.0 as GLenum - S::ASTC_4x4 as GLenum;
+            match cty {
+                C::Unorm => COMPRESSED_RGBA_ASTC_4x4_KHR + index,
+                C::Srgb => COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR + index,
+                _ => return Err(()),
+            }
+        },
     })
 }
 
-fn set_mipmap_range(gl: &gl::Gl, target: GLenum, (base, max): (u8, u8)) { unsafe {
+/// Check if the format can be bound to an image unit, see the table of the
+/// `glBindImageTexture` specification.
+pub fn is_image_format(format: NewFormat) -> bool {
+    use core::format::SurfaceType as S;
+    use core::format::ChannelType as C;
+    match format.0 {
+        S::R8 | S::R8_G8 | S::R8_G8_B8_A8 |
+        S::R16 | S::R16_G16 | S::R16_G16_B16_A16 => format.1 != C::Srgb,
+        S::R32 | S::R32_G32 | S::R32_G32_B32_A32 => true,
+        S::R10_G10_B10_A2 => format.1 == C::Unorm || format.1 == C::Uint,
+        _ => false,
+    }
+}
+
+/// Get the operations that every implementation supports with an uncompressed format,
+/// see the required texture formats tables of the GL 4.5 and GLES 3.0 specifications.
+pub fn get_required_format_support(format: NewFormat, is_embedded: bool) -> Support {
+    use core::format::SurfaceType as S;
+    use core::format::ChannelType as C;
+    let renderable = match (format.0, format.1) {
+        (S::D16, _) | (S::D24, _) | (S::D24_S8, _) | (S::D32, _) => true,
+        (_, C::Inorm) => false,
+        (S::R8, _) | (S::R8_G8, _) | (S::R8_G8_B8_A8, _) |
+        (S::R10_G10_B10_A2, _) | (S::B8_G8_R8_A8, _) |
+        (S::R4_G4_B4_A4, _) | (S::R5_G5_B5_A1, _) | (S::R5_G6_B5, _) => true,
+        (S::R16, C::Unorm) | (S::R16_G16, C::Unorm) | (S::R16_G16_B16_A16, C::Unorm) |
+        (S::R16, C::Float) | (S::R16_G16, C::Float) | (S::R16_G16_B16_A16, C::Float) |
+        (S::R32, C::Float) | (S::R32_G32, C::Float) | (S::R32_G32_B32_A32, C::Float) => !is_embedded,
+        (S::R16, _) | (S::R16_G16, _) | (S::R16_G16_B16_A16, _) |
+        (S::R32, _) | (S::R32_G32, _) | (S::R32_G32_B32_A32, _) => true,
+        _ => false,
+    };
+    // the 16 bits normalized formats are missing from GLES
+    if is_embedded && format.1 == C::Unorm && match format.0 {
+        S::R16 | S::R16_G16 | S::R16_G16_B16 | S::R16_G16_B16_A16 => true,
+        _ => false,
+    } {
+        return Support::empty();
+    }
+    let is_depth = match format.0 {
+        S::D16 | S::D24 | S::D24_S8 | S::D32 => true,
+        _ => false,
+    };
+    match (renderable, is_depth, format.1) {
+        (false, _, _) => SAMPLE,
+        (true, true, _) | (true, _, C::Int) | (true, _, C::Uint) => SAMPLE | RENDER,
+        (true, false, _) => SAMPLE | RENDER | BLEND,
+    }
+}
+
+/// Check if the driver lists the compressed format as supported.
+pub fn is_compressed_format_supported(gl: &gl::Gl, internal_format: GLenum) -> bool {
+    let mut count = 0;
+    unsafe { gl.GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count) };
+    let mut formats = vec![0; count as usize];
+    if count > 0 {
+        unsafe { gl.GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, formats.as_mut_ptr()) };
+    }
+    formats.iter().any(|&f| f as GLenum == internal_format)
+}
+
+pub fn set_mipmap_range(gl: &gl::Gl, target: GLenum, (base, max): (u8, u8)) { unsafe {
     gl.TexParameteri(target, gl::TEXTURE_BASE_LEVEL, base as GLint);
     gl.TexParameteri(target, gl::TEXTURE_MAX_LEVEL, max as GLint);
 }}
 
+fn channel_source_to_gl(source: ChannelSource) -> GLint {
+    (match source {
+        ChannelSource::Zero => gl::ZERO,
+        ChannelSource::One => gl::ONE,
+        ChannelSource::X => gl::RED,
+        ChannelSource::Y => gl::GREEN,
+        ChannelSource::Z => gl::BLUE,
+        ChannelSource::W => gl::ALPHA,
+    }) as GLint
+}
+
+pub fn set_swizzle(gl: &gl::Gl, target: GLenum, swizzle: Swizzle) { unsafe {
+    let mask = [
+        channel_source_to_gl(swizzle.0),
+        channel_source_to_gl(swizzle.1),
+        channel_source_to_gl(swizzle.2),
+        channel_source_to_gl(swizzle.3),
+    ];
+    gl.TexParameteriv(target, gl::TEXTURE_SWIZZLE_RGBA, mask.as_ptr());
+}}
+
+/// Create a view into the immutable storage of a texture, covering the
+/// given inclusive ranges of levels and layers.
+pub fn make_view(gl: &gl::Gl, name: Texture, target: GLenum, format: GLenum,
+                 (min_level, max_level): (u8, u8), (min_layer, num_layers): (u16, u16))
+                 -> Texture { unsafe {
+    let mut view = 0 as GLuint;
+    gl.GenTextures(1, &mut view);
+    gl.TextureView(view, target, name, format,
+                   min_level as GLuint, (max_level + 1 - min_level) as GLuint,
+                   min_layer as GLuint, num_layers as GLuint);
+    gl.BindTexture(target, view);
+    view
+}}
+
 fn make_surface_impl(gl: &gl::Gl, format: GLenum, dim: t::Dimensions)
                      -> Result<Surface, ()> {
     let mut name = 0 as GLuint;
@@ -390,14 +584,82 @@ fn make_widout_storage_impl(gl: &gl::Gl, kind: t::Kind, format: GLint, pix: GLen
     Ok(name)
 }
 
+/// Create a compressed texture with all of its levels, assuming TexStorage* isn't
+/// available. Compressed storage has to be allocated with the size of every image.
+fn make_compressed_widout_storage_impl(gl: &gl::Gl, desc: &t::Info, cty: ChannelType, format: GLenum)
+                                       -> Result<Texture, t::CreationError> {
+    let (name, target) = make_texture(gl, desc.kind);
+    for level in 0 .. desc.levels {
+        let mut img = desc.to_raw_image_info(cty, level);
+        // array layers are not reduced by the mipmap chain
+        if let t::Kind::D2Array(_, _, a, _) = desc.kind {
+            img.depth = a as t::Size;
+        }
+        let size = img.get_byte_count() as GLsizei;
+        match desc.kind {
+            t::Kind::D2(_, _, t::AaMode::Single) => unsafe {
+                gl.CompressedTexImage2D(
+                    target,
+                    level as GLint,
+                    format,
+                    img.width as GLsizei,
+                    img.height as GLsizei,
+                    0,
+                    size,
+                    ::std::ptr::null()
+                );
+            },
+            t::Kind::D2Array(_, _, _, t::AaMode::Single) | t::Kind::D3(_, _, _) => unsafe {
+                gl.CompressedTexImage3D(
+                    target,
+                    level as GLint,
+                    format,
+                    img.width as GLsizei,
+                    img.height as GLsizei,
+                    img.depth as GLsizei,
+                    0,
+                    size,
+                    ::std::ptr::null()
+                );
+            },
+            t::Kind::Cube(_) => {
+                // each face is a separate image
+                img.depth = 1;
+                let size = img.get_byte_count() as GLsizei;
+                for &face in t::CUBE_FACES.iter() {
+                    unsafe { gl.CompressedTexImage2D(
+                        cube_face_to_gl(face),
+                        level as GLint,
+                        format,
+                        img.width as GLsizei,
+                        img.height as GLsizei,
+                        0,
+                        size,
+                        ::std::ptr::null()
+                    )};
+                }
+            },
+            t::Kind::D2(_, _, aa) | t::Kind::D2Array(_, _, _, aa) => return Err(t::CreationError::Samples(aa)),
+            t::Kind::D1(_) | t::Kind::D1Array(_, _) | t::Kind::CubeArray(_, _) =>
+                return Err(t::CreationError::Kind),
+        }
+    }
+
+    set_mipmap_range(gl, target, (0, desc.levels - 1));
+    Ok(name)
+}
+
 /// Create a texture, using the descriptor, assuming TexStorage* isn't available.
 pub fn make_without_storage(gl: &gl::Gl, desc: &t::Info, cty: ChannelType) ->
                             Result<Texture, t::CreationError> {
     let format = NewFormat(desc.format, cty);
     let gl_format = match format_to_glfull(format) {
-        Ok(f) => f as GLint,
+        Ok(f) => f,
         Err(_) => return Err(t::CreationError::Format(desc.format, Some(cty))),
     };
+    if desc.format.is_compressed() {
+        return make_compressed_widout_storage_impl(gl, desc, cty, gl_format)
+    }
     let gl_pixel_format = format_to_glpixel(format);
     let gl_data_type = match format_to_gltype(format) {
         Ok(t) => t,
@@ -405,7 +667,7 @@ pub fn make_without_storage(gl: &gl::Gl, desc: &t::Info, cty: ChannelType) ->
     };
 
     let fixed_loc = desc.bind.contains(SHADER_RESOURCE);
-    make_widout_storage_impl(gl, desc.kind, gl_format, gl_pixel_format, gl_data_type,
+    make_widout_storage_impl(gl, desc.kind, gl_format as GLint, gl_pixel_format, gl_data_type,
                              desc.levels, fixed_loc)
 }
 
@@ -637,6 +899,55 @@ fn tex_sub_image<F>(gl: &gl::Gl, kind: t::Kind, target: GLenum, pix: GLenum,
     })
 }
 
+fn compressed_tex_sub_image(gl: &gl::Gl, kind: t::Kind, target: GLenum, format: GLenum,
+                            img: &t::RawImageInfo, data: *const GLvoid, size: GLsizei)
+                            -> Result<(), t::CreationError> {
+    Ok(match kind {
+        t::Kind::D1(_) => unsafe {
+            gl.CompressedTexSubImage1D(
+                target,
+                img.mipmap as GLint,
+                img.xoffset as GLint,
+                img.width as GLint,
+                format,
+                size,
+                data
+            );
+        },
+        t::Kind::D1Array(_, _) | t::Kind::D2(_, _, t::AaMode::Single) | t::Kind::Cube(_) => unsafe {
+            gl.CompressedTexSubImage2D(
+                target,
+                img.mipmap as GLint,
+                img.xoffset as GLint,
+                img.yoffset as GLint,
+                img.width as GLint,
+                img.height as GLint,
+                format,
+                size,
+                data
+            );
+        },
+        t::Kind::D2Array(_, _, _, t::AaMode::Single) | t::Kind::D3(_, _, _) => unsafe {
+            gl.CompressedTexSubImage3D(
+                target,
+                img.mipmap as GLint,
+                img.xoffset as GLint,
+                img.yoffset as GLint,
+                img.zoffset as GLint,
+                img.width as GLint,
+                img.height as GLint,
+                img.depth as GLint,
+                format,
+                size,
+                data
+            );
+        },
+        t::Kind::CubeArray(_, _) => return Err(t::CreationError::Kind),
+        t::Kind::D2(_, _, aa) => return Err(t::CreationError::Samples(aa)),
+        t::Kind::D2Array(_, _, _, aa) => return Err(t::CreationError::Samples(aa)),
+    })
+}
+
 pub fn copy_from_buffer(gl: &gl::Gl,
                         dst: Texture,
                         kind: t::Kind,
@@ -724,6 +1035,23 @@ pub fn update_texture(gl: &gl::Gl, name: Texture,
                           -> Result<(), t::CreationError> {
     //TODO: check size
     let data = slice.as_ptr() as *const GLvoid;
+    if img.format.0.is_compressed() {
+        let gl_format = match format_to_glfull(img.format) {
+            Ok(f) => f,
+            Err(_) => return Err(t::CreationError::Format(img.format.0, Some(img.format.1))),
+        };
+        if slice.len() != img.get_byte_count() {
+            error!("Compressed image {:?} expects {} bytes, given {}",
+                img, img.get_byte_count(), slice.len());
+            return Err(t::CreationError::Data(slice.len()))
+        }
+
+        let target = kind_to_gl(kind);
+        unsafe { gl.BindTexture(target, name) };
+
+        let target = kind_face_to_gl(kind, face);
+        return compressed_tex_sub_image(gl, kind, target, gl_format, img, data, slice.len() as GLsizei)
+    }
     let pixel_format = format_to_glpixel(img.format);
     let data_type = match format_to_gltype(img.format) {
         Ok(t) => t,
@@ -774,100 +1102,6 @@ pub fn init_texture_data(gl: &gl::Gl, name: Texture, desc: t::Info, channel: Cha
     Ok(())
 }
 
-/*
-pub fn update_texture(gl: &gl::Gl, kind: Kind, face: Option<CubeFace>,
-                      name: Texture, img: &ImageInfo, slice: &[u8])
-                      -> Result<(), Error> {
-    if let Some(fmt_size) = img.format.get_size() {
-        // TODO: can we compute the expected size for compressed formats?
-        let expected_size = img.width as usize * img.height as usize *
-                            img.depth as usize * fmt_size as usize;
-        if slice.len() != expected_size {
-            return Err(Error::IncorrectSize(expected_size));
-        }
-    }
-
-    let data = slice.as_ptr() as *const GLvoid;
-    let pixel_format = old_format_to_glpixel(img.format);
-    let data_type = match old_format_to_gltype(img.format) {
-        Ok(t) => t,
-        Err(_) => return Err(Error::UnsupportedFormat),
-    };
-
-    let target = kind_to_gl(kind);
-    unsafe { gl.BindTexture(target, name) };
-
-    let target = kind_face_to_gl(kind, face);
-    if img.format.is_compressed() {
-        compressed_update(gl, kind, target, img, data, data_type, slice.len() as GLint)
-    }else {
-        update_texture_impl(gl, kind, target, pixel_format, data_type, img, data)
-    }
-}
-
-pub fn compressed_update(gl: &gl::Gl, kind: Kind, target: GLenum, img: &ImageInfo,
-                         data: *const GLvoid, typ: GLenum, size: GLint)
-                         -> Result<(), Error> {
-    match kind {
-        Kind::D1(_) => unsafe {
-            gl.CompressedTexSubImage1D(
-                target,
-                img.mipmap as GLint,
-                img.xoffset as GLint,
-                img.width as GLint,
-                typ,
-                size as GLint,
-                data
-            );
-        },
-        Kind::D1Array(_, _) | Kind::D2(_, _, AaMode::Single) => unsafe {
-            gl.CompressedTexSubImage2D(
-                target,
-                img.mipmap as GLint,
-                img.xoffset as GLint,
-                img.yoffset as GLint,
-                img.width as GLint,
-                img.height as GLint,
-                typ,
-                size as GLint,
-                data
-            );
-        },
-        Kind::D2Array(_, _, _, AaMode::Single) | Kind::D3(_, _, _) => unsafe {
-            gl.CompressedTexSubImage3D(
-                target,
-                img.mipmap as GLint,
-                img.xoffset as GLint,
-                img.yoffset as GLint,
-                img.zoffset as GLint,
-                img.width as GLint,
-                img.height as GLint,
-                img.depth as GLint,
-                typ,
-                size as GLint,
-                data
-            );
-        },
-        Kind::Cube(_, _) => unsafe {
-            gl.CompressedTexSubImage2D(
-                target,
-                img.mipmap as GLint,
-                img.xoffset as GLint,
-                img.yoffset as GLint,
-                img.width as GLint,
-                img.height as GLint,
-                typ,
-                size as GLint,
-                data
-            );
-        },
-        _ => return Err(Error::UnsupportedSamples),
-    }
-
-    Ok(())
-}
-*/
-
 /// Common texture creation routine, just creates and binds.
 fn make_texture(gl: &gl::Gl, kind: t::Kind) -> (Texture, GLuint) {
     let mut name = 0 as GLuint;
@@ -33,7 +33,7 @@ use native::{Rtv, Srv, Dsv};
 
 use metal::*;
 
-use std::ptr;
+use std::{mem, ptr};
 use std::collections::hash_map::{HashMap, Entry};
 
 pub struct CommandBuffer {
@@ -98,6 +98,10 @@ impl command::Buffer<Resources> for CommandBuffer {
         self.encoder.reset();
     }
 
+    fn append(&mut self, _: &CommandBuffer) -> Result<(), command::AppendError> {
+        Err(command::AppendError::Unsupported)
+    }
+
     fn bind_pipeline_state(&mut self, pso: Pipeline) {
         self.encoder.set_render_pipeline_state(pso.pipeline);
         self.encoder.set_front_facing_winding(pso.winding);
@@ -131,12 +135,13 @@ impl command::Buffer<Resources> for CommandBuffer {
             let mask = stage.into();
             for cb in cbs.iter() {
                 if cb.1.contains(mask) {
+                    let offset = cb.3.map_or(0, |range| range.offset as u64);
                     match stage {
                         Stage::Vertex => {
-                            self.encoder.set_vertex_buffer(cb.2 as u64, 0, unsafe { *((cb.0).0).0 });
+                            self.encoder.set_vertex_buffer(cb.2 as u64, offset, unsafe { *((cb.0).0).0 });
                         },
                         Stage::Pixel => {
-                            self.encoder.set_fragment_buffer(cb.2 as u64, 0, unsafe { *((cb.0).0).0 });
+                            self.encoder.set_fragment_buffer(cb.2 as u64, offset, unsafe { *((cb.0).0).0 });
                         },
                         _ => { unimplemented!() }
                     }
@@ -293,6 +298,12 @@ impl command::Buffer<Resources> for CommandBuffer {
         }
     }
 
+    fn bind_stream_output(&mut self, so: Option<pso::StreamOutputParam<Resources>>) {
+        if so.is_some() {
+            error!("Stream output is not supported by the Metal backend");
+        }
+    }
+
     fn bind_index(&mut self, buf: Buffer, idx_type: IndexType) {
         use map::map_index_type;
 
@@ -310,6 +321,23 @@ impl command::Buffer<Resources> for CommandBuffer {
         });*/
     }
 
+    fn set_viewports(&mut self, viewports: &[command::Viewport]) {
+        // the default viewport is set by `bind_pixel_targets`
+        if let Some(vp) = viewports.first() {
+            if viewports.len() > 1 {
+                error!("Only the first of {} viewports is used", viewports.len());
+            }
+            self.encoder.set_viewport(MTLViewport {
+                originX: vp.rect.x as f64,
+                originY: vp.rect.y as f64,
+                width: vp.rect.w as f64,
+                height: vp.rect.h as f64,
+                znear: vp.near as f64,
+                zfar: vp.far as f64
+            });
+        }
+    }
+
     fn set_ref_values(&mut self, vals: state::RefValues) {
         // FIXME: wrong types?
         self.encoder.set_stencil_front_back_reference_value(vals.stencil.0 as u32, vals.stencil.1 as u32);
@@ -317,30 +345,59 @@ impl command::Buffer<Resources> for CommandBuffer {
         // TODO: blend/stencil
     }
 
-    #[allow(unused_variables)]
     fn copy_buffer(&mut self, src: Buffer, dst: Buffer,
                    src_offset_bytes: usize, dst_offset_bytes: usize,
                    size_bytes: usize) {
-        unimplemented!()
+        let (src, dst) = unsafe { (*(src.0).0, *(dst.0).0) };
+        if src.contents().is_null() || dst.contents().is_null() {
+            error!("Copying private buffers is not supported by the Metal backend");
+            return;
+        }
+
+        // FIXME: slow, same as the partial buffer updates
+        self.encoder.end_encoding();
+        if self.encoder.has_command_buffer() {
+            self.encoder.commit_command_buffer(CAMetalDrawable::nil(), true);
+        }
+        self.should_restore = true;
+
+        unsafe {
+            let src_ptr = (src.contents() as *const u8).offset(src_offset_bytes as isize);
+            let dst_ptr = (dst.contents() as *mut u8).offset(dst_offset_bytes as isize);
+            ptr::copy(src_ptr, dst_ptr, size_bytes);
+        }
     }
 
-    #[allow(unused_variables)]
-    fn copy_buffer_to_texture(&mut self, src: Buffer, src_offset_bytes: usize,
-                              dst: Texture,
-                              kind: texture::Kind,
-                              face: Option<texture::CubeFace>,
-                              img: texture::RawImageInfo) {
-        unimplemented!()
+    fn copy_buffer_to_texture(&mut self, _src: Buffer, _src_offset_bytes: usize,
+                              _dst: Texture,
+                              _kind: texture::Kind,
+                              _face: Option<texture::CubeFace>,
+                              _img: texture::RawImageInfo) {
+        error!("Copying buffers to textures is not supported by the Metal backend");
     }
 
-    #[allow(unused_variables)]
     fn copy_texture_to_buffer(&mut self,
-                              src: Texture,
-                              kind: texture::Kind,
-                              face: Option<texture::CubeFace>,
-                              img: texture::RawImageInfo,
-                              dst: Buffer, dst_offset_bytes: usize) {
-        unimplemented!()
+                              _src: Texture,
+                              _kind: texture::Kind,
+                              _face: Option<texture::CubeFace>,
+                              _img: texture::RawImageInfo,
+                              _dst: Buffer, _dst_offset_bytes: usize) {
+        error!("Copying textures to buffers is not supported by the Metal backend");
+    }
+
+    fn copy_texture(&mut self, _src: texture::TextureCopyRegion<Texture>,
+                    _dst: texture::TextureCopyRegion<Texture>) {
+        error!("Copying textures is not supported by the Metal backend");
+    }
+
+    fn resolve_texture(&mut self, _src: texture::TextureCopyRegion<Texture>,
+                       _dst: texture::TextureCopyRegion<Texture>) {
+        error!("Resolving textures is not supported by the Metal backend");
+    }
+
+    fn blit_texture(&mut self, _src: texture::TextureCopyRegion<Texture>,
+                    _dst: texture::TextureCopyRegion<Texture>, _filter: texture::FilterMethod) {
+        error!("Blitting textures is not supported by the Metal backend");
     }
 
     fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
@@ -465,4 +522,35 @@ impl command::Buffer<Resources> for CommandBuffer {
             }
         }
     }
+
+    fn call_draw_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        self.ensure_render_encoder();
+
+        let args = unsafe { *(buf.0).0 };
+        let stride = mem::size_of::<command::DrawIndirectArgs>();
+        for i in 0 .. count {
+            self.encoder.draw_indirect(args, (offset + i * stride) as u64);
+        }
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset: usize, count: usize) {
+        self.ensure_render_encoder();
+
+        let args = unsafe { *(buf.0).0 };
+        let stride = mem::size_of::<command::DrawIndexedIndirectArgs>();
+        for i in 0 .. count {
+            self.encoder.draw_indexed_indirect(args, (offset + i * stride) as u64);
+        }
+    }
+
+    fn call_dispatch(&mut self, _x: command::ThreadGroupCount, _y: command::ThreadGroupCount,
+                     _z: command::ThreadGroupCount) {
+        // compute programs are refused at creation, so there is no pipeline to dispatch with
+        error!("Compute dispatches are not supported by the Metal backend");
+    }
+
+    // `create_query` refuses every kind, so there are no queries to record
+    fn begin_query(&mut self, _query: ()) {}
+    fn end_query(&mut self, _query: ()) {}
+    fn write_timestamp(&mut self, _query: ()) {}
 }
@@ -505,4 +505,28 @@ impl MetalEncoder {
         }
     }
 
+    // the indirect draws are not wrapped by `metal-rs` yet
+    pub fn draw_indirect(&self, args: MTLBuffer, args_offset: u64) {
+        unsafe {
+            let _: () = msg_send![*self.render, drawPrimitives:MTLPrimitiveType::Triangle
+                                                indirectBuffer:*args
+                                                indirectBufferOffset:args_offset];
+        }
+    }
+
+    pub fn draw_indexed_indirect(&self, args: MTLBuffer, args_offset: u64) {
+        if let Some((buf, ty)) = self.cache.index_buffer {
+            unsafe {
+                let _: () = msg_send![*self.render, drawIndexedPrimitives:MTLPrimitiveType::Triangle
+                                                    indexType:ty
+                                                    indexBuffer:*buf
+                                                    indexBufferOffset:0u64
+                                                    indirectBuffer:*args
+                                                    indirectBufferOffset:args_offset];
+            }
+        } else {
+            error!("Cannot draw indexed primitives without a index buffer bound");
+        }
+    }
+
 }
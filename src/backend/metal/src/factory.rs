@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::ops::Range;
 use std::os::raw::c_void;
 use std::sync::Arc;
 use std::{mem, slice, str};
@@ -20,7 +21,7 @@ use std::path::Path;
 // use cocoa::base::{selector, class};
 // use cocoa::foundation::{NSUInteger};
 
-use core::{self, buffer, factory, mapping, memory};
+use core::{self, buffer, factory, mapping, memory, query};
 use core::handle::{self, Producer};
 use core::memory::Typed;
 
@@ -84,6 +85,10 @@ impl Factory {
                               -> Result<handle::RawBuffer<Resources>, buffer::CreationError> {
         use map::map_buffer_usage;
 
+        if info.usage == memory::Usage::Persistent {
+            return Err(buffer::CreationError::UnsupportedUsage(info.usage));
+        }
+
         let usage = map_buffer_usage(info.usage, info.bind);
 
         if info.bind.contains(memory::RENDER_TARGET) | info.bind.contains(memory::DEPTH_STENCIL) {
@@ -97,13 +102,18 @@ impl Factory {
             self.device.new_buffer(info.size as u64, usage)
         };
 
-        let buf = Buffer(native::Buffer(Box::into_raw(Box::new(raw_buf))), info.usage, info.bind);
-
         // TODO(fkaa): if we have a way to track buffers in use (added on
         //             scheduling of command buffers, removed on completion),
         //             we could block while in use on both sides. would need
         //             a state for each mode (`in-use` vs. `mapped`).
-        let mapping = None;
+        let mapping = match info.usage {
+            memory::Usage::Upload | memory::Usage::Download => Some(RawMapping {
+                pointer: raw_buf.contents(),
+            }),
+            _ => None,
+        };
+
+        let buf = Buffer(native::Buffer(Box::into_raw(Box::new(raw_buf))), info.usage, info.bind);
 
         Ok(self.share.handles.borrow_mut().make_buffer(buf, info, mapping))
     }
@@ -215,6 +225,42 @@ impl core::Factory<Resources> for Factory {
         &self.share.capabilities
     }
 
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<Resources>, name: &str) {
+        self.share.handles.borrow_mut().set_label(handle, name);
+    }
+
+    fn get_statistics(&self) -> handle::Statistics {
+        self.share.handles.borrow().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: core::format::Format) -> core::format::Properties {
+        use core::format::{self as fm, ChannelType as C, SurfaceType as S};
+        use map::map_format;
+        // Metal has no way to query the features of a format, so only the
+        // ones supported by every feature set are reported.
+        if map_format(format, false).is_none() {
+            return fm::Properties::unsupported();
+        }
+        let mut support = fm::SAMPLE | fm::RENDER;
+        let blendable = match (format.0, format.1) {
+            (S::D16, _) | (S::D24, _) | (S::D24_S8, _) | (S::D32, _) => false,
+            (_, C::Int) | (_, C::Uint) => false,
+            (S::R32, _) | (S::R32_G32, _) | (S::R32_G32_B32_A32, _) => false,
+            _ => true,
+        };
+        if blendable {
+            support |= fm::BLEND;
+        }
+        fm::Properties {
+            support: support,
+            max_samples: 1,
+        }
+    }
+
     fn create_buffer_raw(&mut self,
                          info: buffer::Info)
                          -> Result<handle::RawBuffer<Resources>, buffer::CreationError> {
@@ -281,6 +327,7 @@ impl core::Factory<Resources> for Factory {
                     unordereds: Vec::new(),
                     samplers: Vec::new(),
                     outputs: Vec::new(),
+                    stream_outputs: Vec::new(),
                     output_depth: false,
                     knows_outputs: false,
                 };
@@ -692,7 +739,8 @@ impl core::Factory<Resources> for Factory {
 
     fn view_texture_as_unordered_access_raw
         (&mut self,
-         _htex: &handle::RawTexture<Resources>)
+         _htex: &handle::RawTexture<Resources>,
+         _desc: core::texture::UnorderedDesc)
          -> Result<handle::RawUnorderedAccessView<Resources>, factory::ResourceViewError> {
         // Err(factory::ResourceViewError::Unsupported) //TODO
         unimplemented!()
@@ -807,19 +855,47 @@ impl core::Factory<Resources> for Factory {
         self.share.handles.borrow_mut().make_sampler(native::Sampler(sampler), info)
     }
 
-    fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources, T>)
-                               -> Result<mapping::Reader<'b, Resources, T>,
-                                         mapping::Error>
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<Resources>, query::CreationError> {
+        Err(query::CreationError::Unsupported(kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, Resources, T>,
+                                               mapping::Error>
         where T: Copy
     {
-        unimplemented!()
+        // the buffers in use by the GPU are not tracked yet, see `create_buffer_internal`
+        unsafe {
+            mapping::read(buf.raw(), range, |_, _| ())
+        }
     }
 
-    fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources, T>)
-                                -> Result<mapping::Writer<'b, Resources, T>,
-                                          mapping::Error>
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, Resources, T>,
+                                                mapping::Error>
         where T: Copy
     {
-        unimplemented!()
+        unsafe {
+            mapping::write(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<Resources, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<Resources, T, _>(buf.raw(), range, memory::WRITE, |_, _| ())
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<Resources, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<Resources, T, _>(buf.raw(), range, memory::READ, |_, _| ())
+        }
     }
 }
+
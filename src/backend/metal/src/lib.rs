@@ -183,6 +183,7 @@ impl core::Resources for Resources {
     type UnorderedAccessView = ();
     type Sampler = native::Sampler;
     type Fence = Fence;
+    type Query = ();
     type Mapping = factory::RawMapping;
 }
 
@@ -243,6 +244,11 @@ impl core::Device for Device {
         unimplemented!()
     }
 
+    fn get_query_result(&mut self, _query: &handle::Query<Self::Resources>) -> Option<u64> {
+        // `create_query` refuses every kind, so there is no query to get the result of
+        None
+    }
+
     fn cleanup(&mut self) {
         use core::handle::Producer;
         self.frame_handles.clear();
@@ -283,7 +289,9 @@ impl core::Device for Device {
                                                    }, // sampler
                                                    |_, _| {
                                                        // fence
-                                                   });
+                                                   },
+                                                   |_, _| {}, // query
+                                                   );
     }
 }
 
@@ -303,6 +311,25 @@ pub fn create(format: core::format::Format,
                         InitError> {
     use core::handle::Producer;
 
+    let mtl_device = create_system_default_device();
+    let feature_sets = {
+        use metal::MTLFeatureSet::*;
+        [OSX_GPUFamily1_v1,
+         //OSX_GPUFamily1_v2,
+         iOS_GPUFamily3_v1,
+         iOS_GPUFamily2_v2,
+         iOS_GPUFamily2_v1,
+         iOS_GPUFamily1_v2,
+         iOS_GPUFamily1_v1]
+    };
+    let selected_set = feature_sets.into_iter()
+                                   .find(|&&f| mtl_device.supports_feature_set(f));
+    // the indirect draws need a macOS or an A9 GPU
+    let draw_indirect_supported = match selected_set {
+        Some(&MTLFeatureSet::OSX_GPUFamily1_v1) | Some(&MTLFeatureSet::iOS_GPUFamily3_v1) => true,
+        _ => false,
+    };
+
     let share = Share {
         capabilities: core::Capabilities {
             max_vertex_count: 0,
@@ -315,27 +342,26 @@ pub fn create(format: core::format::Format,
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: true,
+            constant_buffer_range_supported: true,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            compute_supported: false,
+            draw_indirect_supported: draw_indirect_supported,
+            persistent_mapping_supported: false,
+            alpha_to_coverage_supported: false,
+            sample_mask_supported: false,
+            depth_clamp_supported: false,
+            depth_bias_clamp_supported: false,
+            limits: core::Limits {
+                // buffer offsets of the constant address space
+                constant_buffer_offset_alignment: 256,
+                .. core::Limits::default()
+            },
         },
         handles: RefCell::new(handle::Manager::new()),
     };
 
-    let mtl_device = create_system_default_device();
-    let feature_sets = {
-        use metal::MTLFeatureSet::*;
-        [OSX_GPUFamily1_v1,
-         //OSX_GPUFamily1_v2,
-         iOS_GPUFamily3_v1,
-         iOS_GPUFamily2_v2,
-         iOS_GPUFamily2_v1,
-         iOS_GPUFamily1_v2,
-         iOS_GPUFamily1_v1]
-    };
-    let selected_set = feature_sets.into_iter()
-                                   .find(|&&f| mtl_device.supports_feature_set(f));
-
     let bb = Box::into_raw(Box::new(MTLTexture::nil()));
     let d = Box::into_raw(Box::new(CAMetalDrawable::nil()));
 
@@ -332,6 +332,11 @@ pub fn map_format(format: Format, is_target: bool) -> Option<MTLPixelFormat> {
                 _ => return None,
             }
         }
+        BC1_R8_G8_B8_A1 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC4_R8 | BC5_R8_G8 |
+        BC6_R16_G16_B16 | BC6S_R16_G16_B16 | BC7_R8_G8_B8_A8 | ETC2_R8_G8_B8 |
+        ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 | ASTC_4x4 | ASTC_5x4 |
+        ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 | ASTC_8x6 | ASTC_8x8 | ASTC_10x5 |
+        ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => return None, // TODO
     })
 }
 
@@ -350,6 +355,12 @@ pub fn map_channel_hint(hint: SurfaceType) -> Option<ChannelType> {
         D24 => Unorm,
         D24_S8 => Unorm,
         D32 => Float,
+        BC6_R16_G16_B16 | BC6S_R16_G16_B16 => Float,
+        BC1_R8_G8_B8_A1 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC4_R8 | BC5_R8_G8 |
+        BC7_R8_G8_B8_A8 | ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 |
+        EAC_R11_G11 | ASTC_4x4 | ASTC_5x4 | ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 |
+        ASTC_8x6 | ASTC_8x8 | ASTC_10x5 | ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 |
+        ASTC_12x12 => Unorm,
     })
 }
 
@@ -531,7 +542,7 @@ pub fn map_texture_usage(usage: Usage, bind: Bind) -> (MTLResourceOptions, MTLSt
             (MTLResourceStorageModePrivate, MTLStorageMode::Managed)
         },
         Usage::Dynamic => (MTLResourceCPUCacheModeDefaultCache, MTLStorageMode::Managed),
-        Usage::Upload => (map_access(memory::WRITE), MTLStorageMode::Managed),
+        Usage::Upload | Usage::Persistent => (map_access(memory::WRITE), MTLStorageMode::Managed),
         Usage::Download => (map_access(memory::READ), MTLStorageMode::Managed),
     }
 }
@@ -544,7 +555,7 @@ pub fn map_buffer_usage(usage: Usage, bind: Bind) -> MTLResourceOptions {
             MTLResourceCPUCacheModeDefaultCache | MTLResourceStorageModeManaged
         },
         Usage::Dynamic => MTLResourceCPUCacheModeDefaultCache | MTLResourceStorageModeManaged,
-        Usage::Upload => map_access(memory::WRITE) | MTLResourceStorageModeManaged,
+        Usage::Upload | Usage::Persistent => map_access(memory::WRITE) | MTLResourceStorageModeManaged,
         Usage::Download => map_access(memory::READ) | MTLResourceStorageModeManaged,
     }
 }
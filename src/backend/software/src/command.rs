@@ -0,0 +1,278 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![allow(missing_docs)]
+
+use core::{self as c, command, state as s, texture};
+use core::target::{Depth, Rect, Stencil};
+use {Buffer, PipelineState, Query, Resources, ResourceView, TargetView, Texture};
+
+
+/// The place of some data in the data buffer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DataPointer {
+    offset: u32,
+    size: u32,
+}
+
+pub struct DataBuffer(Vec<u8>);
+impl DataBuffer {
+    /// Create a new empty data buffer.
+    pub fn new() -> DataBuffer {
+        DataBuffer(Vec::new())
+    }
+    /// Copy a given vector slice into the buffer.
+    fn add(&mut self, data: &[u8]) -> DataPointer {
+        self.0.extend_from_slice(data);
+        DataPointer {
+            offset: (self.0.len() - data.len()) as u32,
+            size: data.len() as u32,
+        }
+    }
+    /// Return a reference to a stored data object.
+    pub fn get(&self, ptr: DataPointer) -> &[u8] {
+        &self.0[ptr.offset as usize..(ptr.offset + ptr.size) as usize]
+    }
+}
+
+
+/// Serialized device command.
+#[derive(Clone, Debug)]
+pub enum Command {
+    // states
+    BindPipelineState(PipelineState),
+    BindVertexBuffers(c::pso::VertexBufferSet<Resources>),
+    BindConstantBuffer(c::pso::ConstantBufferParam<Resources>),
+    BindGlobalConstant(c::shade::Location, c::shade::UniformValue),
+    BindResourceView(c::pso::ResourceViewParam<Resources>),
+    BindSampler(c::pso::SamplerParam<Resources>),
+    BindPixelTargets(c::pso::PixelTargetSet<Resources>),
+    BindIndex(Buffer, c::IndexType),
+    SetScissor(Rect),
+    SetViewport(Option<command::Viewport>),
+    SetRefValues(s::RefValues),
+    CopyBuffer(Buffer, Buffer, usize, usize, usize),
+    CopyBufferToTexture(Buffer, usize, Texture, Option<texture::CubeFace>, texture::RawImageInfo),
+    CopyTextureToBuffer(Texture, Option<texture::CubeFace>, texture::RawImageInfo, Buffer, usize),
+    CopyTexture(texture::TextureCopyRegion<Texture>, texture::TextureCopyRegion<Texture>),
+    BlitTexture(texture::TextureCopyRegion<Texture>, texture::TextureCopyRegion<Texture>, texture::FilterMethod),
+    // resource updates
+    UpdateBuffer(Buffer, DataPointer, usize),
+    UpdateTexture(Texture, Option<texture::CubeFace>, DataPointer, texture::RawImageInfo),
+    GenerateMipmap(ResourceView),
+    // drawing
+    ClearColor(TargetView, command::ClearColor),
+    ClearDepthStencil(TargetView, Option<Depth>, Option<Stencil>),
+    Draw(c::VertexCount, c::VertexCount, Option<command::InstanceParams>),
+    DrawIndexed(c::VertexCount, c::VertexCount, c::VertexCount, Option<command::InstanceParams>),
+    DrawIndirect(Buffer, usize, usize),
+    DrawIndexedIndirect(Buffer, usize, usize),
+    BeginQuery(Query),
+    EndQuery(Query),
+    WriteTimestamp(Query),
+}
+
+/// A command buffer recording the calls, to be executed by the device on submission.
+pub struct CommandBuffer {
+    pub buf: Vec<Command>,
+    pub data: DataBuffer,
+}
+
+impl CommandBuffer {
+    pub fn new() -> CommandBuffer {
+        CommandBuffer {
+            buf: Vec::new(),
+            data: DataBuffer::new(),
+        }
+    }
+}
+
+impl command::Buffer<Resources> for CommandBuffer {
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.data.0.clear();
+    }
+
+    fn append(&mut self, other: &CommandBuffer) -> Result<(), command::AppendError> {
+        let data = &mut self.data;
+        self.buf.extend(other.buf.iter().map(|com| match *com {
+            Command::UpdateBuffer(buf, ptr, offset_bytes) =>
+                Command::UpdateBuffer(buf, data.add(other.data.get(ptr)), offset_bytes),
+            Command::UpdateTexture(tex, face, ptr, image) =>
+                Command::UpdateTexture(tex, face, data.add(other.data.get(ptr)), image),
+            ref com => com.clone(),
+        }));
+        Ok(())
+    }
+
+    fn bind_pipeline_state(&mut self, pso: PipelineState) {
+        self.buf.push(Command::BindPipelineState(pso));
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: c::pso::VertexBufferSet<Resources>) {
+        self.buf.push(Command::BindVertexBuffers(vbs));
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[c::pso::ConstantBufferParam<Resources>]) {
+        for param in cbs.iter() {
+            self.buf.push(Command::BindConstantBuffer(param.clone()));
+        }
+    }
+
+    fn bind_global_constant(&mut self, loc: c::shade::Location, value: c::shade::UniformValue) {
+        self.buf.push(Command::BindGlobalConstant(loc, value));
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[c::pso::ResourceViewParam<Resources>]) {
+        for param in srvs.iter() {
+            self.buf.push(Command::BindResourceView(param.clone()));
+        }
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[c::pso::UnorderedViewParam<Resources>]) {
+        if !uavs.is_empty() {
+            error!("Unordered access views are not supported by the software backend");
+        }
+    }
+
+    fn bind_samplers(&mut self, ss: &[c::pso::SamplerParam<Resources>]) {
+        for param in ss.iter() {
+            self.buf.push(Command::BindSampler(param.clone()));
+        }
+    }
+
+    fn bind_pixel_targets(&mut self, pts: c::pso::PixelTargetSet<Resources>) {
+        self.buf.push(Command::BindPixelTargets(pts));
+    }
+
+    fn bind_stream_output(&mut self, so: Option<c::pso::StreamOutputParam<Resources>>) {
+        if so.is_some() {
+            error!("Stream output is not supported by the software backend");
+        }
+    }
+
+    fn bind_index(&mut self, buf: Buffer, itype: c::IndexType) {
+        self.buf.push(Command::BindIndex(buf, itype));
+    }
+
+    fn set_scissor(&mut self, rect: Rect) {
+        self.buf.push(Command::SetScissor(rect));
+    }
+
+    fn set_viewports(&mut self, viewports: &[command::Viewport]) {
+        if viewports.len() > 1 {
+            error!("Only the first of {} viewports is used", viewports.len());
+        }
+        self.buf.push(Command::SetViewport(viewports.first().cloned()));
+    }
+
+    fn set_ref_values(&mut self, rv: s::RefValues) {
+        self.buf.push(Command::SetRefValues(rv));
+    }
+
+    fn copy_buffer(&mut self, src: Buffer, dst: Buffer,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.buf.push(Command::CopyBuffer(src, dst, src_offset_bytes, dst_offset_bytes, size_bytes));
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: Buffer, src_offset_bytes: usize,
+                              dst: Texture, _: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo) {
+        self.buf.push(Command::CopyBufferToTexture(src, src_offset_bytes, dst, face, img));
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: Texture, _: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo,
+                              dst: Buffer, dst_offset_bytes: usize) {
+        self.buf.push(Command::CopyTextureToBuffer(src, face, img, dst, dst_offset_bytes));
+    }
+
+    fn copy_texture(&mut self, src: texture::TextureCopyRegion<Texture>,
+                    dst: texture::TextureCopyRegion<Texture>) {
+        self.buf.push(Command::CopyTexture(src, dst));
+    }
+
+    fn resolve_texture(&mut self, src: texture::TextureCopyRegion<Texture>,
+                       dst: texture::TextureCopyRegion<Texture>) {
+        // multisampled textures are not supported, so there is a single sample to copy
+        self.buf.push(Command::CopyTexture(src, dst));
+    }
+
+    fn blit_texture(&mut self, src: texture::TextureCopyRegion<Texture>,
+                    dst: texture::TextureCopyRegion<Texture>, filter: texture::FilterMethod) {
+        self.buf.push(Command::BlitTexture(src, dst, filter));
+    }
+
+    fn update_buffer(&mut self, buf: Buffer, data: &[u8], offset: usize) {
+        let ptr = self.data.add(data);
+        self.buf.push(Command::UpdateBuffer(buf, ptr, offset));
+    }
+
+    fn update_texture(&mut self, tex: Texture, _: texture::Kind, face: Option<texture::CubeFace>,
+                      data: &[u8], img: texture::RawImageInfo) {
+        let ptr = self.data.add(data);
+        self.buf.push(Command::UpdateTexture(tex, face, ptr, img));
+    }
+
+    fn generate_mipmap(&mut self, srv: ResourceView) {
+        self.buf.push(Command::GenerateMipmap(srv));
+    }
+
+    fn clear_color(&mut self, target: TargetView, value: command::ClearColor) {
+        self.buf.push(Command::ClearColor(target, value));
+    }
+
+    fn clear_depth_stencil(&mut self, target: TargetView,
+                           depth: Option<Depth>, stencil: Option<Stencil>) {
+        self.buf.push(Command::ClearDepthStencil(target, depth, stencil));
+    }
+
+    fn call_draw(&mut self, start: c::VertexCount, count: c::VertexCount,
+                 instances: Option<command::InstanceParams>) {
+        self.buf.push(Command::Draw(start, count, instances));
+    }
+
+    fn call_draw_indexed(&mut self, start: c::VertexCount, count: c::VertexCount,
+                         base: c::VertexCount, instances: Option<command::InstanceParams>) {
+        self.buf.push(Command::DrawIndexed(start, count, base, instances));
+    }
+
+    fn call_draw_indirect(&mut self, buf: Buffer, offset_bytes: usize, draw_count: usize) {
+        self.buf.push(Command::DrawIndirect(buf, offset_bytes, draw_count));
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buf: Buffer, offset_bytes: usize, draw_count: usize) {
+        self.buf.push(Command::DrawIndexedIndirect(buf, offset_bytes, draw_count));
+    }
+
+    fn call_dispatch(&mut self, _: command::ThreadGroupCount, _: command::ThreadGroupCount,
+                     _: command::ThreadGroupCount) {
+        error!("Compute shaders are not supported by the software backend");
+    }
+
+    fn begin_query(&mut self, query: Query) {
+        self.buf.push(Command::BeginQuery(query));
+    }
+
+    fn end_query(&mut self, query: Query) {
+        self.buf.push(Command::EndQuery(query));
+    }
+
+    fn write_timestamp(&mut self, query: Query) {
+        self.buf.push(Command::WriteTimestamp(query));
+    }
+}
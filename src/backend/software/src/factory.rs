@@ -0,0 +1,358 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use std::slice;
+use std::ops::Range;
+
+use core::{self as d, factory as f, texture as t, buffer, mapping, query};
+use core::memory::{self, Bind, UNORDERED_ACCESS, Typed};
+use core::format::ChannelType;
+use core::handle::{self, Producer};
+use core::target::{Layer, Level};
+
+use command::CommandBuffer;
+use {raster, shade, tex};
+use {BufferMemory, Resources as R, Share};
+use {Buffer, Shader, Program, PipelineState, Texture, ResourceView, TargetView, Query};
+
+
+/// Pointer to the memory of a mapped buffer. The memory is owned by the
+/// device storage and never moves, so the mapping stays valid for the
+/// whole lifetime of the buffer.
+#[derive(Debug, Eq, Hash, PartialEq)]
+#[allow(missing_copy_implementations)]
+pub struct MappingGate {
+    pointer: *mut u8,
+}
+
+unsafe impl Send for MappingGate {}
+unsafe impl Sync for MappingGate {}
+
+impl mapping::Gate<R> for MappingGate {
+    unsafe fn set<T>(&self, index: usize, val: T) {
+        *(self.pointer as *mut T).offset(index as isize) = val;
+    }
+
+    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
+        slice::from_raw_parts(self.pointer as *const T, len)
+    }
+
+    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
+        slice::from_raw_parts_mut(self.pointer as *mut T, len)
+    }
+}
+
+
+/// Software resource factory.
+pub struct Factory {
+    share: Rc<Share>,
+    frame_handles: handle::Manager<R>,
+}
+
+impl Clone for Factory {
+    fn clone(&self) -> Factory {
+        Factory::new(self.share.clone())
+    }
+}
+
+impl Factory {
+    /// Create a new `Factory`.
+    pub fn new(share: Rc<Share>) -> Factory {
+        Factory {
+            share: share,
+            frame_handles: handle::Manager::new(),
+        }
+    }
+
+    pub fn create_command_buffer(&mut self) -> CommandBuffer {
+        CommandBuffer::new()
+    }
+
+    fn create_buffer_internal(&mut self, info: buffer::Info, data: Option<&[u8]>)
+                              -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        use core::memory::Usage::*;
+        if info.bind.contains(UNORDERED_ACCESS) {
+            error!("Unordered access is not supported by the software backend");
+            return Err(buffer::CreationError::UnsupportedBind(info.bind));
+        }
+        let mut memory = BufferMemory::new(data, info.size);
+        let mapping = match info.usage {
+            Data | Dynamic => None,
+            Upload | Download | Persistent => Some(MappingGate {
+                pointer: memory.as_mut_ptr(),
+            }),
+        };
+        let id = self.share.storage.borrow_mut().buffers.add(memory);
+        Ok(self.share.handles.borrow_mut().make_buffer(Buffer(id), info, mapping))
+    }
+
+    fn view_texture_as_target(&mut self, htex: &handle::RawTexture<R>, channel: ChannelType,
+                              level: Level, layer: Option<Layer>)
+                              -> Result<TargetView, f::TargetViewError> {
+        let info = htex.get_info();
+        if level >= info.levels {
+            return Err(f::TargetViewError::Level(level));
+        }
+        let texture = *self.frame_handles.ref_texture(htex);
+        let num_layers = match self.share.storage.borrow().textures.get(texture.0) {
+            Some(texture) => texture.num_layers() as Layer,
+            None => return Err(f::TargetViewError::Unsupported),
+        };
+        let layer = match (info.kind, layer) {
+            (t::Kind::D1(..), Some(_)) |
+            (t::Kind::D2(..), Some(_)) |
+            (t::Kind::D3(..), Some(_)) =>
+                return Err(f::TargetViewError::Layer(t::LayerError::NotExpected(info.kind))),
+            (_, Some(l)) if l >= num_layers =>
+                return Err(f::TargetViewError::Layer(t::LayerError::OutOfBounds(l, num_layers))),
+            (_, layer) => layer.unwrap_or(0),
+        };
+        Ok(TargetView {
+            texture: texture,
+            channel: channel,
+            level: level,
+            layer: layer,
+        })
+    }
+}
+
+impl f::Factory<R> for Factory {
+    fn get_capabilities(&self) -> &d::Capabilities {
+        &self.share.capabilities
+    }
+
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<R>, name: &str) {
+        self.share.handles.borrow_mut().set_label(handle, name);
+    }
+
+    fn get_statistics(&self) -> handle::Statistics {
+        self.share.handles.borrow().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: d::format::Format) -> d::format::Properties {
+        use core::format as fm;
+        if tex::texel_size(format.0).is_none() {
+            return fm::Properties::unsupported();
+        }
+        let mut support = fm::SAMPLE | fm::RENDER;
+        if !tex::is_depth(format.0) && format.1 != ChannelType::Int && format.1 != ChannelType::Uint {
+            support |= fm::BLEND;
+        }
+        fm::Properties {
+            support: support,
+            max_samples: 1,
+        }
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        self.create_buffer_internal(info, None)
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        let info = buffer::Info {
+            role: role,
+            usage: memory::Usage::Data,
+            bind: bind,
+            size: data.len(),
+            stride: stride,
+        };
+        self.create_buffer_internal(info, Some(data))
+    }
+
+    fn create_shader(&mut self, stage: d::shade::Stage, code: &[u8])
+                     -> Result<handle::Shader<R>, d::shade::CreateShaderError> {
+        use core::shade::{CreateShaderError, Stage};
+        match stage {
+            Stage::Vertex | Stage::Pixel => (),
+            _ => return Err(CreateShaderError::StageNotSupported(stage)),
+        }
+        let source = match ::std::str::from_utf8(code) {
+            Ok(source) => source,
+            Err(_) => return Err(CreateShaderError::CompilationFailed("The shader code is not valid UTF-8".to_string())),
+        };
+        let shader = try!(shade::Shader::parse(stage, source)
+                                        .map_err(CreateShaderError::CompilationFailed));
+        let id = self.share.storage.borrow_mut().shaders.add(shader);
+        Ok(self.share.handles.borrow_mut().make_shader(Shader(id)))
+    }
+
+    fn create_program(&mut self, shader_set: &d::ShaderSet<R>)
+                      -> Result<handle::Program<R>, d::shade::CreateProgramError> {
+        let (vs, ps) = match shader_set {
+            &d::ShaderSet::Simple(ref vs, ref ps) =>
+                (*vs.reference(&mut self.frame_handles), *ps.reference(&mut self.frame_handles)),
+            _ => return Err("Only the vertex and pixel shader stages are supported by the software backend".into()),
+        };
+        let (program, info) = {
+            let storage = self.share.storage.borrow();
+            match (storage.shaders.get(vs.0), storage.shaders.get(ps.0)) {
+                (Some(vs), Some(ps)) => try!(shade::link(vs, ps)),
+                _ => return Err("Unknown shader".into()),
+            }
+        };
+        let id = self.share.storage.borrow_mut().programs.add(program);
+        Ok(self.share.handles.borrow_mut().make_program(Program(id), info))
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<R>, desc: &d::pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<R>, d::pso::CreationError> {
+        use core::Primitive::*;
+        match desc.primitive {
+            PointList | LineList | LineStrip | TriangleList | TriangleStrip => (),
+            _ => {
+                error!("Primitive {:?} is not supported by the software backend", desc.primitive);
+                return Err(d::pso::CreationError)
+            },
+        }
+        if desc.multisample != d::pso::MultisampleInfo::default() || desc.depth_clamp {
+            error!("Multisample and depth clamp controls are not supported by the software backend");
+            return Err(d::pso::CreationError)
+        }
+        let pipeline = raster::Pipeline {
+            program: self.frame_handles.ref_program(program).0,
+            desc: *desc,
+        };
+        let id = self.share.storage.borrow_mut().psos.add(pipeline);
+        Ok(self.share.handles.borrow_mut().make_pso(PipelineState(id), program))
+    }
+
+    fn create_texture_raw(&mut self, desc: t::Info, hint: Option<ChannelType>, data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<R>, t::CreationError> {
+        use core::texture::CreationError;
+        if desc.levels == 0 {
+            return Err(CreationError::Size(0))
+        }
+        let dim = desc.kind.get_dimensions();
+        let max_size = self.share.capabilities.max_texture_size;
+        for &size in [dim.0, dim.1, dim.2].iter() {
+            if size as usize > max_size {
+                return Err(CreationError::Size(size));
+            }
+        }
+        if dim.3 != t::AaMode::Single {
+            return Err(CreationError::Samples(dim.3));
+        }
+        let mut texture = match tex::Texture::new(desc) {
+            Some(texture) => texture,
+            None => return Err(CreationError::Format(desc.format, hint)),
+        };
+        if let Some(data) = data_opt {
+            try!(texture.init(data));
+        }
+        let id = self.share.storage.borrow_mut().textures.add(texture);
+        Ok(self.share.handles.borrow_mut().make_texture(Texture(id), desc))
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, _hbuf: &handle::RawBuffer<R>)
+                                      -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, _hbuf: &handle::RawBuffer<R>)
+                                       -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::ResourceDesc)
+                                       -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        let info = htex.get_info();
+        if desc.min > desc.max || desc.min >= info.levels {
+            return Err(f::ResourceViewError::Unsupported);
+        }
+        let view = ResourceView::Texture(*self.frame_handles.ref_texture(htex), desc);
+        Ok(self.share.handles.borrow_mut().make_texture_srv(view, htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, _htex: &handle::RawTexture<R>, _desc: t::UnorderedDesc)
+                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        Err(f::ResourceViewError::Unsupported)
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<R>, f::TargetViewError> {
+        self.view_texture_as_target(htex, desc.channel, desc.level, desc.layer)
+            .map(|view| {
+                let dim = htex.get_info().kind.get_level_dimensions(desc.level);
+                self.share.handles.borrow_mut().make_rtv(view, htex, dim)
+            })
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<R>, f::TargetViewError> {
+        self.view_texture_as_target(htex, ChannelType::Unorm, desc.level, desc.layer)
+            .map(|view| {
+                let dim = htex.get_info().kind.get_level_dimensions(desc.level);
+                self.share.handles.borrow_mut().make_dsv(view, htex, dim)
+            })
+    }
+
+    fn create_sampler(&mut self, info: t::SamplerInfo) -> handle::Sampler<R> {
+        self.share.handles.borrow_mut().make_sampler(info, info)
+    }
+
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<R>, query::CreationError> {
+        match kind {
+            query::Kind::Occlusion | query::Kind::Timestamp => (),
+            query::Kind::PipelineStatistics(_) | query::Kind::PrimitivesWritten =>
+                return Err(query::CreationError::Unsupported(kind)),
+        }
+        let id = self.share.storage.borrow_mut().queries.add(None);
+        Ok(self.share.handles.borrow_mut().make_query(Query(id), kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
+        where T: Copy
+    {
+        // commands are executed on submission, so the memory is always up to date
+        unsafe {
+            mapping::read(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::write(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::WRITE, |_, _| ())
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::READ, |_, _| ())
+        }
+    }
+}
@@ -0,0 +1,659 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Software implementation of a device, rasterizing on the CPU into plain
+//! memory. It doesn't need any driver, which makes it suitable for running
+//! rendering tests and comparing the results on machines without a GPU.
+//!
+//! Command buffers get executed synchronously on submission. Shaders are
+//! written in a small interpreted language, described in the README.
+
+#![allow(missing_docs)]
+#![deny(missing_copy_implementations)]
+
+#[macro_use]
+extern crate log;
+extern crate gfx_core as core;
+
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::ops::{self, Deref, DerefMut};
+use std::rc::Rc;
+use std::slice;
+use std::time::Instant;
+use core::{self as c, handle, texture, command as com};
+use core::target::{Layer, Level};
+use command::{Command, DataBuffer};
+
+pub use self::command::CommandBuffer;
+pub use self::factory::Factory;
+
+mod command;
+mod factory;
+mod raster;
+mod shade;
+mod tex;
+
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Buffer(usize);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Shader(usize);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Program(usize);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PipelineState(usize);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Texture(usize);
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Query(usize);
+
+/// Commands are executed upon submission, so the fences are always signaled.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Fence;
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ResourceView {
+    Buffer(Buffer),
+    Texture(Texture, texture::ResourceDesc),
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TargetView {
+    texture: Texture,
+    channel: c::format::ChannelType,
+    level: Level,
+    layer: Layer,
+}
+
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Resources {}
+
+impl c::Resources for Resources {
+    type Buffer              = Buffer;
+    type Shader              = Shader;
+    type Program             = Program;
+    type PipelineStateObject = PipelineState;
+    type Texture             = Texture;
+    type RenderTargetView    = TargetView;
+    type DepthStencilView    = TargetView;
+    type ShaderResourceView  = ResourceView;
+    type UnorderedAccessView = ();
+    type Sampler             = texture::SamplerInfo;
+    type Fence               = Fence;
+    type Query               = Query;
+    type Mapping             = factory::MappingGate;
+}
+
+/// A collection of objects, addressed by the ids given out on insertion.
+struct Store<T> {
+    objects: HashMap<usize, T>,
+    next_id: usize,
+}
+
+impl<T> Store<T> {
+    fn new() -> Store<T> {
+        Store {
+            objects: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    fn add(&mut self, object: T) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.objects.insert(id, object);
+        id
+    }
+
+    fn get(&self, id: usize) -> Option<&T> {
+        let object = self.objects.get(&id);
+        if object.is_none() {
+            error!("Unknown object id {}", id);
+        }
+        object
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        let object = self.objects.get_mut(&id);
+        if object.is_none() {
+            error!("Unknown object id {}", id);
+        }
+        object
+    }
+
+    fn remove(&mut self, id: usize) {
+        self.objects.remove(&id);
+    }
+}
+
+/// Memory of a buffer. It is allocated as 64-bit words, so that it can be
+/// mapped as a slice of any of the element types of the buffers.
+struct BufferMemory {
+    words: Box<[u64]>,
+    size: usize,
+}
+
+impl BufferMemory {
+    fn new(data: Option<&[u8]>, size: usize) -> BufferMemory {
+        let mut memory = BufferMemory {
+            words: vec![0; (size + 7) / 8].into_boxed_slice(),
+            size: size,
+        };
+        if let Some(data) = data {
+            memory.copy_from_slice(data);
+        }
+        memory
+    }
+}
+
+impl Deref for BufferMemory {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.words.as_ptr() as *const u8, self.size) }
+    }
+}
+
+impl DerefMut for BufferMemory {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, self.size) }
+    }
+}
+
+/// Memory and contents of all the device objects.
+struct Storage {
+    buffers: Store<BufferMemory>,
+    textures: Store<tex::Texture>,
+    shaders: Store<shade::Shader>,
+    programs: Store<shade::Program>,
+    psos: Store<raster::Pipeline>,
+    queries: Store<Option<u64>>,
+}
+
+/// Internal struct of shared data between the device and its factories.
+#[doc(hidden)]
+pub struct Share {
+    capabilities: c::Capabilities,
+    storage: RefCell<Storage>,
+    handles: RefCell<handle::Manager<Resources>>,
+}
+
+/// Create a new device with a factory.
+pub fn create() -> (Device, Factory) {
+    let device = Device::new();
+    let factory = Factory::new(device.share.clone());
+    (device, factory)
+}
+
+/// A device rendering on the CPU.
+pub struct Device {
+    share: Rc<Share>,
+    frame_handles: handle::Manager<Resources>,
+    start_time: Instant,
+}
+
+impl Device {
+    fn new() -> Device {
+        let caps = c::Capabilities {
+            max_vertex_count: 1 << 24,
+            max_index_count: 1 << 24,
+            max_texture_size: 1 << 14,
+            max_patch_size: 0,
+            instance_base_supported: true,
+            instance_call_supported: true,
+            instance_rate_supported: true,
+            vertex_base_supported: true,
+            srgb_color_supported: true,
+            constant_buffer_supported: true,
+            constant_buffer_range_supported: true,
+            unordered_access_view_supported: false,
+            separate_blending_slots_supported: true,
+            copy_buffer_supported: true,
+            compute_supported: false,
+            draw_indirect_supported: true,
+            persistent_mapping_supported: true,
+            alpha_to_coverage_supported: false,
+            sample_mask_supported: false,
+            depth_clamp_supported: false,
+            depth_bias_clamp_supported: true,
+            limits: c::Limits {
+                max_texture_3d_size: 1 << 14,
+                max_texture_cube_size: 1 << 14,
+                max_texture_array_layers: 1 << 14,
+                max_anisotropy: 1,
+                max_samples: 1,
+                max_color_targets: c::MAX_COLOR_TARGETS,
+                max_vertex_attributes: c::MAX_VERTEX_ATTRIBUTES,
+                max_viewports: 1,
+                max_constant_buffers: c::MAX_CONSTANT_BUFFERS,
+                max_constant_buffer_size: 1 << 16,
+                constant_buffer_offset_alignment: 16,
+                max_resource_views: c::MAX_RESOURCE_VIEWS,
+                .. c::Limits::default()
+            },
+        };
+        let storage = Storage {
+            buffers: Store::new(),
+            textures: Store::new(),
+            shaders: Store::new(),
+            programs: Store::new(),
+            psos: Store::new(),
+            queries: Store::new(),
+        };
+        let share = Share {
+            capabilities: caps,
+            storage: RefCell::new(storage),
+            handles: RefCell::new(handle::Manager::new()),
+        };
+        Device {
+            share: Rc::new(share),
+            frame_handles: handle::Manager::new(),
+            start_time: Instant::now(),
+        }
+    }
+
+    fn timestamp(&self) -> u64 {
+        let elapsed = self.start_time.elapsed();
+        elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64
+    }
+
+    fn process(&self, storage: &mut Storage, state: &mut raster::State,
+               cmd: &Command, data_buf: &DataBuffer) {
+        match *cmd {
+            Command::BindPipelineState(pso) => {
+                state.pso = Some(pso);
+            },
+            Command::BindVertexBuffers(vbs) => {
+                state.vertex_buffers = vbs;
+            },
+            Command::BindConstantBuffer(c::pso::ConstantBufferParam(buffer, _, slot, range)) => {
+                state.constant_buffers[slot as usize] = Some((buffer, range));
+            },
+            Command::BindGlobalConstant(location, ref value) => {
+                state.globals.insert(location, value.clone());
+            },
+            Command::BindResourceView(c::pso::ResourceViewParam(view, _, slot)) => {
+                state.resource_views[slot as usize] = Some(view);
+            },
+            Command::BindSampler(c::pso::SamplerParam(sampler, _, slot)) => {
+                state.samplers[slot as usize] = Some(sampler);
+            },
+            Command::BindPixelTargets(pts) => {
+                state.targets = pts;
+            },
+            Command::BindIndex(buffer, index_type) => {
+                state.index = Some((buffer, index_type));
+            },
+            Command::SetScissor(rect) => {
+                state.scissor = rect;
+            },
+            Command::SetViewport(viewport) => {
+                state.viewport = viewport;
+            },
+            Command::SetRefValues(rv) => {
+                state.ref_values = rv;
+            },
+            Command::CopyBuffer(src, dst, src_offset, dst_offset, size) => {
+                if src == dst {
+                    let data = match storage.buffers.get_mut(src.0) {
+                        Some(data) => data,
+                        None => return,
+                    };
+                    let (src_range, dst_range) = match (byte_range(src_offset, size), byte_range(dst_offset, size)) {
+                        (Some(src), Some(dst)) if src.end <= data.len() && dst.end <= data.len() => (src, dst),
+                        _ => {
+                            error!("Buffer copy out of bounds");
+                            return
+                        }
+                    };
+                    let temp = data[src_range].to_vec();
+                    data[dst_range].copy_from_slice(&temp);
+                } else {
+                    let temp = match read_buffer(storage, src, src_offset, size) {
+                        Some(slice) => slice.to_vec(),
+                        None => {
+                            error!("Buffer copy source out of bounds");
+                            return
+                        }
+                    };
+                    write_buffer(storage, dst, dst_offset, &temp);
+                }
+            },
+            Command::CopyBufferToTexture(src, src_offset, dst, face, img) => {
+                let temp = match read_buffer(storage, src, src_offset, img.get_byte_count()) {
+                    Some(slice) => slice.to_vec(),
+                    None => {
+                        error!("Buffer to texture copy source out of bounds");
+                        return
+                    }
+                };
+                let texture = match storage.textures.get_mut(dst.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.write_image(face, &img, &temp) {
+                    error!("Buffer to texture copy out of bounds: {:?}", img);
+                }
+            },
+            Command::CopyTextureToBuffer(src, face, img, dst, dst_offset) => {
+                let texture = match storage.textures.get(src.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                let mut temp = vec![0; img.get_byte_count()];
+                if let Err(()) = texture.read_image(face, &img, &mut temp) {
+                    error!("Texture to buffer copy out of bounds: {:?}", img);
+                    return
+                }
+                write_buffer(storage, dst, dst_offset, &temp);
+            },
+            Command::CopyTexture(src, dst) => {
+                let mut temp = vec![0; src.info.get_byte_count()];
+                let texture = match storage.textures.get(src.texture.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.read_image(src.cube_face, &src.info, &mut temp) {
+                    error!("Texture copy source out of bounds: {:?}", src.info);
+                    return
+                }
+                let texture = match storage.textures.get_mut(dst.texture.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.write_image(dst.cube_face, &dst.info, &temp) {
+                    error!("Texture copy destination out of bounds: {:?}", dst.info);
+                }
+            },
+            Command::BlitTexture(src, dst, filter) => {
+                let mut temp = vec![0; src.info.get_byte_count()];
+                let texture = match storage.textures.get(src.texture.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.read_image(src.cube_face, &src.info, &mut temp) {
+                    error!("Texture blit source out of bounds: {:?}", src.info);
+                    return
+                }
+                let extent = |img: &texture::RawImageInfo| (cmp::max(1, img.width as usize),
+                                                            cmp::max(1, img.height as usize),
+                                                            cmp::max(1, img.depth as usize));
+                let mut scaled = vec![0; dst.info.get_byte_count()];
+                tex::scale_image(src.info.format, &temp, extent(&src.info),
+                                 dst.info.format, &mut scaled, extent(&dst.info),
+                                 filter != texture::FilterMethod::Scale);
+                let texture = match storage.textures.get_mut(dst.texture.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.write_image(dst.cube_face, &dst.info, &scaled) {
+                    error!("Texture blit destination out of bounds: {:?}", dst.info);
+                }
+            },
+            Command::UpdateBuffer(buffer, pointer, offset) => {
+                write_buffer(storage, buffer, offset, data_buf.get(pointer));
+            },
+            Command::UpdateTexture(texture, face, pointer, img) => {
+                let data = data_buf.get(pointer);
+                let texture = match storage.textures.get_mut(texture.0) {
+                    Some(texture) => texture,
+                    None => return,
+                };
+                if let Err(()) = texture.write_image(face, &img, data) {
+                    error!("Texture update out of bounds: {:?}", img);
+                }
+            },
+            Command::GenerateMipmap(view) => match view {
+                ResourceView::Texture(texture, desc) => {
+                    if let Some(texture) = storage.textures.get_mut(texture.0) {
+                        texture.generate_mipmap(desc.channel);
+                    }
+                },
+                ResourceView::Buffer(_) => error!("Unable to generate mipmaps for a buffer"),
+            },
+            Command::ClearColor(view, value) => {
+                let color = match value {
+                    com::ClearColor::Float(v) => v,
+                    com::ClearColor::Int(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+                    com::ClearColor::Uint(v) => [v[0] as f32, v[1] as f32, v[2] as f32, v[3] as f32],
+                };
+                if let Some(texture) = storage.textures.get_mut(view.texture.0) {
+                    texture.clear_color(view.level, view.layer, view.channel, color);
+                }
+            },
+            Command::ClearDepthStencil(view, depth, stencil) => {
+                if let Some(texture) = storage.textures.get_mut(view.texture.0) {
+                    texture.clear_depth_stencil(view.level, view.layer, depth, stencil);
+                }
+            },
+            Command::Draw(start, count, instances) => {
+                let vertices = match vertex_range(start, count) {
+                    Some(vertices) => vertices,
+                    None => return,
+                };
+                raster::draw(storage, state, &vertices, instances);
+            },
+            Command::DrawIndexed(start, count, base, instances) => {
+                let vertices = match read_indices(storage, state, start, count, base as i32) {
+                    Some(vertices) => vertices,
+                    None => return,
+                };
+                raster::draw(storage, state, &vertices, instances);
+            },
+            Command::DrawIndirect(buffer, offset, draw_count) => {
+                for i in 0 .. draw_count {
+                    let args = match read_words(storage, buffer, offset, i, 4) {
+                        Some(args) => args,
+                        None => return,
+                    };
+                    let vertices = match vertex_range(args[2], args[0]) {
+                        Some(vertices) => vertices,
+                        None => return,
+                    };
+                    raster::draw(storage, state, &vertices, Some((args[1], args[3])));
+                }
+            },
+            Command::DrawIndexedIndirect(buffer, offset, draw_count) => {
+                for i in 0 .. draw_count {
+                    let args = match read_words(storage, buffer, offset, i, 5) {
+                        Some(args) => args,
+                        None => return,
+                    };
+                    let vertices = match read_indices(storage, state, args[2], args[0], args[3] as i32) {
+                        Some(vertices) => vertices,
+                        None => return,
+                    };
+                    raster::draw(storage, state, &vertices, Some((args[1], args[4])));
+                }
+            },
+            Command::BeginQuery(query) => {
+                if let Some(result) = storage.queries.get_mut(query.0) {
+                    *result = None;
+                }
+                state.occlusion_queries.push((query, 0));
+            },
+            Command::EndQuery(query) => {
+                match state.occlusion_queries.iter().position(|&(q, _)| q == query) {
+                    Some(index) => {
+                        let (_, count) = state.occlusion_queries.swap_remove(index);
+                        if let Some(result) = storage.queries.get_mut(query.0) {
+                            *result = Some(count);
+                        }
+                    },
+                    None => error!("Ending query {:?} that was not started", query),
+                }
+            },
+            Command::WriteTimestamp(query) => {
+                if let Some(result) = storage.queries.get_mut(query.0) {
+                    *result = Some(self.timestamp());
+                }
+            },
+        }
+    }
+}
+
+/// The range of `size` bytes at `offset`, or `None` if it overflows.
+fn byte_range(offset: usize, size: usize) -> Option<ops::Range<usize>> {
+    offset.checked_add(size).map(|end| offset .. end)
+}
+
+/// The vertices of a non-indexed draw, or `None` if the range overflows.
+fn vertex_range(start: c::VertexCount, count: c::VertexCount) -> Option<Vec<usize>> {
+    match start.checked_add(count) {
+        Some(end) => Some((start .. end).map(|v| v as usize).collect()),
+        None => {
+            error!("Vertex range of {} vertices at {} overflows", count, start);
+            None
+        }
+    }
+}
+
+fn read_buffer(storage: &Storage, buffer: Buffer, offset: usize, size: usize) -> Option<&[u8]> {
+    match (storage.buffers.get(buffer.0), byte_range(offset, size)) {
+        (Some(data), Some(range)) => data.get(range),
+        _ => None,
+    }
+}
+
+fn write_buffer(storage: &mut Storage, buffer: Buffer, offset: usize, data: &[u8]) {
+    let slice = match (storage.buffers.get_mut(buffer.0), byte_range(offset, data.len())) {
+        (Some(memory), Some(range)) => memory.get_mut(range),
+        _ => None,
+    };
+    match slice {
+        Some(slice) => slice.copy_from_slice(data),
+        None => error!("Buffer write out of bounds: {} bytes at offset {}", data.len(), offset),
+    }
+}
+
+/// Read the `index`-th record of `count` words of indirect arguments at `offset`.
+fn read_words(storage: &Storage, buffer: Buffer, offset: usize, index: usize, count: usize) -> Option<Vec<u32>> {
+    let bytes = index.checked_mul(count * 4)
+                     .and_then(|stride| stride.checked_add(offset))
+                     .and_then(|offset| read_buffer(storage, buffer, offset, count * 4));
+    match bytes {
+        Some(bytes) => Some(bytes.chunks(4).map(|b| {
+            b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+        }).collect()),
+        None => {
+            error!("Indirect arguments out of bounds at offset {}", offset);
+            None
+        }
+    }
+}
+
+fn read_indices(storage: &Storage, state: &raster::State,
+                start: c::VertexCount, count: c::VertexCount, base: i32) -> Option<Vec<usize>> {
+    let (buffer, index_type) = match state.index {
+        Some(index) => index,
+        None => {
+            error!("Indexed draw without an index buffer bound");
+            return None
+        }
+    };
+    let size = match index_type {
+        c::IndexType::U16 => 2,
+        c::IndexType::U32 => 4,
+    };
+    let bytes = match (start as usize).checked_mul(size).and_then(|offset| {
+        (count as usize).checked_mul(size).and_then(|count| read_buffer(storage, buffer, offset, count))
+    }) {
+        Some(bytes) => bytes,
+        None => {
+            error!("Indices out of bounds of the index buffer");
+            return None
+        }
+    };
+    let vertices: Option<Vec<_>> = bytes.chunks(size).map(|b| {
+        let index = b.iter().rev().fold(0, |u, &b| (u << 8) | b as i64);
+        match index + base as i64 {
+            v if v < 0 || v > usize::max_value() as i64 => None,
+            v => Some(v as usize),
+        }
+    }).collect();
+    if vertices.is_none() {
+        error!("Indices with the base vertex {} are out of the vertex range", base);
+    }
+    vertices
+}
+
+impl c::Device for Device {
+    type Resources = Resources;
+    type CommandBuffer = command::CommandBuffer;
+
+    fn get_capabilities(&self) -> &c::Capabilities {
+        &self.share.capabilities
+    }
+
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<Resources>) {
+        self.frame_handles.extend(man);
+    }
+
+    fn submit(&mut self,
+              cb: &mut command::CommandBuffer,
+              access: &com::AccessInfo<Resources>) -> c::SubmissionResult<()>
+    {
+        // the mappings are coherent, and the work is done by the time we return,
+        // so holding the accesses for the duration of the call is enough
+        let _access = try!(access.take_accesses());
+        let mut storage = self.share.storage.borrow_mut();
+        let mut state = raster::State::new();
+        for com in cb.buf.iter() {
+            self.process(&mut storage, &mut state, com, &cb.data);
+        }
+        Ok(())
+    }
+
+    fn fenced_submit(&mut self,
+                     cb: &mut command::CommandBuffer,
+                     access: &com::AccessInfo<Resources>,
+                     _after: Option<handle::Fence<Resources>>)
+                     -> c::SubmissionResult<handle::Fence<Resources>> {
+        use core::handle::Producer;
+        try!(self.submit(cb, access));
+        Ok(self.share.handles.borrow_mut().make_fence(Fence))
+    }
+
+    fn wait_fence(&mut self, _: &handle::Fence<Self::Resources>) {}
+
+    fn get_query_result(&mut self, query: &handle::Query<Resources>) -> Option<u64> {
+        let id = self.frame_handles.ref_query(query).0;
+        self.share.storage.borrow().queries.get(id).and_then(|result| *result)
+    }
+
+    fn cleanup(&mut self) {
+        use core::handle::Producer;
+        self.frame_handles.clear();
+        self.share.handles.borrow_mut().clean_with(&mut *self.share.storage.borrow_mut(),
+            |s, buffer| s.buffers.remove(buffer.resource().0),
+            |s, shader| s.shaders.remove(shader.0),
+            |s, program| s.programs.remove(program.resource().0),
+            |s, pso| s.psos.remove(pso.0),
+            |s, texture| s.textures.remove(texture.resource().0),
+            |_, _| {}, //SRV
+            |_, _| {}, //UAV
+            |_, _| {}, //RTV
+            |_, _| {}, //DSV
+            |_, _| {}, //sampler
+            |_, _| {}, //fence
+            |s, query| s.queries.remove(query.0),
+        );
+    }
+}
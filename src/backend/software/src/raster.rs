@@ -0,0 +1,741 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rasterization pipeline: vertex fetch and shading, clipping, triangle,
+//! line and point rasterization, and the per-fragment operations.
+//!
+//! Clip space follows the GL conventions: the depth range is mapped from
+//! [-1, 1] to [0, 1], and the normalized device coordinate y = -1 ends up
+//! in the row 0 of the render targets.
+
+use std::{cmp, mem};
+use std::collections::HashMap;
+use core::{self as c, pso, state as s, MAX_CONSTANT_BUFFERS, MAX_RESOURCE_VIEWS, MAX_SAMPLERS};
+use core::command::{InstanceParams, Viewport};
+use core::format::Format;
+use core::shade::{Location, Stage, UniformValue};
+use core::target::{Layer, Level, Rect};
+use core::texture::SamplerInfo;
+use shade::{self, Program, Register};
+use {tex, Buffer, PipelineState, Query, Resources, ResourceView, Storage, TargetView};
+
+
+/// Pipeline state object, referring to the linked program.
+pub struct Pipeline {
+    pub program: usize,
+    pub desc: pso::Descriptor,
+}
+
+/// Bound state of a command buffer being executed.
+pub struct State {
+    pub pso: Option<PipelineState>,
+    pub vertex_buffers: pso::VertexBufferSet<Resources>,
+    /// Bound constant buffers, with the range to read or the whole buffer.
+    pub constant_buffers: [Option<(Buffer, Option<pso::BufferRange>)>; MAX_CONSTANT_BUFFERS],
+    pub globals: HashMap<Location, UniformValue>,
+    pub resource_views: [Option<ResourceView>; MAX_RESOURCE_VIEWS],
+    pub samplers: [Option<SamplerInfo>; MAX_SAMPLERS],
+    pub targets: pso::PixelTargetSet<Resources>,
+    pub index: Option<(Buffer, c::IndexType)>,
+    pub scissor: Rect,
+    /// Custom viewport, covering the whole targets if not set.
+    pub viewport: Option<Viewport>,
+    pub ref_values: s::RefValues,
+    /// Occlusion queries in progress, with the number of samples passed so far.
+    pub occlusion_queries: Vec<(Query, u64)>,
+}
+
+impl State {
+    pub fn new() -> State {
+        State {
+            pso: None,
+            vertex_buffers: pso::VertexBufferSet::new(),
+            constant_buffers: [None; MAX_CONSTANT_BUFFERS],
+            globals: HashMap::new(),
+            resource_views: [None; MAX_RESOURCE_VIEWS],
+            samplers: [None; MAX_SAMPLERS],
+            targets: pso::PixelTargetSet::new(),
+            index: None,
+            scissor: Rect { x: 0, y: 0, w: 1, h: 1 },
+            viewport: None,
+            ref_values: Default::default(),
+            occlusion_queries: Vec::new(),
+        }
+    }
+}
+
+/// A render target, with the texture memory taken out of the storage
+/// for the duration of the draw call.
+struct Target {
+    id: usize,
+    texture: tex::Texture,
+    format: Format,
+    level: Level,
+    layer: Layer,
+}
+
+impl Target {
+    fn take(storage: &mut Storage, view: TargetView) -> Option<Target> {
+        let texture = match storage.textures.get_mut(view.texture.0) {
+            Some(texture) => texture,
+            None => return None,
+        };
+        Some(Target {
+            id: view.texture.0,
+            texture: tex::Texture {
+                info: texture.info,
+                data: mem::replace(&mut texture.data, Vec::new()),
+            },
+            format: Format(texture.info.format, view.channel),
+            level: view.level,
+            layer: view.layer,
+        })
+    }
+
+    fn restore(self, storage: &mut Storage) {
+        if let Some(texture) = storage.textures.get_mut(self.id) {
+            texture.data = self.texture.data;
+        }
+    }
+
+    /// Get the bytes of a texel, or `None` if the texture is bound more than once.
+    fn texel(&mut self, x: usize, y: usize) -> Option<&mut [u8]> {
+        let offset = self.texture.texel_offset(self.level, self.layer as usize, x, y, 0);
+        let size = self.texture.texel_size();
+        self.texture.data.get_mut(offset .. offset + size)
+    }
+}
+
+/// Output of the vertex shader.
+#[derive(Clone)]
+struct Vertex {
+    position: Register,
+    varyings: Vec<Register>,
+}
+
+impl Vertex {
+    fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+        Vertex {
+            position: lerp(self.position, other.position, t),
+            varyings: self.varyings.iter().zip(other.varyings.iter())
+                          .map(|(&a, &b)| lerp(a, b, t))
+                          .collect(),
+        }
+    }
+}
+
+/// Vertex in window coordinates, with the varyings divided by W
+/// for the perspective-correct interpolation.
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    z: f32,
+    inv_w: f32,
+    varyings: Vec<Register>,
+}
+
+fn lerp(a: Register, b: Register, t: f32) -> Register {
+    [a[0] + (b[0] - a[0]) * t,
+     a[1] + (b[1] - a[1]) * t,
+     a[2] + (b[2] - a[2]) * t,
+     a[3] + (b[3] - a[3]) * t]
+}
+
+fn scale(a: Register, k: f32) -> Register {
+    [a[0] * k, a[1] * k, a[2] * k, a[3] * k]
+}
+
+const NUM_CLIP_PLANES: usize = 3;
+
+/// Signed distance to the near, far and W = 0 planes, positive inside.
+fn clip_distance(plane: usize, p: &Register) -> f32 {
+    match plane {
+        0 => p[3] + p[2],
+        1 => p[3] - p[2],
+        _ => p[3] - 1.0e-6,
+    }
+}
+
+fn clip_polygon(mut polygon: Vec<Vertex>) -> Vec<Vertex> {
+    for plane in 0 .. NUM_CLIP_PLANES {
+        if polygon.is_empty() {
+            break
+        }
+        let mut output = Vec::with_capacity(polygon.len() + 1);
+        for i in 0 .. polygon.len() {
+            let (a, b) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+            let (da, db) = (clip_distance(plane, &a.position), clip_distance(plane, &b.position));
+            if da >= 0.0 {
+                output.push(a.clone());
+            }
+            if (da >= 0.0) != (db >= 0.0) {
+                output.push(a.lerp(b, da / (da - db)));
+            }
+        }
+        polygon = output;
+    }
+    polygon
+}
+
+fn clip_line(mut a: Vertex, mut b: Vertex) -> Option<(Vertex, Vertex)> {
+    for plane in 0 .. NUM_CLIP_PLANES {
+        let (da, db) = (clip_distance(plane, &a.position), clip_distance(plane, &b.position));
+        match (da >= 0.0, db >= 0.0) {
+            (true, true) => (),
+            (false, false) => return None,
+            (true, false) => b = a.lerp(&b, da / (da - db)),
+            (false, true) => a = a.lerp(&b, da / (da - db)),
+        }
+    }
+    Some((a, b))
+}
+
+fn compare(fun: s::Comparison, a: u32, b: u32) -> bool {
+    use core::state::Comparison::*;
+    match fun {
+        Never => false,
+        Less => a < b,
+        LessEqual => a <= b,
+        Equal => a == b,
+        GreaterEqual => a >= b,
+        Greater => a > b,
+        NotEqual => a != b,
+        Always => true,
+    }
+}
+
+fn compare_depth(fun: s::Comparison, a: f32, b: f32) -> bool {
+    use core::state::Comparison::*;
+    match fun {
+        Never => false,
+        Less => a < b,
+        LessEqual => a <= b,
+        Equal => a == b,
+        GreaterEqual => a >= b,
+        Greater => a > b,
+        NotEqual => a != b,
+        Always => true,
+    }
+}
+
+fn stencil_op(op: s::StencilOp, value: u8, reference: u8) -> u8 {
+    use core::state::StencilOp::*;
+    match op {
+        Keep => value,
+        Zero => 0,
+        Replace => reference,
+        IncrementClamp => value.saturating_add(1),
+        IncrementWrap => value.wrapping_add(1),
+        DecrementClamp => value.saturating_sub(1),
+        DecrementWrap => value.wrapping_sub(1),
+        Invert => !value,
+    }
+}
+
+fn blend_channel(channel: s::BlendChannel, src: Register, dst: Register,
+                 constant: Register, index: usize) -> f32 {
+    let value = |v: s::BlendValue| match v {
+        s::BlendValue::SourceColor => src[index],
+        s::BlendValue::SourceAlpha => src[3],
+        s::BlendValue::DestColor => dst[index],
+        s::BlendValue::DestAlpha => dst[3],
+        s::BlendValue::ConstColor => constant[index],
+        s::BlendValue::ConstAlpha => constant[3],
+    };
+    let factor = |f: s::Factor| match f {
+        s::Factor::Zero => 0.0,
+        s::Factor::One => 1.0,
+        s::Factor::SourceAlphaSaturated if index == 3 => 1.0,
+        s::Factor::SourceAlphaSaturated => src[3].min(1.0 - dst[3]),
+        s::Factor::ZeroPlus(v) => value(v),
+        s::Factor::OneMinus(v) => 1.0 - value(v),
+    };
+    let s = src[index] * factor(channel.source);
+    let d = dst[index] * factor(channel.destination);
+    match channel.equation {
+        s::Equation::Add => s + d,
+        s::Equation::Sub => s - d,
+        s::Equation::RevSub => d - s,
+        s::Equation::Min => src[index].min(dst[index]),
+        s::Equation::Max => src[index].max(dst[index]),
+    }
+}
+
+/// Everything needed to process the primitives of a draw call.
+struct Context<'a> {
+    storage: &'a Storage,
+    state: &'a State,
+    desc: &'a pso::Descriptor,
+    program: &'a Program,
+    colors: &'a mut [Option<Target>],
+    depth_stencil: &'a mut Option<Target>,
+    ps_registers: Vec<Register>,
+    width: usize,
+    height: usize,
+    viewport: Viewport,
+    samples_passed: u64,
+}
+
+impl<'a> Context<'a> {
+    fn sample(&self, slot: c::ResourceViewSlot, sampler: c::SamplerSlot, coord: Register) -> Register {
+        match (self.state.resource_views[slot as usize], self.state.samplers[sampler as usize]) {
+            (Some(ResourceView::Texture(texture, ref desc)), Some(ref info)) =>
+                match self.storage.textures.get(texture.0) {
+                    Some(texture) => texture.sample(desc, info, coord),
+                    None => [0.0; 4],
+                },
+            _ => [0.0; 4],
+        }
+    }
+
+    fn fetch_attribute(&self, slot: usize, vertex: usize, instance: c::InstanceCount,
+                       base_instance: c::VertexCount) -> Register {
+        let (buffer_index, element) = match self.desc.attributes[slot] {
+            Some(at) => at,
+            None => return [0.0, 0.0, 0.0, 1.0],
+        };
+        let (buffer, offset) = match self.state.vertex_buffers.0[slot] {
+            Some(vb) => vb,
+            None => {
+                error!("No vertex buffer bound for the attribute {}", slot);
+                return [0.0, 0.0, 0.0, 1.0]
+            }
+        };
+        let vb_desc = match self.desc.vertex_buffers[buffer_index as usize] {
+            Some(vb_desc) => vb_desc,
+            None => return [0.0, 0.0, 0.0, 1.0],
+        };
+        let index = match vb_desc.rate {
+            0 => vertex,
+            rate => (base_instance + instance / rate as c::InstanceCount) as usize,
+        };
+        let start = offset + index * vb_desc.stride as usize + element.offset as usize;
+        let size = element.format.0.get_total_bits() as usize / 8;
+        match self.storage.buffers.get(buffer.0).and_then(|data| data.get(start .. start + size)) {
+            Some(bytes) => tex::read_texel(element.format, bytes),
+            None => {
+                error!("Vertex attribute {} is out of the buffer bounds", slot);
+                [0.0, 0.0, 0.0, 1.0]
+            }
+        }
+    }
+
+    fn shade_vertex(&self, base: &[Register], vertex: usize, instance: c::InstanceCount,
+                    base_instance: c::VertexCount) -> Vertex {
+        let mut regs = base.to_vec();
+        for (slot, var) in self.program.vs.attributes.iter().enumerate() {
+            regs[var.reg] = self.fetch_attribute(slot, vertex, instance, base_instance);
+        }
+        regs[shade::VERTEX_ID] = [vertex as f32, 0.0, 0.0, 0.0];
+        regs[shade::INSTANCE_ID] = [instance as f32, 0.0, 0.0, 0.0];
+        self.program.execute(Stage::Vertex, &mut regs, |slot, sampler, coord| self.sample(slot, sampler, coord));
+        Vertex {
+            position: regs[shade::POSITION],
+            varyings: self.program.vs.varyings.iter().map(|var| regs[var.reg]).collect(),
+        }
+    }
+
+    fn to_screen(&self, v: &Vertex) -> ScreenVertex {
+        let inv_w = 1.0 / v.position[3];
+        let vp = &self.viewport;
+        ScreenVertex {
+            x: vp.rect.x as f32 + (v.position[0] * inv_w * 0.5 + 0.5) * vp.rect.w as f32,
+            y: vp.rect.y as f32 + (v.position[1] * inv_w * 0.5 + 0.5) * vp.rect.h as f32,
+            z: vp.near + (v.position[2] * inv_w * 0.5 + 0.5) * (vp.far - vp.near),
+            inv_w: inv_w,
+            varyings: v.varyings.iter().map(|&var| scale(var, inv_w)).collect(),
+        }
+    }
+
+    fn draw_triangle(&mut self, a: &Vertex, b: &Vertex, c: &Vertex) {
+        let polygon = clip_polygon(vec![a.clone(), b.clone(), c.clone()]);
+        let screen: Vec<_> = polygon.iter().map(|v| self.to_screen(v)).collect();
+        for i in 1 .. cmp::max(2, screen.len()) - 1 {
+            self.raster_triangle(&screen[0], &screen[i], &screen[i + 1]);
+        }
+    }
+
+    fn raster_triangle(&mut self, v0: &ScreenVertex, v1: &ScreenVertex, v2: &ScreenVertex) {
+        let area = (v1.x - v0.x) * (v2.y - v0.y) - (v2.x - v0.x) * (v1.y - v0.y);
+        if area == 0.0 {
+            return
+        }
+        let rast = self.desc.rasterizer;
+        let front = match rast.front_face {
+            s::FrontFace::CounterClockwise => area > 0.0,
+            s::FrontFace::Clockwise => area < 0.0,
+        };
+        match (rast.cull_face, front) {
+            (s::CullFace::Front, true) | (s::CullFace::Back, false) => return,
+            _ => (),
+        }
+        match rast.method {
+            s::RasterMethod::Point => {
+                for v in [v0, v1, v2].iter() {
+                    self.raster_point(v, front);
+                }
+                return
+            },
+            s::RasterMethod::Line(_) => {
+                self.raster_line(v0, v1, front);
+                self.raster_line(v1, v2, front);
+                self.raster_line(v2, v0, front);
+                return
+            },
+            s::RasterMethod::Fill => (),
+        }
+        // make the winding counter-clockwise
+        let (v1, v2, area) = if area > 0.0 { (v1, v2, area) } else { (v2, v1, -area) };
+        let bias = match (self.desc.depth_bias, rast.offset) {
+            (Some(b), _) => Some((b.slope_scaled(), b.constant(), b.clamp())),
+            (None, Some(s::Offset(slope, units))) => Some((slope as f32, units as f32, 0.0)),
+            (None, None) => None,
+        };
+        let offset = match bias {
+            Some((slope, units, clamp)) => {
+                let dzdx = ((v1.z - v0.z) * (v2.y - v0.y) - (v2.z - v0.z) * (v1.y - v0.y)) / area;
+                let dzdy = ((v2.z - v0.z) * (v1.x - v0.x) - (v1.z - v0.z) * (v2.x - v0.x)) / area;
+                let offset = slope * dzdx.abs().max(dzdy.abs()) + units / (1 << 24) as f32;
+                if clamp > 0.0 {
+                    offset.min(clamp)
+                } else if clamp < 0.0 {
+                    offset.max(clamp)
+                } else {
+                    offset
+                }
+            },
+            None => 0.0,
+        };
+        let edge = |a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32| {
+            (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+        };
+        // top-left fill convention
+        let is_top_left = |a: &ScreenVertex, b: &ScreenVertex| {
+            (a.y == b.y && b.x < a.x) || b.y < a.y
+        };
+        let bias = [is_top_left(v1, v2), is_top_left(v2, v0), is_top_left(v0, v1)];
+        let (x0, x1, y0, y1) = self.bounds(
+            v0.x.min(v1.x).min(v2.x), v0.x.max(v1.x).max(v2.x),
+            v0.y.min(v1.y).min(v2.y), v0.y.max(v1.y).max(v2.y));
+        let mut varyings = vec![[0.0; 4]; v0.varyings.len()];
+        for y in y0 .. y1 {
+            for x in x0 .. x1 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w = [edge(v1, v2, px, py), edge(v2, v0, px, py), edge(v0, v1, px, py)];
+                if (0 .. 3).any(|i| w[i] < 0.0 || (w[i] == 0.0 && !bias[i])) {
+                    continue
+                }
+                let b = [w[0] / area, w[1] / area, w[2] / area];
+                let z = b[0] * v0.z + b[1] * v1.z + b[2] * v2.z + offset;
+                let inv_w = b[0] * v0.inv_w + b[1] * v1.inv_w + b[2] * v2.inv_w;
+                for (i, var) in varyings.iter_mut().enumerate() {
+                    for j in 0 .. 4 {
+                        var[j] = (b[0] * v0.varyings[i][j] + b[1] * v1.varyings[i][j] +
+                                  b[2] * v2.varyings[i][j]) / inv_w;
+                    }
+                }
+                self.shade_fragment(x, y, z, inv_w, &varyings, front);
+            }
+        }
+    }
+
+    fn draw_line(&mut self, a: &Vertex, b: &Vertex) {
+        if let Some((a, b)) = clip_line(a.clone(), b.clone()) {
+            let (a, b) = (self.to_screen(&a), self.to_screen(&b));
+            self.raster_line(&a, &b, true);
+        }
+    }
+
+    fn raster_line(&mut self, a: &ScreenVertex, b: &ScreenVertex, front: bool) {
+        let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1.0) as usize;
+        let mut varyings = vec![[0.0; 4]; a.varyings.len()];
+        for step in 0 .. steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let (x, y) = (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+            if x < 0.0 || y < 0.0 {
+                continue
+            }
+            let inv_w = a.inv_w + (b.inv_w - a.inv_w) * t;
+            for (i, var) in varyings.iter_mut().enumerate() {
+                *var = scale(lerp(a.varyings[i], b.varyings[i], t), 1.0 / inv_w);
+            }
+            let z = a.z + (b.z - a.z) * t;
+            self.shade_fragment(x as usize, y as usize, z, inv_w, &varyings, front);
+        }
+    }
+
+    fn draw_point(&mut self, v: &Vertex) {
+        if (0 .. NUM_CLIP_PLANES).all(|plane| clip_distance(plane, &v.position) >= 0.0) {
+            let v = self.to_screen(v);
+            self.raster_point(&v, true);
+        }
+    }
+
+    fn raster_point(&mut self, v: &ScreenVertex, front: bool) {
+        if v.x < 0.0 || v.y < 0.0 {
+            return
+        }
+        let varyings: Vec<_> = v.varyings.iter().map(|&var| scale(var, 1.0 / v.inv_w)).collect();
+        self.shade_fragment(v.x as usize, v.y as usize, v.z, v.inv_w, &varyings, front);
+    }
+
+    /// Clamp a bounding box to the render targets, the viewport and the scissor rectangle.
+    fn bounds(&self, x0: f32, x1: f32, y0: f32, y1: f32) -> (usize, usize, usize, usize) {
+        let vp = self.viewport.rect;
+        let (mut min_x, mut max_x) = (vp.x as usize, cmp::min(self.width, vp.x as usize + vp.w as usize));
+        let (mut min_y, mut max_y) = (vp.y as usize, cmp::min(self.height, vp.y as usize + vp.h as usize));
+        if self.desc.scissor {
+            let r = self.state.scissor;
+            min_x = cmp::max(min_x, r.x as usize);
+            max_x = cmp::min(max_x, r.x as usize + r.w as usize);
+            min_y = cmp::max(min_y, r.y as usize);
+            max_y = cmp::min(max_y, r.y as usize + r.h as usize);
+        }
+        let clamp = |v: f32, lo: usize, hi: usize| cmp::min(hi, cmp::max(lo as isize, v as isize) as usize);
+        (clamp(x0.floor(), min_x, max_x), clamp(x1.ceil(), min_x, max_x),
+         clamp(y0.floor(), min_y, max_y), clamp(y1.ceil(), min_y, max_y))
+    }
+
+    fn shade_fragment(&mut self, x: usize, y: usize, z: f32, inv_w: f32,
+                      varyings: &[Register], front: bool) {
+        let (x0, x1, y0, y1) = self.bounds(x as f32, x as f32 + 1.0, y as f32, y as f32 + 1.0);
+        if x0 == x1 || y0 == y1 {
+            return
+        }
+        let mut regs = self.ps_registers.clone();
+        regs[shade::FRAG_COORD] = [x as f32 + 0.5, y as f32 + 0.5, z, inv_w];
+        for (var, &index) in self.program.ps.varyings.iter().zip(self.program.varyings.iter()) {
+            regs[var.reg] = varyings[index];
+        }
+        let alive = {
+            let this = &*self;
+            this.program.execute(Stage::Pixel, &mut regs, |slot, sampler, coord| this.sample(slot, sampler, coord))
+        };
+        if !alive || !self.depth_stencil_test(x, y, z, front) {
+            return
+        }
+        self.samples_passed += 1;
+
+        let constant = self.state.ref_values.blend;
+        for (slot, out) in self.program.ps.outputs.iter().enumerate() {
+            let info = match self.desc.color_targets.get(slot) {
+                Some(&Some((_, info))) => info,
+                _ => continue,
+            };
+            let target = match self.colors[slot] {
+                Some(ref mut target) => target,
+                None => continue,
+            };
+            let format = target.format;
+            let bytes = match target.texel(x, y) {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+            let dst = tex::read_texel(format, bytes);
+            let mut src = regs[out.reg];
+            if info.color.is_some() || info.alpha.is_some() {
+                let color = info.color.unwrap_or_default();
+                let alpha = info.alpha.unwrap_or_default();
+                src = [blend_channel(color, src, dst, constant, 0),
+                       blend_channel(color, src, dst, constant, 1),
+                       blend_channel(color, src, dst, constant, 2),
+                       blend_channel(alpha, src, dst, constant, 3)];
+            }
+            let masks = [s::RED, s::GREEN, s::BLUE, s::ALPHA];
+            for i in 0 .. 4 {
+                if (info.mask & masks[i]).is_empty() {
+                    src[i] = dst[i];
+                }
+            }
+            tex::write_texel(format, bytes, src);
+        }
+    }
+
+    /// Run the stencil and depth tests, updating the depth-stencil target.
+    fn depth_stencil_test(&mut self, x: usize, y: usize, z: f32, front: bool) -> bool {
+        let info = match self.desc.depth_stencil {
+            Some((_, info)) => info,
+            None => return true,
+        };
+        let (has_depth, has_stencil) = (self.state.targets.depth.is_some(), self.state.targets.stencil.is_some());
+        let (stencil_ref, stencil_side) = if front {
+            (self.state.ref_values.stencil.0, info.front)
+        } else {
+            (self.state.ref_values.stencil.1, info.back)
+        };
+        let target = match *self.depth_stencil {
+            Some(ref mut target) => target,
+            None => return true,
+        };
+        let surface = target.format.0;
+        let bytes = match target.texel(x, y) {
+            Some(bytes) => bytes,
+            None => return true,
+        };
+        let stencil = match stencil_side {
+            Some(side) if has_stencil => Some((side, tex::read_stencil(surface, bytes))),
+            _ => None,
+        };
+        let update_stencil = |bytes: &mut [u8], op: fn(&s::StencilSide) -> s::StencilOp| {
+            if let Some((side, value)) = stencil {
+                let new = stencil_op(op(&side), value, stencil_ref);
+                let mask = side.mask_write;
+                tex::write_stencil(surface, bytes, (value & !mask) | (new & mask));
+            }
+        };
+        if let Some((side, value)) = stencil {
+            let mask = side.mask_read;
+            if !compare(side.fun, (stencil_ref & mask) as u32, (value & mask) as u32) {
+                update_stencil(bytes, |side| side.op_fail);
+                return false
+            }
+        }
+        if let Some(depth) = info.depth {
+            if has_depth {
+                if !compare_depth(depth.fun, z, tex::read_depth(surface, bytes)) {
+                    update_stencil(bytes, |side| side.op_depth_fail);
+                    return false
+                }
+                if depth.write {
+                    tex::write_depth(surface, bytes, z);
+                }
+            }
+        }
+        update_stencil(bytes, |side| side.op_pass);
+        true
+    }
+}
+
+/// Draw the vertices with the bound pipeline state, for every instance.
+pub fn draw(storage: &mut Storage, state: &mut State, vertices: &[usize],
+            instances: Option<InstanceParams>) {
+    let pso = match state.pso {
+        Some(pso) => pso,
+        None => {
+            error!("Draw call without a pipeline state bound");
+            return
+        }
+    };
+    let (width, height) = match state.targets.dimensions {
+        Some((w, h, _, _)) => (w as usize, h as usize),
+        None => {
+            error!("Draw call without any pixel targets bound");
+            return
+        }
+    };
+    let mut colors: Vec<_> = state.targets.colors.iter()
+                                  .map(|view| view.and_then(|v| Target::take(storage, v)))
+                                  .collect();
+    let mut depth_stencil = state.targets.depth.or(state.targets.stencil)
+                                 .and_then(|v| Target::take(storage, v));
+
+    let samples_passed = rasterize(storage, state, pso, vertices, instances,
+                                   &mut colors, &mut depth_stencil, width, height);
+
+    // restore in the reverse order, since a texture bound twice
+    // only had its memory taken the first time
+    if let Some(target) = depth_stencil {
+        target.restore(storage);
+    }
+    for target in colors.into_iter().rev().filter_map(|t| t) {
+        target.restore(storage);
+    }
+    for query in state.occlusion_queries.iter_mut() {
+        query.1 += samples_passed;
+    }
+}
+
+/// Process the primitives with the pipeline state into the taken targets,
+/// returning the number of samples that passed the depth and stencil tests.
+fn rasterize(storage: &Storage, state: &State, pso: PipelineState, vertices: &[usize],
+             instances: Option<InstanceParams>, colors: &mut [Option<Target>],
+             depth_stencil: &mut Option<Target>, width: usize, height: usize) -> u64 {
+    let pipeline = match storage.psos.get(pso.0) {
+        Some(pipeline) => pipeline,
+        None => return 0,
+    };
+    let program = match storage.programs.get(pipeline.program) {
+        Some(program) => program,
+        None => return 0,
+    };
+    let buffer = |slot: c::ConstantBufferSlot| {
+        state.constant_buffers[slot as usize].and_then(|(b, range)| {
+            let data = match storage.buffers.get(b.0) {
+                Some(data) => &data[..],
+                None => return None,
+            };
+            Some(match range {
+                Some(pso::BufferRange { offset, size }) => {
+                    let start = cmp::min(offset, data.len());
+                    let end = offset.saturating_add(size);
+                    if end > data.len() {
+                        error!("Constant buffer range {}..{} is out of the buffer bounds", offset, end);
+                    }
+                    let end = cmp::min(end, data.len());
+                    &data[start .. end]
+                },
+                None => data,
+            })
+        })
+    };
+    let vs_registers = program.init_registers(Stage::Vertex, &state.globals, &buffer);
+    let mut ctx = Context {
+        storage: storage,
+        state: state,
+        desc: &pipeline.desc,
+        program: program,
+        colors: colors,
+        depth_stencil: depth_stencil,
+        ps_registers: program.init_registers(Stage::Pixel, &state.globals, &buffer),
+        width: width,
+        height: height,
+        viewport: state.viewport.unwrap_or_else(|| Viewport::new(Rect {
+            x: 0,
+            y: 0,
+            w: width as u16,
+            h: height as u16,
+        })),
+        samples_passed: 0,
+    };
+
+    let (num_instances, base_instance) = instances.unwrap_or((1, 0));
+    for instance in 0 .. num_instances {
+        let shaded: Vec<_> = vertices.iter()
+                                     .map(|&v| ctx.shade_vertex(&vs_registers, v, instance, base_instance))
+                                     .collect();
+        match pipeline.desc.primitive {
+            c::Primitive::PointList => for v in shaded.iter() {
+                ctx.draw_point(v);
+            },
+            c::Primitive::LineList => for pair in shaded.chunks(2).filter(|p| p.len() == 2) {
+                ctx.draw_line(&pair[0], &pair[1]);
+            },
+            c::Primitive::LineStrip => for pair in shaded.windows(2) {
+                ctx.draw_line(&pair[0], &pair[1]);
+            },
+            c::Primitive::TriangleList => for tri in shaded.chunks(3).filter(|t| t.len() == 3) {
+                ctx.draw_triangle(&tri[0], &tri[1], &tri[2]);
+            },
+            c::Primitive::TriangleStrip => for (i, tri) in shaded.windows(3).enumerate() {
+                if i % 2 == 0 {
+                    ctx.draw_triangle(&tri[0], &tri[1], &tri[2]);
+                } else {
+                    ctx.draw_triangle(&tri[1], &tri[0], &tri[2]);
+                }
+            },
+            _ => (),
+        }
+    }
+    ctx.samples_passed
+}
@@ -0,0 +1,820 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parser and interpreter of the shader language. See the README for
+//! the description of the syntax and the instruction set.
+
+use std::collections::HashMap;
+use core::{ConstantBufferSlot, ResourceViewSlot, SamplerSlot};
+use core::shade::{self, BaseType, ConstFormat, ContainerType, IsArray, IsComparison, IsMultiSample,
+                  IsRect, Location, MatrixFormat, SamplerType, Stage, TextureType, UniformValue};
+
+
+/// A four-component register, holding any scalar, vector or matrix column.
+pub type Register = [f32; 4];
+
+/// Register of the clip-space position written by the vertex shader.
+pub const POSITION: usize = 0;
+/// Register of the window coordinates read by the pixel shader.
+pub const FRAG_COORD: usize = 1;
+/// Register of the vertex index.
+pub const VERTEX_ID: usize = 2;
+/// Register of the instance index.
+pub const INSTANCE_ID: usize = 3;
+const BUILTINS: [&'static str; 4] = ["position", "frag_coord", "vertex_id", "instance_id"];
+
+/// A named variable, occupying one or more consecutive registers.
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub format: ConstFormat,
+    /// Number of array elements, 1 for the other variables.
+    pub count: usize,
+    pub reg: usize,
+}
+
+impl Variable {
+    fn num_registers(&self) -> usize {
+        let element = match self.format.1 {
+            ContainerType::Matrix(_, _, columns) => columns as usize,
+            _ => 1,
+        };
+        element * self.count
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    Register {
+        reg: usize,
+        swizzle: [u8; 4],
+        negate: bool,
+    },
+    Literal(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Mov, Add, Sub, Mul, Div, Min, Max, Mad,
+    Abs, Floor, Fract, Rcp, Rsq, Sqrt, Sin, Cos, Exp, Log,
+    Pow, Clamp, Mix, Step,
+    Dp2, Dp3, Dp4, Nrm,
+    /// Multiply a vector by the matrix starting at the register, with the given column count.
+    MulMat(usize, usize),
+    /// Sample the texture with the sampler, both given by their declaration index.
+    Tex(usize, usize),
+    Kill,
+}
+
+#[derive(Clone, Debug)]
+struct Instruction {
+    op: Op,
+    /// Destination register with the list of written components.
+    dst: Option<(usize, Vec<usize>)>,
+    src: Vec<Operand>,
+}
+
+/// A parsed shader of a single stage.
+#[derive(Clone, Debug)]
+pub struct Shader {
+    stage: Stage,
+    num_registers: usize,
+    pub attributes: Vec<Variable>,
+    pub varyings: Vec<Variable>,
+    uniforms: Vec<Variable>,
+    /// Constants with the name of their block.
+    constants: Vec<(String, Variable)>,
+    textures: Vec<(String, TextureType)>,
+    samplers: Vec<String>,
+    pub outputs: Vec<Variable>,
+    code: Vec<Instruction>,
+}
+
+fn parse_format(name: &str) -> Option<ConstFormat> {
+    let (base, dim) = match name {
+        "float" => return Some((BaseType::F32, ContainerType::Single)),
+        "int" => return Some((BaseType::I32, ContainerType::Single)),
+        "uint" => return Some((BaseType::U32, ContainerType::Single)),
+        "mat2" => return Some((BaseType::F32, ContainerType::Matrix(MatrixFormat::ColumnMajor, 2, 2))),
+        "mat3" => return Some((BaseType::F32, ContainerType::Matrix(MatrixFormat::ColumnMajor, 3, 3))),
+        "mat4" => return Some((BaseType::F32, ContainerType::Matrix(MatrixFormat::ColumnMajor, 4, 4))),
+        _ if name.starts_with("vec") => (BaseType::F32, &name[3..]),
+        _ if name.starts_with("ivec") => (BaseType::I32, &name[4..]),
+        _ if name.starts_with("uvec") => (BaseType::U32, &name[4..]),
+        _ => return None,
+    };
+    match dim {
+        "2" | "3" | "4" => Some((base, ContainerType::Vector(dim.parse().unwrap()))),
+        _ => None,
+    }
+}
+
+/// Parse a variable name with an optional array size, like `u_Lights[4]`.
+fn parse_array(text: &str) -> Result<(&str, usize), String> {
+    match text.find('[') {
+        Some(open) if text.ends_with(']') => match text[open + 1 .. text.len() - 1].parse() {
+            Ok(count) if count > 0 => Ok((&text[..open], count)),
+            _ => Err(format!("Invalid array size in {}", text)),
+        },
+        Some(_) => Err(format!("Unterminated array size in {}", text)),
+        None => Ok((text, 1)),
+    }
+}
+
+fn parse_texture_type(name: &str) -> Option<TextureType> {
+    Some(match name {
+        "1d" => TextureType::D1(IsArray::NoArray),
+        "1d_array" => TextureType::D1(IsArray::Array),
+        "2d" => TextureType::D2(IsArray::NoArray, IsMultiSample::NoMultiSample),
+        "2d_array" => TextureType::D2(IsArray::Array, IsMultiSample::NoMultiSample),
+        "3d" => TextureType::D3,
+        "cube" => TextureType::Cube(IsArray::NoArray),
+        "cube_array" => TextureType::Cube(IsArray::Array),
+        _ => return None,
+    })
+}
+
+fn parse_op(name: &str) -> Option<(Op, usize)> {
+    Some(match name {
+        "mov" => (Op::Mov, 1),
+        "add" => (Op::Add, 2),
+        "sub" => (Op::Sub, 2),
+        "mul" => (Op::Mul, 2),
+        "div" => (Op::Div, 2),
+        "min" => (Op::Min, 2),
+        "max" => (Op::Max, 2),
+        "mad" => (Op::Mad, 3),
+        "abs" => (Op::Abs, 1),
+        "floor" => (Op::Floor, 1),
+        "fract" => (Op::Fract, 1),
+        "rcp" => (Op::Rcp, 1),
+        "rsq" => (Op::Rsq, 1),
+        "sqrt" => (Op::Sqrt, 1),
+        "sin" => (Op::Sin, 1),
+        "cos" => (Op::Cos, 1),
+        "exp" => (Op::Exp, 1),
+        "log" => (Op::Log, 1),
+        "pow" => (Op::Pow, 2),
+        "clamp" => (Op::Clamp, 3),
+        "mix" => (Op::Mix, 3),
+        "step" => (Op::Step, 2),
+        "dp2" => (Op::Dp2, 2),
+        "dp3" => (Op::Dp3, 2),
+        "dp4" => (Op::Dp4, 2),
+        "nrm" => (Op::Nrm, 1),
+        "mulmat" => (Op::MulMat(0, 0), 2),
+        "tex" => (Op::Tex(0, 0), 3),
+        "kill" => (Op::Kill, 1),
+        _ => return None,
+    })
+}
+
+fn component(c: char) -> Option<usize> {
+    match c {
+        'x' | 'r' => Some(0),
+        'y' | 'g' => Some(1),
+        'z' | 'b' => Some(2),
+        'w' | 'a' => Some(3),
+        _ => None,
+    }
+}
+
+/// Std140 size and alignment of a constant.
+fn std140_layout(format: ConstFormat) -> (usize, usize) {
+    match format.1 {
+        ContainerType::Single => (4, 4),
+        ContainerType::Vector(2) => (8, 8),
+        ContainerType::Vector(3) => (12, 16),
+        ContainerType::Vector(_) => (16, 16),
+        ContainerType::Matrix(_, _, columns) => (16 * columns as usize, 16),
+    }
+}
+
+struct Parser {
+    shader: Shader,
+    names: HashMap<String, usize>,
+}
+
+impl Parser {
+    fn declare(&mut self, name: &str, format: ConstFormat, count: usize) -> Result<Variable, String> {
+        if self.names.contains_key(name) || BUILTINS.contains(&name) {
+            return Err(format!("{} is already declared", name));
+        }
+        let var = Variable {
+            name: name.to_string(),
+            format: format,
+            count: count,
+            reg: self.shader.num_registers,
+        };
+        self.shader.num_registers += var.num_registers();
+        self.names.insert(name.to_string(), var.reg);
+        Ok(var)
+    }
+
+    fn lookup(&self, name: &str) -> Result<usize, String> {
+        match BUILTINS.iter().position(|&b| b == name) {
+            Some(reg) => Ok(reg),
+            None => self.names.get(name).cloned().ok_or(format!("Unknown register {}", name)),
+        }
+    }
+
+    fn variable_count(&self, reg: usize) -> usize {
+        let s = &self.shader;
+        s.uniforms.iter()
+            .chain(s.constants.iter().map(|&(_, ref var)| var))
+            .find(|var| var.reg == reg)
+            .map_or(1, |var| var.num_registers())
+    }
+
+    fn parse_declaration(&mut self, keyword: &str, args: &[&str]) -> Result<(), String> {
+        let stage = self.shader.stage;
+        match (keyword, args.len()) {
+            ("attribute", 2) if stage == Stage::Vertex => {
+                let format = try!(parse_format(args[0]).ok_or(format!("Unknown type {}", args[0])));
+                if let ContainerType::Matrix(..) = format.1 {
+                    return Err("Matrix attributes are not supported".to_string());
+                }
+                let var = try!(self.declare(args[1], format, 1));
+                self.shader.attributes.push(var);
+            },
+            ("output", 2) if stage == Stage::Pixel => {
+                let format = try!(parse_format(args[0]).ok_or(format!("Unknown type {}", args[0])));
+                let var = try!(self.declare(args[1], format, 1));
+                self.shader.outputs.push(var);
+            },
+            ("varying", 2) => {
+                let format = try!(parse_format(args[0]).ok_or(format!("Unknown type {}", args[0])));
+                let var = try!(self.declare(args[1], format, 1));
+                self.shader.varyings.push(var);
+            },
+            ("uniform", 2) => {
+                let format = try!(parse_format(args[0]).ok_or(format!("Unknown type {}", args[0])));
+                let (name, count) = try!(parse_array(args[1]));
+                let var = try!(self.declare(name, format, count));
+                self.shader.uniforms.push(var);
+            },
+            ("constant", 3) => {
+                let format = try!(parse_format(args[1]).ok_or(format!("Unknown type {}", args[1])));
+                let var = try!(self.declare(args[2], format, 1));
+                self.shader.constants.push((args[0].to_string(), var));
+            },
+            ("texture", 2) => {
+                let ty = try!(parse_texture_type(args[0]).ok_or(format!("Unknown texture type {}", args[0])));
+                self.shader.textures.push((args[1].to_string(), ty));
+            },
+            ("sampler", 1) => {
+                self.shader.samplers.push(args[0].to_string());
+            },
+            ("temp", n) if n > 0 => {
+                for name in args {
+                    try!(self.declare(name, (BaseType::F32, ContainerType::Vector(4)), 1));
+                }
+            },
+            _ => return Err(format!("Invalid declaration of {} in the {:?} shader", keyword, stage)),
+        }
+        Ok(())
+    }
+
+    /// Parse a register name with an optional column index, returning the
+    /// register and the rest of the string.
+    fn parse_register<'a>(&self, text: &'a str) -> Result<(usize, &'a str), String> {
+        let end = text.find(|c| c == '.' || c == '[').unwrap_or(text.len());
+        let reg = try!(self.lookup(&text[..end]));
+        let rest = &text[end..];
+        if rest.starts_with('[') {
+            let close = try!(rest.find(']').ok_or(format!("Unterminated index in {}", text)));
+            let index: usize = try!(rest[1..close].parse().map_err(|_| format!("Invalid index in {}", text)));
+            if index >= self.variable_count(reg) {
+                return Err(format!("Index out of range in {}", text));
+            }
+            Ok((reg + index, &rest[close + 1..]))
+        } else {
+            Ok((reg, rest))
+        }
+    }
+
+    fn parse_destination(&self, text: &str) -> Result<(usize, Vec<usize>), String> {
+        let (reg, rest) = try!(self.parse_register(text));
+        if rest.is_empty() {
+            return Ok((reg, vec![0, 1, 2, 3]));
+        }
+        let mut mask = Vec::new();
+        for c in rest[1..].chars() {
+            match component(c) {
+                Some(i) if mask.last().map_or(true, |&last| last < i) => mask.push(i),
+                _ => return Err(format!("Invalid write mask in {}", text)),
+            }
+        }
+        if mask.is_empty() {
+            return Err(format!("Empty write mask in {}", text));
+        }
+        Ok((reg, mask))
+    }
+
+    fn parse_operand(&self, text: &str) -> Result<Operand, String> {
+        if let Ok(value) = text.parse::<f32>() {
+            return Ok(Operand::Literal(value));
+        }
+        let (negate, text) = if text.starts_with('-') {
+            (true, &text[1..])
+        } else {
+            (false, text)
+        };
+        let (reg, rest) = try!(self.parse_register(text));
+        let mut swizzle = [0, 1, 2, 3];
+        if !rest.is_empty() {
+            let letters = &rest[1..];
+            if letters.is_empty() || letters.len() > 4 {
+                return Err(format!("Invalid swizzle in {}", text));
+            }
+            let mut last = 0;
+            for i in 0 .. 4 {
+                if let Some(c) = letters.chars().nth(i) {
+                    last = try!(component(c).ok_or(format!("Invalid swizzle in {}", text))) as u8;
+                }
+                swizzle[i] = last;
+            }
+        }
+        Ok(Operand::Register {
+            reg: reg,
+            swizzle: swizzle,
+            negate: negate,
+        })
+    }
+
+    fn parse_instruction(&mut self, name: &str, args: &[&str]) -> Result<(), String> {
+        let (mut op, num_sources) = try!(parse_op(name).ok_or(format!("Unknown instruction {}", name)));
+        let has_dst = op != Op::Kill;
+        let expected = num_sources + if has_dst { 1 } else { 0 };
+        if args.len() != expected {
+            return Err(format!("{} expects {} operands, got {}", name, expected, args.len()));
+        }
+        let dst = if has_dst {
+            Some(try!(self.parse_destination(args[0])))
+        } else {
+            None
+        };
+        let mut sources = &args[expected - num_sources ..];
+        match op {
+            Op::MulMat(..) => {
+                let reg = try!(self.lookup(sources[0]));
+                op = match self.shader.uniforms.iter()
+                                  .chain(self.shader.constants.iter().map(|&(_, ref var)| var))
+                                  .find(|var| var.reg == reg) {
+                    Some(&Variable { format: (_, ContainerType::Matrix(_, _, columns)), .. }) =>
+                        Op::MulMat(reg, columns as usize),
+                    _ => return Err(format!("{} is not a matrix", sources[0])),
+                };
+                sources = &sources[1..];
+            },
+            Op::Tex(..) => {
+                let texture = try!(self.shader.textures.iter().position(|&(ref n, _)| n == sources[0])
+                                       .ok_or(format!("Unknown texture {}", sources[0])));
+                let sampler = try!(self.shader.samplers.iter().position(|n| n == sources[1])
+                                       .ok_or(format!("Unknown sampler {}", sources[1])));
+                op = Op::Tex(texture, sampler);
+                sources = &sources[2..];
+            },
+            _ => (),
+        }
+        let mut src = Vec::with_capacity(sources.len());
+        for text in sources {
+            src.push(try!(self.parse_operand(text)));
+        }
+        self.shader.code.push(Instruction {
+            op: op,
+            dst: dst,
+            src: src,
+        });
+        Ok(())
+    }
+}
+
+impl Shader {
+    /// Parse the shader source code.
+    pub fn parse(stage: Stage, source: &str) -> Result<Shader, String> {
+        let mut parser = Parser {
+            shader: Shader {
+                stage: stage,
+                num_registers: BUILTINS.len(),
+                attributes: Vec::new(),
+                varyings: Vec::new(),
+                uniforms: Vec::new(),
+                constants: Vec::new(),
+                textures: Vec::new(),
+                samplers: Vec::new(),
+                outputs: Vec::new(),
+                code: Vec::new(),
+            },
+            names: HashMap::new(),
+        };
+        for (number, line) in source.lines().enumerate() {
+            let line = match line.find("//") {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+            let line = line.replace(',', " ");
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let result = match words.first() {
+                None => continue,
+                Some(&keyword) => match keyword {
+                    "attribute" | "varying" | "uniform" | "constant" |
+                    "texture" | "sampler" | "output" | "temp" =>
+                        parser.parse_declaration(keyword, &words[1..]),
+                    _ => parser.parse_instruction(keyword, &words[1..]),
+                },
+            };
+            if let Err(e) = result {
+                return Err(format!("line {}: {}", number + 1, e));
+            }
+        }
+        Ok(parser.shader)
+    }
+
+    /// Run the shader over the registers. Returns `false` if the invocation
+    /// has been killed.
+    fn execute<F>(&self, regs: &mut [Register], mut sample: F) -> bool
+        where F: FnMut(usize, usize, Register) -> Register
+    {
+        for ins in self.code.iter() {
+            let src: Vec<Register> = ins.src.iter().map(|operand| match *operand {
+                Operand::Literal(v) => [v; 4],
+                Operand::Register { reg, swizzle, negate } => {
+                    let r = regs[reg];
+                    let s = if negate { -1.0 } else { 1.0 };
+                    [s * r[swizzle[0] as usize], s * r[swizzle[1] as usize],
+                     s * r[swizzle[2] as usize], s * r[swizzle[3] as usize]]
+                },
+            }).collect();
+            let map1 = |f: &Fn(f32) -> f32| {
+                let a = src[0];
+                [f(a[0]), f(a[1]), f(a[2]), f(a[3])]
+            };
+            let map2 = |f: &Fn(f32, f32) -> f32| {
+                let (a, b) = (src[0], src[1]);
+                [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])]
+            };
+            let map3 = |f: &Fn(f32, f32, f32) -> f32| {
+                let (a, b, c) = (src[0], src[1], src[2]);
+                [f(a[0], b[0], c[0]), f(a[1], b[1], c[1]), f(a[2], b[2], c[2]), f(a[3], b[3], c[3])]
+            };
+            let dot = |n: usize| {
+                let d = (0 .. n).fold(0.0, |sum, i| sum + src[0][i] * src[1][i]);
+                [d; 4]
+            };
+            let result = match ins.op {
+                Op::Mov => src[0],
+                Op::Add => map2(&|a, b| a + b),
+                Op::Sub => map2(&|a, b| a - b),
+                Op::Mul => map2(&|a, b| a * b),
+                Op::Div => map2(&|a, b| a / b),
+                Op::Min => map2(&|a, b| a.min(b)),
+                Op::Max => map2(&|a, b| a.max(b)),
+                Op::Mad => map3(&|a, b, c| a * b + c),
+                Op::Abs => map1(&|a| a.abs()),
+                Op::Floor => map1(&|a| a.floor()),
+                Op::Fract => map1(&|a| a - a.floor()),
+                Op::Rcp => map1(&|a| 1.0 / a),
+                Op::Rsq => map1(&|a| 1.0 / a.sqrt()),
+                Op::Sqrt => map1(&|a| a.sqrt()),
+                Op::Sin => map1(&|a| a.sin()),
+                Op::Cos => map1(&|a| a.cos()),
+                Op::Exp => map1(&|a| a.exp()),
+                Op::Log => map1(&|a| a.ln()),
+                Op::Pow => map2(&|a, b| a.powf(b)),
+                Op::Clamp => map3(&|x, lo, hi| x.max(lo).min(hi)),
+                Op::Mix => map3(&|a, b, t| a + (b - a) * t),
+                Op::Step => map2(&|edge, x| if x < edge { 0.0 } else { 1.0 }),
+                Op::Dp2 => dot(2),
+                Op::Dp3 => dot(3),
+                Op::Dp4 => dot(4),
+                Op::Nrm => {
+                    let a = src[0];
+                    let len = (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+                    [a[0] / len, a[1] / len, a[2] / len, 0.0]
+                },
+                Op::MulMat(reg, columns) => {
+                    let mut out = [0.0; 4];
+                    for i in 0 .. columns {
+                        let col = regs[reg + i];
+                        for j in 0 .. 4 {
+                            out[j] += col[j] * src[0][i];
+                        }
+                    }
+                    out
+                },
+                Op::Tex(texture, sampler) => sample(texture, sampler, src[0]),
+                Op::Kill => {
+                    if src[0].iter().any(|&c| c < 0.0) {
+                        return false
+                    }
+                    continue
+                },
+            };
+            if let Some((reg, ref mask)) = ins.dst {
+                for (i, &c) in mask.iter().enumerate() {
+                    regs[reg][c] = result[i];
+                }
+            }
+        }
+        true
+    }
+}
+
+fn load_value(regs: &mut [Register], var: &Variable, value: &UniformValue) {
+    let reg = var.reg;
+    match *value {
+        UniformValue::I32(v) => regs[reg][0] = v as f32,
+        UniformValue::U32(v) => regs[reg][0] = v as f32,
+        UniformValue::F32(v) => regs[reg][0] = v,
+        UniformValue::F64(v) => regs[reg][0] = v as f32,
+        UniformValue::Bool(v) => regs[reg][0] = if v { 1.0 } else { 0.0 },
+        UniformValue::I32Vector2(v) => for i in 0 .. 2 { regs[reg][i] = v[i] as f32 },
+        UniformValue::I32Vector3(v) => for i in 0 .. 3 { regs[reg][i] = v[i] as f32 },
+        UniformValue::I32Vector4(v) => for i in 0 .. 4 { regs[reg][i] = v[i] as f32 },
+        UniformValue::U32Vector2(v) => for i in 0 .. 2 { regs[reg][i] = v[i] as f32 },
+        UniformValue::U32Vector3(v) => for i in 0 .. 3 { regs[reg][i] = v[i] as f32 },
+        UniformValue::U32Vector4(v) => for i in 0 .. 4 { regs[reg][i] = v[i] as f32 },
+        UniformValue::F32Vector2(v) => regs[reg][..2].copy_from_slice(&v),
+        UniformValue::F32Vector3(v) => regs[reg][..3].copy_from_slice(&v),
+        UniformValue::F32Vector4(v) => regs[reg] = v,
+        UniformValue::F64Vector2(v) => for i in 0 .. 2 { regs[reg][i] = v[i] as f32 },
+        UniformValue::F64Vector3(v) => for i in 0 .. 3 { regs[reg][i] = v[i] as f32 },
+        UniformValue::F64Vector4(v) => for i in 0 .. 4 { regs[reg][i] = v[i] as f32 },
+        UniformValue::F32Matrix2(m) => for i in 0 .. 2 { regs[reg + i][..2].copy_from_slice(&m[i]) },
+        UniformValue::F32Matrix3(m) => for i in 0 .. 3 { regs[reg + i][..3].copy_from_slice(&m[i]) },
+        UniformValue::F32Matrix4(m) => for i in 0 .. 4 { regs[reg + i] = m[i] },
+        UniformValue::F32Matrix2x3(m) => for i in 0 .. 2 { regs[reg + i][..3].copy_from_slice(&m[i]) },
+        UniformValue::F32Matrix2x4(m) => for i in 0 .. 2 { regs[reg + i] = m[i] },
+        UniformValue::F32Matrix3x2(m) => for i in 0 .. 3 { regs[reg + i][..2].copy_from_slice(&m[i]) },
+        UniformValue::F32Matrix3x4(m) => for i in 0 .. 3 { regs[reg + i] = m[i] },
+        UniformValue::F32Matrix4x2(m) => for i in 0 .. 4 { regs[reg + i][..2].copy_from_slice(&m[i]) },
+        UniformValue::F32Matrix4x3(m) => for i in 0 .. 4 { regs[reg + i][..3].copy_from_slice(&m[i]) },
+        // the elements past the size of the variable are ignored
+        UniformValue::I32Array(ref v) =>
+            for (i, &x) in v.iter().take(var.count).enumerate() { regs[reg + i][0] = x as f32 },
+        UniformValue::U32Array(ref v) =>
+            for (i, &x) in v.iter().take(var.count).enumerate() { regs[reg + i][0] = x as f32 },
+        UniformValue::F32Array(ref v) =>
+            for (i, &x) in v.iter().take(var.count).enumerate() { regs[reg + i][0] = x },
+        UniformValue::F32Vector2Array(ref v) =>
+            for (i, x) in v.iter().take(var.count).enumerate() { regs[reg + i][..2].copy_from_slice(x) },
+        UniformValue::F32Vector3Array(ref v) =>
+            for (i, x) in v.iter().take(var.count).enumerate() { regs[reg + i][..3].copy_from_slice(x) },
+        UniformValue::F32Vector4Array(ref v) =>
+            for (i, x) in v.iter().take(var.count).enumerate() { regs[reg + i] = *x },
+        UniformValue::F32Matrix4Array(ref v) =>
+            for (i, m) in v.iter().take(var.count).enumerate() { regs[reg + 4 * i .. reg + 4 * i + 4].copy_from_slice(m) },
+    }
+}
+
+fn load_constant(regs: &mut [Register], var: &Variable, data: &[u8], offset: usize) {
+    let (count, stride, rows) = match var.format.1 {
+        ContainerType::Single => (1, 0, 1),
+        ContainerType::Vector(n) => (1, 0, n as usize),
+        ContainerType::Matrix(_, rows, columns) => (columns as usize, 16, rows as usize),
+    };
+    for i in 0 .. count {
+        for j in 0 .. rows {
+            let start = offset + i * stride + j * 4;
+            let bytes = match data.get(start .. start + 4) {
+                Some(bytes) => bytes,
+                None => return,
+            };
+            let raw = bytes.iter().rev().fold(0u32, |u, &b| (u << 8) | b as u32);
+            regs[var.reg + i][j] = match var.format.0 {
+                BaseType::I32 => raw as i32 as f32,
+                BaseType::U32 | BaseType::Bool => raw as f32,
+                BaseType::F32 | BaseType::F64 => f32::from_bits(raw),
+            };
+        }
+    }
+}
+
+/// Resource bindings of a single stage, assigned at link time.
+#[derive(Clone, Debug, Default)]
+struct Binding {
+    uniforms: Vec<Location>,
+    constants: Vec<(ConstantBufferSlot, usize)>,
+    textures: Vec<ResourceViewSlot>,
+    samplers: Vec<SamplerSlot>,
+}
+
+/// A linked pair of vertex and pixel shaders.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub vs: Shader,
+    pub ps: Shader,
+    vs_binding: Binding,
+    ps_binding: Binding,
+    /// Index of the vertex shader varying for each pixel shader varying.
+    pub varyings: Vec<usize>,
+}
+
+impl Program {
+    fn stage(&self, stage: Stage) -> (&Shader, &Binding) {
+        match stage {
+            Stage::Vertex => (&self.vs, &self.vs_binding),
+            _ => (&self.ps, &self.ps_binding),
+        }
+    }
+
+    /// Allocate the registers of a stage, with the global and buffer constants loaded.
+    pub fn init_registers<'a, F>(&self, stage: Stage, globals: &HashMap<Location, UniformValue>,
+                                 buffer: F) -> Vec<Register>
+        where F: Fn(ConstantBufferSlot) -> Option<&'a [u8]>
+    {
+        let (shader, binding) = self.stage(stage);
+        let mut regs = vec![[0.0; 4]; shader.num_registers];
+        for (var, location) in shader.uniforms.iter().zip(binding.uniforms.iter()) {
+            if let Some(value) = globals.get(location) {
+                load_value(&mut regs, var, value);
+            }
+        }
+        for (&(_, ref var), &(slot, offset)) in shader.constants.iter().zip(binding.constants.iter()) {
+            if let Some(data) = buffer(slot) {
+                load_constant(&mut regs, var, data, offset);
+            }
+        }
+        regs
+    }
+
+    /// Execute a stage over the registers, sampling textures through the
+    /// given function. Returns `false` if the invocation has been killed.
+    pub fn execute<F>(&self, stage: Stage, regs: &mut [Register], mut sample: F) -> bool
+        where F: FnMut(ResourceViewSlot, SamplerSlot, Register) -> Register
+    {
+        let (shader, binding) = self.stage(stage);
+        shader.execute(regs, |texture, sampler, coord| {
+            sample(binding.textures[texture], binding.samplers[sampler], coord)
+        })
+    }
+}
+
+/// Link the shaders into a program, assigning the slots of all the
+/// resources and gathering the reflection info.
+pub fn link(vs: &Shader, ps: &Shader) -> Result<(Program, shade::ProgramInfo), String> {
+    let mut info = shade::ProgramInfo {
+        vertex_attributes: Vec::new(),
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: Vec::new(),
+        stream_outputs: Vec::new(),
+        output_depth: false,
+        knows_outputs: true,
+    };
+    for (i, var) in vs.attributes.iter().enumerate() {
+        info.vertex_attributes.push(shade::AttributeVar {
+            name: var.name.clone(),
+            slot: i as _,
+            base_type: var.format.0,
+            container: var.format.1,
+        });
+    }
+    for (i, var) in ps.outputs.iter().enumerate() {
+        info.outputs.push(shade::OutputVar {
+            name: var.name.clone(),
+            slot: i as _,
+            base_type: var.format.0,
+            container: var.format.1,
+        });
+    }
+
+    let mut varyings = Vec::new();
+    for var in ps.varyings.iter() {
+        match vs.varyings.iter().position(|v| v.name == var.name) {
+            Some(i) if vs.varyings[i].format == var.format => varyings.push(i),
+            Some(_) => return Err(format!("Varying {} has different types in the shaders", var.name)),
+            None => return Err(format!("Varying {} is not written by the vertex shader", var.name)),
+        }
+    }
+
+    let mut bindings = Vec::new();
+    for &(shader, usage) in [(vs, shade::VERTEX), (ps, shade::PIXEL)].iter() {
+        let mut binding = Binding::default();
+        for var in shader.uniforms.iter() {
+            let location = match info.globals.iter().position(|g| g.name == var.name) {
+                Some(i) if (info.globals[i].base_type, info.globals[i].container) == var.format &&
+                            info.globals[i].count == var.count => i,
+                Some(_) => return Err(format!("Uniform {} has different types in the shaders", var.name)),
+                None => {
+                    info.globals.push(shade::ConstVar {
+                        name: var.name.clone(),
+                        location: info.globals.len(),
+                        count: var.count,
+                        base_type: var.format.0,
+                        container: var.format.1,
+                        layout: None,
+                    });
+                    info.globals.len() - 1
+                },
+            };
+            binding.uniforms.push(location);
+        }
+        let mut block_sizes = HashMap::new();
+        for &(ref block, ref var) in shader.constants.iter() {
+            let (size, align) = std140_layout(var.format);
+            let offset = {
+                let end = block_sizes.entry(block.clone()).or_insert(0);
+                let offset = (*end + align - 1) / align * align;
+                *end = offset + size;
+                offset
+            };
+            let slot = match info.constant_buffers.iter().position(|cb| &cb.name == block) {
+                Some(i) => i,
+                None => {
+                    info.constant_buffers.push(shade::ConstantBufferVar {
+                        name: block.clone(),
+                        slot: info.constant_buffers.len() as _,
+                        size: 0,
+                        usage: shade::Usage::empty(),
+                        elements: Vec::new(),
+                    });
+                    info.constant_buffers.len() - 1
+                },
+            };
+            let cb = &mut info.constant_buffers[slot];
+            cb.usage |= usage;
+            cb.size = ::std::cmp::max(cb.size, (offset + size + 15) / 16 * 16);
+            match cb.elements.iter().find(|e| e.name == var.name) {
+                Some(e) if e.location == offset && (e.base_type, e.container) == var.format => (),
+                Some(_) => return Err(format!("Constant {} has different layouts in the shaders", var.name)),
+                None => cb.elements.push(shade::ConstVar {
+                    name: var.name.clone(),
+                    location: offset,
+                    count: 1,
+                    base_type: var.format.0,
+                    container: var.format.1,
+                    layout: Some(shade::ConstLayout {
+                        array_stride: 0,
+                        matrix_stride: match var.format.1 {
+                            shade::ContainerType::Matrix(..) => 16,
+                            _ => 0,
+                        },
+                    }),
+                }),
+            }
+            binding.constants.push((slot as _, offset));
+        }
+        for &(ref name, ty) in shader.textures.iter() {
+            let slot = match info.textures.iter().position(|t| &t.name == name) {
+                Some(i) if info.textures[i].ty == ty => i,
+                Some(_) => return Err(format!("Texture {} has different types in the shaders", name)),
+                None => {
+                    info.textures.push(shade::TextureVar {
+                        name: name.clone(),
+                        slot: info.textures.len() as _,
+                        base_type: BaseType::F32,
+                        ty: ty,
+                        usage: shade::Usage::empty(),
+                    });
+                    info.textures.len() - 1
+                },
+            };
+            info.textures[slot].usage |= usage;
+            binding.textures.push(slot as _);
+        }
+        for name in shader.samplers.iter() {
+            let slot = match info.samplers.iter().position(|s| &s.name == name) {
+                Some(i) => i,
+                None => {
+                    info.samplers.push(shade::SamplerVar {
+                        name: name.clone(),
+                        slot: info.samplers.len() as _,
+                        ty: SamplerType(IsComparison::NoCompare, IsRect::NoRect),
+                        usage: shade::Usage::empty(),
+                    });
+                    info.samplers.len() - 1
+                },
+            };
+            info.samplers[slot].usage |= usage;
+            binding.samplers.push(slot as _);
+        }
+        bindings.push(binding);
+    }
+
+    let ps_binding = bindings.pop().unwrap();
+    let vs_binding = bindings.pop().unwrap();
+    let program = Program {
+        vs: vs.clone(),
+        ps: ps.clone(),
+        vs_binding: vs_binding,
+        ps_binding: ps_binding,
+        varyings: varyings,
+    };
+    Ok((program, info))
+}
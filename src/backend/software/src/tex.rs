@@ -0,0 +1,662 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Texture memory layout, texel encoding and sampling.
+//!
+//! All the layers (array slices or cube faces) of a texture are stored one
+//! after another, each containing its mip levels in order. Row 0 of an image
+//! corresponds to the texture coordinate 0, as well as to the bottom of the
+//! normalized device coordinates when rendering.
+
+use std::cmp;
+use core::format::{ChannelSource, ChannelType, Format, SurfaceType, Swizzle};
+use core::target::{Layer, Level};
+use core::texture::{self, CubeFace, FilterMethod, Kind, RawImageInfo, WrapMode};
+
+
+/// How the channels of a surface are stored in a texel.
+#[derive(Clone, Copy, Debug)]
+enum Layout {
+    /// Channel count and the number of bytes taken by each of them.
+    Aligned(usize, usize),
+    /// Channels packed into a single integer of the given byte size,
+    /// each given by its bit offset and width.
+    Packed(usize, [(u8, u8); 4], usize),
+    /// Depth and optional stencil.
+    Depth,
+}
+
+fn layout(surface: SurfaceType) -> Option<Layout> {
+    use core::format::SurfaceType as S;
+    Some(match surface {
+        S::R8              => Layout::Aligned(1, 1),
+        S::R8_G8           => Layout::Aligned(2, 1),
+        S::R8_G8_B8_A8 |
+        S::B8_G8_R8_A8     => Layout::Aligned(4, 1),
+        S::R16             => Layout::Aligned(1, 2),
+        S::R16_G16         => Layout::Aligned(2, 2),
+        S::R16_G16_B16     => Layout::Aligned(3, 2),
+        S::R16_G16_B16_A16 => Layout::Aligned(4, 2),
+        S::R32             => Layout::Aligned(1, 4),
+        S::R32_G32         => Layout::Aligned(2, 4),
+        S::R32_G32_B32     => Layout::Aligned(3, 4),
+        S::R32_G32_B32_A32 => Layout::Aligned(4, 4),
+        S::R4_G4_B4_A4     => Layout::Packed(2, [(12, 4), (8, 4), (4, 4), (0, 4)], 4),
+        S::R5_G5_B5_A1     => Layout::Packed(2, [(11, 5), (6, 5), (1, 5), (0, 1)], 4),
+        S::R5_G6_B5        => Layout::Packed(2, [(11, 5), (5, 6), (0, 5), (0, 0)], 3),
+        S::R10_G10_B10_A2  => Layout::Packed(4, [(0, 10), (10, 10), (20, 10), (30, 2)], 4),
+        S::D16 | S::D24 | S::D24_S8 | S::D32 => Layout::Depth,
+        _ => return None,
+    })
+}
+
+/// Check if the surface type holds depth and stencil values.
+pub fn is_depth(surface: SurfaceType) -> bool {
+    match layout(surface) {
+        Some(Layout::Depth) => true,
+        _ => false,
+    }
+}
+
+/// Return the size of a texel in bytes, or `None` if the surface type is not supported.
+pub fn texel_size(surface: SurfaceType) -> Option<usize> {
+    layout(surface).map(|_| surface.get_total_bits() as usize / 8)
+}
+
+fn read_bits(bytes: &[u8], size: usize) -> u32 {
+    bytes[..size].iter().rev().fold(0, |u, &b| (u << 8) | b as u32)
+}
+
+fn write_bits(bytes: &mut [u8], size: usize, value: u32) {
+    for (i, b) in bytes[..size].iter_mut().enumerate() {
+        *b = (value >> (i * 8)) as u8;
+    }
+}
+
+fn max_value(bits: u8) -> u32 {
+    if bits >= 32 { !0 } else { (1 << bits) - 1 }
+}
+
+fn sign_extend(raw: u32, bits: u8) -> i32 {
+    if bits >= 32 {
+        raw as i32
+    } else {
+        let shift = 32 - bits as u32;
+        ((raw << shift) as i32) >> shift
+    }
+}
+
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = (half as u32 & 0x8000) << 16;
+    let exp = (half >> 10) as u32 & 0x1F;
+    let mantissa = half as u32 & 0x3FF;
+    let bits = match exp {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal, renormalize
+            let shift = mantissa.leading_zeros() - 21;
+            sign | (113 - shift) << 23 | ((mantissa << shift) & 0x3FF) << 13
+        },
+        0x1F => sign | 0x7F80_0000 | mantissa << 13,
+        _ => sign | (exp + 112) << 23 | mantissa << 13,
+    };
+    f32::from_bits(bits)
+}
+
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+    if exp == 0xFF {
+        return sign | 0x7C00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1F {
+        sign | 0x7C00
+    } else if exp <= 0 {
+        if exp < -10 {
+            sign
+        } else {
+            let mantissa = mantissa | 0x80_0000;
+            sign | (mantissa >> (14 - exp)) as u16
+        }
+    } else {
+        sign | (exp as u16) << 10 | (mantissa >> 13) as u16
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn decode_channel(raw: u32, bits: u8, channel: ChannelType) -> f32 {
+    let max = max_value(bits);
+    match channel {
+        ChannelType::Unorm | ChannelType::Srgb => raw as f32 / max as f32,
+        ChannelType::Inorm => (sign_extend(raw, bits) as f32 / (max >> 1) as f32).max(-1.0),
+        ChannelType::Uint => raw as f32,
+        ChannelType::Int => sign_extend(raw, bits) as f32,
+        ChannelType::Float => match bits {
+            16 => f16_to_f32(raw as u16),
+            32 => f32::from_bits(raw),
+            _ => raw as f32 / max as f32,
+        },
+    }
+}
+
+fn encode_channel(value: f32, bits: u8, channel: ChannelType) -> u32 {
+    let max = max_value(bits);
+    match channel {
+        ChannelType::Unorm | ChannelType::Srgb =>
+            (value.max(0.0).min(1.0) * max as f32 + 0.5) as u32,
+        ChannelType::Inorm => {
+            let scale = (max >> 1) as f32;
+            (value.max(-1.0).min(1.0) * scale).round() as i32 as u32 & max
+        },
+        ChannelType::Uint => value.max(0.0).min(max as f32) as u32,
+        ChannelType::Int => {
+            let half = (max >> 1) as f32;
+            value.max(-half - 1.0).min(half) as i32 as u32 & max
+        },
+        ChannelType::Float => match bits {
+            16 => f32_to_f16(value) as u32,
+            32 => value.to_bits(),
+            _ => (value.max(0.0).min(1.0) * max as f32 + 0.5) as u32,
+        },
+    }
+}
+
+/// Decode a texel or a vertex attribute. Missing components are filled
+/// with zeros, and alpha with one.
+pub fn read_texel(format: Format, bytes: &[u8]) -> [f32; 4] {
+    let mut out = [0.0, 0.0, 0.0, 1.0];
+    match layout(format.0) {
+        Some(Layout::Aligned(count, size)) => {
+            for i in 0 .. count {
+                let raw = read_bits(&bytes[i * size ..], size);
+                out[i] = decode_channel(raw, size as u8 * 8, format.1);
+            }
+        },
+        Some(Layout::Packed(size, channels, count)) => {
+            let raw = read_bits(bytes, size);
+            for i in 0 .. count {
+                let (shift, bits) = channels[i];
+                out[i] = decode_channel((raw >> shift) & max_value(bits), bits, format.1);
+            }
+        },
+        Some(Layout::Depth) => out[0] = read_depth(format.0, bytes),
+        None => (),
+    }
+    if format.0 == SurfaceType::B8_G8_R8_A8 {
+        out.swap(0, 2);
+    }
+    if format.1 == ChannelType::Srgb {
+        for c in out[..3].iter_mut() {
+            *c = srgb_to_linear(*c);
+        }
+    }
+    out
+}
+
+/// Encode a texel, converting and clamping the values to the format.
+pub fn write_texel(format: Format, bytes: &mut [u8], mut value: [f32; 4]) {
+    if format.1 == ChannelType::Srgb {
+        for c in value[..3].iter_mut() {
+            *c = linear_to_srgb(c.max(0.0).min(1.0));
+        }
+    }
+    if format.0 == SurfaceType::B8_G8_R8_A8 {
+        value.swap(0, 2);
+    }
+    match layout(format.0) {
+        Some(Layout::Aligned(count, size)) => {
+            for i in 0 .. count {
+                let raw = encode_channel(value[i], size as u8 * 8, format.1);
+                write_bits(&mut bytes[i * size ..], size, raw);
+            }
+        },
+        Some(Layout::Packed(size, channels, count)) => {
+            let raw = (0 .. count).fold(0, |u, i| {
+                let (shift, bits) = channels[i];
+                u | encode_channel(value[i], bits, format.1) << shift
+            });
+            write_bits(bytes, size, raw);
+        },
+        Some(Layout::Depth) => write_depth(format.0, bytes, value[0]),
+        None => (),
+    }
+}
+
+pub fn read_depth(surface: SurfaceType, bytes: &[u8]) -> f32 {
+    match surface {
+        SurfaceType::D16 => read_bits(bytes, 2) as f32 / 65535.0,
+        SurfaceType::D24 | SurfaceType::D24_S8 => (read_bits(bytes, 4) >> 8) as f32 / 16777215.0,
+        SurfaceType::D32 => f32::from_bits(read_bits(bytes, 4)),
+        _ => 0.0,
+    }
+}
+
+pub fn write_depth(surface: SurfaceType, bytes: &mut [u8], depth: f32) {
+    let depth = depth.max(0.0).min(1.0);
+    match surface {
+        SurfaceType::D16 => write_bits(bytes, 2, (depth * 65535.0 + 0.5) as u32),
+        SurfaceType::D24 | SurfaceType::D24_S8 => {
+            let stencil = read_bits(bytes, 4) & 0xFF;
+            write_bits(bytes, 4, ((depth as f64 * 16777215.0).round() as u32) << 8 | stencil);
+        },
+        SurfaceType::D32 => write_bits(bytes, 4, depth.to_bits()),
+        _ => (),
+    }
+}
+
+pub fn read_stencil(surface: SurfaceType, bytes: &[u8]) -> u8 {
+    match surface {
+        SurfaceType::D24_S8 => bytes[0],
+        _ => 0,
+    }
+}
+
+pub fn write_stencil(surface: SurfaceType, bytes: &mut [u8], stencil: u8) {
+    if surface == SurfaceType::D24_S8 {
+        bytes[0] = stencil;
+    }
+}
+
+fn face_index(face: CubeFace) -> usize {
+    match face {
+        CubeFace::PosX => 0,
+        CubeFace::NegX => 1,
+        CubeFace::PosY => 2,
+        CubeFace::NegY => 3,
+        CubeFace::PosZ => 4,
+        CubeFace::NegZ => 5,
+    }
+}
+
+/// Select the cube face for a direction, returning the face index
+/// and the texture coordinates on it.
+fn cube_face(dir: [f32; 4]) -> (usize, f32, f32) {
+    let (x, y, z) = (dir[0], dir[1], dir[2]);
+    let (face, sc, tc, ma) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+        if x >= 0.0 { (0, -z, -y, x) } else { (1, z, -y, x) }
+    } else if y.abs() >= z.abs() {
+        if y >= 0.0 { (2, x, z, y) } else { (3, x, -z, y) }
+    } else {
+        if z >= 0.0 { (4, x, -y, z) } else { (5, -x, -y, z) }
+    };
+    let ma = ma.abs().max(1e-20);
+    (face, 0.5 * (sc / ma + 1.0), 0.5 * (tc / ma + 1.0))
+}
+
+/// Resolve the texel index along an axis according to the wrap mode,
+/// returning `None` for the border.
+fn wrap(index: i32, size: usize, mode: WrapMode) -> Option<usize> {
+    let size = size as i32;
+    match mode {
+        WrapMode::Tile => Some(((index % size + size) % size) as usize),
+        WrapMode::Mirror => {
+            let period = (index % (2 * size) + 2 * size) % (2 * size);
+            Some(if period < size { period } else { 2 * size - 1 - period } as usize)
+        },
+        WrapMode::Clamp => Some(cmp::max(0, cmp::min(size - 1, index)) as usize),
+        WrapMode::Border if index < 0 || index >= size => None,
+        WrapMode::Border => Some(index as usize),
+    }
+}
+
+fn swizzle(color: [f32; 4], swizzle: Swizzle) -> [f32; 4] {
+    let pick = |source| match source {
+        ChannelSource::Zero => 0.0,
+        ChannelSource::One => 1.0,
+        ChannelSource::X => color[0],
+        ChannelSource::Y => color[1],
+        ChannelSource::Z => color[2],
+        ChannelSource::W => color[3],
+    };
+    [pick(swizzle.0), pick(swizzle.1), pick(swizzle.2), pick(swizzle.3)]
+}
+
+fn lerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [a[0] + (b[0] - a[0]) * t,
+     a[1] + (b[1] - a[1]) * t,
+     a[2] + (b[2] - a[2]) * t,
+     a[3] + (b[3] - a[3]) * t]
+}
+
+/// Scale a tightly packed image of the `src` extent into one of the `dst` extent,
+/// picking the nearest texel or interpolating linearly within each slice.
+pub fn scale_image(src_format: Format, src: &[u8], (sw, sh, sd): (usize, usize, usize),
+                   dst_format: Format, dst: &mut [u8], (dw, dh, dd): (usize, usize, usize),
+                   linear: bool) {
+    let (src_size, dst_size) = (src_format.0.get_total_bits() as usize / 8,
+                                dst_format.0.get_total_bits() as usize / 8);
+    let fetch = |x: usize, y: usize, z: usize| {
+        let offset = ((z * sh + y) * sw + x) * src_size;
+        &src[offset .. offset + src_size]
+    };
+    for z in 0 .. dd {
+        let sz = z * sd / dd;
+        for y in 0 .. dh {
+            for x in 0 .. dw {
+                let offset = ((z * dh + y) * dw + x) * dst_size;
+                let out = &mut dst[offset .. offset + dst_size];
+                if linear {
+                    // map the texel center back into the source
+                    let fx = ((x as f32 + 0.5) * sw as f32 / dw as f32 - 0.5).max(0.0);
+                    let fy = ((y as f32 + 0.5) * sh as f32 / dh as f32 - 0.5).max(0.0);
+                    let (x0, y0) = (fx as usize, fy as usize);
+                    let (x1, y1) = (cmp::min(x0 + 1, sw - 1), cmp::min(y0 + 1, sh - 1));
+                    let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+                    let bottom = lerp(read_texel(src_format, fetch(x0, y0, sz)),
+                                      read_texel(src_format, fetch(x1, y0, sz)), tx);
+                    let top = lerp(read_texel(src_format, fetch(x0, y1, sz)),
+                                   read_texel(src_format, fetch(x1, y1, sz)), tx);
+                    write_texel(dst_format, out, lerp(bottom, top, ty));
+                } else {
+                    let texel = fetch(x * sw / dw, y * sh / dh, sz);
+                    if src_format == dst_format {
+                        out.copy_from_slice(texel);
+                    } else {
+                        write_texel(dst_format, out, read_texel(src_format, texel));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Texture object, owning the memory of all its layers and levels.
+pub struct Texture {
+    pub info: texture::Info,
+    pub data: Vec<u8>,
+}
+
+impl Texture {
+    /// Allocate a zero-initialized texture, or return `None` if the format is not supported.
+    pub fn new(info: texture::Info) -> Option<Texture> {
+        texel_size(info.format).map(|_| {
+            let mut tex = Texture {
+                info: info,
+                data: Vec::new(),
+            };
+            tex.data = vec![0; tex.layer_size() * tex.num_layers()];
+            tex
+        })
+    }
+
+    pub fn texel_size(&self) -> usize {
+        self.info.format.get_total_bits() as usize / 8
+    }
+
+    pub fn num_layers(&self) -> usize {
+        cmp::max(1, match self.info.kind {
+            Kind::D1(..) | Kind::D2(..) | Kind::D3(..) => 1,
+            Kind::D1Array(_, layers) | Kind::D2Array(_, _, layers, _) => layers as usize,
+            Kind::Cube(_) => 6,
+            Kind::CubeArray(_, layers) => 6 * layers as usize,
+        })
+    }
+
+    /// Get the size of a single layer of the given level, in texels.
+    pub fn level_extent(&self, level: Level) -> (usize, usize, usize) {
+        let (w, h, d, _) = self.info.kind.get_level_dimensions(level);
+        let (w, h, d) = (cmp::max(1, w as usize), cmp::max(1, h as usize), cmp::max(1, d as usize));
+        match self.info.kind {
+            Kind::D1(..) | Kind::D1Array(..) => (w, 1, 1),
+            Kind::D2(..) | Kind::D2Array(..) | Kind::Cube(..) | Kind::CubeArray(..) => (w, h, 1),
+            Kind::D3(..) => (w, h, d),
+        }
+    }
+
+    fn level_size(&self, level: Level) -> usize {
+        let (w, h, d) = self.level_extent(level);
+        w * h * d * self.texel_size()
+    }
+
+    fn layer_size(&self) -> usize {
+        (0 .. self.info.levels).map(|level| self.level_size(level)).sum()
+    }
+
+    /// Get the byte offset of the first texel of the given layer and level.
+    pub fn image_offset(&self, level: Level, layer: usize) -> usize {
+        layer * self.layer_size() +
+        (0 .. level).map(|l| self.level_size(l)).sum::<usize>()
+    }
+
+    /// Get the byte offset of a texel.
+    pub fn texel_offset(&self, level: Level, layer: usize, x: usize, y: usize, z: usize) -> usize {
+        let (w, h, _) = self.level_extent(level);
+        self.image_offset(level, layer) + ((z * h + y) * w + x) * self.texel_size()
+    }
+
+    /// Call the closure for every row of the image region, in the order of tightly
+    /// packed data, passing the byte offset of the row in the texture memory.
+    fn for_each_row<F>(&self, face: Option<CubeFace>, img: &RawImageInfo, mut fun: F) -> Result<(), ()>
+        where F: FnMut(usize)
+    {
+        if img.mipmap >= self.info.levels {
+            return Err(())
+        }
+        let (w, h, d) = self.level_extent(img.mipmap);
+        let face = face.map(face_index);
+        let (x0, y0, z0) = (img.xoffset as usize, img.yoffset as usize, img.zoffset as usize);
+        if x0 + cmp::max(1, img.width as usize) > w {
+            return Err(())
+        }
+        for k in 0 .. cmp::max(1, img.depth as usize) {
+            for j in 0 .. cmp::max(1, img.height as usize) {
+                let (layer, y, z) = match (self.info.kind, face) {
+                    (Kind::D1(..), _) => (0, 0, 0),
+                    (Kind::D1Array(..), _) => (y0 + j, 0, 0),
+                    (Kind::D2(..), _) => (0, y0 + j, 0),
+                    (Kind::D2Array(..), _) => (z0 + k, y0 + j, 0),
+                    (Kind::D3(..), _) => (0, y0 + j, z0 + k),
+                    (Kind::Cube(..), Some(f)) => (f, y0 + j, 0),
+                    (Kind::CubeArray(..), Some(f)) => (6 * (z0 + k) + f, y0 + j, 0),
+                    (Kind::Cube(..), None) | (Kind::CubeArray(..), None) => return Err(()),
+                };
+                if layer >= self.num_layers() || y >= h || z >= d {
+                    return Err(())
+                }
+                fun(self.texel_offset(img.mipmap, layer, x0, y, z));
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy tightly packed data into the image region.
+    pub fn write_image(&mut self, face: Option<CubeFace>, img: &RawImageInfo, data: &[u8]) -> Result<(), ()> {
+        let row = cmp::max(1, img.width as usize) * self.texel_size();
+        if data.len() < img.get_texel_count() * self.texel_size() {
+            return Err(())
+        }
+        let mut rows = Vec::new();
+        try!(self.for_each_row(face, img, |offset| rows.push(offset)));
+        for (i, offset) in rows.into_iter().enumerate() {
+            self.data[offset .. offset + row].copy_from_slice(&data[i * row .. (i + 1) * row]);
+        }
+        Ok(())
+    }
+
+    /// Copy the image region into tightly packed data.
+    pub fn read_image(&self, face: Option<CubeFace>, img: &RawImageInfo, data: &mut [u8]) -> Result<(), ()> {
+        let row = cmp::max(1, img.width as usize) * self.texel_size();
+        if data.len() < img.get_texel_count() * self.texel_size() {
+            return Err(())
+        }
+        let mut i = 0;
+        self.for_each_row(face, img, |offset| {
+            data[i * row .. (i + 1) * row].copy_from_slice(&self.data[offset .. offset + row]);
+            i += 1;
+        })
+    }
+
+    /// Fill the texture with the initial data, given for every layer and level.
+    pub fn init(&mut self, data: &[&[u8]]) -> Result<(), texture::CreationError> {
+        let num_images = self.num_layers() * self.info.levels as usize;
+        if data.len() != num_images {
+            error!("Texture expects {} images of data, got {}", num_images, data.len());
+            return Err(texture::CreationError::Data(data.len()));
+        }
+        for (i, image) in data.iter().enumerate() {
+            let (layer, level) = (i / self.info.levels as usize, (i % self.info.levels as usize) as Level);
+            let size = self.level_size(level);
+            if image.len() != size {
+                return Err(texture::CreationError::Data(image.len()));
+            }
+            let offset = self.image_offset(level, layer);
+            self.data[offset .. offset + size].copy_from_slice(image);
+        }
+        Ok(())
+    }
+
+    fn fetch(&self, format: Format, level: Level, layer: usize, x: usize, y: usize, z: usize) -> [f32; 4] {
+        let offset = self.texel_offset(level, layer, x, y, z);
+        match self.data.get(offset .. offset + self.texel_size()) {
+            Some(bytes) => read_texel(format, bytes),
+            // the texture is currently bound as a render target
+            None => [0.0; 4],
+        }
+    }
+
+    /// Sample the texture through a view at the given coordinates.
+    /// Only the base level of the view is used.
+    pub fn sample(&self, view: &texture::ResourceDesc, sampler: &texture::SamplerInfo,
+                  coord: [f32; 4]) -> [f32; 4] {
+        let format = Format(self.info.format, view.channel);
+        let level = cmp::min(view.min, self.info.levels - 1);
+        let (w, h, d) = self.level_extent(level);
+        let layer_count = self.num_layers();
+        let array_layer = |c: f32, faces: usize| {
+            let count = layer_count / faces;
+            cmp::min(count - 1, c.round().max(0.0) as usize) * faces
+        };
+        let (u, v, s, layer) = match self.info.kind {
+            Kind::D1(..) => (coord[0], 0.0, 0.0, 0),
+            Kind::D1Array(..) => (coord[0], 0.0, 0.0, array_layer(coord[1], 1)),
+            Kind::D2(..) => (coord[0], coord[1], 0.0, 0),
+            Kind::D2Array(..) => (coord[0], coord[1], 0.0, array_layer(coord[2], 1)),
+            Kind::D3(..) => (coord[0], coord[1], coord[2], 0),
+            Kind::Cube(..) => {
+                let (face, u, v) = cube_face(coord);
+                (u, v, 0.0, face)
+            },
+            Kind::CubeArray(..) => {
+                let (face, u, v) = cube_face(coord);
+                (u, v, 0.0, face + array_layer(coord[3], 6))
+            },
+        };
+        let layer = cmp::min(layer_count - 1, layer + view.layer.unwrap_or(0) as usize);
+        let border: [f32; 4] = sampler.border.into();
+        let (wrap_u, wrap_v, wrap_w) = sampler.wrap_mode;
+        let z = wrap((s * d as f32).floor() as i32, d, wrap_w);
+        let texel = |x: i32, y: i32| match (wrap(x, w, wrap_u), wrap(y, h, wrap_v), z) {
+            (Some(x), Some(y), Some(z)) => self.fetch(format, level, layer, x, y, z),
+            _ => border,
+        };
+        let color = match sampler.filter {
+            FilterMethod::Scale => texel((u * w as f32).floor() as i32, (v * h as f32).floor() as i32),
+            _ => {
+                let x = u * w as f32 - 0.5;
+                let y = v * h as f32 - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+                if h == 1 && d == 1 && layer_count == 1 {
+                    lerp(texel(x0, 0), texel(x0 + 1, 0), fx)
+                } else {
+                    let bottom = lerp(texel(x0, y0), texel(x0 + 1, y0), fx);
+                    let top = lerp(texel(x0, y0 + 1), texel(x0 + 1, y0 + 1), fx);
+                    lerp(bottom, top, fy)
+                }
+            },
+        };
+        swizzle(color, view.swizzle)
+    }
+
+    /// Fill an image of the texture with a color.
+    pub fn clear_color(&mut self, level: Level, layer: Layer, channel: ChannelType, color: [f32; 4]) {
+        let mut texel = vec![0; self.texel_size()];
+        write_texel(Format(self.info.format, channel), &mut texel, color);
+        let (w, h, d) = self.level_extent(level);
+        let offset = self.image_offset(level, layer as usize);
+        for chunk in self.data[offset .. offset + w * h * d * texel.len()].chunks_mut(texel.len()) {
+            chunk.copy_from_slice(&texel);
+        }
+    }
+
+    /// Fill an image of the texture with a depth and/or stencil value.
+    pub fn clear_depth_stencil(&mut self, level: Level, layer: Layer,
+                               depth: Option<f32>, stencil: Option<u8>) {
+        let size = self.texel_size();
+        let (w, h, d) = self.level_extent(level);
+        let offset = self.image_offset(level, layer as usize);
+        let surface = self.info.format;
+        for chunk in self.data[offset .. offset + w * h * d * size].chunks_mut(size) {
+            if let Some(depth) = depth {
+                write_depth(surface, chunk, depth);
+            }
+            if let Some(stencil) = stencil {
+                write_stencil(surface, chunk, stencil);
+            }
+        }
+    }
+
+    /// Fill the mip levels by filtering down the base level.
+    pub fn generate_mipmap(&mut self, channel: ChannelType) {
+        let format = Format(self.info.format, channel);
+        let size = self.texel_size();
+        let depth_step = match self.info.kind {
+            Kind::D3(..) => 2,
+            _ => 1,
+        };
+        for layer in 0 .. self.num_layers() {
+            for level in 1 .. self.info.levels {
+                let (w, h, d) = self.level_extent(level);
+                let (pw, ph, pd) = self.level_extent(level - 1);
+                for z in 0 .. d {
+                    for y in 0 .. h {
+                        for x in 0 .. w {
+                            let mut sum = [0.0; 4];
+                            let mut count = 0.0;
+                            for k in 0 .. depth_step {
+                                for j in 0 .. 2 {
+                                    for i in 0 .. 2 {
+                                        let (sx, sy, sz) = (2 * x + i, 2 * y + j, z * depth_step + k);
+                                        if sx >= pw || sy >= ph || sz >= pd {
+                                            continue
+                                        }
+                                        let c = self.fetch(format, level - 1, layer, sx, sy, sz);
+                                        for (s, c) in sum.iter_mut().zip(c.iter()) {
+                                            *s += *c;
+                                        }
+                                        count += 1.0;
+                                    }
+                                }
+                            }
+                            let offset = self.texel_offset(level, layer, x, y, z);
+                            let color = [sum[0] / count, sum[1] / count, sum[2] / count, sum[3] / count];
+                            write_texel(format, &mut self.data[offset .. offset + size], color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
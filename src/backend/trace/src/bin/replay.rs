@@ -0,0 +1,69 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Replays a trace on the software backend.
+//!
+//! Usage: `gfx_replay <trace>`
+
+extern crate gfx_core;
+extern crate gfx_device_software;
+extern crate gfx_device_trace;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::process;
+use gfx_core::Device;
+use gfx_device_trace::{Event, Replayer};
+
+
+fn main() {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            let _ = writeln!(io::stderr(), "Usage: gfx_replay <trace>");
+            process::exit(2)
+        }
+    };
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "Unable to open {}: {}", path, e);
+            process::exit(1)
+        }
+    };
+
+    let (mut device, mut factory) = gfx_device_software::create();
+    let mut cb = factory.create_command_buffer();
+    let mut replayer = Replayer::new();
+    let mut submissions = 0;
+    for (i, event) in gfx_device_trace::read(BufReader::new(file)).enumerate() {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                let _ = writeln!(io::stderr(), "Malformed event {}: {}", i, e);
+                process::exit(1)
+            }
+        };
+        if let Event::Submit(_) = event {
+            submissions += 1;
+        }
+        if let Err(e) = replayer.replay(event, &mut device, &mut factory, &mut cb) {
+            let _ = writeln!(io::stderr(), "Event {} failed: {}", i, e);
+            process::exit(1)
+        }
+    }
+    device.cleanup();
+    println!("Replayed {} submissions from {}", submissions, path);
+}
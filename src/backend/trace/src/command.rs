@@ -0,0 +1,285 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use core::{self as c, command as com, pso, shade, state, target, texture};
+use core::{IndexType, VertexCount};
+use event::{Command, PixelTargets};
+use {Resources, Traced};
+
+
+/// A command buffer forwarding to the wrapped one, and recording the commands.
+pub struct CommandBuffer<C> {
+    /// Wrapped command buffer. Commands added to it directly are not recorded.
+    pub inner: C,
+    /// Commands recorded since the last reset.
+    pub commands: Vec<Command>,
+}
+
+impl<C> CommandBuffer<C> {
+    /// Wrap a command buffer of the backend traced by the device.
+    pub fn new(inner: C) -> CommandBuffer<C> {
+        CommandBuffer {
+            inner: inner,
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl<R: c::Resources, C: com::Buffer<R>> com::Buffer<Resources<R>> for CommandBuffer<C> {
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.commands.clear();
+    }
+
+    fn append(&mut self, other: &CommandBuffer<C>) -> Result<(), com::AppendError> {
+        try!(self.inner.append(&other.inner));
+        self.commands.extend(other.commands.iter().cloned());
+        Ok(())
+    }
+
+    fn bind_pipeline_state(&mut self, pso: Traced<R::PipelineStateObject>) {
+        self.commands.push(Command::BindPipelineState(pso.id));
+        self.inner.bind_pipeline_state(pso.inner);
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<Resources<R>>) {
+        let mut inner = pso::VertexBufferSet::new();
+        let mut ids = [None; c::MAX_VERTEX_ATTRIBUTES];
+        for (i, vb) in vbs.0.iter().enumerate() {
+            if let Some((buffer, offset)) = *vb {
+                inner.0[i] = Some((buffer.inner, offset));
+                ids[i] = Some((buffer.id, offset));
+            }
+        }
+        self.commands.push(Command::BindVertexBuffers(ids));
+        self.inner.bind_vertex_buffers(inner);
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<Resources<R>>]) {
+        let inner = cbs.iter().map(|&pso::ConstantBufferParam(buffer, usage, slot, range)|
+            pso::ConstantBufferParam(buffer.inner, usage, slot, range)
+        ).collect::<Vec<_>>();
+        self.commands.push(Command::BindConstantBuffers(
+            cbs.iter().map(|cb| (cb.0.id, cb.1, cb.2, cb.3)).collect()));
+        self.inner.bind_constant_buffers(&inner);
+    }
+
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.commands.push(Command::BindGlobalConstant(loc, value.clone()));
+        self.inner.bind_global_constant(loc, value);
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<Resources<R>>]) {
+        let inner = srvs.iter().map(|&pso::ResourceViewParam(view, usage, slot)|
+            pso::ResourceViewParam(view.inner, usage, slot)
+        ).collect::<Vec<_>>();
+        self.commands.push(Command::BindResourceViews(
+            srvs.iter().map(|srv| (srv.0.id, srv.1, srv.2)).collect()));
+        self.inner.bind_resource_views(&inner);
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<Resources<R>>]) {
+        let inner = uavs.iter().map(|&pso::UnorderedViewParam(view, usage, slot)|
+            pso::UnorderedViewParam(view.inner, usage, slot)
+        ).collect::<Vec<_>>();
+        self.commands.push(Command::BindUnorderedViews(
+            uavs.iter().map(|uav| (uav.0.id, uav.1, uav.2)).collect()));
+        self.inner.bind_unordered_views(&inner);
+    }
+
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<Resources<R>>]) {
+        let inner = ss.iter().map(|&pso::SamplerParam(sampler, usage, slot)|
+            pso::SamplerParam(sampler.inner, usage, slot)
+        ).collect::<Vec<_>>();
+        self.commands.push(Command::BindSamplers(
+            ss.iter().map(|s| (s.0.id, s.1, s.2)).collect()));
+        self.inner.bind_samplers(&inner);
+    }
+
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<Resources<R>>) {
+        let mut inner = pso::PixelTargetSet::new();
+        let mut ids = PixelTargets {
+            colors: [None; c::MAX_COLOR_TARGETS],
+            depth: pts.depth.as_ref().map(|view| view.id),
+            stencil: pts.stencil.as_ref().map(|view| view.id),
+            dimensions: pts.dimensions,
+        };
+        for (i, color) in pts.colors.iter().enumerate() {
+            if let Some(view) = *color {
+                inner.colors[i] = Some(view.inner);
+                ids.colors[i] = Some(view.id);
+            }
+        }
+        inner.depth = pts.depth.map(|view| view.inner);
+        inner.stencil = pts.stencil.map(|view| view.inner);
+        inner.dimensions = pts.dimensions;
+        self.commands.push(Command::BindPixelTargets(ids));
+        self.inner.bind_pixel_targets(inner);
+    }
+
+    fn bind_stream_output(&mut self, so: Option<pso::StreamOutputParam<Resources<R>>>) {
+        self.commands.push(Command::BindStreamOutput(so.as_ref().map(|p| (p.0.id, p.1))));
+        self.inner.bind_stream_output(so.map(|p| pso::StreamOutputParam(p.0.inner, p.1)));
+    }
+
+    fn bind_index(&mut self, buffer: Traced<R::Buffer>, itype: IndexType) {
+        self.commands.push(Command::BindIndex(buffer.id, itype));
+        self.inner.bind_index(buffer.inner, itype);
+    }
+
+    fn set_scissor(&mut self, rect: target::Rect) {
+        self.commands.push(Command::SetScissor(rect));
+        self.inner.set_scissor(rect);
+    }
+
+    fn set_viewports(&mut self, viewports: &[com::Viewport]) {
+        self.commands.push(Command::SetViewports(viewports.to_vec()));
+        self.inner.set_viewports(viewports);
+    }
+
+    fn set_ref_values(&mut self, rv: state::RefValues) {
+        self.commands.push(Command::SetRefValues(rv));
+        self.inner.set_ref_values(rv);
+    }
+
+    fn copy_buffer(&mut self, src: Traced<R::Buffer>, dst: Traced<R::Buffer>,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.commands.push(Command::CopyBuffer(src.id, dst.id,
+                                               src_offset_bytes, dst_offset_bytes, size_bytes));
+        self.inner.copy_buffer(src.inner, dst.inner, src_offset_bytes, dst_offset_bytes, size_bytes);
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: Traced<R::Buffer>, src_offset_bytes: usize,
+                              dst: Traced<R::Texture>, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo) {
+        self.commands.push(Command::CopyBufferToTexture(src.id, src_offset_bytes,
+                                                        dst.id, kind, face, img));
+        self.inner.copy_buffer_to_texture(src.inner, src_offset_bytes, dst.inner, kind, face, img);
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: Traced<R::Texture>, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo,
+                              dst: Traced<R::Buffer>, dst_offset_bytes: usize) {
+        self.commands.push(Command::CopyTextureToBuffer(src.id, kind, face, img,
+                                                        dst.id, dst_offset_bytes));
+        self.inner.copy_texture_to_buffer(src.inner, kind, face, img, dst.inner, dst_offset_bytes);
+    }
+
+    fn copy_texture(&mut self, src: texture::TextureCopyRegion<Traced<R::Texture>>,
+                    dst: texture::TextureCopyRegion<Traced<R::Texture>>) {
+        let (src_id, dst_id) = (src.convert(src.texture.id), dst.convert(dst.texture.id));
+        self.commands.push(Command::CopyTexture(src_id, dst_id));
+        self.inner.copy_texture(src_id.convert(src.texture.inner), dst_id.convert(dst.texture.inner));
+    }
+
+    fn resolve_texture(&mut self, src: texture::TextureCopyRegion<Traced<R::Texture>>,
+                       dst: texture::TextureCopyRegion<Traced<R::Texture>>) {
+        let (src_id, dst_id) = (src.convert(src.texture.id), dst.convert(dst.texture.id));
+        self.commands.push(Command::ResolveTexture(src_id, dst_id));
+        self.inner.resolve_texture(src_id.convert(src.texture.inner), dst_id.convert(dst.texture.inner));
+    }
+
+    fn blit_texture(&mut self, src: texture::TextureCopyRegion<Traced<R::Texture>>,
+                    dst: texture::TextureCopyRegion<Traced<R::Texture>>, filter: texture::FilterMethod) {
+        let (src_id, dst_id) = (src.convert(src.texture.id), dst.convert(dst.texture.id));
+        self.commands.push(Command::BlitTexture(src_id, dst_id, filter));
+        self.inner.blit_texture(src_id.convert(src.texture.inner), dst_id.convert(dst.texture.inner), filter);
+    }
+
+    fn update_buffer(&mut self, buffer: Traced<R::Buffer>, data: &[u8], offset: usize) {
+        self.commands.push(Command::UpdateBuffer(buffer.id, data.to_vec(), offset));
+        self.inner.update_buffer(buffer.inner, data, offset);
+    }
+
+    fn update_texture(&mut self, texture: Traced<R::Texture>, kind: texture::Kind,
+                      face: Option<texture::CubeFace>, data: &[u8], img: texture::RawImageInfo) {
+        self.commands.push(Command::UpdateTexture(texture.id, kind, face, data.to_vec(), img));
+        self.inner.update_texture(texture.inner, kind, face, data, img);
+    }
+
+    fn generate_mipmap(&mut self, srv: Traced<R::ShaderResourceView>) {
+        self.commands.push(Command::GenerateMipmap(srv.id));
+        self.inner.generate_mipmap(srv.inner);
+    }
+
+    fn clear_color(&mut self, target: Traced<R::RenderTargetView>, value: com::ClearColor) {
+        self.commands.push(Command::ClearColor(target.id, value));
+        self.inner.clear_color(target.inner, value);
+    }
+
+    fn clear_depth_stencil(&mut self, target: Traced<R::DepthStencilView>,
+                           depth: Option<target::Depth>, stencil: Option<target::Stencil>) {
+        self.commands.push(Command::ClearDepthStencil(target.id, depth, stencil));
+        self.inner.clear_depth_stencil(target.inner, depth, stencil);
+    }
+
+    fn call_draw(&mut self, start: VertexCount, count: VertexCount, instances: Option<com::InstanceParams>) {
+        self.commands.push(Command::Draw(start, count, instances));
+        self.inner.call_draw(start, count, instances);
+    }
+
+    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
+                         base: VertexCount, instances: Option<com::InstanceParams>) {
+        self.commands.push(Command::DrawIndexed(start, count, base, instances));
+        self.inner.call_draw_indexed(start, count, base, instances);
+    }
+
+    fn call_draw_indirect(&mut self, buffer: Traced<R::Buffer>, offset_bytes: usize, draw_count: usize) {
+        self.commands.push(Command::DrawIndirect(buffer.id, offset_bytes, draw_count));
+        self.inner.call_draw_indirect(buffer.inner, offset_bytes, draw_count);
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buffer: Traced<R::Buffer>, offset_bytes: usize, draw_count: usize) {
+        self.commands.push(Command::DrawIndexedIndirect(buffer.id, offset_bytes, draw_count));
+        self.inner.call_draw_indexed_indirect(buffer.inner, offset_bytes, draw_count);
+    }
+
+    fn call_dispatch(&mut self, x: com::ThreadGroupCount, y: com::ThreadGroupCount, z: com::ThreadGroupCount) {
+        self.commands.push(Command::Dispatch(x, y, z));
+        self.inner.call_dispatch(x, y, z);
+    }
+
+    fn begin_query(&mut self, query: Traced<R::Query>) {
+        self.commands.push(Command::BeginQuery(query.id));
+        self.inner.begin_query(query.inner);
+    }
+
+    fn end_query(&mut self, query: Traced<R::Query>) {
+        self.commands.push(Command::EndQuery(query.id));
+        self.inner.end_query(query.inner);
+    }
+
+    fn write_timestamp(&mut self, query: Traced<R::Query>) {
+        self.commands.push(Command::WriteTimestamp(query.id));
+        self.inner.write_timestamp(query.inner);
+    }
+
+    fn push_debug_group(&mut self, name: &str) {
+        self.commands.push(Command::PushDebugGroup(name.to_string()));
+        self.inner.push_debug_group(name);
+    }
+
+    fn pop_debug_group(&mut self) {
+        self.commands.push(Command::PopDebugGroup);
+        self.inner.pop_debug_group();
+    }
+
+    fn insert_debug_marker(&mut self, name: &str) {
+        self.commands.push(Command::InsertDebugMarker(name.to_string()));
+        self.inner.insert_debug_marker(name);
+    }
+}
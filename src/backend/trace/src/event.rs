@@ -0,0 +1,135 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+use serde_json;
+use core::{self as c, buffer, command as com, query, shade, state, target, texture};
+use core::format::ChannelType;
+use core::pso::{self, BufferOffset};
+use Id;
+
+
+/// Shaders of a program, by their ids.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum ShaderSet {
+    Simple(Id, Id),
+    Geometry(Id, Id, Id),
+    Tessellated(Id, Id, Id, Id),
+    Compute(Id),
+    TransformFeedback(Id, Option<Id>, Vec<String>),
+}
+
+/// Set of the bound pixel targets, by the ids of their views.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct PixelTargets {
+    pub colors: [Option<Id>; c::MAX_COLOR_TARGETS],
+    pub depth: Option<Id>,
+    pub stencil: Option<Id>,
+    pub dimensions: Option<texture::Dimensions>,
+}
+
+/// A recorded command, mirroring the methods of `command::Buffer`.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    BindPipelineState(Id),
+    BindVertexBuffers([Option<(Id, BufferOffset)>; c::MAX_VERTEX_ATTRIBUTES]),
+    BindConstantBuffers(Vec<(Id, shade::Usage, c::ConstantBufferSlot, Option<pso::BufferRange>)>),
+    BindGlobalConstant(shade::Location, shade::UniformValue),
+    BindResourceViews(Vec<(Id, shade::Usage, c::ResourceViewSlot)>),
+    BindUnorderedViews(Vec<(Id, shade::Usage, c::UnorderedViewSlot)>),
+    BindSamplers(Vec<(Id, shade::Usage, c::SamplerSlot)>),
+    BindPixelTargets(PixelTargets),
+    BindStreamOutput(Option<(Id, usize)>),
+    BindIndex(Id, c::IndexType),
+    SetScissor(target::Rect),
+    SetViewports(Vec<com::Viewport>),
+    SetRefValues(state::RefValues),
+    CopyBuffer(Id, Id, usize, usize, usize),
+    CopyBufferToTexture(Id, usize, Id, texture::Kind, Option<texture::CubeFace>, texture::RawImageInfo),
+    CopyTextureToBuffer(Id, texture::Kind, Option<texture::CubeFace>, texture::RawImageInfo, Id, usize),
+    CopyTexture(texture::TextureCopyRegion<Id>, texture::TextureCopyRegion<Id>),
+    ResolveTexture(texture::TextureCopyRegion<Id>, texture::TextureCopyRegion<Id>),
+    BlitTexture(texture::TextureCopyRegion<Id>, texture::TextureCopyRegion<Id>, texture::FilterMethod),
+    UpdateBuffer(Id, Vec<u8>, usize),
+    UpdateTexture(Id, texture::Kind, Option<texture::CubeFace>, Vec<u8>, texture::RawImageInfo),
+    GenerateMipmap(Id),
+    ClearColor(Id, com::ClearColor),
+    ClearDepthStencil(Id, Option<target::Depth>, Option<target::Stencil>),
+    Draw(c::VertexCount, c::VertexCount, Option<com::InstanceParams>),
+    DrawIndexed(c::VertexCount, c::VertexCount, c::VertexCount, Option<com::InstanceParams>),
+    DrawIndirect(Id, usize, usize),
+    DrawIndexedIndirect(Id, usize, usize),
+    Dispatch(com::ThreadGroupCount, com::ThreadGroupCount, com::ThreadGroupCount),
+    BeginQuery(Id),
+    EndQuery(Id),
+    WriteTimestamp(Id),
+    PushDebugGroup(String),
+    PopDebugGroup,
+    InsertDebugMarker(String),
+}
+
+/// An entry of the trace. The objects are created with the id given
+/// as the first field, and referenced by it afterwards.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    /// Capabilities of the device the trace was captured on.
+    Capabilities(c::Capabilities),
+    /// Buffer creation, with the initial contents of the immutable ones.
+    CreateBuffer(Id, buffer::Info, Option<Vec<u8>>),
+    CreateShader(Id, shade::Stage, Vec<u8>),
+    CreateProgram(Id, ShaderSet),
+    CreatePipelineState(Id, Id, pso::Descriptor),
+    CreateTexture(Id, texture::Info, Option<ChannelType>, Option<Vec<Vec<u8>>>),
+    ViewBufferAsShaderResource(Id, Id),
+    ViewBufferAsUnorderedAccess(Id, Id),
+    ViewTextureAsShaderResource(Id, Id, texture::ResourceDesc),
+    ViewTextureAsUnorderedAccess(Id, Id, texture::UnorderedDesc),
+    ViewTextureAsRenderTarget(Id, Id, texture::RenderDesc),
+    ViewTextureAsDepthStencil(Id, Id, texture::DepthStencilDesc),
+    CreateSampler(Id, texture::SamplerInfo),
+    CreateQuery(Id, query::Kind),
+    /// The object is given a name for the debugging tools.
+    SetDebugName(Id, String),
+    /// Contents of a mapped buffer written by the CPU before a submission,
+    /// starting at the given byte offset.
+    WriteBuffer(Id, usize, Vec<u8>),
+    /// Submission of a command buffer.
+    Submit(Vec<Command>),
+    /// The object is no longer used.
+    Free(Id),
+}
+
+/// Iterator over the events of a trace, see `read`.
+pub struct Events<R: io::Read> {
+    stream: serde_json::StreamDeserializer<'static, serde_json::de::IoRead<R>, Event>,
+}
+
+impl<R: io::Read> Iterator for Events<R> {
+    type Item = Result<Event, serde_json::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stream.next()
+    }
+}
+
+/// Read the events of a trace, as written by the trace device.
+pub fn read<R: io::Read>(reader: R) -> Events<R> {
+    Events {
+        stream: serde_json::Deserializer::from_reader(reader).into_iter(),
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+
+use core::{self as c, factory as f, texture as t, buffer, mapping, query, shade};
+use core::memory::{self, Bind, Typed};
+use core::format::{self, ChannelType};
+use core::handle::{self, Producer};
+
+use command::CommandBuffer;
+use event::{Event, ShaderSet};
+use {Id, MappingGate, Object, Resources, Share, Traced};
+
+
+/// A factory forwarding to the wrapped one, and recording the created objects.
+pub struct Factory<R: c::Resources, F> {
+    inner: F,
+    share: Rc<Share<R>>,
+    frame_handles: handle::Manager<Resources<R>>,
+    inner_handles: handle::Manager<R>,
+}
+
+impl<R: c::Resources, F: Clone> Clone for Factory<R, F> {
+    fn clone(&self) -> Factory<R, F> {
+        Factory::new(self.inner.clone(), self.share.clone())
+    }
+}
+
+impl<R: c::Resources, F> Factory<R, F> {
+    /// Create a new `Factory`.
+    pub fn new(inner: F, share: Rc<Share<R>>) -> Factory<R, F> {
+        Factory {
+            inner: inner,
+            share: share,
+            frame_handles: handle::Manager::new(),
+            inner_handles: handle::Manager::new(),
+        }
+    }
+
+    /// Create a command buffer, wrapping the one created by `fun` from the wrapped factory.
+    pub fn create_command_buffer<C, Fun>(&mut self, fun: Fun) -> CommandBuffer<C>
+        where Fun: FnOnce(&mut F) -> C
+    {
+        CommandBuffer::new(fun(&mut self.inner))
+    }
+
+    fn inner_buffer<T>(&self, buf: &handle::Buffer<Resources<R>, T>) -> handle::Buffer<R, T> {
+        let objects = self.share.objects.borrow();
+        let raw = objects.buffer(buf.raw().resource().id).expect("Unknown buffer");
+        Typed::new(raw.clone())
+    }
+
+    fn inner_texture(&self, htex: &handle::RawTexture<Resources<R>>) -> handle::RawTexture<R> {
+        self.share.objects.borrow().texture(htex.resource().id).expect("Unknown texture").clone()
+    }
+
+    fn add_buffer(&mut self, buffer: handle::RawBuffer<R>, data: Option<&[u8]>)
+                  -> handle::RawBuffer<Resources<R>> {
+        let info = buffer.get_info().clone();
+        let resource = *buffer.resource();
+        let mapping = if buffer.is_mapped() {
+            Some(MappingGate { buffer: buffer.clone() })
+        } else {
+            None
+        };
+        let id = self.share.add(Object::Buffer(buffer));
+        self.share.record(&Event::CreateBuffer(id, info.clone(), data.map(|d| d.to_vec())));
+        self.share.handles.borrow_mut().make_buffer(Traced { id: id, inner: resource }, info, mapping)
+    }
+}
+
+impl<R: c::Resources, F: f::Factory<R>> f::Factory<Resources<R>> for Factory<R, F> {
+    fn get_capabilities(&self) -> &c::Capabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<Resources<R>>, name: &str) {
+        use core::handle::Ref;
+        self.share.handles.borrow_mut().set_label(handle, name);
+        let mut handles = handle::Manager::new();
+        let id = match handle {
+            Ref::Buffer(h) => handles.ref_buffer(h).id,
+            Ref::Shader(h) => handles.ref_shader(h).id,
+            Ref::Program(h) => handles.ref_program(h).id,
+            Ref::PipelineState(h) => handles.ref_pso(h).0.id,
+            Ref::Texture(h) => handles.ref_texture(h).id,
+            Ref::ShaderResourceView(h) => handles.ref_srv(h).id,
+            Ref::UnorderedAccessView(h) => handles.ref_uav(h).id,
+            Ref::RenderTargetView(h) => handles.ref_rtv(h).id,
+            Ref::DepthStencilView(h) => handles.ref_dsv(h).id,
+            Ref::Sampler(h) => handles.ref_sampler(h).id,
+            Ref::Fence(h) => handles.ref_fence(h).id,
+            Ref::Query(h) => handles.ref_query(h).id,
+        };
+        if let Some(object) = self.share.objects.borrow().get(id) {
+            self.inner.set_debug_name_raw(object.as_ref(), name);
+        }
+        self.share.record(&Event::SetDebugName(id, name.to_string()));
+    }
+
+    fn get_statistics(&self) -> handle::Statistics {
+        self.share.handles.borrow().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: format::Format) -> format::Properties {
+        self.inner.get_format_properties(format)
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info)
+                         -> Result<handle::RawBuffer<Resources<R>>, buffer::CreationError> {
+        let buffer = try!(self.inner.create_buffer_raw(info));
+        Ok(self.add_buffer(buffer, None))
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::RawBuffer<Resources<R>>, buffer::CreationError> {
+        let buffer = try!(self.inner.create_buffer_immutable_raw(data, stride, role, bind));
+        Ok(self.add_buffer(buffer, Some(data)))
+    }
+
+    fn create_shader(&mut self, stage: shade::Stage, code: &[u8])
+                     -> Result<handle::Shader<Resources<R>>, shade::CreateShaderError> {
+        let shader = try!(self.inner.create_shader(stage, code));
+        let resource = self.inner_handles.ref_shader(&shader).clone();
+        self.inner_handles.clear();
+        let id = self.share.add(Object::Shader(shader));
+        self.share.record(&Event::CreateShader(id, stage, code.to_vec()));
+        Ok(self.share.handles.borrow_mut().make_shader(Traced { id: id, inner: resource }))
+    }
+
+    fn create_program(&mut self, shader_set: &c::ShaderSet<Resources<R>>)
+                      -> Result<handle::Program<Resources<R>>, shade::CreateProgramError> {
+        let (set, ids) = {
+            let handles = &mut self.frame_handles;
+            let objects = self.share.objects.borrow();
+            let shader = |id: Id| objects.shader(id).expect("Unknown shader").clone();
+            match *shader_set {
+                c::ShaderSet::Simple(ref vs, ref ps) => {
+                    let (vs, ps) = (vs.reference(handles).id, ps.reference(handles).id);
+                    (c::ShaderSet::Simple(c::VertexShader::new(shader(vs)),
+                                          c::PixelShader::new(shader(ps))),
+                     ShaderSet::Simple(vs, ps))
+                },
+                c::ShaderSet::Geometry(ref vs, ref gs, ref ps) => {
+                    let (vs, gs, ps) = (vs.reference(handles).id, gs.reference(handles).id,
+                                        ps.reference(handles).id);
+                    (c::ShaderSet::Geometry(c::VertexShader::new(shader(vs)),
+                                            c::GeometryShader::new(shader(gs)),
+                                            c::PixelShader::new(shader(ps))),
+                     ShaderSet::Geometry(vs, gs, ps))
+                },
+                c::ShaderSet::Tessellated(ref vs, ref hs, ref ds, ref ps) => {
+                    let (vs, hs, ds, ps) = (vs.reference(handles).id, hs.reference(handles).id,
+                                            ds.reference(handles).id, ps.reference(handles).id);
+                    (c::ShaderSet::Tessellated(c::VertexShader::new(shader(vs)),
+                                               c::HullShader::new(shader(hs)),
+                                               c::DomainShader::new(shader(ds)),
+                                               c::PixelShader::new(shader(ps))),
+                     ShaderSet::Tessellated(vs, hs, ds, ps))
+                },
+                c::ShaderSet::Compute(ref cs) => {
+                    let cs = cs.reference(handles).id;
+                    (c::ShaderSet::Compute(c::ComputeShader::new(shader(cs))),
+                     ShaderSet::Compute(cs))
+                },
+                c::ShaderSet::TransformFeedback(ref vs, ref gs, ref outputs) => {
+                    let vs = vs.reference(handles).id;
+                    let gs = gs.as_ref().map(|gs| gs.reference(handles).id);
+                    (c::ShaderSet::TransformFeedback(c::VertexShader::new(shader(vs)),
+                                                     gs.map(|gs| c::GeometryShader::new(shader(gs))),
+                                                     outputs.clone()),
+                     ShaderSet::TransformFeedback(vs, gs, outputs.clone()))
+                },
+            }
+        };
+        self.frame_handles.clear();
+        let program = try!(self.inner.create_program(&set));
+        let resource = program.resource().clone();
+        let info = program.get_info().clone();
+        let id = self.share.add(Object::Program(program));
+        self.share.record(&Event::CreateProgram(id, ids));
+        Ok(self.share.handles.borrow_mut().make_program(Traced { id: id, inner: resource }, info))
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<Resources<R>>, desc: &c::pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<Resources<R>>, c::pso::CreationError> {
+        let program_id = program.resource().id;
+        let inner_program = self.share.objects.borrow().program(program_id).expect("Unknown program").clone();
+        let pso = try!(self.inner.create_pipeline_state_raw(&inner_program, desc));
+        let resource = self.inner_handles.ref_pso(&pso).0.clone();
+        self.inner_handles.clear();
+        let id = self.share.add(Object::PipelineState(pso));
+        self.share.record(&Event::CreatePipelineState(id, program_id, *desc));
+        Ok(self.share.handles.borrow_mut().make_pso(Traced { id: id, inner: resource }, program))
+    }
+
+    fn create_texture_raw(&mut self, desc: t::Info, hint: Option<ChannelType>, data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<Resources<R>>, t::CreationError> {
+        let texture = try!(self.inner.create_texture_raw(desc, hint, data_opt));
+        let resource = texture.resource().clone();
+        let id = self.share.add(Object::Texture(texture));
+        let data = data_opt.map(|data| data.iter().map(|slice| slice.to_vec()).collect());
+        self.share.record(&Event::CreateTexture(id, desc, hint, data));
+        Ok(self.share.handles.borrow_mut().make_texture(Traced { id: id, inner: resource }, desc))
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, hbuf: &handle::RawBuffer<Resources<R>>)
+                                      -> Result<handle::RawShaderResourceView<Resources<R>>, f::ResourceViewError> {
+        let buf_id = hbuf.resource().id;
+        let buffer = self.share.objects.borrow().buffer(buf_id).expect("Unknown buffer").clone();
+        let view = try!(self.inner.view_buffer_as_shader_resource_raw(&buffer));
+        let resource = *self.inner_handles.ref_srv(&view);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::ShaderResourceView(view));
+        self.share.record(&Event::ViewBufferAsShaderResource(id, buf_id));
+        Ok(self.share.handles.borrow_mut().make_buffer_srv(Traced { id: id, inner: resource }, hbuf))
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, hbuf: &handle::RawBuffer<Resources<R>>)
+                                       -> Result<handle::RawUnorderedAccessView<Resources<R>>, f::ResourceViewError> {
+        let buf_id = hbuf.resource().id;
+        let buffer = self.share.objects.borrow().buffer(buf_id).expect("Unknown buffer").clone();
+        let view = try!(self.inner.view_buffer_as_unordered_access_raw(&buffer));
+        let resource = *self.inner_handles.ref_uav(&view);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::UnorderedAccessView(view));
+        self.share.record(&Event::ViewBufferAsUnorderedAccess(id, buf_id));
+        Ok(self.share.handles.borrow_mut().make_buffer_uav(Traced { id: id, inner: resource }, hbuf))
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<Resources<R>>, desc: t::ResourceDesc)
+                                       -> Result<handle::RawShaderResourceView<Resources<R>>, f::ResourceViewError> {
+        let texture = self.inner_texture(htex);
+        let view = try!(self.inner.view_texture_as_shader_resource_raw(&texture, desc));
+        let resource = *self.inner_handles.ref_srv(&view);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::ShaderResourceView(view));
+        self.share.record(&Event::ViewTextureAsShaderResource(id, htex.resource().id, desc));
+        Ok(self.share.handles.borrow_mut().make_texture_srv(Traced { id: id, inner: resource }, htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, htex: &handle::RawTexture<Resources<R>>, desc: t::UnorderedDesc)
+                                        -> Result<handle::RawUnorderedAccessView<Resources<R>>, f::ResourceViewError> {
+        let texture = self.inner_texture(htex);
+        let view = try!(self.inner.view_texture_as_unordered_access_raw(&texture, desc));
+        let resource = *self.inner_handles.ref_uav(&view);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::UnorderedAccessView(view));
+        self.share.record(&Event::ViewTextureAsUnorderedAccess(id, htex.resource().id, desc));
+        Ok(self.share.handles.borrow_mut().make_texture_uav(Traced { id: id, inner: resource }, htex))
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<Resources<R>>, desc: t::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<Resources<R>>, f::TargetViewError> {
+        let texture = self.inner_texture(htex);
+        let view = try!(self.inner.view_texture_as_render_target_raw(&texture, desc));
+        let resource = *self.inner_handles.ref_rtv(&view);
+        self.inner_handles.clear();
+        let dim = view.get_dimensions();
+        let id = self.share.add(Object::RenderTargetView(view));
+        self.share.record(&Event::ViewTextureAsRenderTarget(id, htex.resource().id, desc));
+        Ok(self.share.handles.borrow_mut().make_rtv(Traced { id: id, inner: resource }, htex, dim))
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<Resources<R>>, desc: t::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<Resources<R>>, f::TargetViewError> {
+        let texture = self.inner_texture(htex);
+        let view = try!(self.inner.view_texture_as_depth_stencil_raw(&texture, desc));
+        let resource = self.inner_handles.ref_dsv(&view).clone();
+        self.inner_handles.clear();
+        let dim = view.get_dimensions();
+        let id = self.share.add(Object::DepthStencilView(view));
+        self.share.record(&Event::ViewTextureAsDepthStencil(id, htex.resource().id, desc));
+        Ok(self.share.handles.borrow_mut().make_dsv(Traced { id: id, inner: resource }, htex, dim))
+    }
+
+    fn create_sampler(&mut self, info: t::SamplerInfo) -> handle::Sampler<Resources<R>> {
+        let sampler = self.inner.create_sampler(info);
+        let resource = *self.inner_handles.ref_sampler(&sampler);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::Sampler(sampler));
+        self.share.record(&Event::CreateSampler(id, info));
+        self.share.handles.borrow_mut().make_sampler(Traced { id: id, inner: resource }, info)
+    }
+
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<Resources<R>>, query::CreationError> {
+        let query = try!(self.inner.create_query(kind));
+        let resource = *self.inner_handles.ref_query(&query);
+        self.inner_handles.clear();
+        let id = self.share.add(Object::Query(query));
+        self.share.record(&Event::CreateQuery(id, kind));
+        Ok(self.share.handles.borrow_mut().make_query(Traced { id: id, inner: resource }, kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources<R>, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, Resources<R>, T>,
+                                               mapping::Error>
+        where T: Copy
+    {
+        // let the wrapped factory synchronize its mapping, which the gate reads through
+        let inner = self.inner_buffer(buf);
+        try!(self.inner.read_mapping_range(&inner, range.clone()));
+        unsafe {
+            mapping::read(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<Resources<R>, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, Resources<R>, T>,
+                                                mapping::Error>
+        where T: Copy
+    {
+        let inner = self.inner_buffer(buf);
+        try!(self.inner.write_mapping_range(&inner, range.clone()));
+        let stride = mem::size_of::<T>();
+        self.share.mark_dirty(buf.raw().resource().id, range.start * stride .. range.end * stride);
+        unsafe {
+            mapping::write(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<Resources<R>, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let inner = self.inner_buffer(buf);
+        try!(self.inner.flush_mapping(&inner, range.clone()));
+        let stride = mem::size_of::<T>();
+        self.share.mark_dirty(buf.raw().resource().id, range.start * stride .. range.end * stride);
+        unsafe {
+            mapping::sync::<Resources<R>, T, _>(buf.raw(), range, memory::WRITE, |_, _| ())
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<Resources<R>, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let inner = self.inner_buffer(buf);
+        try!(self.inner.invalidate_mapping(&inner, range.clone()));
+        unsafe {
+            mapping::sync::<Resources<R>, T, _>(buf.raw(), range, memory::READ, |_, _| ())
+        }
+    }
+}
@@ -0,0 +1,394 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tracing wrapper around the device and factory of any other backend.
+//!
+//! Every call is forwarded to the wrapped backend, while the created
+//! resources and the submitted commands are recorded into a stream of
+//! `Event`s, serialized as one JSON object per line. Objects are referred
+//! to by their `Id` in the trace, so it doesn't depend on the backend it was
+//! captured on, and can be re-executed on another one with the `Replayer`.
+
+#![deny(missing_docs, missing_copy_implementations)]
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate gfx_core as core;
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::{cmp, mem};
+use std::rc::Rc;
+use core::{self as c, handle};
+use core::command::AccessInfo;
+use core::handle::Producer;
+use core::mapping::Gate;
+
+pub use self::command::CommandBuffer;
+pub use self::event::{read, Command, Event, Events, PixelTargets, ShaderSet};
+pub use self::factory::Factory;
+pub use self::replay::{ReplayError, Replayer};
+
+mod command;
+mod event;
+mod factory;
+mod replay;
+
+
+/// Identifier of a traced object, unique across all the object types.
+pub type Id = usize;
+
+/// A raw resource of the wrapped backend, tagged with its trace id.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Traced<T> {
+    id: Id,
+    inner: T,
+}
+
+impl<T> Traced<T> {
+    /// Get the id of the resource in the trace.
+    pub fn get_id(&self) -> Id { self.id }
+}
+
+/// Resources of the trace backend, wrapping the ones of `R`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Resources<R>(PhantomData<R>);
+
+impl<R: c::Resources> c::Resources for Resources<R> {
+    type Buffer              = Traced<R::Buffer>;
+    type Shader              = Traced<R::Shader>;
+    type Program             = Traced<R::Program>;
+    type PipelineStateObject = Traced<R::PipelineStateObject>;
+    type Texture             = Traced<R::Texture>;
+    type RenderTargetView    = Traced<R::RenderTargetView>;
+    type DepthStencilView    = Traced<R::DepthStencilView>;
+    type ShaderResourceView  = Traced<R::ShaderResourceView>;
+    type UnorderedAccessView = Traced<R::UnorderedAccessView>;
+    type Sampler             = Traced<R::Sampler>;
+    type Fence               = Traced<R::Fence>;
+    type Query               = Traced<R::Query>;
+    type Mapping             = MappingGate<R>;
+}
+
+/// Mapping of a traced buffer, accessing the memory of the wrapped one.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub struct MappingGate<R: c::Resources> {
+    buffer: handle::RawBuffer<R>,
+}
+
+impl<R: c::Resources> MappingGate<R> {
+    unsafe fn inner(&self) -> &R::Mapping {
+        self.buffer.mapping().expect("The wrapped buffer is not mapped").use_access()
+    }
+}
+
+impl<R: c::Resources> c::mapping::Gate<Resources<R>> for MappingGate<R> {
+    unsafe fn set<T>(&self, index: usize, val: T) {
+        self.inner().set(index, val)
+    }
+
+    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
+        self.inner().slice(len)
+    }
+
+    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
+        self.inner().mut_slice(len)
+    }
+}
+
+/// A handle of the wrapped backend, kept alive for a traced object.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub enum Object<R: c::Resources> {
+    Buffer(handle::RawBuffer<R>),
+    Shader(handle::Shader<R>),
+    Program(handle::Program<R>),
+    PipelineState(handle::RawPipelineState<R>),
+    Texture(handle::RawTexture<R>),
+    ShaderResourceView(handle::RawShaderResourceView<R>),
+    UnorderedAccessView(handle::RawUnorderedAccessView<R>),
+    RenderTargetView(handle::RawRenderTargetView<R>),
+    DepthStencilView(handle::RawDepthStencilView<R>),
+    Sampler(handle::Sampler<R>),
+    Fence(handle::Fence<R>),
+    Query(handle::Query<R>),
+}
+
+impl<R: c::Resources> Object<R> {
+    /// Get a reference to the handle, whatever its type.
+    pub fn as_ref(&self) -> handle::Ref<R> {
+        match *self {
+            Object::Buffer(ref h) => handle::Ref::Buffer(h),
+            Object::Shader(ref h) => handle::Ref::Shader(h),
+            Object::Program(ref h) => handle::Ref::Program(h),
+            Object::PipelineState(ref h) => handle::Ref::PipelineState(h),
+            Object::Texture(ref h) => handle::Ref::Texture(h),
+            Object::ShaderResourceView(ref h) => handle::Ref::ShaderResourceView(h),
+            Object::UnorderedAccessView(ref h) => handle::Ref::UnorderedAccessView(h),
+            Object::RenderTargetView(ref h) => handle::Ref::RenderTargetView(h),
+            Object::DepthStencilView(ref h) => handle::Ref::DepthStencilView(h),
+            Object::Sampler(ref h) => handle::Ref::Sampler(h),
+            Object::Fence(ref h) => handle::Ref::Fence(h),
+            Object::Query(ref h) => handle::Ref::Query(h),
+        }
+    }
+}
+
+/// Handles of the wrapped backend, addressed by the ids of the trace.
+#[derive(Debug)]
+pub struct Objects<R: c::Resources>(HashMap<Id, Object<R>>);
+
+macro_rules! impl_objects {
+    ($($name:ident: $variant:ident = $handle:ty,)*) => {
+        impl<R: c::Resources> Objects<R> {
+            fn new() -> Objects<R> {
+                Objects(HashMap::new())
+            }
+
+            fn insert(&mut self, id: Id, object: Object<R>) {
+                self.0.insert(id, object);
+            }
+
+            fn remove(&mut self, id: Id) -> Option<Object<R>> {
+                self.0.remove(&id)
+            }
+
+            /// Get the object with the given id.
+            pub fn get(&self, id: Id) -> Option<&Object<R>> {
+                self.0.get(&id)
+            }
+
+            $(
+                /// Get the handle with the given id, if the object is of this type.
+                pub fn $name(&self, id: Id) -> Option<&$handle> {
+                    match self.0.get(&id) {
+                        Some(&Object::$variant(ref handle)) => Some(handle),
+                        _ => None,
+                    }
+                }
+            )*
+        }
+    }
+}
+
+impl_objects! {
+    buffer: Buffer = handle::RawBuffer<R>,
+    shader: Shader = handle::Shader<R>,
+    program: Program = handle::Program<R>,
+    pso: PipelineState = handle::RawPipelineState<R>,
+    texture: Texture = handle::RawTexture<R>,
+    srv: ShaderResourceView = handle::RawShaderResourceView<R>,
+    uav: UnorderedAccessView = handle::RawUnorderedAccessView<R>,
+    rtv: RenderTargetView = handle::RawRenderTargetView<R>,
+    dsv: DepthStencilView = handle::RawDepthStencilView<R>,
+    sampler: Sampler = handle::Sampler<R>,
+    fence: Fence = handle::Fence<R>,
+    query: Query = handle::Query<R>,
+}
+
+/// Internal struct of shared data between the device and its factories.
+#[doc(hidden)]
+pub struct Share<R: c::Resources> {
+    handles: RefCell<handle::Manager<Resources<R>>>,
+    objects: RefCell<Objects<R>>,
+    /// Byte ranges of the mapped buffers that may have been written
+    /// by the CPU since the last submission.
+    dirty: RefCell<HashMap<Id, Range<usize>>>,
+    next_id: Cell<Id>,
+    writer: RefCell<Box<Write>>,
+}
+
+impl<R: c::Resources> Share<R> {
+    fn add(&self, object: Object<R>) -> Id {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.objects.borrow_mut().insert(id, object);
+        id
+    }
+
+    /// Extend the dirty range of a mapped buffer with the given bytes.
+    fn mark_dirty(&self, id: Id, bytes: Range<usize>) {
+        let mut dirty = self.dirty.borrow_mut();
+        let range = dirty.entry(id).or_insert(bytes.clone());
+        *range = cmp::min(range.start, bytes.start) .. cmp::max(range.end, bytes.end);
+    }
+
+    fn record(&self, event: &Event) {
+        let mut writer = self.writer.borrow_mut();
+        let result = serde_json::to_writer(&mut *writer, event)
+            .map_err(io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"));
+        if let Err(e) = result {
+            error!("Failed to record a trace event: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        if let Err(e) = self.writer.borrow_mut().flush() {
+            error!("Failed to flush the trace: {}", e);
+        }
+    }
+}
+
+/// Wrap a device and its factory, recording everything done with them into the `writer`.
+pub fn create<D, F, W>(device: D, factory: F, writer: W)
+                       -> (Device<D>, Factory<D::Resources, F>)
+    where D: c::Device, F: c::Factory<D::Resources>, W: Write + 'static
+{
+    let share = Rc::new(Share {
+        handles: RefCell::new(handle::Manager::new()),
+        objects: RefCell::new(Objects::new()),
+        dirty: RefCell::new(HashMap::new()),
+        next_id: Cell::new(0),
+        writer: RefCell::new(Box::new(writer)),
+    });
+    share.record(&Event::Capabilities(*device.get_capabilities()));
+    let device = Device {
+        inner: device,
+        share: share.clone(),
+        frame_handles: handle::Manager::new(),
+        inner_handles: handle::Manager::new(),
+    };
+    (device, Factory::new(factory, share))
+}
+
+/// A device forwarding to the wrapped one, and recording the submissions.
+pub struct Device<D: c::Device> {
+    inner: D,
+    share: Rc<Share<D::Resources>>,
+    frame_handles: handle::Manager<Resources<D::Resources>>,
+    inner_handles: handle::Manager<D::Resources>,
+}
+
+impl<D: c::Device> Device<D> {
+    /// Record the ranges of the mapped buffers written by the CPU, so that
+    /// the submitted commands can see them on replay.
+    fn record_mapped_writes(&self) {
+        let dirty = mem::replace(&mut *self.share.dirty.borrow_mut(), HashMap::new());
+        let objects = self.share.objects.borrow();
+        for (id, range) in dirty {
+            if let Some(buffer) = objects.buffer(id) {
+                let data = unsafe {
+                    let mapping = buffer.mapping().expect("Dirty buffer is not mapped");
+                    mapping.use_access().slice::<u8>(range.end)[range.start ..].to_vec()
+                };
+                self.share.record(&Event::WriteBuffer(id, range.start, data));
+            }
+        }
+    }
+
+    fn record_submit(&self, cb: &CommandBuffer<D::CommandBuffer>,
+                     access: &AccessInfo<Resources<D::Resources>>)
+                     -> AccessInfo<D::Resources> {
+        self.record_mapped_writes();
+        self.share.record(&Event::Submit(cb.commands.clone()));
+        self.share.flush();
+        let objects = self.share.objects.borrow();
+        let mut inner = AccessInfo::new();
+        for buffer in access.mapped_reads() {
+            inner.buffer_read(objects.buffer(buffer.resource().id).expect("Unknown buffer"));
+        }
+        for buffer in access.mapped_writes() {
+            inner.buffer_write(objects.buffer(buffer.resource().id).expect("Unknown buffer"));
+        }
+        inner
+    }
+
+    fn inner_fence(&mut self, fence: &handle::Fence<Resources<D::Resources>>) -> handle::Fence<D::Resources> {
+        let id = self.frame_handles.ref_fence(fence).id;
+        self.share.objects.borrow().fence(id).expect("Unknown fence").clone()
+    }
+}
+
+impl<D: c::Device> c::Device for Device<D> {
+    type Resources = Resources<D::Resources>;
+    type CommandBuffer = CommandBuffer<D::CommandBuffer>;
+
+    fn get_capabilities(&self) -> &c::Capabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<Self::Resources>) {
+        self.frame_handles.extend(man);
+    }
+
+    fn submit(&mut self,
+              cb: &mut Self::CommandBuffer,
+              access: &AccessInfo<Self::Resources>)
+              -> c::SubmissionResult<()> {
+        let _guard = try!(access.take_accesses());
+        let inner_access = self.record_submit(cb, access);
+        self.inner.submit(&mut cb.inner, &inner_access)
+    }
+
+    fn fenced_submit(&mut self,
+                     cb: &mut Self::CommandBuffer,
+                     access: &AccessInfo<Self::Resources>,
+                     after: Option<handle::Fence<Self::Resources>>)
+                     -> c::SubmissionResult<handle::Fence<Self::Resources>> {
+        let _guard = try!(access.take_accesses());
+        let after = after.map(|fence| self.inner_fence(&fence));
+        let inner_access = self.record_submit(cb, access);
+        let fence = try!(self.inner.fenced_submit(&mut cb.inner, &inner_access, after));
+        let resource = self.inner_handles.ref_fence(&fence).clone();
+        self.inner_handles.clear();
+        let id = self.share.add(Object::Fence(fence));
+        Ok(self.share.handles.borrow_mut().make_fence(Traced { id: id, inner: resource }))
+    }
+
+    fn wait_fence(&mut self, fence: &handle::Fence<Self::Resources>) {
+        let fence = self.inner_fence(fence);
+        self.inner.wait_fence(&fence);
+    }
+
+    fn get_query_result(&mut self, query: &handle::Query<Self::Resources>) -> Option<u64> {
+        let id = self.frame_handles.ref_query(query).id;
+        let query = self.share.objects.borrow().query(id).expect("Unknown query").clone();
+        self.inner.get_query_result(&query)
+    }
+
+    fn cleanup(&mut self) {
+        self.frame_handles.clear();
+        let mut freed = Vec::new();
+        self.share.handles.borrow_mut().clean_with(&mut freed,
+            |ids, b| ids.push(b.resource().id),
+            |ids, s| ids.push(s.id),
+            |ids, p| ids.push(p.resource().id),
+            |ids, pso| ids.push(pso.id),
+            |ids, t| ids.push(t.resource().id),
+            |ids, v| ids.push(v.id),
+            |ids, v| ids.push(v.id),
+            |ids, v| ids.push(v.id),
+            |ids, v| ids.push(v.id),
+            |ids, s| ids.push(s.id),
+            |ids, f| ids.push(f.id),
+            |ids, q| ids.push(q.id),
+        );
+        for id in freed {
+            // fences are not a part of the trace
+            let object = self.share.objects.borrow_mut().remove(id);
+            match object {
+                Some(Object::Fence(_)) | None => (),
+                Some(_) => self.share.record(&Event::Free(id)),
+            }
+        }
+        self.inner.cleanup();
+    }
+}
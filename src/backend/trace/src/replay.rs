@@ -0,0 +1,473 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::error::Error;
+use std::fmt;
+
+use core::{self as c, factory as f, buffer, mapping, pso, query, shade, texture};
+use core::command::{AccessInfo, Buffer};
+use core::handle;
+use core::memory::Typed;
+use event::{Command, Event, ShaderSet};
+use {Id, Object, Objects};
+
+
+/// An error from replaying a trace.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReplayError {
+    /// The event refers to an object that doesn't exist, or has a different type.
+    UnknownObject(Id),
+    /// Failed to create a buffer.
+    Buffer(buffer::CreationError),
+    /// Failed to create a shader.
+    Shader(shade::CreateShaderError),
+    /// Failed to create a program.
+    Program(shade::CreateProgramError),
+    /// Failed to create a pipeline state object.
+    PipelineState(pso::CreationError),
+    /// Failed to create a texture.
+    Texture(texture::CreationError),
+    /// Failed to create SRV or UAV.
+    ResourceView(f::ResourceViewError),
+    /// Failed to create RTV or DSV.
+    TargetView(f::TargetViewError),
+    /// Failed to create a query.
+    Query(query::CreationError),
+    /// Failed to map a buffer.
+    Mapping(mapping::Error),
+    /// Failed to submit the commands.
+    Submission(c::SubmissionError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ReplayError::*;
+        match *self {
+            UnknownObject(id) => write!(f, "{}: {}", self.description(), id),
+            Buffer(ref e) => write!(f, "{}: {}", self.description(), e),
+            Shader(ref e) => write!(f, "{}: {}", self.description(), e),
+            Program(ref e) => write!(f, "{}: {}", self.description(), e),
+            PipelineState(ref e) => write!(f, "{}: {}", self.description(), e),
+            Texture(ref e) => write!(f, "{}: {}", self.description(), e),
+            ResourceView(ref e) => write!(f, "{}: {}", self.description(), e),
+            TargetView(ref e) => write!(f, "{}: {}", self.description(), e),
+            Query(ref e) => write!(f, "{}: {}", self.description(), e),
+            Mapping(ref e) => write!(f, "{}: {}", self.description(), e),
+            Submission(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn description(&self) -> &str {
+        use self::ReplayError::*;
+        match *self {
+            UnknownObject(_) => "The event refers to an unknown object",
+            Buffer(_) => "Failed to create a buffer",
+            Shader(_) => "Failed to create a shader",
+            Program(_) => "Failed to create a program",
+            PipelineState(_) => "Failed to create a pipeline state object",
+            Texture(_) => "Failed to create a texture",
+            ResourceView(_) => "Failed to create SRV or UAV",
+            TargetView(_) => "Failed to create RTV or DSV",
+            Query(_) => "Failed to create a query",
+            Mapping(_) => "Failed to map a buffer",
+            Submission(_) => "Failed to submit the commands",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        use self::ReplayError::*;
+        match *self {
+            UnknownObject(_) => None,
+            Buffer(ref e) => Some(e),
+            Shader(ref e) => Some(e),
+            Program(ref e) => Some(e),
+            PipelineState(ref e) => Some(e),
+            Texture(ref e) => Some(e),
+            ResourceView(ref e) => Some(e),
+            TargetView(ref e) => Some(e),
+            Query(ref e) => Some(e),
+            Mapping(ref e) => Some(e),
+            Submission(ref e) => Some(e),
+        }
+    }
+}
+
+/// Re-executes the events of a trace on a device, creating the traced
+/// objects with its factory.
+pub struct Replayer<R: c::Resources> {
+    objects: Objects<R>,
+    handles: handle::Manager<R>,
+}
+
+impl<R: c::Resources> Replayer<R> {
+    /// Create a new `Replayer`, starting with no objects.
+    pub fn new() -> Replayer<R> {
+        Replayer {
+            objects: Objects::new(),
+            handles: handle::Manager::new(),
+        }
+    }
+
+    /// Get the objects created so far, for inspecting their contents.
+    pub fn objects(&self) -> &Objects<R> {
+        &self.objects
+    }
+
+    /// Replay a single event, using the command buffer for the submissions.
+    pub fn replay<D, F>(&mut self, event: Event, device: &mut D, factory: &mut F,
+                        cb: &mut D::CommandBuffer) -> Result<(), ReplayError>
+        where D: c::Device<Resources = R>, F: f::Factory<R>
+    {
+        let (id, object) = match event {
+            Event::Capabilities(caps) => {
+                info!("Replaying a trace captured with {:?}", caps);
+                return Ok(())
+            },
+            Event::CreateBuffer(id, info, data) => {
+                let buffer = match data {
+                    Some(data) => factory.create_buffer_immutable_raw(&data, info.stride, info.role, info.bind),
+                    None => factory.create_buffer_raw(info),
+                };
+                (id, Object::Buffer(try!(buffer.map_err(ReplayError::Buffer))))
+            },
+            Event::CreateShader(id, stage, code) => {
+                let shader = try!(factory.create_shader(stage, &code).map_err(ReplayError::Shader));
+                (id, Object::Shader(shader))
+            },
+            Event::CreateProgram(id, set) => {
+                let set = try!(self.shader_set(set));
+                let program = try!(factory.create_program(&set).map_err(ReplayError::Program));
+                (id, Object::Program(program))
+            },
+            Event::CreatePipelineState(id, program, desc) => {
+                let program = try!(self.objects.program(program).ok_or(ReplayError::UnknownObject(program)));
+                let pso = try!(factory.create_pipeline_state_raw(program, &desc)
+                                      .map_err(ReplayError::PipelineState));
+                (id, Object::PipelineState(pso))
+            },
+            Event::CreateTexture(id, info, hint, data) => {
+                let slices = data.as_ref().map(|data| data.iter().map(|v| &v[..]).collect::<Vec<_>>());
+                let texture = try!(factory.create_texture_raw(info, hint, slices.as_ref().map(|s| &s[..]))
+                                          .map_err(ReplayError::Texture));
+                (id, Object::Texture(texture))
+            },
+            Event::ViewBufferAsShaderResource(id, buffer) => {
+                let buffer = try!(self.objects.buffer(buffer).ok_or(ReplayError::UnknownObject(buffer)));
+                let view = try!(factory.view_buffer_as_shader_resource_raw(buffer)
+                                       .map_err(ReplayError::ResourceView));
+                (id, Object::ShaderResourceView(view))
+            },
+            Event::ViewBufferAsUnorderedAccess(id, buffer) => {
+                let buffer = try!(self.objects.buffer(buffer).ok_or(ReplayError::UnknownObject(buffer)));
+                let view = try!(factory.view_buffer_as_unordered_access_raw(buffer)
+                                       .map_err(ReplayError::ResourceView));
+                (id, Object::UnorderedAccessView(view))
+            },
+            Event::ViewTextureAsShaderResource(id, texture, desc) => {
+                let texture = try!(self.objects.texture(texture).ok_or(ReplayError::UnknownObject(texture)));
+                let view = try!(factory.view_texture_as_shader_resource_raw(texture, desc)
+                                       .map_err(ReplayError::ResourceView));
+                (id, Object::ShaderResourceView(view))
+            },
+            Event::ViewTextureAsUnorderedAccess(id, texture, desc) => {
+                let texture = try!(self.objects.texture(texture).ok_or(ReplayError::UnknownObject(texture)));
+                let view = try!(factory.view_texture_as_unordered_access_raw(texture, desc)
+                                       .map_err(ReplayError::ResourceView));
+                (id, Object::UnorderedAccessView(view))
+            },
+            Event::ViewTextureAsRenderTarget(id, texture, desc) => {
+                let texture = try!(self.objects.texture(texture).ok_or(ReplayError::UnknownObject(texture)));
+                let view = try!(factory.view_texture_as_render_target_raw(texture, desc)
+                                       .map_err(ReplayError::TargetView));
+                (id, Object::RenderTargetView(view))
+            },
+            Event::ViewTextureAsDepthStencil(id, texture, desc) => {
+                let texture = try!(self.objects.texture(texture).ok_or(ReplayError::UnknownObject(texture)));
+                let view = try!(factory.view_texture_as_depth_stencil_raw(texture, desc)
+                                       .map_err(ReplayError::TargetView));
+                (id, Object::DepthStencilView(view))
+            },
+            Event::CreateSampler(id, info) => {
+                (id, Object::Sampler(factory.create_sampler(info)))
+            },
+            Event::CreateQuery(id, kind) => {
+                let query = try!(factory.create_query(kind).map_err(ReplayError::Query));
+                (id, Object::Query(query))
+            },
+            Event::SetDebugName(id, name) => {
+                let object = try!(self.objects.get(id).ok_or(ReplayError::UnknownObject(id)));
+                factory.set_debug_name_raw(object.as_ref(), &name);
+                return Ok(())
+            },
+            Event::WriteBuffer(id, offset, data) => {
+                let buffer = try!(self.objects.buffer(id).ok_or(ReplayError::UnknownObject(id)));
+                let buffer = handle::Buffer::<R, u8>::new(buffer.clone());
+                let mut writer = try!(factory.write_mapping_range(&buffer, offset .. offset + data.len())
+                                             .map_err(ReplayError::Mapping));
+                writer.copy_from_slice(&data);
+                return Ok(())
+            },
+            Event::Submit(commands) => {
+                return self.submit(&commands, device, cb)
+            },
+            Event::Free(id) => {
+                self.objects.remove(id);
+                return Ok(())
+            },
+        };
+        self.objects.insert(id, object);
+        Ok(())
+    }
+
+    fn shader_set(&self, set: ShaderSet) -> Result<c::ShaderSet<R>, ReplayError> {
+        let shader = |id| match self.objects.shader(id) {
+            Some(shader) => Ok(shader.clone()),
+            None => Err(ReplayError::UnknownObject(id)),
+        };
+        Ok(match set {
+            ShaderSet::Simple(vs, ps) =>
+                c::ShaderSet::Simple(c::VertexShader::new(try!(shader(vs))),
+                                     c::PixelShader::new(try!(shader(ps)))),
+            ShaderSet::Geometry(vs, gs, ps) =>
+                c::ShaderSet::Geometry(c::VertexShader::new(try!(shader(vs))),
+                                       c::GeometryShader::new(try!(shader(gs))),
+                                       c::PixelShader::new(try!(shader(ps)))),
+            ShaderSet::Tessellated(vs, hs, ds, ps) =>
+                c::ShaderSet::Tessellated(c::VertexShader::new(try!(shader(vs))),
+                                          c::HullShader::new(try!(shader(hs))),
+                                          c::DomainShader::new(try!(shader(ds))),
+                                          c::PixelShader::new(try!(shader(ps)))),
+            ShaderSet::Compute(cs) =>
+                c::ShaderSet::Compute(c::ComputeShader::new(try!(shader(cs)))),
+            ShaderSet::TransformFeedback(vs, gs, outputs) => {
+                let gs = match gs {
+                    Some(gs) => Some(c::GeometryShader::new(try!(shader(gs)))),
+                    None => None,
+                };
+                c::ShaderSet::TransformFeedback(c::VertexShader::new(try!(shader(vs))), gs, outputs)
+            },
+        })
+    }
+
+    fn submit<D>(&mut self, commands: &[Command], device: &mut D, cb: &mut D::CommandBuffer)
+                 -> Result<(), ReplayError>
+        where D: c::Device<Resources = R>
+    {
+        let mut access = AccessInfo::new();
+        cb.reset();
+        {
+            let objects = &self.objects;
+            let handles = &mut self.handles;
+            macro_rules! lookup {
+                ($kind:ident, $id:expr) => {
+                    match objects.$kind($id) {
+                        Some(handle) => handle,
+                        None => return Err(ReplayError::UnknownObject($id)),
+                    }
+                }
+            }
+            for command in commands {
+                match *command {
+                    Command::BindPipelineState(id) => {
+                        let pso = handles.ref_pso(lookup!(pso, id)).0.clone();
+                        cb.bind_pipeline_state(pso);
+                    },
+                    Command::BindVertexBuffers(ref ids) => {
+                        let mut vbs = pso::VertexBufferSet::new();
+                        for (i, vb) in ids.iter().enumerate() {
+                            if let Some((id, offset)) = *vb {
+                                let buffer = lookup!(buffer, id);
+                                access.buffer_read(buffer);
+                                vbs.0[i] = Some((*handles.ref_buffer(buffer), offset));
+                            }
+                        }
+                        cb.bind_vertex_buffers(vbs);
+                    },
+                    Command::BindConstantBuffers(ref cbs) => {
+                        let mut params = Vec::with_capacity(cbs.len());
+                        for &(id, usage, slot, range) in cbs {
+                            let buffer = lookup!(buffer, id);
+                            access.buffer_read(buffer);
+                            params.push(pso::ConstantBufferParam(*handles.ref_buffer(buffer), usage, slot, range));
+                        }
+                        cb.bind_constant_buffers(&params);
+                    },
+                    Command::BindGlobalConstant(loc, ref value) => {
+                        cb.bind_global_constant(loc, value.clone());
+                    },
+                    Command::BindResourceViews(ref srvs) => {
+                        let mut params = Vec::with_capacity(srvs.len());
+                        for &(id, usage, slot) in srvs {
+                            let view = *handles.ref_srv(lookup!(srv, id));
+                            params.push(pso::ResourceViewParam(view, usage, slot));
+                        }
+                        cb.bind_resource_views(&params);
+                    },
+                    Command::BindUnorderedViews(ref uavs) => {
+                        let mut params = Vec::with_capacity(uavs.len());
+                        for &(id, usage, slot) in uavs {
+                            let view = *handles.ref_uav(lookup!(uav, id));
+                            params.push(pso::UnorderedViewParam(view, usage, slot));
+                        }
+                        cb.bind_unordered_views(&params);
+                    },
+                    Command::BindSamplers(ref samplers) => {
+                        let mut params = Vec::with_capacity(samplers.len());
+                        for &(id, usage, slot) in samplers {
+                            let sampler = *handles.ref_sampler(lookup!(sampler, id));
+                            params.push(pso::SamplerParam(sampler, usage, slot));
+                        }
+                        cb.bind_samplers(&params);
+                    },
+                    Command::BindPixelTargets(ref pts) => {
+                        let mut set = pso::PixelTargetSet::new();
+                        for (i, color) in pts.colors.iter().enumerate() {
+                            if let Some(id) = *color {
+                                set.colors[i] = Some(*handles.ref_rtv(lookup!(rtv, id)));
+                            }
+                        }
+                        if let Some(id) = pts.depth {
+                            set.depth = Some(handles.ref_dsv(lookup!(dsv, id)).clone());
+                        }
+                        if let Some(id) = pts.stencil {
+                            set.stencil = Some(handles.ref_dsv(lookup!(dsv, id)).clone());
+                        }
+                        set.dimensions = pts.dimensions;
+                        cb.bind_pixel_targets(set);
+                    },
+                    Command::BindStreamOutput(so) => {
+                        let param = match so {
+                            Some((id, offset)) => {
+                                let buffer = lookup!(buffer, id);
+                                access.buffer_write(buffer);
+                                Some(pso::StreamOutputParam(*handles.ref_buffer(buffer), offset))
+                            },
+                            None => None,
+                        };
+                        cb.bind_stream_output(param);
+                    },
+                    Command::BindIndex(id, itype) => {
+                        let buffer = lookup!(buffer, id);
+                        access.buffer_read(buffer);
+                        cb.bind_index(*handles.ref_buffer(buffer), itype);
+                    },
+                    Command::SetScissor(rect) => {
+                        cb.set_scissor(rect);
+                    },
+                    Command::SetViewports(ref viewports) => {
+                        cb.set_viewports(viewports);
+                    },
+                    Command::SetRefValues(rv) => {
+                        cb.set_ref_values(rv);
+                    },
+                    Command::CopyBuffer(src, dst, src_offset, dst_offset, size) => {
+                        let (src, dst) = (lookup!(buffer, src), lookup!(buffer, dst));
+                        access.buffer_read(src);
+                        access.buffer_write(dst);
+                        cb.copy_buffer(*handles.ref_buffer(src), *handles.ref_buffer(dst),
+                                       src_offset, dst_offset, size);
+                    },
+                    Command::CopyBufferToTexture(src, src_offset, dst, kind, face, img) => {
+                        let src = lookup!(buffer, src);
+                        access.buffer_read(src);
+                        let dst = handles.ref_texture(lookup!(texture, dst)).clone();
+                        cb.copy_buffer_to_texture(*handles.ref_buffer(src), src_offset, dst, kind, face, img);
+                    },
+                    Command::CopyTextureToBuffer(src, kind, face, img, dst, dst_offset) => {
+                        let src = handles.ref_texture(lookup!(texture, src)).clone();
+                        let dst = lookup!(buffer, dst);
+                        access.buffer_write(dst);
+                        cb.copy_texture_to_buffer(src, kind, face, img, *handles.ref_buffer(dst), dst_offset);
+                    },
+                    Command::CopyTexture(src, dst) => {
+                        let src_tex = handles.ref_texture(lookup!(texture, src.texture)).clone();
+                        let dst_tex = handles.ref_texture(lookup!(texture, dst.texture)).clone();
+                        cb.copy_texture(src.convert(src_tex), dst.convert(dst_tex));
+                    },
+                    Command::ResolveTexture(src, dst) => {
+                        let src_tex = handles.ref_texture(lookup!(texture, src.texture)).clone();
+                        let dst_tex = handles.ref_texture(lookup!(texture, dst.texture)).clone();
+                        cb.resolve_texture(src.convert(src_tex), dst.convert(dst_tex));
+                    },
+                    Command::BlitTexture(src, dst, filter) => {
+                        let src_tex = handles.ref_texture(lookup!(texture, src.texture)).clone();
+                        let dst_tex = handles.ref_texture(lookup!(texture, dst.texture)).clone();
+                        cb.blit_texture(src.convert(src_tex), dst.convert(dst_tex), filter);
+                    },
+                    Command::UpdateBuffer(id, ref data, offset) => {
+                        let buffer = lookup!(buffer, id);
+                        access.buffer_write(buffer);
+                        cb.update_buffer(*handles.ref_buffer(buffer), data, offset);
+                    },
+                    Command::UpdateTexture(id, kind, face, ref data, img) => {
+                        let texture = handles.ref_texture(lookup!(texture, id)).clone();
+                        cb.update_texture(texture, kind, face, data, img);
+                    },
+                    Command::GenerateMipmap(id) => {
+                        cb.generate_mipmap(*handles.ref_srv(lookup!(srv, id)));
+                    },
+                    Command::ClearColor(id, value) => {
+                        cb.clear_color(*handles.ref_rtv(lookup!(rtv, id)), value);
+                    },
+                    Command::ClearDepthStencil(id, depth, stencil) => {
+                        let view = handles.ref_dsv(lookup!(dsv, id)).clone();
+                        cb.clear_depth_stencil(view, depth, stencil);
+                    },
+                    Command::Draw(start, count, instances) => {
+                        cb.call_draw(start, count, instances);
+                    },
+                    Command::DrawIndexed(start, count, base, instances) => {
+                        cb.call_draw_indexed(start, count, base, instances);
+                    },
+                    Command::DrawIndirect(id, offset, draw_count) => {
+                        let buffer = lookup!(buffer, id);
+                        access.buffer_read(buffer);
+                        cb.call_draw_indirect(*handles.ref_buffer(buffer), offset, draw_count);
+                    },
+                    Command::DrawIndexedIndirect(id, offset, draw_count) => {
+                        let buffer = lookup!(buffer, id);
+                        access.buffer_read(buffer);
+                        cb.call_draw_indexed_indirect(*handles.ref_buffer(buffer), offset, draw_count);
+                    },
+                    Command::Dispatch(x, y, z) => {
+                        cb.call_dispatch(x, y, z);
+                    },
+                    Command::BeginQuery(id) => {
+                        cb.begin_query(*handles.ref_query(lookup!(query, id)));
+                    },
+                    Command::EndQuery(id) => {
+                        cb.end_query(*handles.ref_query(lookup!(query, id)));
+                    },
+                    Command::WriteTimestamp(id) => {
+                        cb.write_timestamp(*handles.ref_query(lookup!(query, id)));
+                    },
+                    Command::PushDebugGroup(ref name) => {
+                        cb.push_debug_group(name);
+                    },
+                    Command::PopDebugGroup => {
+                        cb.pop_debug_group();
+                    },
+                    Command::InsertDebugMarker(ref name) => {
+                        cb.insert_debug_marker(name);
+                    },
+                }
+            }
+        }
+        device.pin_submitted_resources(&self.handles);
+        self.handles.clear();
+        device.submit(cb, &access).map_err(ReplayError::Submission)
+    }
+}
@@ -0,0 +1,567 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::mem;
+use core::{self as c, buffer, command as com, pso, shade, state, target, texture};
+use core::{MAX_CONSTANT_BUFFERS, MAX_RESOURCE_VIEWS, MAX_SAMPLERS, MAX_UNORDERED_VIEWS, MAX_VIEWPORTS};
+use core::command::{AppendError, DrawIndexedIndirectArgs, DrawIndirectArgs};
+use {Error, Registry, SharedRegistry};
+
+
+/// A parameter bound since the last draw call, to be checked against
+/// the pipeline state the draw call uses.
+enum Binding<R: c::Resources> {
+    VertexBuffer(R::Buffer, c::AttributeSlot),
+    ConstantBuffer(R::Buffer, c::ConstantBufferSlot),
+    ResourceView(R::ShaderResourceView, c::ResourceViewSlot),
+    UnorderedView(R::UnorderedAccessView, c::UnorderedViewSlot),
+    Sampler(R::Sampler, c::SamplerSlot),
+    ColorTarget(R::RenderTargetView, c::ColorSlot),
+}
+
+/// Bindings currently in effect.
+struct State<R: c::Resources> {
+    pso: Option<R::PipelineStateObject>,
+    vertex_buffers: pso::VertexBufferSet<R>,
+    constant_buffers: [Option<R::Buffer>; MAX_CONSTANT_BUFFERS],
+    resource_views: [Option<R::ShaderResourceView>; MAX_RESOURCE_VIEWS],
+    unordered_views: [Option<R::UnorderedAccessView>; MAX_UNORDERED_VIEWS],
+    samplers: [Option<R::Sampler>; MAX_SAMPLERS],
+    targets: pso::PixelTargetSet<R>,
+    index: Option<(R::Buffer, c::IndexType)>,
+    fresh: Vec<Binding<R>>,
+    debug_groups: usize,
+}
+
+impl<R: c::Resources> State<R> {
+    fn new() -> State<R> {
+        State {
+            pso: None,
+            vertex_buffers: pso::VertexBufferSet::new(),
+            constant_buffers: [None; MAX_CONSTANT_BUFFERS],
+            resource_views: [None; MAX_RESOURCE_VIEWS],
+            unordered_views: [None; MAX_UNORDERED_VIEWS],
+            samplers: [None; MAX_SAMPLERS],
+            targets: pso::PixelTargetSet::new(),
+            index: None,
+            fresh: Vec::new(),
+            debug_groups: 0,
+        }
+    }
+}
+
+/// A command buffer validating the commands before forwarding them to the wrapped one.
+pub struct CommandBuffer<R: c::Resources, C> {
+    /// The wrapped command buffer
+    pub inner: C,
+    registry: SharedRegistry<R>,
+    state: State<R>,
+    /// Errors detected since the last reset
+    pub errors: Vec<Error<R>>,
+}
+
+impl<R: c::Resources, C> CommandBuffer<R, C> {
+    /// Create a new `CommandBuffer`, validating against the given registry.
+    pub fn new(inner: C, registry: SharedRegistry<R>) -> CommandBuffer<R, C> {
+        CommandBuffer {
+            inner: inner,
+            registry: registry,
+            state: State::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, error: Error<R>) {
+        error!("{}", error);
+        self.errors.push(error);
+    }
+
+    fn check_role(&mut self, buffer: R::Buffer, expected: buffer::Role) {
+        let found = match self.registry.read().unwrap().buffers.get(&buffer) {
+            Some(handle) => handle.get_info().role,
+            None => return,
+        };
+        if found != expected {
+            self.report(Error::WrongBufferRole {
+                buffer: buffer,
+                expected: expected,
+                found: found,
+            });
+        }
+    }
+
+    /// Check the bindings against the pipeline state before a draw call,
+    /// and the vertex buffer bounds against the vertex and instance ranges.
+    fn check_draw(&mut self, vertices: Option<(c::VertexCount, c::VertexCount)>,
+                  instances: Option<com::InstanceParams>) {
+        self.check_pipeline(Some((vertices, instances)));
+    }
+
+    /// Check the bindings against the pipeline state before a draw call or
+    /// a dispatch, which has neither vertex buffers nor targets to check.
+    fn check_pipeline(&mut self, draw: Option<(Option<(c::VertexCount, c::VertexCount)>,
+                                               Option<com::InstanceParams>)>) {
+        let fresh = mem::replace(&mut self.state.fresh, Vec::new());
+        let pso = match self.state.pso {
+            Some(ref pso) => pso.clone(),
+            None => return self.report(Error::NoPipelineState),
+        };
+        let mut errors = Vec::new();
+        {
+            let registry = self.registry.read().unwrap();
+            match registry.psos.get(&pso) {
+                Some(&(_, ref desc)) => {
+                    check_fresh(desc, &fresh, &mut errors);
+                    check_parameters(desc, &self.state, &mut errors);
+                    if let Some((vertices, instances)) = draw {
+                        check_vertices(desc, &self.state, &registry, vertices, instances, &mut errors);
+                        check_targets(desc, &self.state, &registry, &mut errors);
+                    }
+                }
+                None => errors.push(Error::UnknownPipelineState(pso)),
+            }
+        }
+        for error in errors {
+            self.report(error);
+        }
+    }
+
+    fn check_indices(&mut self, start: c::VertexCount, count: c::VertexCount) {
+        let (buffer, index_type) = match self.state.index {
+            Some(index) => index,
+            None => return self.report(Error::MissingIndexBuffer),
+        };
+        let size = match self.registry.read().unwrap().buffers.get(&buffer) {
+            Some(handle) => handle.get_info().size,
+            None => return,
+        };
+        let index_size = match index_type {
+            c::IndexType::U16 => 2,
+            c::IndexType::U32 => 4,
+        };
+        let end = (start as usize).saturating_add(count as usize).saturating_mul(index_size);
+        if end > size {
+            self.report(Error::IndexBufferOutOfBounds {
+                buffer: buffer,
+                end: end,
+                size: size,
+            });
+        }
+    }
+
+    fn check_constant_range(&mut self, buffer: R::Buffer, slot: c::ConstantBufferSlot, range: pso::BufferRange) {
+        let size = match self.registry.read().unwrap().buffers.get(&buffer) {
+            Some(handle) => handle.get_info().size,
+            None => return,
+        };
+        let end = range.offset.saturating_add(range.size);
+        if end > size {
+            self.report(Error::ConstantBufferOutOfBounds {
+                buffer: buffer,
+                slot: slot,
+                end: end,
+                size: size,
+            });
+        }
+    }
+
+    fn check_indirect(&mut self, buffer: R::Buffer, offset: usize, count: usize, stride: usize) {
+        self.check_role(buffer, buffer::Role::Indirect);
+        let size = match self.registry.read().unwrap().buffers.get(&buffer) {
+            Some(handle) => handle.get_info().size,
+            None => return,
+        };
+        let end = offset.saturating_add(count.saturating_mul(stride));
+        if end > size {
+            self.report(Error::IndirectBufferOutOfBounds {
+                buffer: buffer,
+                end: end,
+                size: size,
+            });
+        }
+    }
+}
+
+fn check_fresh<R: c::Resources>(desc: &pso::Descriptor, fresh: &[Binding<R>], errors: &mut Vec<Error<R>>) {
+    fn used<T>(descs: &[Option<T>], slot: u8) -> bool {
+        descs.get(slot as usize).map_or(false, |d| d.is_some())
+    }
+    for binding in fresh {
+        match *binding {
+            Binding::VertexBuffer(buffer, slot) if !used(&desc.attributes, slot) =>
+                errors.push(Error::UnexpectedVertexBuffer(buffer, slot)),
+            Binding::ConstantBuffer(buffer, slot) if !used(&desc.constant_buffers, slot) =>
+                errors.push(Error::UnexpectedConstantBuffer(buffer, slot)),
+            Binding::ResourceView(view, slot) if !used(&desc.resource_views, slot) =>
+                errors.push(Error::UnexpectedResourceView(view, slot)),
+            Binding::UnorderedView(view, slot) if !used(&desc.unordered_views, slot) =>
+                errors.push(Error::UnexpectedUnorderedView(view, slot)),
+            Binding::Sampler(sampler, slot) if !used(&desc.samplers, slot) =>
+                errors.push(Error::UnexpectedSampler(sampler, slot)),
+            Binding::ColorTarget(view, slot) if !used(&desc.color_targets, slot) =>
+                errors.push(Error::UnexpectedColorTarget(view, slot)),
+            _ => (),
+        }
+    }
+}
+
+fn check_vertices<R: c::Resources>(desc: &pso::Descriptor, state: &State<R>, registry: &Registry<R>,
+                                   vertices: Option<(c::VertexCount, c::VertexCount)>,
+                                   instances: Option<com::InstanceParams>, errors: &mut Vec<Error<R>>) {
+    for (slot, attribute) in desc.attributes.iter().enumerate() {
+        let (buffer_index, element) = match *attribute {
+            Some(at) => at,
+            None => continue,
+        };
+        let (buffer, offset) = match state.vertex_buffers.0[slot] {
+            Some(vb) => vb,
+            None => {
+                errors.push(Error::MissingVertexBuffer(slot as c::AttributeSlot));
+                continue
+            }
+        };
+        let (vb_desc, size) = match (desc.vertex_buffers[buffer_index as usize], registry.buffers.get(&buffer)) {
+            (Some(vb_desc), Some(handle)) => (vb_desc, handle.get_info().size),
+            _ => continue,
+        };
+        // the last element fetched by the draw call, if known, saturating
+        // on overflow so that it gets reported as out of bounds
+        let last = match (vb_desc.rate, vertices, instances) {
+            (0, Some((_, 0)), _) | (0, None, _) | (_, _, Some((0, _))) => continue,
+            (0, Some((start, count)), _) => (start as usize).saturating_add(count as usize - 1),
+            (rate, _, Some((count, base))) =>
+                (base as usize).saturating_add((count - 1) as usize / rate as usize),
+            (_, _, None) => 0,
+        };
+        let end = offset.saturating_add(last.saturating_mul(vb_desc.stride as usize))
+                        .saturating_add(element.offset as usize + element.format.0.get_total_bits() as usize / 8);
+        if end > size {
+            errors.push(Error::VertexBufferOutOfBounds {
+                buffer: buffer,
+                slot: slot as c::AttributeSlot,
+                end: end,
+                size: size,
+            });
+        }
+    }
+}
+
+fn check_parameters<R: c::Resources>(desc: &pso::Descriptor, state: &State<R>, errors: &mut Vec<Error<R>>) {
+    for (slot, (cb, bound)) in desc.constant_buffers.iter().zip(state.constant_buffers.iter()).enumerate() {
+        if cb.is_some() && bound.is_none() {
+            errors.push(Error::MissingConstantBuffer(slot as c::ConstantBufferSlot));
+        }
+    }
+    for (slot, (srv, bound)) in desc.resource_views.iter().zip(state.resource_views.iter()).enumerate() {
+        if srv.is_some() && bound.is_none() {
+            errors.push(Error::MissingResourceView(slot as c::ResourceViewSlot));
+        }
+    }
+    for (slot, (uav, bound)) in desc.unordered_views.iter().zip(state.unordered_views.iter()).enumerate() {
+        if uav.is_some() && bound.is_none() {
+            errors.push(Error::MissingUnorderedView(slot as c::UnorderedViewSlot));
+        }
+    }
+    for (slot, (sampler, bound)) in desc.samplers.iter().zip(state.samplers.iter()).enumerate() {
+        if sampler.is_some() && bound.is_none() {
+            errors.push(Error::MissingSampler(slot as c::SamplerSlot));
+        }
+    }
+}
+
+fn check_targets<R: c::Resources>(desc: &pso::Descriptor, state: &State<R>, registry: &Registry<R>,
+                                  errors: &mut Vec<Error<R>>) {
+    for (slot, (color, bound)) in desc.color_targets.iter().zip(state.targets.colors.iter()).enumerate() {
+        let slot = slot as c::ColorSlot;
+        match (*color, *bound) {
+            (Some((expected, _)), Some(view)) => match registry.rtvs.get(&view) {
+                Some(&(_, found)) if found != expected => errors.push(Error::ColorTargetFormat {
+                    view: view,
+                    slot: slot,
+                    expected: expected,
+                    found: found,
+                }),
+                _ => (),
+            },
+            (Some(_), None) => errors.push(Error::MissingColorTarget(slot)),
+            (None, _) => (),
+        }
+    }
+    if let Some((format, ref info)) = desc.depth_stencil {
+        let view = if info.depth.is_some() {
+            state.targets.depth.as_ref()
+        } else {
+            state.targets.stencil.as_ref()
+        };
+        match view {
+            Some(view) => match registry.dsvs.get(view) {
+                Some(&(_, found)) if found != format.0 => errors.push(Error::DepthStencilFormat {
+                    view: view.clone(),
+                    expected: format.0,
+                    found: found,
+                }),
+                _ => (),
+            },
+            None => errors.push(Error::MissingDepthStencil),
+        }
+    }
+}
+
+impl<R, C> com::Buffer<R> for CommandBuffer<R, C>
+    where R: c::Resources, C: com::Buffer<R>
+{
+    fn reset(&mut self) {
+        self.state = State::new();
+        self.errors.clear();
+        self.inner.reset();
+    }
+
+    fn append(&mut self, other: &CommandBuffer<R, C>) -> Result<(), AppendError> {
+        try!(self.inner.append(&other.inner));
+        // the bindings have to be set again after the appended commands,
+        // only the debug groups left open carry over
+        let debug_groups = self.state.debug_groups + other.state.debug_groups;
+        self.state = State::new();
+        self.state.debug_groups = debug_groups;
+        self.errors.extend(other.errors.iter().cloned());
+        Ok(())
+    }
+
+    fn bind_pipeline_state(&mut self, pso: R::PipelineStateObject) {
+        self.state.pso = Some(pso.clone());
+        self.inner.bind_pipeline_state(pso);
+    }
+
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<R>) {
+        for (slot, vb) in vbs.0.iter().enumerate() {
+            if let Some((buffer, _)) = *vb {
+                self.check_role(buffer, buffer::Role::Vertex);
+                self.state.fresh.push(Binding::VertexBuffer(buffer, slot as c::AttributeSlot));
+            }
+        }
+        self.state.vertex_buffers = vbs.clone();
+        self.inner.bind_vertex_buffers(vbs);
+    }
+
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<R>]) {
+        for &pso::ConstantBufferParam(buffer, _, slot, range) in cbs {
+            self.check_role(buffer, buffer::Role::Constant);
+            if let Some(range) = range {
+                self.check_constant_range(buffer, slot, range);
+            }
+            self.state.fresh.push(Binding::ConstantBuffer(buffer, slot));
+            if let Some(bound) = self.state.constant_buffers.get_mut(slot as usize) {
+                *bound = Some(buffer);
+            }
+        }
+        self.inner.bind_constant_buffers(cbs);
+    }
+
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.inner.bind_global_constant(loc, value);
+    }
+
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<R>]) {
+        for &pso::ResourceViewParam(view, _, slot) in srvs {
+            self.state.fresh.push(Binding::ResourceView(view, slot));
+            if let Some(bound) = self.state.resource_views.get_mut(slot as usize) {
+                *bound = Some(view);
+            }
+        }
+        self.inner.bind_resource_views(srvs);
+    }
+
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<R>]) {
+        for &pso::UnorderedViewParam(view, _, slot) in uavs {
+            self.state.fresh.push(Binding::UnorderedView(view, slot));
+            if let Some(bound) = self.state.unordered_views.get_mut(slot as usize) {
+                *bound = Some(view);
+            }
+        }
+        self.inner.bind_unordered_views(uavs);
+    }
+
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<R>]) {
+        for &pso::SamplerParam(sampler, _, slot) in ss {
+            self.state.fresh.push(Binding::Sampler(sampler, slot));
+            if let Some(bound) = self.state.samplers.get_mut(slot as usize) {
+                *bound = Some(sampler);
+            }
+        }
+        self.inner.bind_samplers(ss);
+    }
+
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<R>) {
+        for (slot, color) in pts.colors.iter().enumerate() {
+            if let Some(view) = *color {
+                self.state.fresh.push(Binding::ColorTarget(view, slot as c::ColorSlot));
+            }
+        }
+        self.state.targets = pts.clone();
+        self.inner.bind_pixel_targets(pts);
+    }
+
+    fn bind_stream_output(&mut self, so: Option<pso::StreamOutputParam<R>>) {
+        if let Some(pso::StreamOutputParam(buffer, _)) = so {
+            // captured vertices are meant to be fed back as vertex data
+            self.check_role(buffer, buffer::Role::Vertex);
+        }
+        self.inner.bind_stream_output(so);
+    }
+
+    fn bind_index(&mut self, buffer: R::Buffer, index_type: c::IndexType) {
+        self.check_role(buffer, buffer::Role::Index);
+        self.state.index = Some((buffer, index_type));
+        self.inner.bind_index(buffer, index_type);
+    }
+
+    fn set_scissor(&mut self, rect: target::Rect) {
+        self.inner.set_scissor(rect);
+    }
+
+    fn set_viewports(&mut self, viewports: &[com::Viewport]) {
+        if viewports.len() > MAX_VIEWPORTS {
+            self.report(Error::TooManyViewports(viewports.len()));
+        }
+        self.inner.set_viewports(viewports);
+    }
+
+    fn set_ref_values(&mut self, rv: state::RefValues) {
+        self.inner.set_ref_values(rv);
+    }
+
+    fn copy_buffer(&mut self, src: R::Buffer, dst: R::Buffer,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.inner.copy_buffer(src, dst, src_offset_bytes, dst_offset_bytes, size_bytes);
+    }
+
+    fn copy_buffer_to_texture(&mut self,
+                              src: R::Buffer, src_offset_bytes: usize,
+                              dst: R::Texture,
+                              kind: texture::Kind,
+                              face: Option<texture::CubeFace>,
+                              img: texture::RawImageInfo) {
+        self.inner.copy_buffer_to_texture(src, src_offset_bytes, dst, kind, face, img);
+    }
+
+    fn copy_texture_to_buffer(&mut self,
+                              src: R::Texture,
+                              kind: texture::Kind,
+                              face: Option<texture::CubeFace>,
+                              img: texture::RawImageInfo,
+                              dst: R::Buffer, dst_offset_bytes: usize) {
+        self.inner.copy_texture_to_buffer(src, kind, face, img, dst, dst_offset_bytes);
+    }
+
+    fn copy_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                    dst: texture::TextureCopyRegion<R::Texture>) {
+        self.inner.copy_texture(src, dst);
+    }
+
+    fn resolve_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                       dst: texture::TextureCopyRegion<R::Texture>) {
+        self.inner.resolve_texture(src, dst);
+    }
+
+    fn blit_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                    dst: texture::TextureCopyRegion<R::Texture>, filter: texture::FilterMethod) {
+        self.inner.blit_texture(src, dst, filter);
+    }
+
+    fn update_buffer(&mut self, buffer: R::Buffer, data: &[u8], offset: usize) {
+        self.inner.update_buffer(buffer, data, offset);
+    }
+
+    fn update_texture(&mut self, texture: R::Texture, kind: texture::Kind, face: Option<texture::CubeFace>,
+                      data: &[u8], img: texture::RawImageInfo) {
+        self.inner.update_texture(texture, kind, face, data, img);
+    }
+
+    fn generate_mipmap(&mut self, srv: R::ShaderResourceView) {
+        self.inner.generate_mipmap(srv);
+    }
+
+    fn clear_color(&mut self, target: R::RenderTargetView, value: com::ClearColor) {
+        self.inner.clear_color(target, value);
+    }
+
+    fn clear_depth_stencil(&mut self, target: R::DepthStencilView,
+                           depth: Option<target::Depth>, stencil: Option<target::Stencil>) {
+        self.inner.clear_depth_stencil(target, depth, stencil);
+    }
+
+    fn call_draw(&mut self, start: c::VertexCount, count: c::VertexCount,
+                 instances: Option<com::InstanceParams>) {
+        self.check_draw(Some((start, count)), instances);
+        self.inner.call_draw(start, count, instances);
+    }
+
+    fn call_draw_indexed(&mut self, start: c::VertexCount, count: c::VertexCount,
+                         base: c::VertexCount, instances: Option<com::InstanceParams>) {
+        self.check_draw(None, instances);
+        self.check_indices(start, count);
+        self.inner.call_draw_indexed(start, count, base, instances);
+    }
+
+    fn call_draw_indirect(&mut self, buffer: R::Buffer, offset: usize, count: usize) {
+        // the vertex and instance ranges are only known to the GPU
+        self.check_draw(None, Some((0, 0)));
+        self.check_indirect(buffer, offset, count, mem::size_of::<DrawIndirectArgs>());
+        self.inner.call_draw_indirect(buffer, offset, count);
+    }
+
+    fn call_draw_indexed_indirect(&mut self, buffer: R::Buffer, offset: usize, count: usize) {
+        self.check_draw(None, Some((0, 0)));
+        if self.state.index.is_none() {
+            self.report(Error::MissingIndexBuffer);
+        }
+        self.check_indirect(buffer, offset, count, mem::size_of::<DrawIndexedIndirectArgs>());
+        self.inner.call_draw_indexed_indirect(buffer, offset, count);
+    }
+
+    fn call_dispatch(&mut self, x: com::ThreadGroupCount, y: com::ThreadGroupCount, z: com::ThreadGroupCount) {
+        self.check_pipeline(None);
+        self.inner.call_dispatch(x, y, z);
+    }
+
+    fn begin_query(&mut self, query: R::Query) {
+        self.inner.begin_query(query);
+    }
+
+    fn end_query(&mut self, query: R::Query) {
+        self.inner.end_query(query);
+    }
+
+    fn write_timestamp(&mut self, query: R::Query) {
+        self.inner.write_timestamp(query);
+    }
+
+    fn push_debug_group(&mut self, name: &str) {
+        self.state.debug_groups += 1;
+        self.inner.push_debug_group(name);
+    }
+
+    fn pop_debug_group(&mut self) {
+        if self.state.debug_groups == 0 {
+            return self.report(Error::UnbalancedDebugGroup);
+        }
+        self.state.debug_groups -= 1;
+        self.inner.pop_debug_group();
+    }
+
+    fn insert_debug_marker(&mut self, name: &str) {
+        self.inner.insert_debug_marker(name);
+    }
+}
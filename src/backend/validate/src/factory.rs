@@ -0,0 +1,196 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::ops::Range;
+use core::{self as c, factory as f, texture as t, buffer, mapping, query, shade};
+use core::format::{ChannelType, Format};
+use core::handle;
+use core::memory::Bind;
+
+use command::CommandBuffer;
+use SharedRegistry;
+
+
+/// A factory forwarding to the wrapped one, and registering the metadata
+/// needed for validation.
+pub struct Factory<R: c::Resources, F> {
+    inner: F,
+    registry: SharedRegistry<R>,
+    handles: handle::Manager<R>,
+}
+
+impl<R: c::Resources, F: Clone> Clone for Factory<R, F> {
+    fn clone(&self) -> Factory<R, F> {
+        Factory::new(self.inner.clone(), self.registry.clone())
+    }
+}
+
+impl<R: c::Resources, F> Factory<R, F> {
+    /// Create a new `Factory`.
+    pub fn new(inner: F, registry: SharedRegistry<R>) -> Factory<R, F> {
+        Factory {
+            inner: inner,
+            registry: registry,
+            handles: handle::Manager::new(),
+        }
+    }
+
+    /// Create a command buffer, wrapping the one created by `fun` from the wrapped factory.
+    pub fn create_command_buffer<C, Fun>(&mut self, fun: Fun) -> CommandBuffer<R, C>
+        where Fun: FnOnce(&mut F) -> C
+    {
+        CommandBuffer::new(fun(&mut self.inner), self.registry.clone())
+    }
+
+    fn add_buffer(&mut self, buffer: &handle::RawBuffer<R>) {
+        self.registry.write().unwrap().buffers.insert(*buffer.resource(), buffer.clone());
+    }
+}
+
+impl<R: c::Resources, F: f::Factory<R>> f::Factory<R> for Factory<R, F> {
+    fn get_capabilities(&self) -> &c::Capabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<R>, name: &str) {
+        self.inner.set_debug_name_raw(handle, name)
+    }
+
+    fn get_statistics(&self) -> handle::Statistics {
+        self.inner.get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.inner.get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: Format) -> c::format::Properties {
+        self.inner.get_format_properties(format)
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info)
+                         -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        let buffer = try!(self.inner.create_buffer_raw(info));
+        self.add_buffer(&buffer);
+        Ok(buffer)
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::RawBuffer<R>, buffer::CreationError> {
+        let buffer = try!(self.inner.create_buffer_immutable_raw(data, stride, role, bind));
+        self.add_buffer(&buffer);
+        Ok(buffer)
+    }
+
+    fn create_shader(&mut self, stage: shade::Stage, code: &[u8])
+                     -> Result<handle::Shader<R>, shade::CreateShaderError> {
+        self.inner.create_shader(stage, code)
+    }
+
+    fn create_program(&mut self, shader_set: &c::ShaderSet<R>)
+                      -> Result<handle::Program<R>, shade::CreateProgramError> {
+        self.inner.create_program(shader_set)
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<R>, desc: &c::pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<R>, c::pso::CreationError> {
+        let pso = try!(self.inner.create_pipeline_state_raw(program, desc));
+        let resource = self.handles.ref_pso(&pso).0.clone();
+        self.handles.clear();
+        self.registry.write().unwrap().psos.insert(resource, (pso.clone(), *desc));
+        Ok(pso)
+    }
+
+    fn create_texture_raw(&mut self, desc: t::Info, hint: Option<ChannelType>, data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<R>, t::CreationError> {
+        self.inner.create_texture_raw(desc, hint, data_opt)
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, hbuf: &handle::RawBuffer<R>)
+                                      -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        self.inner.view_buffer_as_shader_resource_raw(hbuf)
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, hbuf: &handle::RawBuffer<R>)
+                                       -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        self.inner.view_buffer_as_unordered_access_raw(hbuf)
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::ResourceDesc)
+                                       -> Result<handle::RawShaderResourceView<R>, f::ResourceViewError> {
+        self.inner.view_texture_as_shader_resource_raw(htex, desc)
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::UnorderedDesc)
+                                        -> Result<handle::RawUnorderedAccessView<R>, f::ResourceViewError> {
+        self.inner.view_texture_as_unordered_access_raw(htex, desc)
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<R>, f::TargetViewError> {
+        let view = try!(self.inner.view_texture_as_render_target_raw(htex, desc));
+        let resource = *self.handles.ref_rtv(&view);
+        self.handles.clear();
+        let format = Format(htex.get_info().format, desc.channel);
+        self.registry.write().unwrap().rtvs.insert(resource, (view.clone(), format));
+        Ok(view)
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<R>, desc: t::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<R>, f::TargetViewError> {
+        let view = try!(self.inner.view_texture_as_depth_stencil_raw(htex, desc));
+        let resource = self.handles.ref_dsv(&view).clone();
+        self.handles.clear();
+        self.registry.write().unwrap().dsvs.insert(resource, (view.clone(), htex.get_info().format));
+        Ok(view)
+    }
+
+    fn create_sampler(&mut self, info: t::SamplerInfo) -> handle::Sampler<R> {
+        self.inner.create_sampler(info)
+    }
+
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<R>, query::CreationError> {
+        self.inner.create_query(kind)
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
+        where T: Copy
+    {
+        self.inner.read_mapping_range(buf, range)
+    }
+
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
+        where T: Copy
+    {
+        self.inner.write_mapping_range(buf, range)
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        self.inner.flush_mapping(buf, range)
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        self.inner.invalidate_mapping(buf, range)
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validating wrapper around the device and factory of any other backend.
+//!
+//! The factory remembers the metadata of the buffers, pipeline states and
+//! target views it creates, and the command buffer checks every binding,
+//! draw call and dispatch against it and the currently bound pipeline state. Invalid
+//! commands are logged, collected as `Error`s naming the offending handle
+//! and slot, and the device refuses to submit the command buffer containing
+//! them.
+//!
+//! Resources that were not created through the wrapping factory, such as
+//! the main window targets, are not known and only partially validated.
+
+#![deny(missing_docs, missing_copy_implementations)]
+
+#[macro_use]
+extern crate log;
+extern crate gfx_core as core;
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::mem;
+use std::sync::{Arc, RwLock};
+use core::{self as c, buffer, format, handle, pso};
+use core::command::AccessInfo;
+
+pub use self::command::CommandBuffer;
+pub use self::factory::Factory;
+
+mod command;
+mod factory;
+
+
+/// Metadata of the resources created by the wrapping factory, shared with the
+/// command buffers. The entries hold the handles of their resources, so that
+/// the backend can't reuse them until the device removes the unused entries.
+#[derive(Debug)]
+pub struct Registry<R: c::Resources> {
+    buffers: HashMap<R::Buffer, handle::RawBuffer<R>>,
+    psos: HashMap<R::PipelineStateObject, (handle::RawPipelineState<R>, pso::Descriptor)>,
+    rtvs: HashMap<R::RenderTargetView, (handle::RawRenderTargetView<R>, format::Format)>,
+    dsvs: HashMap<R::DepthStencilView, (handle::RawDepthStencilView<R>, format::SurfaceType)>,
+}
+
+impl<R: c::Resources> Registry<R> {
+    fn new() -> Registry<R> {
+        Registry {
+            buffers: HashMap::new(),
+            psos: HashMap::new(),
+            rtvs: HashMap::new(),
+            dsvs: HashMap::new(),
+        }
+    }
+
+    /// Remove the entries of the resources only referenced by the registry
+    /// and the manager of the wrapped factory, which frees them on cleanup.
+    fn remove_unused(&mut self) {
+        fn used<'a, R: c::Resources, H>(handle: H) -> bool where H: Into<handle::Ref<'a, R>> {
+            handle.into().count() > 2
+        }
+        self.buffers.retain(|_, h| used(&*h));
+        self.psos.retain(|_, &mut (ref h, _)| used(h));
+        self.rtvs.retain(|_, &mut (ref h, _)| used(h));
+        self.dsvs.retain(|_, &mut (ref h, _)| used(h));
+    }
+}
+
+/// A shared reference to the `Registry`.
+pub type SharedRegistry<R> = Arc<RwLock<Registry<R>>>;
+
+/// An invalid command, detected by the validating command buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error<R: c::Resources> {
+    /// A draw call or a dispatch was issued without a pipeline state bound.
+    NoPipelineState,
+    /// The bound pipeline state was not created by the wrapping factory.
+    UnknownPipelineState(R::PipelineStateObject),
+    /// A buffer of the wrong role was bound.
+    WrongBufferRole {
+        /// The offending buffer
+        buffer: R::Buffer,
+        /// Role required by the binding
+        expected: buffer::Role,
+        /// Role the buffer was created with
+        found: buffer::Role,
+    },
+    /// A vertex buffer was bound for an attribute the pipeline state doesn't read.
+    UnexpectedVertexBuffer(R::Buffer, c::AttributeSlot),
+    /// No vertex buffer is bound for an attribute of the pipeline state.
+    MissingVertexBuffer(c::AttributeSlot),
+    /// The draw call reads past the end of a vertex buffer.
+    VertexBufferOutOfBounds {
+        /// The offending buffer
+        buffer: R::Buffer,
+        /// Attribute the buffer is bound to
+        slot: c::AttributeSlot,
+        /// End of the last element read, in bytes
+        end: usize,
+        /// Size of the buffer, in bytes
+        size: usize,
+    },
+    /// A constant buffer was bound to a slot the pipeline state doesn't use.
+    UnexpectedConstantBuffer(R::Buffer, c::ConstantBufferSlot),
+    /// No constant buffer is bound to a slot of the pipeline state.
+    MissingConstantBuffer(c::ConstantBufferSlot),
+    /// The bound range of a constant buffer exceeds its size.
+    ConstantBufferOutOfBounds {
+        /// The offending buffer
+        buffer: R::Buffer,
+        /// Slot the buffer is bound to
+        slot: c::ConstantBufferSlot,
+        /// End of the bound range, in bytes
+        end: usize,
+        /// Size of the buffer, in bytes
+        size: usize,
+    },
+    /// A shader resource view was bound to a slot the pipeline state doesn't use.
+    UnexpectedResourceView(R::ShaderResourceView, c::ResourceViewSlot),
+    /// No shader resource view is bound to a slot of the pipeline state.
+    MissingResourceView(c::ResourceViewSlot),
+    /// An unordered access view was bound to a slot the pipeline state doesn't use.
+    UnexpectedUnorderedView(R::UnorderedAccessView, c::UnorderedViewSlot),
+    /// No unordered access view is bound to a slot of the pipeline state.
+    MissingUnorderedView(c::UnorderedViewSlot),
+    /// A sampler was bound to a slot the pipeline state doesn't use.
+    UnexpectedSampler(R::Sampler, c::SamplerSlot),
+    /// No sampler is bound to a slot of the pipeline state.
+    MissingSampler(c::SamplerSlot),
+    /// A color target was bound to a slot the pipeline state doesn't write.
+    UnexpectedColorTarget(R::RenderTargetView, c::ColorSlot),
+    /// No color target is bound to a slot of the pipeline state.
+    MissingColorTarget(c::ColorSlot),
+    /// The format of a color target doesn't match the pipeline state.
+    ColorTargetFormat {
+        /// The offending view
+        view: R::RenderTargetView,
+        /// Slot the view is bound to
+        slot: c::ColorSlot,
+        /// Format of the pipeline state
+        expected: format::Format,
+        /// Format of the view
+        found: format::Format,
+    },
+    /// No depth-stencil target is bound while the pipeline state uses one.
+    MissingDepthStencil,
+    /// The surface of the depth-stencil target doesn't match the pipeline state.
+    DepthStencilFormat {
+        /// The offending view
+        view: R::DepthStencilView,
+        /// Surface of the pipeline state
+        expected: format::SurfaceType,
+        /// Surface of the view
+        found: format::SurfaceType,
+    },
+    /// More viewports were set than `MAX_VIEWPORTS`.
+    TooManyViewports(usize),
+    /// An indexed draw call was issued without an index buffer bound.
+    MissingIndexBuffer,
+    /// The draw call reads past the end of the index buffer.
+    IndexBufferOutOfBounds {
+        /// The offending buffer
+        buffer: R::Buffer,
+        /// End of the last index read, in bytes
+        end: usize,
+        /// Size of the buffer, in bytes
+        size: usize,
+    },
+    /// The indirect draw call reads past the end of the arguments buffer.
+    IndirectBufferOutOfBounds {
+        /// The offending buffer
+        buffer: R::Buffer,
+        /// End of the last arguments read, in bytes
+        end: usize,
+        /// Size of the buffer, in bytes
+        size: usize,
+    },
+    /// A debug group was popped while none was open.
+    UnbalancedDebugGroup,
+}
+
+impl<R: c::Resources> fmt::Display for Error<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Error::*;
+        let description = self.description();
+        match *self {
+            NoPipelineState | MissingDepthStencil | MissingIndexBuffer | UnbalancedDebugGroup =>
+                write!(f, "{}", description),
+            UnknownPipelineState(ref pso) =>
+                write!(f, "{}: {:?}", description, pso),
+            WrongBufferRole { ref buffer, expected, found } =>
+                write!(f, "{}: {:?} is {:?} instead of {:?}", description, buffer, found, expected),
+            UnexpectedVertexBuffer(ref buffer, slot) |
+            UnexpectedConstantBuffer(ref buffer, slot) =>
+                write!(f, "{}: {:?} at slot {}", description, buffer, slot),
+            UnexpectedResourceView(ref view, slot) =>
+                write!(f, "{}: {:?} at slot {}", description, view, slot),
+            UnexpectedUnorderedView(ref view, slot) =>
+                write!(f, "{}: {:?} at slot {}", description, view, slot),
+            UnexpectedSampler(ref sampler, slot) =>
+                write!(f, "{}: {:?} at slot {}", description, sampler, slot),
+            UnexpectedColorTarget(ref view, slot) =>
+                write!(f, "{}: {:?} at slot {}", description, view, slot),
+            MissingVertexBuffer(slot) |
+            MissingConstantBuffer(slot) |
+            MissingResourceView(slot) |
+            MissingUnorderedView(slot) |
+            MissingSampler(slot) |
+            MissingColorTarget(slot) =>
+                write!(f, "{}: slot {}", description, slot),
+            VertexBufferOutOfBounds { ref buffer, slot, end, size } |
+            ConstantBufferOutOfBounds { ref buffer, slot, end, size } =>
+                write!(f, "{}: {:?} at slot {} is read up to {} bytes, but has {}",
+                       description, buffer, slot, end, size),
+            ColorTargetFormat { ref view, slot, expected, found } =>
+                write!(f, "{}: {:?} at slot {} is {:?} instead of {:?}",
+                       description, view, slot, found, expected),
+            DepthStencilFormat { ref view, expected, found } =>
+                write!(f, "{}: {:?} is {:?} instead of {:?}", description, view, found, expected),
+            TooManyViewports(count) =>
+                write!(f, "{}: {}", description, count),
+            IndexBufferOutOfBounds { ref buffer, end, size } |
+            IndirectBufferOutOfBounds { ref buffer, end, size } =>
+                write!(f, "{}: {:?} is read up to {} bytes, but has {}", description, buffer, end, size),
+        }
+    }
+}
+
+impl<R: c::Resources> StdError for Error<R> {
+    fn description(&self) -> &str {
+        use self::Error::*;
+        match *self {
+            NoPipelineState => "No pipeline state is bound",
+            UnknownPipelineState(_) => "The pipeline state is not known to the validation",
+            WrongBufferRole { .. } => "The buffer has a wrong role",
+            UnexpectedVertexBuffer(..) => "The vertex buffer is not used by the pipeline state",
+            MissingVertexBuffer(_) => "A vertex buffer required by the pipeline state is not bound",
+            VertexBufferOutOfBounds { .. } => "The vertex buffer is read out of bounds",
+            UnexpectedConstantBuffer(..) => "The constant buffer is not used by the pipeline state",
+            MissingConstantBuffer(_) => "A constant buffer required by the pipeline state is not bound",
+            ConstantBufferOutOfBounds { .. } => "The constant buffer range is out of bounds",
+            UnexpectedResourceView(..) => "The shader resource view is not used by the pipeline state",
+            MissingResourceView(_) => "A shader resource view required by the pipeline state is not bound",
+            UnexpectedUnorderedView(..) => "The unordered access view is not used by the pipeline state",
+            MissingUnorderedView(_) => "An unordered access view required by the pipeline state is not bound",
+            UnexpectedSampler(..) => "The sampler is not used by the pipeline state",
+            MissingSampler(_) => "A sampler required by the pipeline state is not bound",
+            UnexpectedColorTarget(..) => "The color target is not used by the pipeline state",
+            MissingColorTarget(_) => "A color target required by the pipeline state is not bound",
+            ColorTargetFormat { .. } => "The color target format doesn't match the pipeline state",
+            MissingDepthStencil => "The depth-stencil target required by the pipeline state is not bound",
+            DepthStencilFormat { .. } => "The depth-stencil format doesn't match the pipeline state",
+            TooManyViewports(_) => "Too many viewports are set",
+            MissingIndexBuffer => "No index buffer is bound",
+            IndexBufferOutOfBounds { .. } => "The index buffer is read out of bounds",
+            IndirectBufferOutOfBounds { .. } => "The indirect arguments buffer is read out of bounds",
+            UnbalancedDebugGroup => "A debug group is popped without having been pushed",
+        }
+    }
+}
+
+/// Create a validating device and factory, wrapping the given ones.
+pub fn create<D, F>(device: D, factory: F) -> (Device<D>, Factory<D::Resources, F>)
+    where D: c::Device
+{
+    let registry = Arc::new(RwLock::new(Registry::new()));
+    (Device::new(device, registry.clone()), Factory::new(factory, registry))
+}
+
+/// A device forwarding to the wrapped one, and refusing to submit the command
+/// buffers that failed validation.
+pub struct Device<D: c::Device> {
+    inner: D,
+    registry: SharedRegistry<D::Resources>,
+    errors: Vec<Error<D::Resources>>,
+}
+
+impl<D: c::Device> Device<D> {
+    /// Create a new `Device`, cleaning up the given registry.
+    pub fn new(inner: D, registry: SharedRegistry<D::Resources>) -> Device<D> {
+        Device {
+            inner: inner,
+            registry: registry,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Take the errors of the command buffers rejected so far.
+    pub fn take_errors(&mut self) -> Vec<Error<D::Resources>> {
+        mem::replace(&mut self.errors, Vec::new())
+    }
+
+    fn check(&mut self, cb: &mut CommandBuffer<D::Resources, D::CommandBuffer>) -> c::SubmissionResult<()> {
+        if cb.errors.is_empty() {
+            Ok(())
+        } else {
+            self.errors.extend(cb.errors.drain(..));
+            Err(c::SubmissionError::InvalidCommands)
+        }
+    }
+}
+
+impl<D: c::Device> c::Device for Device<D> {
+    type Resources = D::Resources;
+    type CommandBuffer = CommandBuffer<D::Resources, D::CommandBuffer>;
+
+    fn get_capabilities(&self) -> &c::Capabilities {
+        self.inner.get_capabilities()
+    }
+
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<Self::Resources>) {
+        self.inner.pin_submitted_resources(man)
+    }
+
+    fn submit(&mut self,
+              cb: &mut Self::CommandBuffer,
+              access: &AccessInfo<Self::Resources>)
+              -> c::SubmissionResult<()> {
+        try!(self.check(cb));
+        self.inner.submit(&mut cb.inner, access)
+    }
+
+    fn fenced_submit(&mut self,
+                     cb: &mut Self::CommandBuffer,
+                     access: &AccessInfo<Self::Resources>,
+                     after: Option<handle::Fence<Self::Resources>>)
+                     -> c::SubmissionResult<handle::Fence<Self::Resources>> {
+        try!(self.check(cb));
+        self.inner.fenced_submit(&mut cb.inner, access, after)
+    }
+
+    fn wait_fence(&mut self, fence: &handle::Fence<Self::Resources>) {
+        self.inner.wait_fence(fence)
+    }
+
+    fn get_query_result(&mut self, query: &handle::Query<Self::Resources>) -> Option<u64> {
+        self.inner.get_query_result(query)
+    }
+
+    fn cleanup(&mut self) {
+        self.registry.write().unwrap().remove_unused();
+        self.inner.cleanup()
+    }
+}
@@ -130,6 +130,10 @@ impl command::Buffer<Resources> for Buffer {
         });
     }
 
+    fn append(&mut self, _: &Buffer) -> Result<(), command::AppendError> {
+        Err(command::AppendError::Unsupported)
+    }
+
     fn bind_pipeline_state(&mut self, pso: native::Pipeline) {
         let (_, vk) = self.share.get_device();
         self.last_render_pass = pso.render_pass;
@@ -220,8 +224,11 @@ impl command::Buffer<Resources> for Buffer {
         //TODO: EndRenderPass
     }
 
+    fn bind_stream_output(&mut self, _: Option<pso::StreamOutputParam<Resources>>) {}
+
     fn bind_index(&mut self, _: native::Buffer, _: IndexType) {}
     fn set_scissor(&mut self, _: target::Rect) {}
+    fn set_viewports(&mut self, _: &[command::Viewport]) {}
     fn set_ref_values(&mut self, _: RefValues) {}
 
     fn copy_buffer(&mut self, src: native::Buffer, dst: native::Buffer,
@@ -261,6 +268,12 @@ impl command::Buffer<Resources> for Buffer {
         unimplemented!()
     }
 
+    fn copy_texture(&mut self, _: tex::TextureCopyRegion<native::Texture>,
+                    _: tex::TextureCopyRegion<native::Texture>) {}
+    fn resolve_texture(&mut self, _: tex::TextureCopyRegion<native::Texture>,
+                       _: tex::TextureCopyRegion<native::Texture>) {}
+    fn blit_texture(&mut self, _: tex::TextureCopyRegion<native::Texture>,
+                    _: tex::TextureCopyRegion<native::Texture>, _: tex::FilterMethod) {}
     fn update_buffer(&mut self, _: native::Buffer, _: &[u8], _: usize) {}
     fn update_texture(&mut self, _: native::Texture, _: tex::Kind, _: Option<tex::CubeFace>,
                       _: &[u8], _: tex::RawImageInfo) {}
@@ -293,6 +306,13 @@ impl command::Buffer<Resources> for Buffer {
     fn call_draw(&mut self, _: VertexCount, _: VertexCount, _: Option<command::InstanceParams>) {}
     fn call_draw_indexed(&mut self, _: VertexCount, _: VertexCount,
                          _: VertexCount, _: Option<command::InstanceParams>) {}
+    fn call_draw_indirect(&mut self, _: native::Buffer, _: usize, _: usize) {}
+    fn call_draw_indexed_indirect(&mut self, _: native::Buffer, _: usize, _: usize) {}
+    fn call_dispatch(&mut self, _: command::ThreadGroupCount, _: command::ThreadGroupCount,
+                     _: command::ThreadGroupCount) {}
+    fn begin_query(&mut self, _: ()) {}
+    fn end_query(&mut self, _: ()) {}
+    fn write_timestamp(&mut self, _: ()) {}
 }
 
 
@@ -317,9 +337,18 @@ impl GraphicsQueue {
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: false,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: true,
+            compute_supported: false,
+            draw_indirect_supported: false,
+            persistent_mapping_supported: false,
+            alpha_to_coverage_supported: false,
+            sample_mask_supported: false,
+            depth_clamp_supported: false,
+            depth_bias_clamp_supported: false,
+            limits: core::Limits::default(),
         };
         GraphicsQueue {
             share: share,
@@ -344,13 +373,13 @@ impl GraphicsQueue {
     fn ensure_mappings_flushed(&mut self, access: &mut AccessGuard<Resources>) {
         let (dev, vk) = self.share.get_device();
         for (buffer, mapping) in access.access_mapped_reads() {
-            mapping.status.ensure_flushed(|| {
+            mapping.status.ensure_flushed(|range| {
                 let memory_range = vk::MappedMemoryRange {
                     sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
                     pNext: ptr::null(),
                     memory: buffer.resource().memory,
-                    offset: 0,
-                    size: vk::WHOLE_SIZE,
+                    offset: range.start as vk::DeviceSize,
+                    size: (range.end - range.start) as vk::DeviceSize,
                 };
                 assert_eq!(vk::SUCCESS, unsafe {
                     vk.FlushMappedMemoryRanges(dev, 1, &memory_range)
@@ -445,6 +474,11 @@ impl core::Device for GraphicsQueue {
         unimplemented!()
     }
 
+    fn get_query_result(&mut self, _query: &handle::Query<Self::Resources>) -> Option<u64> {
+        // `create_query` refuses every kind, so there is no query to get the result of
+        None
+    }
+
     //note: this should really live elsewhere (Factory?)
     fn cleanup(&mut self) {
         let (dev, mut functions) = self.share.get_device();
@@ -486,6 +520,7 @@ impl core::Device for GraphicsQueue {
             |vk, fence| unsafe {
                 vk.DestroyFence(dev, fence.0, ptr::null());
             },
+            |_, _| (), //query
         );
     }
 }
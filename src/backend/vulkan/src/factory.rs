@@ -13,8 +13,9 @@
 // limitations under the License.
 
 use std::{cell, mem, ptr, slice};
+use std::ops::Range;
 use std::os::raw::c_void;
-use core::{self, handle as h, pso, state, texture, buffer, mapping};
+use core::{self, handle as h, pso, query, state, texture, buffer, mapping};
 use core::memory::{self, Bind};
 use core::factory::{self as f};
 use core::format::ChannelType;
@@ -218,7 +219,7 @@ impl Factory {
         use core::memory::Usage::*;
         let mapping = match info.usage {
             Data | Dynamic => None,
-            Upload | Download => Some({
+            Upload | Download | Persistent => Some({
                 let mut m = MappingGate {
                     pointer: ptr::null_mut(),
                     status: mapping::Status::clean(),
@@ -249,7 +250,7 @@ impl Factory {
             memoryTypeIndex: match usage {
                 // TODO: more fine-grained memory selection
                 // HOST_CACHED if possible for Download
-                Upload | Download => self.mem_system_id,
+                Upload | Download | Persistent => self.mem_system_id,
                 Data | Dynamic => self.mem_video_id,
             },
         };
@@ -316,8 +317,56 @@ impl core::Factory<R> for Factory {
         unimplemented!()
     }
 
+    fn set_debug_name_raw(&mut self, handle: h::Ref<R>, name: &str) {
+        self.share.handles.lock().unwrap().set_label(handle, name);
+    }
+
+    fn get_statistics(&self) -> h::Statistics {
+        self.share.handles.lock().unwrap().get_statistics()
+    }
+
+    fn get_live_resources(&self) -> Vec<h::LiveResource> {
+        self.share.handles.lock().unwrap().get_live_resources()
+    }
+
+    fn get_format_properties(&self, format: core::format::Format) -> core::format::Properties {
+        use core::format as fm;
+        let vk_format = match data::map_format(format.0, format.1) {
+            Some(f) => f,
+            None => return fm::Properties::unsupported(),
+        };
+        let (_, vk) = self.share.get_instance();
+        let props: vk::FormatProperties = unsafe {
+            let mut out = mem::zeroed();
+            vk.GetPhysicalDeviceFormatProperties(self.share.get_physical_device(), vk_format, &mut out);
+            out
+        };
+        let features = props.optimalTilingFeatures;
+        let mut support = fm::Support::empty();
+        if features & vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT != 0 {
+            support |= fm::SAMPLE;
+        }
+        if features & (vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT | vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT) != 0 {
+            support |= fm::RENDER;
+        }
+        if features & vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT != 0 {
+            support |= fm::BLEND;
+        }
+        if features & vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT != 0 {
+            support |= fm::UNORDERED;
+        }
+        fm::Properties {
+            support: support,
+            // the pipelines are not multisampled yet
+            max_samples: 1,
+        }
+    }
+
     fn create_buffer_raw(&mut self, info: buffer::Info) -> Result<h::RawBuffer<R>, buffer::CreationError> {
         use core::handle::Producer;
+        if info.usage == memory::Usage::Persistent {
+            return Err(buffer::CreationError::UnsupportedUsage(info.usage));
+        }
         let (buffer, mapping) = self.create_buffer_impl(&info);
         Ok(self.share.handles.lock().unwrap().make_buffer(buffer, info, mapping))
     }
@@ -383,6 +432,7 @@ impl core::Factory<R> for Factory {
             unordereds: Vec::new(),
             samplers: Vec::new(),
             outputs: Vec::new(),
+            stream_outputs: Vec::new(),
             output_depth: false,
             knows_outputs: false,
         };
@@ -411,6 +461,12 @@ impl core::Factory<R> for Factory {
                 }
             },
             core::ShaderSet::Tessellated(..) => unimplemented!(),
+            core::ShaderSet::Compute(..) => {
+                return Err("Compute programs are not supported by this backend".into())
+            },
+            core::ShaderSet::TransformFeedback(..) => {
+                return Err("Transform feedback is not supported by this backend".into())
+            },
         };
 
         Ok(self.share.handles.lock().unwrap().make_program(prog, info))
@@ -837,7 +893,7 @@ impl core::Factory<R> for Factory {
             self.share.handles.lock().unwrap().make_texture_srv(view, htex))
     }
 
-    fn view_texture_as_unordered_access_raw(&mut self, _htex: &h::RawTexture<R>)
+    fn view_texture_as_unordered_access_raw(&mut self, _htex: &h::RawTexture<R>, _desc: texture::UnorderedDesc)
                                         -> Result<h::RawUnorderedAccessView<R>, f::ResourceViewError> {
         Err(f::ResourceViewError::Unsupported) //TODO
     }
@@ -906,19 +962,85 @@ impl core::Factory<R> for Factory {
         self.share.handles.lock().unwrap().make_sampler(sampler, info)
     }
 
-    fn read_mapping<'a, 'b, T>(&'a mut self, _: &'b h::Buffer<R, T>)
-                               -> Result<mapping::Reader<'b, R, T>,
-                                         mapping::Error>
+    fn create_query(&mut self, kind: query::Kind) -> Result<h::Query<R>, query::CreationError> {
+        Err(query::CreationError::Unsupported(kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
         where T: Copy
     {
-        unimplemented!()
+        let (dev, vk) = self.share.get_device();
+        let handles = &mut self.frame_handles;
+        unsafe {
+            mapping::read(buf.raw(), range, |mapping, _| {
+                mapping.status.cpu_access(|fence| wait_fence(handles.ref_fence(&fence), dev, vk))
+            })
+        }
     }
 
-    fn write_mapping<'a, 'b, T>(&'a mut self, _: &'b h::Buffer<R, T>)
-                                -> Result<mapping::Writer<'b, R, T>,
-                                          mapping::Error>
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b h::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
         where T: Copy
     {
-        unimplemented!()
+        let (dev, vk) = self.share.get_device();
+        let handles = &mut self.frame_handles;
+        unsafe {
+            mapping::write(buf.raw(), range, |mapping, bytes| {
+                mapping.status.cpu_write_access(bytes, |fence| wait_fence(handles.ref_fence(&fence), dev, vk))
+            })
+        }
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &h::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let (dev, vk) = self.share.get_device();
+        let memory = buf.raw().resource().memory;
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::WRITE, |mapping, bytes| {
+                let memory_range = vk::MappedMemoryRange {
+                    sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+                    pNext: ptr::null(),
+                    memory: memory,
+                    offset: bytes.start as vk::DeviceSize,
+                    size: (bytes.end - bytes.start) as vk::DeviceSize,
+                };
+                assert_eq!(vk::SUCCESS, vk.FlushMappedMemoryRanges(dev, 1, &memory_range));
+                mapping.status.flushed(bytes);
+            })
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &h::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        let (dev, vk) = self.share.get_device();
+        let memory = buf.raw().resource().memory;
+        let handles = &mut self.frame_handles;
+        unsafe {
+            mapping::sync::<R, T, _>(buf.raw(), range, memory::READ, |mapping, bytes| {
+                mapping.status.cpu_access(|fence| wait_fence(handles.ref_fence(&fence), dev, vk));
+                let memory_range = vk::MappedMemoryRange {
+                    sType: vk::STRUCTURE_TYPE_MAPPED_MEMORY_RANGE,
+                    pNext: ptr::null(),
+                    memory: memory,
+                    offset: bytes.start as vk::DeviceSize,
+                    size: (bytes.end - bytes.start) as vk::DeviceSize,
+                };
+                assert_eq!(vk::SUCCESS, vk.InvalidateMappedMemoryRanges(dev, 1, &memory_range));
+            })
+        }
     }
 }
+
+fn wait_fence(fence: &::Fence, dev: vk::Device, vk: &vk::DevicePointers) {
+    assert_eq!(vk::SUCCESS, unsafe {
+        vk.WaitForFences(dev, 1, &fence.0, vk::TRUE, !0)
+    });
+}
+
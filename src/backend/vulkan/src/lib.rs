@@ -332,6 +332,7 @@ impl core::Resources for Resources {
     type DepthStencilView     = native::TextureView;
     type Sampler              = vk::Sampler;
     type Fence                = Fence;
+    type Query                = ();
     type Mapping              = factory::MappingGate;
 }
 
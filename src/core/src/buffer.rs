@@ -92,6 +92,8 @@ pub enum Role {
     Constant,
     /// Staging buffer
     Staging,
+    /// Indirect draw arguments buffer
+    Indirect,
 }
 
 /// An information block that is immutable and associated to each buffer.
@@ -120,7 +122,8 @@ pub enum CreationError {
     Other,
     /// Usage mode is not supported
     UnsupportedUsage(memory::Usage),
-    // TODO: unsupported role
+    /// Role is not supported
+    UnsupportedRole(Role),
 }
 
 impl fmt::Display for CreationError {
@@ -128,6 +131,7 @@ impl fmt::Display for CreationError {
         match *self {
             CreationError::UnsupportedBind(ref bind) => write!(f, "{}: {:?}", self.description(), bind),
             CreationError::UnsupportedUsage(usage) => write!(f, "{}: {:?}", self.description(), usage),
+            CreationError::UnsupportedRole(role) => write!(f, "{}: {:?}", self.description(), role),
             _ => write!(f, "{}", self.description()),
         }
     }
@@ -139,6 +143,7 @@ impl Error for CreationError {
             CreationError::UnsupportedBind(_) => "Bind flags are not supported",
             CreationError::Other => "An unknown error occurred",
             CreationError::UnsupportedUsage(_) => "Requested memory usage mode is not supported",
+            CreationError::UnsupportedRole(_) => "Requested buffer role is not supported",
         }
     }
 }
@@ -14,11 +14,35 @@
 
 //! Command Buffer device interface
 
+use std::error::Error;
+use std::fmt;
 use std::ops::Deref;
 use std::collections::hash_set::{self, HashSet};
 use {Resources, IndexType, InstanceCount, VertexCount,
      SubmissionResult, SubmissionError};
 use {state, target, pso, shade, texture, handle};
+use memory::Pod;
+
+/// An error occuring when appending a command buffer to another.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AppendError {
+    /// The backend records into native command buffers that can't be nested.
+    Unsupported,
+}
+
+impl fmt::Display for AppendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for AppendError {
+    fn description(&self) -> &str {
+        match *self {
+            AppendError::Unsupported => "Appending command buffers is not supported by the backend",
+        }
+    }
+}
 
 /// A universal clear color supporting integet formats
 /// as well as the standard floating-point.
@@ -33,15 +57,75 @@ pub enum ClearColor {
     Uint([u32; 4]),
 }
 
+/// A viewport rectangle together with the range its depth values are mapped to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Viewport {
+    /// Area of the render target, with the origin at the top left corner
+    pub rect: target::Rect,
+    /// Depth value that the near plane is mapped to
+    pub near: f32,
+    /// Depth value that the far plane is mapped to
+    pub far: f32,
+}
+
+impl Viewport {
+    /// Create a viewport covering the given area with the full depth range.
+    pub fn new(rect: target::Rect) -> Viewport {
+        Viewport {
+            rect: rect,
+            near: 0.0,
+            far: 1.0,
+        }
+    }
+}
+
 /// Optional instance parameters: (instance count, buffer offset)
 pub type InstanceParams = (InstanceCount, VertexCount);
 
+/// Number of compute thread groups along a single dimension.
+pub type ThreadGroupCount = u32;
+
+/// Arguments of a single indirect draw call, as laid out in the argument buffer.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[repr(C)]
+pub struct DrawIndirectArgs {
+    pub vertex_count: VertexCount,
+    pub instance_count: InstanceCount,
+    pub first_vertex: VertexCount,
+    pub first_instance: InstanceCount,
+}
+
+unsafe impl Pod for DrawIndirectArgs {}
+
+/// Arguments of a single indexed indirect draw call, as laid out in the argument buffer.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+#[repr(C)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: VertexCount,
+    pub instance_count: InstanceCount,
+    pub first_index: VertexCount,
+    pub base_vertex: i32,
+    pub first_instance: InstanceCount,
+}
+
+unsafe impl Pod for DrawIndexedIndirectArgs {}
+
 /// An interface of the abstract command buffer. It collects commands in an
 /// efficient API-specific manner, to be ready for execution on the device.
 #[allow(missing_docs)]
 pub trait Buffer<R: Resources>: Send {
     /// Reset the command buffer contents, retain the allocated storage
     fn reset(&mut self);
+    /// Append the commands of another command buffer, to be executed
+    /// after the ones already recorded. Backends recording straight into
+    /// native command buffers that can't be nested (Metal and Vulkan) refuse,
+    /// leaving both command buffers untouched.
+    fn append(&mut self, other: &Self) -> Result<(), AppendError> where Self: Sized;
     /// Bind a pipeline state object
     fn bind_pipeline_state(&mut self, R::PipelineStateObject);
     /// Bind a complete set of vertex buffers
@@ -59,10 +143,16 @@ pub trait Buffer<R: Resources>: Send {
     /// Bind a complete set of pixel targets, including multiple
     /// colors views and an optional depth/stencil view.
     fn bind_pixel_targets(&mut self, pso::PixelTargetSet<R>);
+    /// Bind the buffer capturing the stream output of the following draws,
+    /// or stop capturing with `None`.
+    fn bind_stream_output(&mut self, Option<pso::StreamOutputParam<R>>);
     /// Bind an index buffer
     fn bind_index(&mut self, R::Buffer, IndexType);
     /// Set scissor rectangle
     fn set_scissor(&mut self, target::Rect);
+    /// Set the viewports, up to `MAX_VIEWPORTS`. An empty slice sets a single
+    /// viewport covering the bound pixel targets.
+    fn set_viewports(&mut self, &[Viewport]);
     /// Set reference values for the blending and stencil front/back
     fn set_ref_values(&mut self, state::RefValues);
     /// Copy part of a buffer to another
@@ -79,6 +169,17 @@ pub trait Buffer<R: Resources>: Send {
                               src: R::Texture, texture::Kind,
                               Option<texture::CubeFace>, texture::RawImageInfo,
                               dst: R::Buffer, dst_offset_bytes: usize);
+    /// Copy part of a texture to another one with the same texel size,
+    /// the regions having equal dimensions
+    fn copy_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                    dst: texture::TextureCopyRegion<R::Texture>);
+    /// Resolve a multisampled texture region into a single-sampled one
+    fn resolve_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                       dst: texture::TextureCopyRegion<R::Texture>);
+    /// Copy part of a texture to another one, scaling it with either nearest
+    /// (`FilterMethod::Scale`) or linear (any other method) filtering
+    fn blit_texture(&mut self, src: texture::TextureCopyRegion<R::Texture>,
+                    dst: texture::TextureCopyRegion<R::Texture>, texture::FilterMethod);
     /// Update a vertex/index/uniform buffer
     fn update_buffer(&mut self, R::Buffer, data: &[u8], offset: usize);
     /// Update a texture
@@ -93,6 +194,27 @@ pub trait Buffer<R: Resources>: Send {
     fn call_draw(&mut self, VertexCount, VertexCount, Option<InstanceParams>);
     /// Draw a primitive with index buffer
     fn call_draw_indexed(&mut self, VertexCount, VertexCount, VertexCount, Option<InstanceParams>);
+    /// Draw a number of primitives, reading the `DrawIndirectArgs` records
+    /// from a buffer, starting at the given offset
+    fn call_draw_indirect(&mut self, R::Buffer, offset_bytes: usize, draw_count: usize);
+    /// Draw a number of primitives with index buffer, reading the
+    /// `DrawIndexedIndirectArgs` records from a buffer, starting at the given offset
+    fn call_draw_indexed_indirect(&mut self, R::Buffer, offset_bytes: usize, draw_count: usize);
+    /// Dispatch a number of compute thread groups
+    fn call_dispatch(&mut self, ThreadGroupCount, ThreadGroupCount, ThreadGroupCount);
+    /// Start counting into an occlusion or pipeline statistics query
+    fn begin_query(&mut self, R::Query);
+    /// Stop counting into an occlusion or pipeline statistics query
+    fn end_query(&mut self, R::Query);
+    /// Write the current GPU time into a timestamp query
+    fn write_timestamp(&mut self, R::Query);
+    /// Open a named group of commands for the graphics debugging tools.
+    /// Backends without debug markers ignore the groups and markers.
+    fn push_debug_group(&mut self, _name: &str) {}
+    /// Close the last opened group of commands
+    fn pop_debug_group(&mut self) {}
+    /// Insert a named marker between the commands
+    fn insert_debug_marker(&mut self, _name: &str) {}
 }
 
 macro_rules! impl_clear {
@@ -157,6 +279,12 @@ impl<R: Resources> AccessInfo<R> {
         self.mapped_writes.clear();
     }
 
+    /// Add the accesses of another bunch of commands
+    pub fn extend(&mut self, other: &AccessInfo<R>) {
+        self.mapped_reads.extend(other.mapped_reads().cloned());
+        self.mapped_writes.extend(other.mapped_writes().cloned());
+    }
+
     /// Register a buffer read access
     pub fn buffer_read(&mut self, buffer: &handle::RawBuffer<R>) {
         if buffer.is_mapped() {
@@ -12,17 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Dummy backend implementation to test the code for compile errors
-//! outside of the graphics development environment.
+//! Dummy backend implementation to test the code outside of the graphics
+//! development environment.
+//!
+//! Buffers and textures are backed by host memory, and can be mapped.
+//! Nothing is rendered, but buffer copies and texture uploads are executed
+//! on submission, and every submitted command is logged by the device.
 
-use {Capabilities, Device, SubmissionResult, Resources, IndexType, VertexCount};
-use {state, target, handle, mapping, pso, shade, texture};
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut, Range};
+use std::rc::Rc;
+use std::slice;
+use {Capabilities, Device, Limits, SubmissionResult, Resources, IndexType, VertexCount};
+use {buffer, format, handle, mapping, memory, pso, query, shade, state, target, texture};
 use command::{self, AccessInfo};
+use factory::{self, Factory};
+use handle::Producer;
+use memory::Typed;
 
 /// Dummy device which does minimal work, just to allow testing
-/// gfx-rs apps for compilation.
+/// gfx-rs apps without a GPU.
 pub struct DummyDevice {
-    capabilities: Capabilities,
+    share: Rc<Share>,
+    frame_handles: handle::Manager<DummyResources>,
+    log: Vec<DummyCommand>,
 }
 
 /// Dummy resources phantom type
@@ -30,32 +45,360 @@ pub struct DummyDevice {
 pub enum DummyResources {}
 
 impl Resources for DummyResources {
-    type Buffer               = ();
+    type Buffer               = DummyBuffer;
     type Shader               = ();
     type Program              = ();
     type PipelineStateObject  = ();
-    type Texture              = ();
+    type Texture              = DummyTexture;
     type ShaderResourceView   = ();
     type UnorderedAccessView  = ();
-    type RenderTargetView     = ();
-    type DepthStencilView     = ();
+    type RenderTargetView     = DummyTexture;
+    type DepthStencilView     = DummyTexture;
     type Sampler              = ();
     type Fence                = DummyFence;
+    type Query                = ();
     type Mapping              = DummyMapping;
 }
 
+/// Dummy buffer, identified by the index of its memory.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DummyBuffer(pub usize);
+
+/// Dummy texture, identified by the index of its memory. Target views
+/// refer to the whole texture they are created for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DummyTexture(pub usize);
+
 /// Dummy fence that does nothing.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct DummyFence;
 
-/// Dummy mapping which will crash on use.
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct DummyMapping;
+/// Dummy mapping, pointing to the host memory of the buffer. The memory
+/// is never moved for the whole lifetime of the buffer.
+#[derive(Debug, Eq, Hash, PartialEq)]
+pub struct DummyMapping {
+    pointer: *mut u8,
+}
+
+unsafe impl Send for DummyMapping {}
+unsafe impl Sync for DummyMapping {}
 
 impl mapping::Gate<DummyResources> for DummyMapping {
-    unsafe fn set<T>(&self, _index: usize, _val: T) { unimplemented!() }
-    unsafe fn slice<'a, 'b, T>(&'a self, _len: usize) -> &'b [T] { unimplemented!() }
-    unsafe fn mut_slice<'a, 'b, T>(&'a self, _len: usize) -> &'b mut [T] { unimplemented!() }
+    unsafe fn set<T>(&self, index: usize, val: T) {
+        *(self.pointer as *mut T).offset(index as isize) = val;
+    }
+
+    unsafe fn slice<'a, 'b, T>(&'a self, len: usize) -> &'b [T] {
+        slice::from_raw_parts(self.pointer as *const T, len)
+    }
+
+    unsafe fn mut_slice<'a, 'b, T>(&'a self, len: usize) -> &'b mut [T] {
+        slice::from_raw_parts_mut(self.pointer as *mut T, len)
+    }
+}
+
+/// Host memory of a texture, with the images of every layer and level
+/// stored one after another, each as tightly packed rows of blocks.
+struct TextureMemory {
+    info: texture::Info,
+    data: Vec<u8>,
+}
+
+impl TextureMemory {
+    fn new(info: texture::Info) -> TextureMemory {
+        let mut memory = TextureMemory {
+            info: info,
+            data: Vec::new(),
+        };
+        memory.data = vec![0; memory.num_layers() * memory.image_offset(info.levels, 0)];
+        memory
+    }
+
+    fn num_layers(&self) -> usize {
+        use texture::Kind::*;
+        match self.info.kind {
+            D1(..) | D2(..) | D3(..) => 1,
+            D1Array(_, layers) | D2Array(_, _, layers, _) => layers as usize,
+            Cube(_) => 6,
+            CubeArray(_, layers) => 6 * layers as usize,
+        }
+    }
+
+    /// Get the size of a single layer of the given level, in blocks.
+    fn level_extent(&self, level: target::Level) -> (usize, usize, usize) {
+        use texture::Kind::*;
+        let (bw, bh) = self.info.format.get_block_dim();
+        let (w, h, d, _) = self.info.kind.get_level_dimensions(level);
+        let w = (cmp::max(1, w as usize) + bw as usize - 1) / bw as usize;
+        let h = (cmp::max(1, h as usize) + bh as usize - 1) / bh as usize;
+        match self.info.kind {
+            D1(..) | D1Array(..) => (w, 1, 1),
+            D2(..) | D2Array(..) | Cube(..) | CubeArray(..) => (w, h, 1),
+            D3(..) => (w, h, cmp::max(1, d as usize)),
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        self.info.format.get_total_bits() as usize / 8
+    }
+
+    fn level_size(&self, level: target::Level) -> usize {
+        let (w, h, d) = self.level_extent(level);
+        w * h * d * self.block_size()
+    }
+
+    fn image_offset(&self, level: target::Level, layer: usize) -> usize {
+        let layer_size = (0 .. self.info.levels).map(|l| self.level_size(l)).sum::<usize>();
+        layer * layer_size + (0 .. level).map(|l| self.level_size(l)).sum::<usize>()
+    }
+
+    /// Get the byte ranges of the rows of an image region, in the order of tightly packed data,
+    /// or `None` if the region is out of the texture, or larger than `size` bytes.
+    fn rows(&self, face: Option<texture::CubeFace>, img: &texture::RawImageInfo, size: usize)
+            -> Option<Vec<Range<usize>>> {
+        use texture::Kind::*;
+        if img.mipmap >= self.info.levels {
+            error!("Mipmap {} is out of the texture levels", img.mipmap);
+            return None;
+        }
+        let (bw, bh) = self.info.format.get_block_dim();
+        let (w, h, d) = self.level_extent(img.mipmap);
+        let (x0, y0, z0) = (img.xoffset as usize / bw as usize, img.yoffset as usize / bh as usize,
+                            img.zoffset as usize);
+        let width = (cmp::max(1, img.width as usize) + bw as usize - 1) / bw as usize;
+        let height = (cmp::max(1, img.height as usize) + bh as usize - 1) / bh as usize;
+        let face = face.map(|f| texture::CUBE_FACES.iter().position(|&cf| cf == f).unwrap());
+        let mut rows = Vec::new();
+        for k in 0 .. cmp::max(1, img.depth as usize) {
+            for j in 0 .. height {
+                let (layer, y, z) = match (self.info.kind, face) {
+                    (D1(..), _) => (0, 0, 0),
+                    (D1Array(..), _) => (y0 + j, 0, 0),
+                    (D2(..), _) => (0, y0 + j, 0),
+                    (D2Array(..), _) => (z0 + k, y0 + j, 0),
+                    (D3(..), _) => (0, y0 + j, z0 + k),
+                    (Cube(..), Some(f)) => (f, y0 + j, 0),
+                    (CubeArray(..), Some(f)) => (6 * (z0 + k) + f, y0 + j, 0),
+                    (Cube(..), None) | (CubeArray(..), None) => {
+                        error!("A cube face is required to access {:?}", img);
+                        return None;
+                    },
+                };
+                if x0 + width > w || y >= h || z >= d || layer >= self.num_layers() {
+                    error!("Image {:?} is out of the texture bounds", img);
+                    return None;
+                }
+                let start = self.image_offset(img.mipmap, layer) + ((z * h + y) * w + x0) * self.block_size();
+                rows.push(start .. start + width * self.block_size());
+            }
+        }
+        let total = rows.iter().map(|row| row.end - row.start).sum::<usize>();
+        if size < total {
+            error!("Image {:?} needs {} bytes, only {} are given", img, total, size);
+            return None;
+        }
+        Some(rows)
+    }
+
+    fn write(&mut self, face: Option<texture::CubeFace>, img: &texture::RawImageInfo, data: &[u8]) {
+        let rows = match self.rows(face, img, data.len()) {
+            Some(rows) => rows,
+            None => return,
+        };
+        let mut offset = 0;
+        for row in rows {
+            let size = row.end - row.start;
+            self.data[row].copy_from_slice(&data[offset .. offset + size]);
+            offset += size;
+        }
+    }
+
+    fn read(&self, face: Option<texture::CubeFace>, img: &texture::RawImageInfo, data: &mut [u8]) -> bool {
+        let rows = match self.rows(face, img, data.len()) {
+            Some(rows) => rows,
+            None => return false,
+        };
+        let mut offset = 0;
+        for row in rows {
+            let size = row.end - row.start;
+            data[offset .. offset + size].copy_from_slice(&self.data[row]);
+            offset += size;
+        }
+        true
+    }
+}
+
+/// Host memory of a buffer. It is allocated as 64-bit words, so that it
+/// can be mapped as a slice of any of the element types of the buffers.
+struct BufferMemory {
+    words: Box<[u64]>,
+    size: usize,
+}
+
+impl BufferMemory {
+    fn new(data: Option<&[u8]>, size: usize) -> BufferMemory {
+        let mut memory = BufferMemory {
+            words: vec![0; (size + 7) / 8].into_boxed_slice(),
+            size: size,
+        };
+        if let Some(data) = data {
+            memory.copy_from_slice(data);
+        }
+        memory
+    }
+}
+
+impl Deref for BufferMemory {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.words.as_ptr() as *const u8, self.size) }
+    }
+}
+
+impl DerefMut for BufferMemory {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, self.size) }
+    }
+}
+
+/// Memory of all the buffers and textures.
+struct Storage {
+    buffers: HashMap<usize, BufferMemory>,
+    textures: HashMap<usize, TextureMemory>,
+    next_id: usize,
+}
+
+impl Storage {
+    fn buffer(&mut self, buffer: DummyBuffer) -> Option<&mut BufferMemory> {
+        let memory = self.buffers.get_mut(&buffer.0);
+        if memory.is_none() {
+            error!("Unknown {:?}", buffer);
+        }
+        memory
+    }
+
+    fn texture(&mut self, texture: DummyTexture) -> Option<&mut TextureMemory> {
+        let memory = self.textures.get_mut(&texture.0);
+        if memory.is_none() {
+            error!("Unknown {:?}", texture);
+        }
+        memory
+    }
+
+    /// Get a range of a buffer, or `None` if it's out of the buffer.
+    fn buffer_range(&mut self, buffer: DummyBuffer, offset: usize, size: usize) -> Option<&mut [u8]> {
+        let data = match self.buffer(buffer) {
+            Some(data) => data,
+            None => return None,
+        };
+        let len = data.len();
+        let range = data.get_mut(offset .. offset.saturating_add(size));
+        if range.is_none() {
+            error!("Range of {} bytes at offset {} is out of {:?} of {} bytes", size, offset, buffer, len);
+        }
+        range
+    }
+
+    /// Execute a command, skipping it with an error if it accesses memory
+    /// out of the resources.
+    fn execute(&mut self, command: &DummyCommand) {
+        match *command {
+            DummyCommand::CopyBuffer(src, dst, src_offset, dst_offset, size) => {
+                let data = match self.buffer_range(src, src_offset, size) {
+                    Some(data) => data.to_vec(),
+                    None => return,
+                };
+                if let Some(range) = self.buffer_range(dst, dst_offset, size) {
+                    range.copy_from_slice(&data);
+                }
+            },
+            DummyCommand::CopyBufferToTexture(src, offset, dst, _, face, img) => {
+                let size = match self.buffer(src) {
+                    Some(data) => data.len().saturating_sub(offset),
+                    None => return,
+                };
+                let data = match self.buffer_range(src, offset, size) {
+                    Some(data) => data.to_vec(),
+                    None => return,
+                };
+                if let Some(texture) = self.texture(dst) {
+                    texture.write(face, &img, &data);
+                }
+            },
+            DummyCommand::CopyTextureToBuffer(src, _, face, img, dst, offset) => {
+                let mut data = vec![0; img.get_byte_count()];
+                if !self.texture(src).map_or(false, |texture| texture.read(face, &img, &mut data)) {
+                    return;
+                }
+                if let Some(range) = self.buffer_range(dst, offset, data.len()) {
+                    range.copy_from_slice(&data);
+                }
+            },
+            // textures have a single sample, so resolving is just copying
+            DummyCommand::CopyTexture(src, dst) | DummyCommand::ResolveTexture(src, dst) => {
+                let mut data = vec![0; src.info.get_byte_count()];
+                if !self.texture(src.texture).map_or(false, |texture| texture.read(src.cube_face, &src.info, &mut data)) {
+                    return;
+                }
+                if let Some(texture) = self.texture(dst.texture) {
+                    texture.write(dst.cube_face, &dst.info, &data);
+                }
+            },
+            DummyCommand::BlitTexture(src, dst, _) => {
+                // always picks the nearest texel
+                let mut data = vec![0; src.info.get_byte_count()];
+                if !self.texture(src.texture).map_or(false, |texture| texture.read(src.cube_face, &src.info, &mut data)) {
+                    return;
+                }
+                let texel = src.info.format.0.get_total_bits() as usize / 8;
+                let extent = |img: &texture::RawImageInfo| (cmp::max(1, img.width as usize),
+                                                            cmp::max(1, img.height as usize),
+                                                            cmp::max(1, img.depth as usize));
+                let (sw, sh, sd) = extent(&src.info);
+                let (dw, dh, dd) = extent(&dst.info);
+                let mut scaled = vec![0; dw * dh * dd * texel];
+                for z in 0 .. dd {
+                    for y in 0 .. dh {
+                        for x in 0 .. dw {
+                            let (sx, sy, sz) = (x * sw / dw, y * sh / dh, z * sd / dd);
+                            let from = ((sz * sh + sy) * sw + sx) * texel;
+                            let to = ((z * dh + y) * dw + x) * texel;
+                            scaled[to .. to + texel].copy_from_slice(&data[from .. from + texel]);
+                        }
+                    }
+                }
+                if let Some(texture) = self.texture(dst.texture) {
+                    texture.write(dst.cube_face, &dst.info, &scaled);
+                }
+            },
+            DummyCommand::UpdateBuffer(buffer, ref data, offset) => {
+                if let Some(range) = self.buffer_range(buffer, offset, data.len()) {
+                    range.copy_from_slice(data);
+                }
+            },
+            DummyCommand::UpdateTexture(texture, _, face, ref data, img) => {
+                if let Some(texture) = self.texture(texture) {
+                    texture.write(face, &img, data);
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Internal struct of shared data between the device and its factories.
+#[doc(hidden)]
+pub struct Share {
+    capabilities: Capabilities,
+    storage: RefCell<Storage>,
+    handles: RefCell<handle::Manager<DummyResources>>,
+}
+
+/// Create a dummy device with a factory, reporting the given capabilities.
+pub fn create(capabilities: Capabilities) -> (DummyDevice, DummyFactory) {
+    let device = DummyDevice::with_capabilities(capabilities);
+    let factory = DummyFactory::new(device.share.clone());
+    (device, factory)
 }
 
 impl DummyDevice {
@@ -72,52 +415,251 @@ impl DummyDevice {
             vertex_base_supported: false,
             srgb_color_supported: false,
             constant_buffer_supported: false,
+            constant_buffer_range_supported: false,
             unordered_access_view_supported: false,
             separate_blending_slots_supported: false,
             copy_buffer_supported: false,
+            compute_supported: false,
+            draw_indirect_supported: false,
+            persistent_mapping_supported: false,
+            alpha_to_coverage_supported: false,
+            sample_mask_supported: false,
+            depth_clamp_supported: false,
+            depth_bias_clamp_supported: false,
+            limits: Limits::default(),
+        };
+        DummyDevice::with_capabilities(caps)
+    }
+
+    /// Create a new dummy device, reporting the given capabilities.
+    pub fn with_capabilities(capabilities: Capabilities) -> DummyDevice {
+        let storage = Storage {
+            buffers: HashMap::new(),
+            textures: HashMap::new(),
+            next_id: 0,
+        };
+        let share = Share {
+            capabilities: capabilities,
+            storage: RefCell::new(storage),
+            handles: RefCell::new(handle::Manager::new()),
         };
         DummyDevice {
-            capabilities: caps,
+            share: Rc::new(share),
+            frame_handles: handle::Manager::new(),
+            log: Vec::new(),
+        }
+    }
+
+    /// Get all the commands submitted so far.
+    pub fn get_log(&self) -> &[DummyCommand] {
+        &self.log
+    }
+
+    /// Forget the commands submitted so far.
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    fn execute(&mut self, cb: &DummyCommandBuffer, access: &AccessInfo<DummyResources>)
+               -> SubmissionResult<()> {
+        // the mapped memory is the buffer memory, so it doesn't need any synchronization
+        let _guard = try!(access.take_accesses());
+        let mut storage = self.share.storage.borrow_mut();
+        for command in &cb.commands {
+            storage.execute(command);
+        }
+        self.log.extend_from_slice(&cb.commands);
+        Ok(())
+    }
+}
+
+/// A recorded command, mirroring the methods of `command::Buffer`.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DummyCommand {
+    BindPipelineState,
+    BindVertexBuffers(pso::VertexBufferSet<DummyResources>),
+    BindConstantBuffers(Vec<pso::ConstantBufferParam<DummyResources>>),
+    BindGlobalConstant(shade::Location, shade::UniformValue),
+    BindResourceViews(Vec<pso::ResourceViewParam<DummyResources>>),
+    BindUnorderedViews(Vec<pso::UnorderedViewParam<DummyResources>>),
+    BindSamplers(Vec<pso::SamplerParam<DummyResources>>),
+    BindPixelTargets(pso::PixelTargetSet<DummyResources>),
+    BindStreamOutput(Option<pso::StreamOutputParam<DummyResources>>),
+    BindIndex(DummyBuffer, IndexType),
+    SetScissor(target::Rect),
+    SetViewports(Vec<command::Viewport>),
+    SetRefValues(state::RefValues),
+    CopyBuffer(DummyBuffer, DummyBuffer, usize, usize, usize),
+    CopyBufferToTexture(DummyBuffer, usize, DummyTexture, texture::Kind,
+                        Option<texture::CubeFace>, texture::RawImageInfo),
+    CopyTextureToBuffer(DummyTexture, texture::Kind, Option<texture::CubeFace>,
+                        texture::RawImageInfo, DummyBuffer, usize),
+    CopyTexture(texture::TextureCopyRegion<DummyTexture>, texture::TextureCopyRegion<DummyTexture>),
+    ResolveTexture(texture::TextureCopyRegion<DummyTexture>, texture::TextureCopyRegion<DummyTexture>),
+    BlitTexture(texture::TextureCopyRegion<DummyTexture>, texture::TextureCopyRegion<DummyTexture>,
+                texture::FilterMethod),
+    UpdateBuffer(DummyBuffer, Vec<u8>, usize),
+    UpdateTexture(DummyTexture, texture::Kind, Option<texture::CubeFace>, Vec<u8>, texture::RawImageInfo),
+    GenerateMipmap,
+    ClearColor(DummyTexture, command::ClearColor),
+    ClearDepthStencil(DummyTexture, Option<target::Depth>, Option<target::Stencil>),
+    Draw(VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndexed(VertexCount, VertexCount, VertexCount, Option<command::InstanceParams>),
+    DrawIndirect(DummyBuffer, usize, usize),
+    DrawIndexedIndirect(DummyBuffer, usize, usize),
+    Dispatch(command::ThreadGroupCount, command::ThreadGroupCount, command::ThreadGroupCount),
+    BeginQuery,
+    EndQuery,
+    WriteTimestamp,
+    PushDebugGroup(String),
+    PopDebugGroup,
+    InsertDebugMarker(String),
+}
+
+/// Dummy command buffer, which records all the calls.
+pub struct DummyCommandBuffer {
+    /// Commands recorded since the last reset
+    pub commands: Vec<DummyCommand>,
+}
+
+impl DummyCommandBuffer {
+    /// Create a new empty command buffer.
+    pub fn new() -> DummyCommandBuffer {
+        DummyCommandBuffer {
+            commands: Vec::new(),
         }
     }
 }
 
-/// Dummy command buffer, which ignores all the calls.
-pub struct DummyCommandBuffer;
 impl command::Buffer<DummyResources> for DummyCommandBuffer {
-    fn reset(&mut self) {}
-    fn bind_pipeline_state(&mut self, _: ()) {}
-    fn bind_vertex_buffers(&mut self, _: pso::VertexBufferSet<DummyResources>) {}
-    fn bind_constant_buffers(&mut self, _: &[pso::ConstantBufferParam<DummyResources>]) {}
-    fn bind_global_constant(&mut self, _: shade::Location, _: shade::UniformValue) {}
-    fn bind_resource_views(&mut self, _: &[pso::ResourceViewParam<DummyResources>]) {}
-    fn bind_unordered_views(&mut self, _: &[pso::UnorderedViewParam<DummyResources>]) {}
-    fn bind_samplers(&mut self, _: &[pso::SamplerParam<DummyResources>]) {}
-    fn bind_pixel_targets(&mut self, _: pso::PixelTargetSet<DummyResources>) {}
-    fn bind_index(&mut self, _: (), _: IndexType) {}
-    fn set_scissor(&mut self, _: target::Rect) {}
-    fn set_ref_values(&mut self, _: state::RefValues) {}
-    fn copy_buffer(&mut self, _: (), _: (),
-                   _: usize, _: usize,
-                   _: usize) {}
+    fn reset(&mut self) {
+        self.commands.clear();
+    }
+    fn append(&mut self, other: &DummyCommandBuffer) -> Result<(), command::AppendError> {
+        self.commands.extend(other.commands.iter().cloned());
+        Ok(())
+    }
+    fn bind_pipeline_state(&mut self, _: ()) {
+        self.commands.push(DummyCommand::BindPipelineState);
+    }
+    fn bind_vertex_buffers(&mut self, vbs: pso::VertexBufferSet<DummyResources>) {
+        self.commands.push(DummyCommand::BindVertexBuffers(vbs));
+    }
+    fn bind_constant_buffers(&mut self, cbs: &[pso::ConstantBufferParam<DummyResources>]) {
+        self.commands.push(DummyCommand::BindConstantBuffers(cbs.to_vec()));
+    }
+    fn bind_global_constant(&mut self, loc: shade::Location, value: shade::UniformValue) {
+        self.commands.push(DummyCommand::BindGlobalConstant(loc, value));
+    }
+    fn bind_resource_views(&mut self, srvs: &[pso::ResourceViewParam<DummyResources>]) {
+        self.commands.push(DummyCommand::BindResourceViews(srvs.to_vec()));
+    }
+    fn bind_unordered_views(&mut self, uavs: &[pso::UnorderedViewParam<DummyResources>]) {
+        self.commands.push(DummyCommand::BindUnorderedViews(uavs.to_vec()));
+    }
+    fn bind_samplers(&mut self, ss: &[pso::SamplerParam<DummyResources>]) {
+        self.commands.push(DummyCommand::BindSamplers(ss.to_vec()));
+    }
+    fn bind_pixel_targets(&mut self, pts: pso::PixelTargetSet<DummyResources>) {
+        self.commands.push(DummyCommand::BindPixelTargets(pts));
+    }
+    fn bind_stream_output(&mut self, so: Option<pso::StreamOutputParam<DummyResources>>) {
+        self.commands.push(DummyCommand::BindStreamOutput(so));
+    }
+    fn bind_index(&mut self, buffer: DummyBuffer, index_type: IndexType) {
+        self.commands.push(DummyCommand::BindIndex(buffer, index_type));
+    }
+    fn set_scissor(&mut self, rect: target::Rect) {
+        self.commands.push(DummyCommand::SetScissor(rect));
+    }
+    fn set_viewports(&mut self, viewports: &[command::Viewport]) {
+        self.commands.push(DummyCommand::SetViewports(viewports.to_vec()));
+    }
+    fn set_ref_values(&mut self, rv: state::RefValues) {
+        self.commands.push(DummyCommand::SetRefValues(rv));
+    }
+    fn copy_buffer(&mut self, src: DummyBuffer, dst: DummyBuffer,
+                   src_offset_bytes: usize, dst_offset_bytes: usize,
+                   size_bytes: usize) {
+        self.commands.push(DummyCommand::CopyBuffer(src, dst, src_offset_bytes, dst_offset_bytes, size_bytes));
+    }
     fn copy_buffer_to_texture(&mut self,
-                              _: (), _: usize,
-                              _: (), _: texture::Kind,
-                              _: Option<texture::CubeFace>, _: texture::RawImageInfo) {}
+                              src: DummyBuffer, src_offset_bytes: usize,
+                              dst: DummyTexture, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo) {
+        self.commands.push(DummyCommand::CopyBufferToTexture(src, src_offset_bytes, dst, kind, face, img));
+    }
     fn copy_texture_to_buffer(&mut self,
-                              _: (), _: texture::Kind,
-                              _: Option<texture::CubeFace>, _: texture::RawImageInfo,
-                              _: (), _: usize) {}
-    fn update_buffer(&mut self, _: (), _: &[u8], _: usize) {}
-    fn update_texture(&mut self, _: (), _: texture::Kind, _: Option<texture::CubeFace>,
-                      _: &[u8], _: texture::RawImageInfo) {}
-    fn generate_mipmap(&mut self, _: ()) {}
-    fn clear_color(&mut self, _: (), _: command::ClearColor) {}
-    fn clear_depth_stencil(&mut self, _: (), _: Option<target::Depth>,
-                           _: Option<target::Stencil>) {}
-    fn call_draw(&mut self, _: VertexCount, _: VertexCount, _: Option<command::InstanceParams>) {}
-    fn call_draw_indexed(&mut self, _: VertexCount, _: VertexCount,
-                         _: VertexCount, _: Option<command::InstanceParams>) {}
+                              src: DummyTexture, kind: texture::Kind,
+                              face: Option<texture::CubeFace>, img: texture::RawImageInfo,
+                              dst: DummyBuffer, dst_offset_bytes: usize) {
+        self.commands.push(DummyCommand::CopyTextureToBuffer(src, kind, face, img, dst, dst_offset_bytes));
+    }
+    fn copy_texture(&mut self, src: texture::TextureCopyRegion<DummyTexture>,
+                    dst: texture::TextureCopyRegion<DummyTexture>) {
+        self.commands.push(DummyCommand::CopyTexture(src, dst));
+    }
+    fn resolve_texture(&mut self, src: texture::TextureCopyRegion<DummyTexture>,
+                       dst: texture::TextureCopyRegion<DummyTexture>) {
+        self.commands.push(DummyCommand::ResolveTexture(src, dst));
+    }
+    fn blit_texture(&mut self, src: texture::TextureCopyRegion<DummyTexture>,
+                    dst: texture::TextureCopyRegion<DummyTexture>, filter: texture::FilterMethod) {
+        self.commands.push(DummyCommand::BlitTexture(src, dst, filter));
+    }
+    fn update_buffer(&mut self, buffer: DummyBuffer, data: &[u8], offset: usize) {
+        self.commands.push(DummyCommand::UpdateBuffer(buffer, data.to_vec(), offset));
+    }
+    fn update_texture(&mut self, texture: DummyTexture, kind: texture::Kind, face: Option<texture::CubeFace>,
+                      data: &[u8], img: texture::RawImageInfo) {
+        self.commands.push(DummyCommand::UpdateTexture(texture, kind, face, data.to_vec(), img));
+    }
+    fn generate_mipmap(&mut self, _: ()) {
+        self.commands.push(DummyCommand::GenerateMipmap);
+    }
+    fn clear_color(&mut self, target: DummyTexture, value: command::ClearColor) {
+        self.commands.push(DummyCommand::ClearColor(target, value));
+    }
+    fn clear_depth_stencil(&mut self, target: DummyTexture, depth: Option<target::Depth>,
+                           stencil: Option<target::Stencil>) {
+        self.commands.push(DummyCommand::ClearDepthStencil(target, depth, stencil));
+    }
+    fn call_draw(&mut self, start: VertexCount, count: VertexCount, instances: Option<command::InstanceParams>) {
+        self.commands.push(DummyCommand::Draw(start, count, instances));
+    }
+    fn call_draw_indexed(&mut self, start: VertexCount, count: VertexCount,
+                         base: VertexCount, instances: Option<command::InstanceParams>) {
+        self.commands.push(DummyCommand::DrawIndexed(start, count, base, instances));
+    }
+    fn call_draw_indirect(&mut self, buffer: DummyBuffer, offset: usize, count: usize) {
+        self.commands.push(DummyCommand::DrawIndirect(buffer, offset, count));
+    }
+    fn call_draw_indexed_indirect(&mut self, buffer: DummyBuffer, offset: usize, count: usize) {
+        self.commands.push(DummyCommand::DrawIndexedIndirect(buffer, offset, count));
+    }
+    fn call_dispatch(&mut self, x: command::ThreadGroupCount, y: command::ThreadGroupCount,
+                     z: command::ThreadGroupCount) {
+        self.commands.push(DummyCommand::Dispatch(x, y, z));
+    }
+    fn begin_query(&mut self, _: ()) {
+        self.commands.push(DummyCommand::BeginQuery);
+    }
+    fn end_query(&mut self, _: ()) {
+        self.commands.push(DummyCommand::EndQuery);
+    }
+    fn write_timestamp(&mut self, _: ()) {
+        self.commands.push(DummyCommand::WriteTimestamp);
+    }
+    fn push_debug_group(&mut self, name: &str) {
+        self.commands.push(DummyCommand::PushDebugGroup(name.to_string()));
+    }
+    fn pop_debug_group(&mut self) {
+        self.commands.push(DummyCommand::PopDebugGroup);
+    }
+    fn insert_debug_marker(&mut self, name: &str) {
+        self.commands.push(DummyCommand::InsertDebugMarker(name.to_string()));
+    }
 }
 
 impl Device for DummyDevice {
@@ -125,27 +667,291 @@ impl Device for DummyDevice {
     type CommandBuffer = DummyCommandBuffer;
 
     fn get_capabilities(&self) -> &Capabilities {
-        &self.capabilities
+        &self.share.capabilities
+    }
+    fn pin_submitted_resources(&mut self, man: &handle::Manager<DummyResources>) {
+        self.frame_handles.extend(man);
     }
-    fn pin_submitted_resources(&mut self, _: &handle::Manager<DummyResources>) {}
     fn submit(&mut self,
-              _: &mut DummyCommandBuffer,
-              _: &AccessInfo<Self::Resources>)
+              cb: &mut DummyCommandBuffer,
+              access: &AccessInfo<Self::Resources>)
               -> SubmissionResult<()> {
-        unimplemented!()
+        self.execute(cb, access)
     }
 
     fn fenced_submit(&mut self,
-                     _: &mut Self::CommandBuffer,
-                     _: &AccessInfo<Self::Resources>,
+                     cb: &mut Self::CommandBuffer,
+                     access: &AccessInfo<Self::Resources>,
                      _after: Option<handle::Fence<Self::Resources>>)
                      -> SubmissionResult<handle::Fence<Self::Resources>> {
-        unimplemented!()
+        try!(self.execute(cb, access));
+        Ok(self.share.handles.borrow_mut().make_fence(DummyFence))
+    }
+
+    fn wait_fence(&mut self, _: &handle::Fence<Self::Resources>) {}
+
+    fn get_query_result(&mut self, _: &handle::Query<Self::Resources>) -> Option<u64> {
+        Some(0)
+    }
+
+    fn cleanup(&mut self) {
+        self.frame_handles.clear();
+        self.share.handles.borrow_mut().clean_with(&mut *self.share.storage.borrow_mut(),
+            |s, buffer| { s.buffers.remove(&buffer.resource().0); },
+            |_, _| {}, //shader
+            |_, _| {}, //program
+            |_, _| {}, //PSO
+            |s, texture| { s.textures.remove(&texture.resource().0); },
+            |_, _| {}, //SRV
+            |_, _| {}, //UAV
+            |_, _| {}, //RTV
+            |_, _| {}, //DSV
+            |_, _| {}, //sampler
+            |_, _| {}, //fence
+            |_, _| {}, //query
+        );
+    }
+}
+
+/// Dummy resource factory, allocating the buffers and textures in host memory.
+pub struct DummyFactory {
+    share: Rc<Share>,
+    frame_handles: handle::Manager<DummyResources>,
+}
+
+impl Clone for DummyFactory {
+    fn clone(&self) -> DummyFactory {
+        DummyFactory::new(self.share.clone())
+    }
+}
+
+impl DummyFactory {
+    /// Create a new `DummyFactory`.
+    pub fn new(share: Rc<Share>) -> DummyFactory {
+        DummyFactory {
+            share: share,
+            frame_handles: handle::Manager::new(),
+        }
+    }
+
+    /// Create a new empty command buffer.
+    pub fn create_command_buffer(&mut self) -> DummyCommandBuffer {
+        DummyCommandBuffer::new()
+    }
+
+    fn create_buffer_internal(&mut self, info: buffer::Info, data: Option<&[u8]>)
+                              -> Result<handle::RawBuffer<DummyResources>, buffer::CreationError> {
+        use memory::Usage::*;
+        let mut memory = BufferMemory::new(data, info.size);
+        let mapping = match info.usage {
+            Data | Dynamic => None,
+            Upload | Download | Persistent => Some(DummyMapping {
+                pointer: memory.as_mut_ptr(),
+            }),
+        };
+        let id = {
+            let mut storage = self.share.storage.borrow_mut();
+            let id = storage.next_id;
+            storage.next_id += 1;
+            storage.buffers.insert(id, memory);
+            id
+        };
+        Ok(self.share.handles.borrow_mut().make_buffer(DummyBuffer(id), info, mapping))
+    }
+}
+
+impl Factory<DummyResources> for DummyFactory {
+    fn get_capabilities(&self) -> &Capabilities {
+        &self.share.capabilities
+    }
+
+    fn set_debug_name_raw(&mut self, handle: handle::Ref<DummyResources>, name: &str) {
+        self.share.handles.borrow_mut().set_label(handle, name);
     }
 
-    fn wait_fence(&mut self, _: &handle::Fence<Self::Resources>) {
-        unimplemented!()
+    fn get_statistics(&self) -> handle::Statistics {
+        self.share.handles.borrow().get_statistics()
     }
 
-    fn cleanup(&mut self) {}
+    fn get_live_resources(&self) -> Vec<handle::LiveResource> {
+        self.share.handles.borrow().get_live_resources()
+    }
+
+    fn get_format_properties(&self, _: format::Format) -> format::Properties {
+        // every format is stored as plain bytes, so only the capabilities restrict it
+        let caps = &self.share.capabilities;
+        let mut support = format::SAMPLE | format::RENDER | format::BLEND;
+        if caps.unordered_access_view_supported {
+            support |= format::UNORDERED;
+        }
+        format::Properties {
+            support: support,
+            max_samples: cmp::max(caps.limits.max_samples, 1),
+        }
+    }
+
+    fn create_buffer_raw(&mut self, info: buffer::Info)
+                         -> Result<handle::RawBuffer<DummyResources>, buffer::CreationError> {
+        self.create_buffer_internal(info, None)
+    }
+
+    fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, role: buffer::Role, bind: memory::Bind)
+                                   -> Result<handle::RawBuffer<DummyResources>, buffer::CreationError> {
+        let info = buffer::Info {
+            role: role,
+            usage: memory::Usage::Data,
+            bind: bind,
+            size: data.len(),
+            stride: stride,
+        };
+        self.create_buffer_internal(info, Some(data))
+    }
+
+    fn create_shader(&mut self, _: shade::Stage, _: &[u8])
+                     -> Result<handle::Shader<DummyResources>, shade::CreateShaderError> {
+        Ok(self.share.handles.borrow_mut().make_shader(()))
+    }
+
+    fn create_program(&mut self, _: &::ShaderSet<DummyResources>)
+                      -> Result<handle::Program<DummyResources>, shade::CreateProgramError> {
+        let info = shade::ProgramInfo {
+            vertex_attributes: Vec::new(),
+            globals: Vec::new(),
+            constant_buffers: Vec::new(),
+            textures: Vec::new(),
+            unordereds: Vec::new(),
+            samplers: Vec::new(),
+            outputs: Vec::new(),
+            stream_outputs: Vec::new(),
+            output_depth: false,
+            knows_outputs: false,
+        };
+        Ok(self.share.handles.borrow_mut().make_program((), info))
+    }
+
+    fn create_pipeline_state_raw(&mut self, program: &handle::Program<DummyResources>, _: &pso::Descriptor)
+                                 -> Result<handle::RawPipelineState<DummyResources>, pso::CreationError> {
+        Ok(self.share.handles.borrow_mut().make_pso((), program))
+    }
+
+    fn create_texture_raw(&mut self, desc: texture::Info, _: Option<format::ChannelType>,
+                          data_opt: Option<&[&[u8]]>)
+                          -> Result<handle::RawTexture<DummyResources>, texture::CreationError> {
+        if desc.levels == 0 {
+            return Err(texture::CreationError::Size(0))
+        }
+        let mut memory = TextureMemory::new(desc);
+        if let Some(data) = data_opt {
+            let num_images = memory.num_layers() * desc.levels as usize;
+            if data.len() != num_images {
+                return Err(texture::CreationError::Data(data.len()))
+            }
+            for (i, image) in data.iter().enumerate() {
+                let (layer, level) = (i / desc.levels as usize, (i % desc.levels as usize) as target::Level);
+                let (offset, size) = (memory.image_offset(level, layer), memory.level_size(level));
+                if image.len() != size {
+                    return Err(texture::CreationError::Data(image.len()))
+                }
+                memory.data[offset .. offset + size].copy_from_slice(image);
+            }
+        }
+        let id = {
+            let mut storage = self.share.storage.borrow_mut();
+            let id = storage.next_id;
+            storage.next_id += 1;
+            storage.textures.insert(id, memory);
+            id
+        };
+        Ok(self.share.handles.borrow_mut().make_texture(DummyTexture(id), desc))
+    }
+
+    fn view_buffer_as_shader_resource_raw(&mut self, hbuf: &handle::RawBuffer<DummyResources>)
+                                      -> Result<handle::RawShaderResourceView<DummyResources>, factory::ResourceViewError> {
+        Ok(self.share.handles.borrow_mut().make_buffer_srv((), hbuf))
+    }
+
+    fn view_buffer_as_unordered_access_raw(&mut self, hbuf: &handle::RawBuffer<DummyResources>)
+                                       -> Result<handle::RawUnorderedAccessView<DummyResources>, factory::ResourceViewError> {
+        Ok(self.share.handles.borrow_mut().make_buffer_uav((), hbuf))
+    }
+
+    fn view_texture_as_shader_resource_raw(&mut self, htex: &handle::RawTexture<DummyResources>, _: texture::ResourceDesc)
+                                       -> Result<handle::RawShaderResourceView<DummyResources>, factory::ResourceViewError> {
+        Ok(self.share.handles.borrow_mut().make_texture_srv((), htex))
+    }
+
+    fn view_texture_as_unordered_access_raw(&mut self, htex: &handle::RawTexture<DummyResources>, desc: texture::UnorderedDesc)
+                                        -> Result<handle::RawUnorderedAccessView<DummyResources>, factory::ResourceViewError> {
+        if desc.level >= htex.get_info().levels {
+            return Err(factory::ResourceViewError::Level(desc.level))
+        }
+        Ok(self.share.handles.borrow_mut().make_texture_uav((), htex))
+    }
+
+    fn view_texture_as_render_target_raw(&mut self, htex: &handle::RawTexture<DummyResources>, desc: texture::RenderDesc)
+                                         -> Result<handle::RawRenderTargetView<DummyResources>, factory::TargetViewError> {
+        if desc.level >= htex.get_info().levels {
+            return Err(factory::TargetViewError::Level(desc.level))
+        }
+        let texture = *self.frame_handles.ref_texture(htex);
+        self.frame_handles.clear();
+        let dim = htex.get_info().kind.get_level_dimensions(desc.level);
+        Ok(self.share.handles.borrow_mut().make_rtv(texture, htex, dim))
+    }
+
+    fn view_texture_as_depth_stencil_raw(&mut self, htex: &handle::RawTexture<DummyResources>, desc: texture::DepthStencilDesc)
+                                         -> Result<handle::RawDepthStencilView<DummyResources>, factory::TargetViewError> {
+        if desc.level >= htex.get_info().levels {
+            return Err(factory::TargetViewError::Level(desc.level))
+        }
+        let texture = *self.frame_handles.ref_texture(htex);
+        self.frame_handles.clear();
+        let dim = htex.get_info().kind.get_level_dimensions(desc.level);
+        Ok(self.share.handles.borrow_mut().make_dsv(texture, htex, dim))
+    }
+
+    fn create_sampler(&mut self, info: texture::SamplerInfo) -> handle::Sampler<DummyResources> {
+        self.share.handles.borrow_mut().make_sampler((), info)
+    }
+
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<DummyResources>, query::CreationError> {
+        Ok(self.share.handles.borrow_mut().make_query((), kind))
+    }
+
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<DummyResources, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, DummyResources, T>, mapping::Error>
+        where T: Copy
+    {
+        // commands are executed on submission, so the memory is always up to date
+        unsafe {
+            mapping::read(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<DummyResources, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, DummyResources, T>, mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::write(buf.raw(), range, |_, _| ())
+        }
+    }
+
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<DummyResources, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<DummyResources, T, _>(buf.raw(), range, memory::WRITE, |_, _| ())
+        }
+    }
+
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<DummyResources, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
+        where T: Copy
+    {
+        unsafe {
+            mapping::sync::<DummyResources, T, _>(buf.raw(), range, memory::READ, |_, _| ())
+        }
+    }
 }
@@ -19,9 +19,10 @@
 
 use std::error::Error;
 use std::{mem, fmt};
-use {buffer, handle, format, mapping, pso, shade, target, texture};
+use std::ops::Range;
+use {buffer, handle, format, mapping, pso, query, shade, target, texture};
 use {Capabilities, Resources, ShaderSet,
-     VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
+     VertexShader, HullShader, DomainShader, GeometryShader, PixelShader, ComputeShader};
 use memory::{Usage, Typed, Pod, cast_slice};
 use memory::{Bind, RENDER_TARGET, DEPTH_STENCIL, SHADER_RESOURCE, UNORDERED_ACCESS};
 
@@ -32,6 +33,8 @@ pub enum ResourceViewError {
     NoBindFlag,
     /// Selected channel type is not supported for this texture.
     Channel(format::ChannelType),
+    /// Selected mip level doesn't exist.
+    Level(target::Level),
     /// Selected layer can not be viewed for this texture.
     Layer(texture::LayerError),
     /// The backend was refused for some reason.
@@ -42,6 +45,7 @@ impl fmt::Display for ResourceViewError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             ResourceViewError::Channel(ref channel_type) => write!(f, "{}: {:?}", self.description(), channel_type),
+            ResourceViewError::Level(ref level) => write!(f, "{}: {}", self.description(), level),
             ResourceViewError::Layer(ref le) => write!(f, "{}: {}", self.description(), le),
             _ => write!(f, "{}", self.description())
         }
@@ -53,6 +57,7 @@ impl Error for ResourceViewError {
         match *self {
             ResourceViewError::NoBindFlag => "The corresponding bind flag is not present in the texture",
             ResourceViewError::Channel(_) => "Selected channel type is not supported for this texture",
+            ResourceViewError::Level(_) => "Selected mip level doesn't exist",
             ResourceViewError::Layer(_) => "Selected layer can not be viewed for this texture",
             ResourceViewError::Unsupported => "The backend was refused for some reason",
         }
@@ -195,6 +200,24 @@ pub trait Factory<R: Resources> {
     /// used.
     fn get_capabilities(&self) -> &Capabilities;
 
+    /// Returns what the device supports doing with a format.
+    fn get_format_properties(&self, format::Format) -> format::Properties;
+
+    /// Label the object behind a handle, for the graphics debugging tools.
+    /// Backends that can't name their objects ignore it.
+    fn set_debug_name_raw(&mut self, _handle: handle::Ref<R>, _name: &str) {}
+    fn set_debug_name<'a, H>(&mut self, handle: H, name: &str)
+        where H: Into<handle::Ref<'a, R>>, R: 'a
+    {
+        self.set_debug_name_raw(handle.into(), name)
+    }
+
+    /// Returns the number of resources of each type created by this `Factory`
+    /// and not yet cleaned up by the device, along with their estimated memory.
+    fn get_statistics(&self) -> handle::Statistics;
+    /// Returns the resources still referenced by the application, to find the leaks.
+    fn get_live_resources(&self) -> Vec<handle::LiveResource>;
+
     // resource creation
     fn create_buffer_raw(&mut self, buffer::Info) -> Result<handle::RawBuffer<R>, buffer::CreationError>;
     fn create_buffer_immutable_raw(&mut self, data: &[u8], stride: usize, buffer::Role, Bind)
@@ -251,16 +274,26 @@ pub trait Factory<R: Resources> {
     fn create_shader_pixel(&mut self, code: &[u8]) -> Result<PixelShader<R>, shade::CreateShaderError> {
         self.create_shader(shade::Stage::Pixel, code).map(|s| PixelShader(s))
     }
+    /// Compiles a `ComputeShader` from source.
+    fn create_shader_compute(&mut self, code: &[u8]) -> Result<ComputeShader<R>, shade::CreateShaderError> {
+        self.create_shader(shade::Stage::Compute, code).map(|s| ComputeShader(s))
+    }
 
     fn create_sampler(&mut self, texture::SamplerInfo) -> handle::Sampler<R>;
 
+    /// Creates a new query of the given kind.
+    fn create_query(&mut self, kind: query::Kind) -> Result<handle::Query<R>, query::CreationError>;
+
     /// Acquire a mapping Reader
     ///
     /// See `write_mapping` for more information.
     fn read_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
                                -> Result<mapping::Reader<'b, R, T>,
                                          mapping::Error>
-        where T: Copy;
+        where T: Copy
+    {
+        self.read_mapping_range(buf, 0..buf.len())
+    }
 
     /// Acquire a mapping Writer
     ///
@@ -268,10 +301,49 @@ pub trait Factory<R: Resources> {
     /// Any access overlap will result in an error.
     /// Submitting commands involving this buffer to the device
     /// implicitly requires exclusive access. Additionally,
-    /// further access will be stalled until execution completion.
+    /// further access will be stalled until execution completion,
+    /// unless the buffer was created with `Usage::Persistent`.
     fn write_mapping<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>)
                                 -> Result<mapping::Writer<'b, R, T>,
                                           mapping::Error>
+        where T: Copy
+    {
+        self.write_mapping_range(buf, 0..buf.len())
+    }
+
+    /// Acquire a mapping Reader for the given range of elements.
+    ///
+    /// See `write_mapping` for more information.
+    fn read_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                     -> Result<mapping::Reader<'b, R, T>,
+                                               mapping::Error>
+        where T: Copy;
+
+    /// Acquire a mapping Writer for the given range of elements.
+    ///
+    /// Only the written range gets flushed to the GPU upon submission.
+    /// See `write_mapping` for more information.
+    fn write_mapping_range<'a, 'b, T>(&'a mut self, buf: &'b handle::Buffer<R, T>, range: Range<usize>)
+                                      -> Result<mapping::Writer<'b, R, T>,
+                                                mapping::Error>
+        where T: Copy;
+
+    /// Make the CPU writes into the given range of elements visible to the GPU.
+    ///
+    /// Written ranges are flushed on submission anyway, this allows doing it
+    /// earlier. No mapping reader or writer of the buffer can be alive at this point.
+    fn flush_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                        -> Result<(), mapping::Error>
+        where T: Copy;
+
+    /// Make the GPU writes into the given range of elements visible to the CPU,
+    /// waiting for the GPU to finish using the buffer if needed.
+    ///
+    /// This is required before reading from a `Usage::Persistent` buffer,
+    /// since its mapping is not synchronized automatically.
+    /// No mapping reader or writer of the buffer can be alive at this point.
+    fn invalidate_mapping<T>(&mut self, buf: &handle::Buffer<R, T>, range: Range<usize>)
+                             -> Result<(), mapping::Error>
         where T: Copy;
 
     /// Create a new empty raw texture with no data. The channel type parameter is a hint,
@@ -287,7 +359,7 @@ pub trait Factory<R: Resources> {
         -> Result<handle::RawUnorderedAccessView<R>, ResourceViewError>;
     fn view_texture_as_shader_resource_raw(&mut self, &handle::RawTexture<R>, texture::ResourceDesc)
         -> Result<handle::RawShaderResourceView<R>, ResourceViewError>;
-    fn view_texture_as_unordered_access_raw(&mut self, &handle::RawTexture<R>)
+    fn view_texture_as_unordered_access_raw(&mut self, &handle::RawTexture<R>, texture::UnorderedDesc)
         -> Result<handle::RawUnorderedAccessView<R>, ResourceViewError>;
     fn view_texture_as_render_target_raw(&mut self, &handle::RawTexture<R>, texture::RenderDesc)
         -> Result<handle::RawRenderTargetView<R>, TargetViewError>;
@@ -343,13 +415,18 @@ pub trait Factory<R: Resources> {
             .map(Typed::new)
     }
 
-    fn view_texture_as_unordered_access<T: format::TextureFormat>(&mut self, tex: &handle::Texture<R, T::Surface>)
+    fn view_texture_as_unordered_access<T: format::TextureFormat>(&mut self, tex: &handle::Texture<R, T::Surface>,
+                                        level: target::Level)
                                         -> Result<handle::UnorderedAccessView<R, T::View>, ResourceViewError>
     {
         if !tex.get_info().bind.contains(UNORDERED_ACCESS) {
             return Err(ResourceViewError::NoBindFlag)
         }
-        self.view_texture_as_unordered_access_raw(tex.raw())
+        let desc = texture::UnorderedDesc {
+            channel: <T::Channel as format::ChannelTyped>::get_channel_type(),
+            level: level,
+        };
+        self.view_texture_as_unordered_access_raw(tex.raw(), desc)
             .map(Typed::new)
     }
 
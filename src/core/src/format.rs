@@ -15,11 +15,6 @@
 //! Universal format specification.
 //! Applicable to textures, views, and vertex buffers.
 
-//TODO:
-//  DXT 1-5, BC7
-//  ETC2_RGB, // Use the EXT2 algorithm on 3 components.
-//  ETC2_SRGB, // Use the EXT2 algorithm on 4 components (RGBA) in the sRGB color space.
-//  ETC2_EAC_RGBA8, // Use the EXT2 EAC algorithm on 4 components.
 use memory::Pod;
 
 macro_rules! impl_channel_type {
@@ -75,6 +70,7 @@ macro_rules! impl_formats {
         }
         impl SurfaceType {
             /// Return the total number of bits for this format.
+            /// For block-compressed formats, this is the size of a single block.
             pub fn get_total_bits(&self) -> u8 {
                 use std::mem::size_of;
                 match *self {
@@ -152,8 +148,104 @@ impl_formats! {
     D24_S8          : Vec1<Unorm, Uint> = u32 {8} [TextureSurface, DepthSurface, StencilSurface],
     D32             : Vec1<Float> = f32 {0} [TextureSurface, DepthSurface],
     //D32_S8          : Vec1<Unorm, Float, Uint> = (f32, u32) {32} [TextureSurface, DepthSurface, StencilSurface],
+    // block-compressed, the data type is a single block
+    BC1_R8_G8_B8_A1 : Vec4<Unorm, Srgb> = [u8; 8] {1} [TextureSurface],
+    BC2_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {4} [TextureSurface],
+    BC3_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    BC4_R8          : Vec1<Inorm, Unorm> = [u8; 8] {0} [TextureSurface],
+    BC5_R8_G8       : Vec2<Inorm, Unorm> = [u8; 16] {0} [TextureSurface],
+    BC6_R16_G16_B16 : Vec3<Float> = [u8; 16] {0} [TextureSurface],
+    BC6S_R16_G16_B16: Vec3<Float> = [u8; 16] {0} [TextureSurface], // signed floats
+    BC7_R8_G8_B8_A8 : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ETC2_R8_G8_B8   : Vec3<Unorm, Srgb> = [u8; 8] {0} [TextureSurface],
+    ETC2_R8_G8_B8_A1: Vec4<Unorm, Srgb> = [u8; 8] {1} [TextureSurface],
+    ETC2_R8_G8_B8_A8: Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    EAC_R11         : Vec1<Inorm, Unorm> = [u8; 8] {0} [TextureSurface],
+    EAC_R11_G11     : Vec2<Inorm, Unorm> = [u8; 16] {0} [TextureSurface],
+    ASTC_4x4        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_5x4        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_5x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_6x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_6x6        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x5        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x6        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_8x8        : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x5       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x6       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x8       : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_10x10      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_12x10      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
+    ASTC_12x12      : Vec4<Unorm, Srgb> = [u8; 16] {8} [TextureSurface],
 }
 
+impl SurfaceType {
+    /// Return the width and height of a compression block, in texels.
+    /// Uncompressed formats have 1x1 blocks.
+    pub fn get_block_dim(&self) -> (u8, u8) {
+        use self::SurfaceType::*;
+        match *self {
+            BC1_R8_G8_B8_A1 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC4_R8 | BC5_R8_G8 |
+            BC6_R16_G16_B16 | BC6S_R16_G16_B16 | BC7_R8_G8_B8_A8 | ETC2_R8_G8_B8 |
+            ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 | ASTC_4x4 => (4, 4),
+            ASTC_5x4 => (5, 4),
+            ASTC_5x5 => (5, 5),
+            ASTC_6x5 => (6, 5),
+            ASTC_6x6 => (6, 6),
+            ASTC_8x5 => (8, 5),
+            ASTC_8x6 => (8, 6),
+            ASTC_8x8 => (8, 8),
+            ASTC_10x5 => (10, 5),
+            ASTC_10x6 => (10, 6),
+            ASTC_10x8 => (10, 8),
+            ASTC_10x10 => (10, 10),
+            ASTC_12x10 => (12, 10),
+            ASTC_12x12 => (12, 12),
+            _ => (1, 1),
+        }
+    }
+
+    /// Check if the surface is block-compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.get_block_dim() != (1, 1)
+    }
+}
+
+
+bitflags!(
+    /// Operations that a device supports with a format.
+    #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+    pub flags Support: u8 {
+        /// Sampled in the shaders as a texture.
+        const SAMPLE    = 0x1,
+        /// Used as a color, depth or stencil target.
+        const RENDER    = 0x2,
+        /// Blended as a color target.
+        const BLEND     = 0x4,
+        /// Read and written by the shaders as an unordered access view.
+        const UNORDERED = 0x8,
+    }
+);
+
+/// Support of a format by the device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Properties {
+    /// Supported operations.
+    pub support: Support,
+    /// Maximum number of samples of a multisampled target, 1 if it can't be multisampled
+    /// and 0 if it can't be rendered to at all.
+    pub max_samples: u8,
+}
+
+impl Properties {
+    /// Properties of a format that can't be used in any way.
+    pub fn unsupported() -> Properties {
+        Properties {
+            support: Support::empty(),
+            max_samples: 0,
+        }
+    }
+}
 
 /// Source channel in a swizzle configuration. Some may redirect onto
 /// different physical channels, some may be hardcoded to 0 or 1.
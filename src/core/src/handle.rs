@@ -16,10 +16,14 @@
 
 //! Resource handles
 
+#[cfg(feature = "track-resources")]
+use std::collections::HashMap;
+#[cfg(feature = "track-resources")]
+use std::io;
 use std::marker::PhantomData;
 use std::ops::Deref;
 use std::sync::Arc;
-use {buffer, shade, texture, Resources};
+use {buffer, query, shade, texture, Resources};
 use memory::Typed;
 
 /// Untyped buffer handle
@@ -248,6 +252,129 @@ impl<R: Resources> Sampler<R> {
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Fence<R: Resources>(Arc<R::Fence>);
 
+/// Query Handle
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Query<R: Resources>(Arc<R::Query>, query::Kind);
+
+impl<R: Resources> Query<R> {
+    /// Get the kind of the query
+    pub fn get_kind(&self) -> query::Kind { self.1 }
+}
+
+/// Reference to a handle of any type, for the factory operations that apply to
+/// all of them. The typed handles convert into it with `From`.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub enum Ref<'a, R: Resources + 'a> {
+    Buffer(&'a RawBuffer<R>),
+    Shader(&'a Shader<R>),
+    Program(&'a Program<R>),
+    PipelineState(&'a RawPipelineState<R>),
+    Texture(&'a RawTexture<R>),
+    ShaderResourceView(&'a RawShaderResourceView<R>),
+    UnorderedAccessView(&'a RawUnorderedAccessView<R>),
+    RenderTargetView(&'a RawRenderTargetView<R>),
+    DepthStencilView(&'a RawDepthStencilView<R>),
+    Sampler(&'a Sampler<R>),
+    Fence(&'a Fence<R>),
+    Query(&'a Query<R>),
+}
+
+impl<'a, R: Resources> Clone for Ref<'a, R> {
+    fn clone(&self) -> Ref<'a, R> { *self }
+}
+
+impl<'a, R: Resources> Copy for Ref<'a, R> {}
+
+impl<'a, R: Resources> Ref<'a, R> {
+    /// Identify the resource by the address of its shared data.
+    fn key(&self) -> usize {
+        match *self {
+            Ref::Buffer(h) => key(&h.0),
+            Ref::Shader(h) => key(&h.0),
+            Ref::Program(h) => key(&h.0),
+            Ref::PipelineState(h) => key(&h.0),
+            Ref::Texture(h) => key(&h.0),
+            Ref::ShaderResourceView(h) => key(&h.0),
+            Ref::UnorderedAccessView(h) => key(&h.0),
+            Ref::RenderTargetView(h) => key(&h.0),
+            Ref::DepthStencilView(h) => key(&h.0),
+            Ref::Sampler(h) => key(&h.0),
+            Ref::Fence(h) => key(&h.0),
+            Ref::Query(h) => key(&h.0),
+        }
+    }
+
+    /// Count the references to the resource, held by the handles and the managers.
+    pub fn count(&self) -> usize {
+        match *self {
+            Ref::Buffer(h) => Arc::strong_count(&h.0),
+            Ref::Shader(h) => Arc::strong_count(&h.0),
+            Ref::Program(h) => Arc::strong_count(&h.0),
+            Ref::PipelineState(h) => Arc::strong_count(&h.0),
+            Ref::Texture(h) => Arc::strong_count(&h.0),
+            Ref::ShaderResourceView(h) => Arc::strong_count(&h.0),
+            Ref::UnorderedAccessView(h) => Arc::strong_count(&h.0),
+            Ref::RenderTargetView(h) => Arc::strong_count(&h.0),
+            Ref::DepthStencilView(h) => Arc::strong_count(&h.0),
+            Ref::Sampler(h) => Arc::strong_count(&h.0),
+            Ref::Fence(h) => Arc::strong_count(&h.0),
+            Ref::Query(h) => Arc::strong_count(&h.0),
+        }
+    }
+}
+
+fn key<X>(arc: &Arc<X>) -> usize {
+    &**arc as *const X as usize
+}
+
+macro_rules! impl_ref_from {
+    ($( $variant:ident = $raw:ident, $typed:ident<$ty:ident>; )*) => {
+        $(
+            impl<'a, R: Resources> From<&'a $raw<R>> for Ref<'a, R> {
+                fn from(handle: &'a $raw<R>) -> Ref<'a, R> {
+                    Ref::$variant(handle)
+                }
+            }
+            impl<'a, R: Resources, $ty> From<&'a $typed<R, $ty>> for Ref<'a, R> {
+                fn from(handle: &'a $typed<R, $ty>) -> Ref<'a, R> {
+                    Ref::$variant(handle.raw())
+                }
+            }
+        )*
+    }
+}
+
+impl_ref_from! {
+    Buffer = RawBuffer, Buffer<T>;
+    Texture = RawTexture, Texture<S>;
+    ShaderResourceView = RawShaderResourceView, ShaderResourceView<T>;
+    UnorderedAccessView = RawUnorderedAccessView, UnorderedAccessView<T>;
+    RenderTargetView = RawRenderTargetView, RenderTargetView<T>;
+    DepthStencilView = RawDepthStencilView, DepthStencilView<T>;
+}
+
+macro_rules! impl_ref_from_untyped {
+    ($( $variant:ident = $handle:ident, )*) => {
+        $(
+            impl<'a, R: Resources> From<&'a $handle<R>> for Ref<'a, R> {
+                fn from(handle: &'a $handle<R>) -> Ref<'a, R> {
+                    Ref::$variant(handle)
+                }
+            }
+        )*
+    }
+}
+
+impl_ref_from_untyped! {
+    Shader = Shader,
+    Program = Program,
+    PipelineState = RawPipelineState,
+    Sampler = Sampler,
+    Fence = Fence,
+    Query = Query,
+}
+
 /// Stores reference-counted resources used in a command buffer.
 /// Seals actual resource names behind the interface, automatically
 /// referencing them both by the Factory on resource creation
@@ -266,6 +393,124 @@ pub struct Manager<R: Resources> {
     dsvs:          Vec<Arc<R::DepthStencilView>>,
     samplers:      Vec<Arc<R::Sampler>>,
     fences:        Vec<Arc<R::Fence>>,
+    queries:       Vec<Arc<R::Query>>,
+    tracker:       Tracker,
+}
+
+/// Number of resources of each type held by a `Manager`, along with
+/// the estimated memory taken by the buffers and the textures.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Statistics {
+    pub buffers: usize,
+    pub buffer_bytes: usize,
+    pub shaders: usize,
+    pub programs: usize,
+    pub pipeline_states: usize,
+    pub textures: usize,
+    pub texture_bytes: usize,
+    pub shader_resource_views: usize,
+    pub unordered_access_views: usize,
+    pub render_target_views: usize,
+    pub depth_stencil_views: usize,
+    pub samplers: usize,
+    pub fences: usize,
+    pub queries: usize,
+}
+
+/// Type of a resource behind a handle.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ResourceType {
+    Buffer,
+    Shader,
+    Program,
+    PipelineState,
+    Texture,
+    ShaderResourceView,
+    UnorderedAccessView,
+    RenderTargetView,
+    DepthStencilView,
+    Sampler,
+    Fence,
+    Query,
+}
+
+/// A resource still referenced by the application.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct LiveResource {
+    /// Type of the resource.
+    pub ty: ResourceType,
+    /// Estimated memory of a buffer or a texture, zero for other resources.
+    pub bytes: usize,
+    /// Debug name given to the resource by the factory.
+    pub label: Option<String>,
+    /// Call stack at the creation of the resource.
+    pub backtrace: Option<String>,
+}
+
+/// Write the resources as a JSON array, to find out which ones are leaked.
+#[cfg(feature = "track-resources")]
+pub fn write_json<W: io::Write>(resources: &[LiveResource], writer: W) -> io::Result<()> {
+    serde_json::to_writer_pretty(writer, resources).map_err(From::from)
+}
+
+/// Labels and creation backtraces of the resources made by a `Manager`,
+/// keyed by the address of their shared data.
+#[cfg(feature = "track-resources")]
+#[derive(Debug, Default)]
+struct Tracker {
+    records: HashMap<usize, (Option<String>, backtrace::Backtrace)>,
+}
+
+#[cfg(feature = "track-resources")]
+impl Tracker {
+    fn record(&mut self, key: usize) {
+        self.records.insert(key, (None, backtrace::Backtrace::new()));
+    }
+    fn forget(&mut self, key: usize) {
+        self.records.remove(&key);
+    }
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+    fn set_label(&mut self, key: usize, name: &str) {
+        if let Some(record) = self.records.get_mut(&key) {
+            record.0 = Some(name.to_string());
+        }
+    }
+    fn describe(&self, key: usize, ty: ResourceType, bytes: usize) -> LiveResource {
+        let record = self.records.get(&key);
+        LiveResource {
+            ty: ty,
+            bytes: bytes,
+            label: record.and_then(|r| r.0.clone()),
+            backtrace: record.map(|r| format!("{:?}", r.1)),
+        }
+    }
+}
+
+#[cfg(not(feature = "track-resources"))]
+#[derive(Debug, Default)]
+struct Tracker;
+
+#[cfg(not(feature = "track-resources"))]
+impl Tracker {
+    fn record(&mut self, _: usize) {}
+    fn forget(&mut self, _: usize) {}
+    fn clear(&mut self) {}
+    fn set_label(&mut self, _: usize, _: &str) {}
+    fn describe(&self, _: usize, ty: ResourceType, bytes: usize) -> LiveResource {
+        LiveResource {
+            ty: ty,
+            bytes: bytes,
+            label: None,
+            backtrace: None,
+        }
+    }
 }
 
 /// A service trait to be used by the device implementation
@@ -287,6 +532,7 @@ pub trait Producer<R: Resources> {
     fn make_dsv(&mut self, R::DepthStencilView, &RawTexture<R>, texture::Dimensions) -> RawDepthStencilView<R>;
     fn make_sampler(&mut self, R::Sampler, texture::SamplerInfo) -> Sampler<R>;
     fn make_fence(&mut self, name: R::Fence) -> Fence<R>;
+    fn make_query(&mut self, R::Query, query::Kind) -> Query<R>;
 
     /// Walk through all the handles, keep ones that are reference elsewhere
     /// and call the provided delete function (resource-specific) for others
@@ -302,7 +548,8 @@ pub trait Producer<R: Resources> {
         I: Fn(&mut T, &R::DepthStencilView),
         J: Fn(&mut T, &R::Sampler),
         K: Fn(&mut T, &R::Fence),
-    >(&mut self, &mut T, A, B, C, D, E, F, G, H, I, J, K);
+        L: Fn(&mut T, &R::Query),
+    >(&mut self, &mut T, A, B, C, D, E, F, G, H, I, J, K, L);
 }
 
 impl<R: Resources> Producer<R> for Manager<R> {
@@ -312,81 +559,101 @@ impl<R: Resources> Producer<R> for Manager<R> {
                    mapping: Option<R::Mapping>) -> RawBuffer<R> {
         let r = Arc::new(buffer::Raw::new(res, info, mapping));
         self.buffers.push(r.clone());
+        self.tracker.record(key(&r));
         RawBuffer(r)
     }
 
     fn make_shader(&mut self, res: R::Shader) -> Shader<R> {
         let r = Arc::new(res);
         self.shaders.push(r.clone());
+        self.tracker.record(key(&r));
         Shader(r)
     }
 
     fn make_program(&mut self, res: R::Program, info: shade::ProgramInfo) -> Program<R> {
         let r = Arc::new(shade::Program::new(res, info));
         self.programs.push(r.clone());
+        self.tracker.record(key(&r));
         Program(r)
     }
 
     fn make_pso(&mut self, res: R::PipelineStateObject, program: &Program<R>) -> RawPipelineState<R> {
         let r = Arc::new(res);
         self.psos.push(r.clone());
+        self.tracker.record(key(&r));
         RawPipelineState(r, program.clone())
     }
 
     fn make_texture(&mut self, res: R::Texture, info: texture::Info) -> RawTexture<R> {
         let r = Arc::new(texture::Raw::new(res, info));
         self.textures.push(r.clone());
+        self.tracker.record(key(&r));
         RawTexture(r)
     }
 
     fn make_buffer_srv(&mut self, res: R::ShaderResourceView, buf: &RawBuffer<R>) -> RawShaderResourceView<R> {
         let r = Arc::new(res);
         self.srvs.push(r.clone());
+        self.tracker.record(key(&r));
         RawShaderResourceView(r, ViewSource::Buffer(buf.clone()))
     }
 
     fn make_texture_srv(&mut self, res: R::ShaderResourceView, tex: &RawTexture<R>) -> RawShaderResourceView<R> {
         let r = Arc::new(res);
         self.srvs.push(r.clone());
+        self.tracker.record(key(&r));
         RawShaderResourceView(r, ViewSource::Texture(tex.clone()))
     }
 
     fn make_buffer_uav(&mut self, res: R::UnorderedAccessView, buf: &RawBuffer<R>) -> RawUnorderedAccessView<R> {
         let r = Arc::new(res);
         self.uavs.push(r.clone());
+        self.tracker.record(key(&r));
         RawUnorderedAccessView(r, ViewSource::Buffer(buf.clone()))
     }
 
     fn make_texture_uav(&mut self, res: R::UnorderedAccessView, tex: &RawTexture<R>) -> RawUnorderedAccessView<R> {
         let r = Arc::new(res);
         self.uavs.push(r.clone());
+        self.tracker.record(key(&r));
         RawUnorderedAccessView(r, ViewSource::Texture(tex.clone()))
     }
 
     fn make_rtv(&mut self, res: R::RenderTargetView, tex: &RawTexture<R>, dim: texture::Dimensions) -> RawRenderTargetView<R> {
         let r = Arc::new(res);
         self.rtvs.push(r.clone());
+        self.tracker.record(key(&r));
         RawRenderTargetView(r, tex.clone(), dim)
     }
 
     fn make_dsv(&mut self, res: R::DepthStencilView, tex: &RawTexture<R>, dim: texture::Dimensions) -> RawDepthStencilView<R> {
         let r = Arc::new(res);
         self.dsvs.push(r.clone());
+        self.tracker.record(key(&r));
         RawDepthStencilView(r, tex.clone(), dim)
     }
 
     fn make_sampler(&mut self, res: R::Sampler, info: texture::SamplerInfo) -> Sampler<R> {
         let r = Arc::new(res);
         self.samplers.push(r.clone());
+        self.tracker.record(key(&r));
         Sampler(r, info)
     }
 
     fn make_fence(&mut self, res: R::Fence) -> Fence<R> {
         let r = Arc::new(res);
         self.fences.push(r.clone());
+        self.tracker.record(key(&r));
         Fence(r)
     }
 
+    fn make_query(&mut self, res: R::Query, kind: query::Kind) -> Query<R> {
+        let r = Arc::new(res);
+        self.queries.push(r.clone());
+        self.tracker.record(key(&r));
+        Query(r, kind)
+    }
+
     fn clean_with<T,
         A: Fn(&mut T, &buffer::Raw<R>),
         B: Fn(&mut T, &R::Shader),
@@ -399,8 +666,10 @@ impl<R: Resources> Producer<R> for Manager<R> {
         I: Fn(&mut T, &R::DepthStencilView),
         J: Fn(&mut T, &R::Sampler),
         K: Fn(&mut T, &R::Fence),
-    >(&mut self, param: &mut T, fa: A, fb: B, fc: C, fd: D, fe: E, ff: F, fg: G, fh: H, fi: I, fj: J, fk: K) {
-        fn clean_vec<X, Param, Fun>(param: &mut Param, vector: &mut Vec<Arc<X>>, fun: Fun)
+        L: Fn(&mut T, &R::Query),
+    >(&mut self, param: &mut T, fa: A, fb: B, fc: C, fd: D, fe: E, ff: F, fg: G, fh: H, fi: I, fj: J, fk: K, fl: L) {
+        fn clean_vec<X, Param, Fun>(param: &mut Param, vector: &mut Vec<Arc<X>>,
+                                    tracker: &mut Tracker, fun: Fun)
             where Fun: Fn(&mut Param, &X)
         {
             let mut temp = Vec::new();
@@ -414,20 +683,36 @@ impl<R: Resources> Producer<R> for Manager<R> {
             // update the resource vector by removing the elements
             // starting from the last one
             for t in temp.iter().rev() {
-                vector.swap_remove(*t);
+                let x = vector.swap_remove(*t);
+                tracker.forget(key(&x));
+            }
+        }
+        clean_vec(param, &mut self.buffers,       &mut self.tracker, fa);
+        clean_vec(param, &mut self.shaders,       &mut self.tracker, fb);
+        clean_vec(param, &mut self.programs,      &mut self.tracker, fc);
+        clean_vec(param, &mut self.psos,          &mut self.tracker, fd);
+        clean_vec(param, &mut self.textures,      &mut self.tracker, fe);
+        clean_vec(param, &mut self.srvs,          &mut self.tracker, ff);
+        clean_vec(param, &mut self.uavs,          &mut self.tracker, fg);
+        clean_vec(param, &mut self.rtvs,          &mut self.tracker, fh);
+        clean_vec(param, &mut self.dsvs,          &mut self.tracker, fi);
+        clean_vec(param, &mut self.samplers,      &mut self.tracker, fj);
+        clean_vec(param, &mut self.fences,        &mut self.tracker, fk);
+        clean_vec(param, &mut self.queries,       &mut self.tracker, fl);
+    }
+}
+
+/// Log the resources made by this manager that outlive it, typically
+/// the ones still held by the application when the device is destroyed.
+#[cfg(feature = "track-resources")]
+impl<R: Resources> Drop for Manager<R> {
+    fn drop(&mut self) {
+        for res in self.get_live_resources() {
+            // only the resources made by this manager have a backtrace
+            if let Some(backtrace) = res.backtrace {
+                warn!("Leaked {:?} {:?} of {} bytes, created at:\n{}", res.ty, res.label, res.bytes, backtrace);
             }
         }
-        clean_vec(param, &mut self.buffers,       fa);
-        clean_vec(param, &mut self.shaders,       fb);
-        clean_vec(param, &mut self.programs,      fc);
-        clean_vec(param, &mut self.psos,          fd);
-        clean_vec(param, &mut self.textures,      fe);
-        clean_vec(param, &mut self.srvs,          ff);
-        clean_vec(param, &mut self.uavs,          fg);
-        clean_vec(param, &mut self.rtvs,          fh);
-        clean_vec(param, &mut self.dsvs,          fi);
-        clean_vec(param, &mut self.samplers,      fj);
-        clean_vec(param, &mut self.fences,        fk);
     }
 }
 
@@ -446,6 +731,8 @@ impl<R: Resources> Manager<R> {
             dsvs: Vec::new(),
             samplers: Vec::new(),
             fences: Vec::new(),
+            queries: Vec::new(),
+            tracker: Tracker::default(),
         }
     }
     /// Clear all references
@@ -461,6 +748,8 @@ impl<R: Resources> Manager<R> {
         self.dsvs.clear();
         self.samplers.clear();
         self.fences.clear();
+        self.queries.clear();
+        self.tracker.clear();
     }
     /// Extend with all references of another handle manager
     pub fn extend(&mut self, other: &Manager<R>) {
@@ -475,6 +764,7 @@ impl<R: Resources> Manager<R> {
         self.dsvs     .extend(other.dsvs     .iter().map(|h| h.clone()));
         self.samplers .extend(other.samplers .iter().map(|h| h.clone()));
         self.fences   .extend(other.fences   .iter().map(|h| h.clone()));
+        self.queries  .extend(other.queries  .iter().map(|h| h.clone()));
     }
     /// Count the total number of referenced resources
     pub fn count(&self) -> usize {
@@ -488,7 +778,65 @@ impl<R: Resources> Manager<R> {
         self.rtvs.len() +
         self.dsvs.len() +
         self.samplers.len() +
-        self.fences.len()
+        self.fences.len() +
+        self.queries.len()
+    }
+    /// Count the resources of each type and estimate the memory of the buffers
+    /// and the textures. Only the manager of the factory holds every resource
+    /// exactly once, until the device cleans up the ones no longer used.
+    pub fn get_statistics(&self) -> Statistics {
+        Statistics {
+            buffers: self.buffers.len(),
+            buffer_bytes: self.buffers.iter().map(|b| b.get_info().size).sum(),
+            shaders: self.shaders.len(),
+            programs: self.programs.len(),
+            pipeline_states: self.psos.len(),
+            textures: self.textures.len(),
+            texture_bytes: self.textures.iter().map(|t| t.get_info().get_byte_count()).sum(),
+            shader_resource_views: self.srvs.len(),
+            unordered_access_views: self.uavs.len(),
+            render_target_views: self.rtvs.len(),
+            depth_stencil_views: self.dsvs.len(),
+            samplers: self.samplers.len(),
+            fences: self.fences.len(),
+            queries: self.queries.len(),
+        }
+    }
+    /// List the resources that are still referenced outside of this manager.
+    /// With the `track-resources` feature, the resources made by the manager
+    /// also report their label and the backtrace of their creation, and the
+    /// ones still alive when the manager is dropped are logged as leaks.
+    pub fn get_live_resources(&self) -> Vec<LiveResource> {
+        fn collect<X, Fun>(list: &mut Vec<LiveResource>, tracker: &Tracker, vector: &[Arc<X>],
+                           ty: ResourceType, bytes: Fun)
+            where Fun: Fn(&X) -> usize
+        {
+            for x in vector {
+                if Arc::strong_count(x) > 1 {
+                    list.push(tracker.describe(key(x), ty, bytes(x)));
+                }
+            }
+        }
+        let mut list = Vec::new();
+        let tr = &self.tracker;
+        collect(&mut list, tr, &self.buffers,  ResourceType::Buffer,              |b| b.get_info().size);
+        collect(&mut list, tr, &self.shaders,  ResourceType::Shader,              |_| 0);
+        collect(&mut list, tr, &self.programs, ResourceType::Program,             |_| 0);
+        collect(&mut list, tr, &self.psos,     ResourceType::PipelineState,       |_| 0);
+        collect(&mut list, tr, &self.textures, ResourceType::Texture,             |x| x.get_info().get_byte_count());
+        collect(&mut list, tr, &self.srvs,     ResourceType::ShaderResourceView,  |_| 0);
+        collect(&mut list, tr, &self.uavs,     ResourceType::UnorderedAccessView, |_| 0);
+        collect(&mut list, tr, &self.rtvs,     ResourceType::RenderTargetView,    |_| 0);
+        collect(&mut list, tr, &self.dsvs,     ResourceType::DepthStencilView,    |_| 0);
+        collect(&mut list, tr, &self.samplers, ResourceType::Sampler,             |_| 0);
+        collect(&mut list, tr, &self.fences,   ResourceType::Fence,               |_| 0);
+        collect(&mut list, tr, &self.queries,  ResourceType::Query,               |_| 0);
+        list
+    }
+    /// Label a resource made by this manager, for `get_live_resources`.
+    /// The label is only kept with the `track-resources` feature.
+    pub fn set_label(&mut self, handle: Ref<R>, name: &str) {
+        self.tracker.set_label(handle.key(), name);
     }
     /// Reference a buffer
     pub fn ref_buffer<'a>(&mut self, handle: &'a RawBuffer<R>) -> &'a R::Buffer {
@@ -548,4 +896,9 @@ impl<R: Resources> Manager<R> {
         self.fences.push(fence.0.clone());
         &fence.0
     }
+    /// Reference a query
+    pub fn ref_query<'a>(&mut self, query: &'a Query<R>) -> &'a R::Query {
+        self.queries.push(query.0.clone());
+        &query.0
+    }
 }
@@ -22,6 +22,7 @@ extern crate bitflags;
 #[macro_use]
 extern crate derivative;
 extern crate draw_state;
+#[macro_use]
 extern crate log;
 
 #[cfg(feature = "cgmath-types")]
@@ -33,6 +34,11 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 
+#[cfg(feature = "track-resources")]
+extern crate backtrace;
+#[cfg(feature = "track-resources")]
+extern crate serde_json;
+
 use std::fmt::{self, Debug};
 use std::error::Error;
 use std::hash::Hash;
@@ -50,6 +56,7 @@ pub mod handle;
 pub mod mapping;
 pub mod memory;
 pub mod pso;
+pub mod query;
 pub mod shade;
 pub mod texture;
 
@@ -65,6 +72,8 @@ pub const MAX_RESOURCE_VIEWS: usize = 32;
 pub const MAX_UNORDERED_VIEWS: usize = 4;
 /// Compile-time maximum number of samplers.
 pub const MAX_SAMPLERS: usize = 16;
+/// Compile-time maximum number of viewports.
+pub const MAX_VIEWPORTS: usize = 16;
 
 /// Draw vertex count.
 pub type VertexCount = u32;
@@ -108,7 +117,7 @@ macro_rules! define_shaders {
     }
 }
 
-define_shaders!(VertexShader, HullShader, DomainShader, GeometryShader, PixelShader);
+define_shaders!(VertexShader, HullShader, DomainShader, GeometryShader, PixelShader, ComputeShader);
 
 /// A complete set of shaders to link a program.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -117,8 +126,13 @@ pub enum ShaderSet<R: Resources> {
     Simple(VertexShader<R>, PixelShader<R>),
     /// Geometry shader programs: Vs-Gs-Ps
     Geometry(VertexShader<R>, GeometryShader<R>, PixelShader<R>),
-    /// Tessellated TODO: Tessellated, TessellatedGeometry, TransformFeedback
+    /// Tessellated TODO: Tessellated, TessellatedGeometry
     Tessellated(VertexShader<R>, HullShader<R>, DomainShader<R>, PixelShader<R>),
+    /// Compute program: Cs
+    Compute(ComputeShader<R>),
+    /// Transform feedback program: Vs with an optional Gs, no rasterization.
+    /// The named outputs of the last stage get captured, interleaved, into the stream output buffer.
+    TransformFeedback(VertexShader<R>, Option<GeometryShader<R>>, Vec<String>),
 }
 
 impl<R: Resources> ShaderSet<R> {
@@ -128,6 +142,9 @@ impl<R: Resources> ShaderSet<R> {
             &ShaderSet::Simple(..) => shade::VERTEX | shade::PIXEL,
             &ShaderSet::Geometry(..) => shade::VERTEX | shade::GEOMETRY | shade::PIXEL,
             &ShaderSet::Tessellated(..) => shade::VERTEX | shade::HULL | shade::DOMAIN | shade::PIXEL,
+            &ShaderSet::Compute(..) => shade::COMPUTE,
+            &ShaderSet::TransformFeedback(_, None, _) => shade::VERTEX,
+            &ShaderSet::TransformFeedback(_, Some(_), _) => shade::VERTEX | shade::GEOMETRY,
         }
     }
 }
@@ -149,9 +166,51 @@ pub struct Capabilities {
     pub vertex_base_supported: bool,
     pub srgb_color_supported: bool,
     pub constant_buffer_supported: bool,
+    /// Constant buffers can be bound with an offset and a size, as `RingConstantBuffer` does.
+    pub constant_buffer_range_supported: bool,
     pub unordered_access_view_supported: bool,
     pub separate_blending_slots_supported: bool,
     pub copy_buffer_supported: bool,
+    pub compute_supported: bool,
+    pub draw_indirect_supported: bool,
+    pub persistent_mapping_supported: bool,
+    pub alpha_to_coverage_supported: bool,
+    pub sample_mask_supported: bool,
+    pub depth_clamp_supported: bool,
+    pub depth_bias_clamp_supported: bool,
+
+    pub limits: Limits,
+}
+
+/// Device limits beyond the basic `Capabilities`. A zero value means that the
+/// limit is unknown to the backend, or that the related feature is not supported.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Limits {
+    pub max_texture_3d_size: usize,
+    pub max_texture_cube_size: usize,
+    pub max_texture_array_layers: usize,
+    pub max_anisotropy: u8,
+    /// Maximum number of samples supported by all the renderable formats.
+    /// Some formats may allow more, see `Factory::get_format_properties`.
+    pub max_samples: u8,
+    pub max_color_targets: usize,
+    pub max_vertex_attributes: usize,
+    pub max_viewports: usize,
+    /// Maximum number of constant buffers bound to a single shader stage.
+    pub max_constant_buffers: usize,
+    /// Maximum size of a constant buffer, in bytes.
+    pub max_constant_buffer_size: usize,
+    /// Required alignment of the constant buffer offsets, in bytes.
+    pub constant_buffer_offset_alignment: usize,
+    /// Maximum number of resource views bound to a single shader stage.
+    pub max_resource_views: usize,
+    pub max_unordered_views: usize,
+    pub max_compute_group_count: [u32; 3],
+    pub max_compute_group_size: [u32; 3],
+    /// Maximum total number of invocations in a compute group.
+    pub max_compute_invocations: u32,
 }
 
 /// Describes what geometric primitives are created from vertex data.
@@ -223,6 +282,7 @@ pub trait Resources:          Clone + Hash + Debug + Eq + PartialEq + Any {
     type DepthStencilView:    Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync;
     type Sampler:             Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync + Copy;
     type Fence:               Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync;
+    type Query:               Clone + Hash + Debug + Eq + PartialEq + Any + Send + Sync + Copy;
     type Mapping:             Hash + Debug + Eq + PartialEq + Any + Send + Sync + mapping::Gate<Self>;
 }
 
@@ -231,13 +291,18 @@ pub trait Resources:          Clone + Hash + Debug + Eq + PartialEq + Any {
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum SubmissionError {
     AccessOverlap,
+    InvalidCommands,
+    /// The device raised an error while executing the commands, with
+    /// a backend-specific message telling which command failed.
+    DeviceError(String),
 }
 
 impl fmt::Display for SubmissionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::SubmissionError::*;
         match *self {
-            AccessOverlap => write!(f, "{}", self.description()),
+            AccessOverlap | InvalidCommands => write!(f, "{}", self.description()),
+            DeviceError(ref message) => write!(f, "{}: {}", self.description(), message),
         }
     }
 }
@@ -246,7 +311,9 @@ impl Error for SubmissionError {
     fn description(&self) -> &str {
         use self::SubmissionError::*;
         match *self {
-            AccessOverlap => "A resource access overlaps with another"
+            AccessOverlap => "A resource access overlaps with another",
+            InvalidCommands => "The command buffer failed validation",
+            DeviceError(_) => "The device failed to execute the commands",
         }
     }
 }
@@ -285,6 +352,10 @@ pub trait Device: Sized {
     /// Stalls the current thread until the fence is satisfied
     fn wait_fence(&mut self, &handle::Fence<Self::Resources>);
 
+    /// Returns the result of a query, or `None` if the GPU hasn't made it available yet.
+    /// This never blocks.
+    fn get_query_result(&mut self, &handle::Query<Self::Resources>) -> Option<u64>;
+
     /// Cleanup unused resources. This should be called between frames. 
     fn cleanup(&mut self);
 }
@@ -19,7 +19,9 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::cell::UnsafeCell;
-use std::ops::{Deref, DerefMut};
+use std::cmp;
+use std::mem;
+use std::ops::{Deref, DerefMut, Range};
 use std::sync::atomic::{self, AtomicBool};
 use Resources;
 use {memory, buffer, handle};
@@ -42,6 +44,8 @@ pub enum Error {
     InvalidAccess(memory::Access, memory::Usage),
     /// The requested mapping access overlaps with another.
     AccessOverlap,
+    /// The requested range is out of the buffer bounds.
+    OutOfBounds,
 }
 
 impl fmt::Display for Error {
@@ -51,7 +55,7 @@ impl fmt::Display for Error {
             InvalidAccess(ref access, ref usage) => {
                 write!(f, "{}: access = {:?}, usage = {:?}", self.description(), access, usage)
             }
-            AccessOverlap => write!(f, "{}", self.description())
+            AccessOverlap | OutOfBounds => write!(f, "{}", self.description())
         }
     }
 }
@@ -61,7 +65,8 @@ impl StdError for Error {
         use self::Error::*;
         match *self {
             InvalidAccess(..) => "The requested mapping access did not match the expected usage",
-            AccessOverlap => "The requested mapping access overlaps with another"
+            AccessOverlap => "The requested mapping access overlaps with another",
+            OutOfBounds => "The requested range is out of the buffer bounds",
         }
     }
 }
@@ -146,40 +151,70 @@ fn take_access_checked<R>(access: memory::Access, buffer: &buffer::Raw<R>)
     match usage {
         Upload if access == memory::WRITE => (),
         Download if access == memory::READ => (),
+        Persistent => (),
         _ => return Err(Error::InvalidAccess(access, usage)),
     }
 
     Guard::new(buffer.mapping().unwrap())
 }
 
+/// Check the range of elements against the buffer length,
+/// and convert it into a range of bytes.
+fn byte_range<R, T>(buffer: &buffer::Raw<R>, range: &Range<usize>)
+                    -> Result<Range<usize>, Error>
+    where R: Resources
+{
+    if range.start > range.end || range.end > unsafe { buffer.len::<T>() } {
+        return Err(Error::OutOfBounds);
+    }
+    let stride = mem::size_of::<T>();
+    Ok(range.start * stride .. range.end * stride)
+}
+
 #[doc(hidden)]
-pub unsafe fn read<R, T, S>(buffer: &buffer::Raw<R>, sync: S)
+pub unsafe fn read<R, T, S>(buffer: &buffer::Raw<R>, range: Range<usize>, sync: S)
                             -> Result<Reader<R, T>, Error>
-    where R: Resources, T: Copy, S: FnOnce(&mut R::Mapping)
+    where R: Resources, T: Copy, S: FnOnce(&mut R::Mapping, Range<usize>)
 {
+    let bytes = try!(byte_range::<R, T>(buffer, &range));
     let mut mapping = try!(take_access_checked(memory::READ, buffer));
-    sync(&mut mapping);
+    sync(&mut mapping, bytes);
 
     Ok(Reader {
-        slice: mapping.slice(buffer.len::<T>()),
+        slice: &mapping.slice(range.end)[range.start..],
         mapping: mapping,
     })
 }
 
 #[doc(hidden)]
-pub unsafe fn write<R, T, S>(buffer: &buffer::Raw<R>, sync: S)
+pub unsafe fn write<R, T, S>(buffer: &buffer::Raw<R>, range: Range<usize>, sync: S)
                              -> Result<Writer<R, T>, Error>
-    where R: Resources, T: Copy, S: FnOnce(&mut R::Mapping)
+    where R: Resources, T: Copy, S: FnOnce(&mut R::Mapping, Range<usize>)
 {
+    let bytes = try!(byte_range::<R, T>(buffer, &range));
     let mut mapping = try!(take_access_checked(memory::WRITE, buffer));
-    sync(&mut mapping);
+    sync(&mut mapping, bytes);
 
     Ok(Writer {
-        slice: mapping.mut_slice(buffer.len::<T>()),
+        slice: &mut mapping.mut_slice(range.end)[range.start..],
         mapping: mapping,
     })
 }
 
+/// Get exclusive access to the mapping for an explicit flush (`memory::WRITE`)
+/// or invalidation (`memory::READ`) of the given range of elements.
+#[doc(hidden)]
+pub unsafe fn sync<R, T, S>(buffer: &buffer::Raw<R>, range: Range<usize>,
+                            access: memory::Access, sync: S)
+                            -> Result<(), Error>
+    where R: Resources, T: Copy, S: FnOnce(&mut R::Mapping, Range<usize>)
+{
+    let bytes = try!(byte_range::<R, T>(buffer, &range));
+    let mut mapping = try!(take_access_checked(access, buffer));
+    sync(&mut mapping, bytes);
+    Ok(())
+}
+
 /// Mapping reader
 #[derive(Debug)]
 pub struct Reader<'a, R: Resources, T: 'a + Copy> {
@@ -216,7 +251,7 @@ impl<'a, R: Resources, T: 'a + Copy> DerefMut for Writer<'a, R, T> {
 #[derive(Debug, Eq, Hash, PartialEq)]
 #[doc(hidden)]
 pub struct Status<R: Resources> {
-    cpu_wrote: bool,
+    cpu_wrote: Option<Range<usize>>,
     gpu_access: Option<handle::Fence<R>>,
 }
 
@@ -224,7 +259,7 @@ pub struct Status<R: Resources> {
 impl<R: Resources> Status<R> {
     pub fn clean() -> Self {
         Status {
-            cpu_wrote: false,
+            cpu_wrote: None,
             gpu_access: None,
         }
     }
@@ -235,23 +270,37 @@ impl<R: Resources> Status<R> {
         self.gpu_access.take().map(wait_fence);
     }
 
-    pub fn cpu_write_access<F>(&mut self, wait_fence: F)
+    pub fn cpu_write_access<F>(&mut self, range: Range<usize>, wait_fence: F)
         where F: FnOnce(handle::Fence<R>)
     {
         self.cpu_access(wait_fence);
-        self.cpu_wrote = true;
+        self.cpu_wrote = Some(match self.cpu_wrote.take() {
+            Some(ref wrote) => cmp::min(wrote.start, range.start) ..
+                               cmp::max(wrote.end, range.end),
+            None => range,
+        });
     }
 
     pub fn gpu_access(&mut self, fence: handle::Fence<R>) {
         self.gpu_access = Some(fence);
     }
 
+    /// Notify that the given byte range got flushed explicitly.
+    pub fn flushed(&mut self, range: Range<usize>) {
+        let covered = match self.cpu_wrote {
+            Some(ref wrote) => range.start <= wrote.start && wrote.end <= range.end,
+            None => false,
+        };
+        if covered {
+            self.cpu_wrote = None;
+        }
+    }
+
     pub fn ensure_flushed<F>(&mut self, flush: F)
-        where F: FnOnce()
+        where F: FnOnce(Range<usize>)
     {
-        if self.cpu_wrote {
-            flush();
-            self.cpu_wrote = false;
+        if let Some(range) = self.cpu_wrote.take() {
+            flush(range);
         }
     }
 }
@@ -33,6 +33,11 @@ pub enum Usage {
     /// GPU to CPU data flow with mapping.
     /// Used for staging for download from GPU.
     Download,
+    /// CPU to GPU data flow with a persistent and coherent mapping.
+    /// The mapping is not synchronized with the GPU, so the application
+    /// has to avoid writing the ranges that are still in use by the GPU.
+    /// Used for streaming per-frame data through a ring buffer.
+    Persistent,
 }
 
 bitflags!(
@@ -26,7 +26,7 @@ use {ConstantBufferSlot, ColorSlot, ResourceViewSlot,
 use {format, state as s, texture};
 use shade::Usage;
 use std::error::Error;
-use std::fmt;
+use std::{fmt, hash};
 
 /// Maximum number of vertex buffers used in a PSO definition.
 pub const MAX_VERTEX_BUFFERS: usize = 4;
@@ -119,6 +119,84 @@ impl From<(s::Depth, s::Stencil)> for DepthStencilInfo {
     }
 }
 
+/// Multisampling state of the PSO.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct MultisampleInfo {
+    /// Derive the sample coverage from the alpha of the first color target.
+    /// Requires `Capabilities::alpha_to_coverage_supported`.
+    pub alpha_to_coverage: bool,
+    /// Bit mask of the samples that can be updated, all bits set by default.
+    /// Requires `Capabilities::sample_mask_supported` for any other value.
+    pub sample_mask: u32,
+}
+
+impl Default for MultisampleInfo {
+    fn default() -> MultisampleInfo {
+        MultisampleInfo {
+            alpha_to_coverage: false,
+            sample_mask: !0,
+        }
+    }
+}
+
+/// Depth bias of the PSO. Unlike the integer `Offset` of the rasterizer,
+/// it has fractional factors and can be clamped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DepthBias {
+    constant: f32,
+    slope_scaled: f32,
+    clamp: f32,
+}
+
+impl DepthBias {
+    /// Create a depth bias from its factors, or `None` if any of them is NaN.
+    ///
+    /// - `constant`: bias in the units of the minimum resolvable depth difference,
+    ///   rounded to an integer on DX11
+    /// - `slope_scaled`: bias scaled by the maximum depth slope of the primitive
+    /// - `clamp`: maximum magnitude of the resulting bias, or zero for no clamping.
+    ///   Requires `Capabilities::depth_bias_clamp_supported` for a non-zero value.
+    pub fn new(constant: f32, slope_scaled: f32, clamp: f32) -> Option<DepthBias> {
+        if constant.is_nan() || slope_scaled.is_nan() || clamp.is_nan() {
+            return None
+        }
+        // adding zero turns `-0.0` into `0.0`, so that equal biases hash the same
+        Some(DepthBias {
+            constant: constant + 0.0,
+            slope_scaled: slope_scaled + 0.0,
+            clamp: clamp + 0.0,
+        })
+    }
+
+    /// Get the constant bias, in the units of the minimum resolvable depth difference.
+    pub fn constant(&self) -> f32 {
+        self.constant
+    }
+
+    /// Get the bias scaled by the maximum depth slope of the primitive.
+    pub fn slope_scaled(&self) -> f32 {
+        self.slope_scaled
+    }
+
+    /// Get the maximum magnitude of the resulting bias, zero for no clamping.
+    pub fn clamp(&self) -> f32 {
+        self.clamp
+    }
+}
+
+// the factors are never NaN, so the comparison is an equivalence
+impl Eq for DepthBias {}
+
+impl hash::Hash for DepthBias {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.constant.to_bits().hash(state);
+        self.slope_scaled.to_bits().hash(state);
+        self.clamp.to_bits().hash(state);
+    }
+}
+
 /// Index of a vertex buffer.
 pub type BufferIndex = u8;
 /// Offset of an attribute from the start of the buffer, in bytes
@@ -162,6 +240,8 @@ pub type SamplerDesc = Usage;
 pub type ColorTargetDesc = (format::Format, ColorInfo);
 /// PSO depth-stencil target descriptor
 pub type DepthStencilDesc = (format::Format, DepthStencilInfo);
+/// PSO stream output descriptor: the stride of a captured vertex, in bytes
+pub type StreamOutputDesc = ElemStride;
 
 /// All the information surrounding a shader program that is required
 /// for PSO creation, including the formats of vertex buffers and pixel targets;
@@ -190,6 +270,15 @@ pub struct Descriptor {
     pub color_targets: [Option<ColorTargetDesc>; MAX_COLOR_TARGETS],
     /// Depth stencil view (DSV)
     pub depth_stencil: Option<DepthStencilDesc>,
+    /// Stream output buffer
+    pub stream_output: Option<StreamOutputDesc>,
+    /// Multisampling controls
+    pub multisample: MultisampleInfo,
+    /// Depth bias, taking over the polygon offset of the rasterizer
+    pub depth_bias: Option<DepthBias>,
+    /// Clamp the depth of the primitives to the depth range instead of clipping them.
+    /// Requires `Capabilities::depth_clamp_supported`.
+    pub depth_clamp: bool,
 }
 
 impl Descriptor {
@@ -207,6 +296,10 @@ impl Descriptor {
             samplers: [None; MAX_SAMPLERS],
             color_targets: [None; MAX_COLOR_TARGETS],
             depth_stencil: None,
+            stream_output: None,
+            multisample: MultisampleInfo::default(),
+            depth_bias: None,
+            depth_clamp: false,
         }
     }
 }
@@ -225,9 +318,22 @@ impl<R: Resources> VertexBufferSet<R> {
     }
 }
 
-/// A constant buffer run-time parameter for PSO.
+/// A byte range of a buffer, bound in place of the whole buffer.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-pub struct ConstantBufferParam<R: Resources>(pub R::Buffer, pub Usage, pub ConstantBufferSlot);
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct BufferRange {
+    /// Offset of the range, in bytes. Has to be a multiple of
+    /// `Limits::constant_buffer_offset_alignment` for constant buffers.
+    pub offset: BufferOffset,
+    /// Size of the range, in bytes
+    pub size: usize,
+}
+
+/// A constant buffer run-time parameter for PSO: the buffer, and
+/// optionally the range of it to bind, or the whole buffer if `None`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ConstantBufferParam<R: Resources>(pub R::Buffer, pub Usage, pub ConstantBufferSlot,
+                                             pub Option<BufferRange>);
 
 /// A shader resource view (SRV) run-time parameter for PSO.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -241,6 +347,11 @@ pub struct UnorderedViewParam<R: Resources>(pub R::UnorderedAccessView, pub Usag
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct SamplerParam<R: Resources>(pub R::Sampler, pub Usage, pub SamplerSlot);
 
+/// A stream output buffer run-time parameter for PSO: the buffer
+/// and the byte offset at which the captured vertices get written.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StreamOutputParam<R: Resources>(pub R::Buffer, pub BufferOffset);
+
 /// A complete set of render targets to be used for pixel export in PSO.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct PixelTargetSet<R: Resources> {
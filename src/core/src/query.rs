@@ -0,0 +1,91 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! GPU queries
+//!
+//! Queries let the application read back counters that the GPU gathers while
+//! executing the command buffers: passed samples, timestamps, and various
+//! pipeline statistics. Results are retrieved through `Device::get_query_result`
+//! without stalling, once the GPU has made them available.
+
+use std::error::Error;
+use std::fmt;
+
+/// A single counter of the graphics or compute pipeline.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum PipelineStatistic {
+    InputVertices,
+    InputPrimitives,
+    VertexShaderInvocations,
+    HullShaderInvocations,
+    DomainShaderInvocations,
+    GeometryShaderInvocations,
+    GeometryShaderPrimitives,
+    ClippingInvocations,
+    ClippingPrimitives,
+    PixelShaderInvocations,
+    ComputeShaderInvocations,
+}
+
+/// The kind of a query, determining what gets counted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Kind {
+    /// Number of samples passing the depth and stencil tests
+    /// between the query begin and end.
+    Occlusion,
+    /// GPU time in nanoseconds at which the query gets written.
+    Timestamp,
+    /// The value of a pipeline counter accumulated between the query begin and end.
+    PipelineStatistics(PipelineStatistic),
+    /// Number of primitives written into the stream output buffer
+    /// between the query begin and end.
+    PrimitivesWritten,
+}
+
+impl Kind {
+    /// Check if the query of this kind needs to enclose a range of commands
+    /// with `begin` and `end`, as opposed to being written once.
+    pub fn is_ranged(&self) -> bool {
+        match *self {
+            Kind::Occlusion | Kind::PipelineStatistics(_) | Kind::PrimitivesWritten => true,
+            Kind::Timestamp => false,
+        }
+    }
+}
+
+/// An error occurring at the query creation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CreationError {
+    /// The device doesn't support the requested kind of query.
+    Unsupported(Kind),
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::Unsupported(kind) => write!(f, "{}: {:?}", self.description(), kind),
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Unsupported(_) => "The query kind is not supported",
+        }
+    }
+}
@@ -18,6 +18,7 @@
 
 use std::{fmt, cmp, hash};
 use std::error::Error;
+use std::sync::Arc;
 use {Resources};
 use {AttributeSlot, ColorSlot, ConstantBufferSlot, ResourceViewSlot, SamplerSlot, UnorderedViewSlot};
 
@@ -128,10 +129,11 @@ pub enum Stage {
     Domain,
     Geometry,
     Pixel,
+    Compute,
 }
 
 /// A constant static array of all shader stages.
-pub const STAGES: [Stage; 5] = [Stage::Vertex, Stage::Hull, Stage::Domain, Stage::Geometry, Stage::Pixel];
+pub const STAGES: [Stage; 6] = [Stage::Vertex, Stage::Hull, Stage::Domain, Stage::Geometry, Stage::Pixel, Stage::Compute];
 
 // Describing program data
 
@@ -140,40 +142,139 @@ pub type Location = usize;
 
 // unable to derive anything for fixed arrays
 /// A value that can be uploaded to the device as a uniform.
+/// Non-square matrices are named after the GLSL `matCxR` types, with `C` columns of `R` rows.
+/// Arrays are shared, so that binding the same value for every draw call doesn't copy them.
 #[allow(missing_docs)]
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum UniformValue {
     I32(i32),
+    U32(u32),
     F32(f32),
+    F64(f64),
+    Bool(bool),
 
     I32Vector2([i32; 2]),
     I32Vector3([i32; 3]),
     I32Vector4([i32; 4]),
 
+    U32Vector2([u32; 2]),
+    U32Vector3([u32; 3]),
+    U32Vector4([u32; 4]),
+
     F32Vector2([f32; 2]),
     F32Vector3([f32; 3]),
     F32Vector4([f32; 4]),
 
+    F64Vector2([f64; 2]),
+    F64Vector3([f64; 3]),
+    F64Vector4([f64; 4]),
+
     F32Matrix2([[f32; 2]; 2]),
     F32Matrix3([[f32; 3]; 3]),
     F32Matrix4([[f32; 4]; 4]),
+
+    F32Matrix2x3([[f32; 3]; 2]),
+    F32Matrix2x4([[f32; 4]; 2]),
+    F32Matrix3x2([[f32; 2]; 3]),
+    F32Matrix3x4([[f32; 4]; 3]),
+    F32Matrix4x2([[f32; 2]; 4]),
+    F32Matrix4x3([[f32; 3]; 4]),
+
+    I32Array(Arc<[i32]>),
+    U32Array(Arc<[u32]>),
+    F32Array(Arc<[f32]>),
+    F32Vector2Array(Arc<[[f32; 2]]>),
+    F32Vector3Array(Arc<[[f32; 3]]>),
+    F32Vector4Array(Arc<[[f32; 4]]>),
+    F32Matrix4Array(Arc<[[[f32; 4]; 4]]>),
+}
+
+impl UniformValue {
+    /// Check if the value is an array, which can be shorter than the variable it's stored in.
+    pub fn is_array(&self) -> bool {
+        match *self {
+            UniformValue::I32Array(_) |
+            UniformValue::U32Array(_) |
+            UniformValue::F32Array(_) |
+            UniformValue::F32Vector2Array(_) |
+            UniformValue::F32Vector3Array(_) |
+            UniformValue::F32Vector4Array(_) |
+            UniformValue::F32Matrix4Array(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Get the format of a single element of the value, together with the number of
+    /// elements, which is only different from 1 for arrays. Matrix dimensions are
+    /// given as the number of columns, then rows.
+    pub fn get_format(&self) -> (ConstFormat, usize) {
+        use self::BaseType as B;
+        use self::ContainerType::{Single, Vector, Matrix};
+        let cm = MatrixFormat::ColumnMajor;
+        match *self {
+            UniformValue::I32(_)             => ((B::I32, Single), 1),
+            UniformValue::U32(_)             => ((B::U32, Single), 1),
+            UniformValue::F32(_)             => ((B::F32, Single), 1),
+            UniformValue::F64(_)             => ((B::F64, Single), 1),
+            UniformValue::Bool(_)            => ((B::Bool, Single), 1),
+            UniformValue::I32Vector2(_)      => ((B::I32, Vector(2)), 1),
+            UniformValue::I32Vector3(_)      => ((B::I32, Vector(3)), 1),
+            UniformValue::I32Vector4(_)      => ((B::I32, Vector(4)), 1),
+            UniformValue::U32Vector2(_)      => ((B::U32, Vector(2)), 1),
+            UniformValue::U32Vector3(_)      => ((B::U32, Vector(3)), 1),
+            UniformValue::U32Vector4(_)      => ((B::U32, Vector(4)), 1),
+            UniformValue::F32Vector2(_)      => ((B::F32, Vector(2)), 1),
+            UniformValue::F32Vector3(_)      => ((B::F32, Vector(3)), 1),
+            UniformValue::F32Vector4(_)      => ((B::F32, Vector(4)), 1),
+            UniformValue::F64Vector2(_)      => ((B::F64, Vector(2)), 1),
+            UniformValue::F64Vector3(_)      => ((B::F64, Vector(3)), 1),
+            UniformValue::F64Vector4(_)      => ((B::F64, Vector(4)), 1),
+            UniformValue::F32Matrix2(_)      => ((B::F32, Matrix(cm, 2, 2)), 1),
+            UniformValue::F32Matrix3(_)      => ((B::F32, Matrix(cm, 3, 3)), 1),
+            UniformValue::F32Matrix4(_)      => ((B::F32, Matrix(cm, 4, 4)), 1),
+            UniformValue::F32Matrix2x3(_)    => ((B::F32, Matrix(cm, 2, 3)), 1),
+            UniformValue::F32Matrix2x4(_)    => ((B::F32, Matrix(cm, 2, 4)), 1),
+            UniformValue::F32Matrix3x2(_)    => ((B::F32, Matrix(cm, 3, 2)), 1),
+            UniformValue::F32Matrix3x4(_)    => ((B::F32, Matrix(cm, 3, 4)), 1),
+            UniformValue::F32Matrix4x2(_)    => ((B::F32, Matrix(cm, 4, 2)), 1),
+            UniformValue::F32Matrix4x3(_)    => ((B::F32, Matrix(cm, 4, 3)), 1),
+            UniformValue::I32Array(ref v)    => ((B::I32, Single), v.len()),
+            UniformValue::U32Array(ref v)    => ((B::U32, Single), v.len()),
+            UniformValue::F32Array(ref v)    => ((B::F32, Single), v.len()),
+            UniformValue::F32Vector2Array(ref v) => ((B::F32, Vector(2)), v.len()),
+            UniformValue::F32Vector3Array(ref v) => ((B::F32, Vector(3)), v.len()),
+            UniformValue::F32Vector4Array(ref v) => ((B::F32, Vector(4)), v.len()),
+            UniformValue::F32Matrix4Array(ref v) => ((B::F32, Matrix(cm, 4, 4)), v.len()),
+        }
+    }
 }
 
 impl fmt::Debug for UniformValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             UniformValue::I32(x)            => write!(f, "ValueI32({:?})", x),
+            UniformValue::U32(x)            => write!(f, "ValueU32({:?})", x),
             UniformValue::F32(x)            => write!(f, "ValueF32({:?})", x),
+            UniformValue::F64(x)            => write!(f, "ValueF64({:?})", x),
+            UniformValue::Bool(x)           => write!(f, "ValueBool({:?})", x),
 
             UniformValue::I32Vector2(ref v) => write!(f, "ValueI32Vector2({:?})", &v[..]),
             UniformValue::I32Vector3(ref v) => write!(f, "ValueI32Vector3({:?})", &v[..]),
             UniformValue::I32Vector4(ref v) => write!(f, "ValueI32Vector4({:?})", &v[..]),
 
+            UniformValue::U32Vector2(ref v) => write!(f, "ValueU32Vector2({:?})", &v[..]),
+            UniformValue::U32Vector3(ref v) => write!(f, "ValueU32Vector3({:?})", &v[..]),
+            UniformValue::U32Vector4(ref v) => write!(f, "ValueU32Vector4({:?})", &v[..]),
+
             UniformValue::F32Vector2(ref v) => write!(f, "ValueF32Vector2({:?})", &v[..]),
             UniformValue::F32Vector3(ref v) => write!(f, "ValueF32Vector3({:?})", &v[..]),
             UniformValue::F32Vector4(ref v) => write!(f, "ValueF32Vector4({:?})", &v[..]),
 
+            UniformValue::F64Vector2(ref v) => write!(f, "ValueF64Vector2({:?})", &v[..]),
+            UniformValue::F64Vector3(ref v) => write!(f, "ValueF64Vector3({:?})", &v[..]),
+            UniformValue::F64Vector4(ref v) => write!(f, "ValueF64Vector4({:?})", &v[..]),
+
             UniformValue::F32Matrix2(ref m) => {
                 try!(write!(f, "ValueF32Matrix2("));
                 for v in m.iter() {
@@ -195,6 +296,21 @@ impl fmt::Debug for UniformValue {
                 }
                 write!(f, ")")
             },
+
+            UniformValue::F32Matrix2x3(ref m) => write!(f, "ValueF32Matrix2x3({:?})", m),
+            UniformValue::F32Matrix2x4(ref m) => write!(f, "ValueF32Matrix2x4({:?})", m),
+            UniformValue::F32Matrix3x2(ref m) => write!(f, "ValueF32Matrix3x2({:?})", m),
+            UniformValue::F32Matrix3x4(ref m) => write!(f, "ValueF32Matrix3x4({:?})", m),
+            UniformValue::F32Matrix4x2(ref m) => write!(f, "ValueF32Matrix4x2({:?})", m),
+            UniformValue::F32Matrix4x3(ref m) => write!(f, "ValueF32Matrix4x3({:?})", m),
+
+            UniformValue::I32Array(ref v)        => write!(f, "ValueI32Array({:?})", v),
+            UniformValue::U32Array(ref v)        => write!(f, "ValueU32Array({:?})", v),
+            UniformValue::F32Array(ref v)        => write!(f, "ValueF32Array({:?})", v),
+            UniformValue::F32Vector2Array(ref v) => write!(f, "ValueF32Vector2Array({:?})", v),
+            UniformValue::F32Vector3Array(ref v) => write!(f, "ValueF32Vector3Array({:?})", v),
+            UniformValue::F32Vector4Array(ref v) => write!(f, "ValueF32Vector4Array({:?})", v),
+            UniformValue::F32Matrix4Array(ref v) => write!(f, "ValueF32Matrix4Array({:?})", v),
         }
     }
 }
@@ -331,6 +447,8 @@ bitflags!(
         const HULL    = 0x8,
         /// Used by the pixel shader
         const DOMAIN    = 0x16,
+        /// Used by the compute shader
+        const COMPUTE   = 0x20,
 
     }
 );
@@ -343,6 +461,7 @@ impl From<Stage> for Usage {
             Stage::Pixel => PIXEL,
             Stage::Hull => HULL,
             Stage::Domain => DOMAIN,
+            Stage::Compute => COMPUTE,
         }
     }
 }
@@ -377,6 +496,20 @@ pub struct ConstVar {
     pub base_type: BaseType,
     /// "Scalarness" of this constant.
     pub container: ContainerType,
+    /// Memory layout of a constant buffer element, if known to the backend.
+    /// Always `None` for the free-standing constants.
+    pub layout: Option<ConstLayout>,
+}
+
+/// Memory layout of a constant buffer element.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ConstLayout {
+    /// Distance in bytes between the elements of an array, zero if it's not an array.
+    pub array_stride: usize,
+    /// Distance in bytes between the columns of a column-major matrix, or the rows of a
+    /// row-major one. Zero if it's not a matrix.
+    pub matrix_stride: usize,
 }
 
 /// A constant buffer.
@@ -395,6 +528,87 @@ pub struct ConstantBufferVar {
     pub elements: Vec<ConstVar>,
 }
 
+impl ConstantBufferVar {
+    /// Build the tree of the buffer members, splitting the element names
+    /// on the structure fields and the array indices.
+    pub fn members(&self) -> Vec<ConstMember> {
+        let mut members = Vec::new();
+        for (i, el) in self.elements.iter().enumerate() {
+            let path = el.name.split('.').collect::<Vec<_>>();
+            insert_member(&mut members, &path, i);
+        }
+        members
+    }
+}
+
+fn split_index(segment: &str) -> (&str, Option<usize>) {
+    match segment.find('[') {
+        Some(pos) => {
+            let index = segment[pos+1 ..].split(']').next().and_then(|s| s.parse().ok());
+            (&segment[.. pos], index)
+        },
+        None => (segment, None),
+    }
+}
+
+fn insert_member(list: &mut Vec<ConstMember>, path: &[&str], element: usize) {
+    let (name, index) = split_index(path[0]);
+    if path.len() == 1 {
+        list.push(ConstMember {
+            name: name.to_string(),
+            index: None,
+            content: ConstMemberContent::Element(element),
+        });
+        return
+    }
+    let pos = match list.iter().position(|m| m.name == name && m.index == index && m.is_struct()) {
+        Some(pos) => pos,
+        None => {
+            list.push(ConstMember {
+                name: name.to_string(),
+                index: index,
+                content: ConstMemberContent::Struct(Vec::new()),
+            });
+            list.len() - 1
+        },
+    };
+    if let ConstMemberContent::Struct(ref mut children) = list[pos].content {
+        insert_member(children, &path[1..], element);
+    }
+}
+
+/// A member of a constant buffer, as a node in the tree of the nested structures.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ConstMember {
+    /// Name of this member inside the parent structure.
+    pub name: String,
+    /// Index of the structure in its array, if it's an array of structures.
+    pub index: Option<usize>,
+    /// What this member is made of.
+    pub content: ConstMemberContent,
+}
+
+impl ConstMember {
+    /// Check if this member is a structure.
+    pub fn is_struct(&self) -> bool {
+        match self.content {
+            ConstMemberContent::Struct(_) => true,
+            ConstMemberContent::Element(_) => false,
+        }
+    }
+}
+
+/// Content of a constant buffer member.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ConstMemberContent {
+    /// A basic constant, given by its index in the buffer elements.
+    Element(usize),
+    /// A structure with its own members.
+    Struct(Vec<ConstMember>),
+}
+
 /// Texture shader parameter.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -451,6 +665,20 @@ pub struct OutputVar {
     pub container: ContainerType,
 }
 
+/// Metadata about a varying captured by transform feedback.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct StreamOutputVar {
+    /// Name of this varying.
+    pub name: String,
+    /// Offset of the varying in the captured vertex, in bytes.
+    pub offset: u32,
+    /// Type of the varying component.
+    pub base_type: BaseType,
+    /// "Scalarness" of this varying.
+    pub container: ContainerType,
+}
+
 /// Metadata about a program.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -469,6 +697,8 @@ pub struct ProgramInfo {
     pub samplers: Vec<SamplerVar>,
     /// Output targets in the program
     pub outputs: Vec<OutputVar>,
+    /// Varyings captured into the stream output buffer, in the capture order
+    pub stream_outputs: Vec<StreamOutputVar>,
     /// A flag indicating that the pixel shader manually assigns the depth.
     pub output_depth: bool,
     /// A hacky flag to make sure the clients know we are
@@ -551,26 +781,21 @@ impl ConstVar {
     /// Whether a value is compatible with this variable. That is, whether the value can be stored
     /// in this variable.
     pub fn is_compatible(&self, value: &UniformValue) -> Result<(), CompatibilityError> {
-        if self.count != 1 {
+        let ((base_type, container), count) = value.get_format();
+        if count > self.count || (count < self.count && !value.is_array()) {
             return Err(CompatibilityError::ErrorArraySize)
         }
-        match (self.base_type, self.container, *value) {
-            (BaseType::I32, ContainerType::Single,         UniformValue::I32(_))        => Ok(()),
-            (BaseType::F32, ContainerType::Single,         UniformValue::F32(_))        => Ok(()),
-
-            (BaseType::F32, ContainerType::Vector(2),      UniformValue::F32Vector2(_)) => Ok(()),
-            (BaseType::F32, ContainerType::Vector(3),      UniformValue::F32Vector3(_)) => Ok(()),
-            (BaseType::F32, ContainerType::Vector(4),      UniformValue::F32Vector4(_)) => Ok(()),
-
-            (BaseType::I32, ContainerType::Vector(2),      UniformValue::I32Vector2(_)) => Ok(()),
-            (BaseType::I32, ContainerType::Vector(3),      UniformValue::I32Vector3(_)) => Ok(()),
-            (BaseType::I32, ContainerType::Vector(4),      UniformValue::I32Vector4(_)) => Ok(()),
-
-            (BaseType::F32, ContainerType::Matrix(_, 2,2), UniformValue::F32Matrix2(_)) => Ok(()),
-            (BaseType::F32, ContainerType::Matrix(_, 3,3), UniformValue::F32Matrix3(_)) => Ok(()),
-            (BaseType::F32, ContainerType::Matrix(_, 4,4), UniformValue::F32Matrix4(_)) => Ok(()),
-
-            _ => Err(CompatibilityError::ErrorBaseType)
+        match (self.base_type, base_type) {
+            // booleans can also be set from integers
+            (BaseType::Bool, BaseType::I32) |
+            (BaseType::Bool, BaseType::U32) => (),
+            (a, b) if a == b => (),
+            _ => return Err(CompatibilityError::ErrorBaseType),
+        }
+        match (self.container, container) {
+            (ContainerType::Matrix(_, c0, r0), ContainerType::Matrix(_, c1, r1)) if (c0, r0) == (c1, r1) => Ok(()),
+            (a, b) if a == b => Ok(()),
+            _ => Err(CompatibilityError::ErrorContainer),
         }
     }
 }
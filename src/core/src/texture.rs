@@ -343,6 +343,16 @@ impl<F> ImageInfoCommon<F> {
         max(1, self.depth) as usize
     }
 
+    /// Get the number of blocks of the given width and height, in texels,
+    /// needed to cover the image. Uncompressed formats have 1x1 blocks.
+    pub fn get_block_count(&self, (bw, bh): (u8, u8)) -> usize {
+        use std::cmp::max;
+        let (bw, bh) = (bw as usize, bh as usize);
+        (max(1, self.width) as usize + bw - 1) / bw *
+        ((max(1, self.height) as usize + bh - 1) / bh) *
+        max(1, self.depth) as usize
+    }
+
     /// Convert into a differently typed format.
     pub fn convert<T>(&self, new_format: T) -> ImageInfoCommon<T> {
         ImageInfoCommon {
@@ -369,8 +379,35 @@ impl<F> ImageInfoCommon<F> {
 impl RawImageInfo {
     /// Get the total number of bytes.
     pub fn get_byte_count(&self) -> usize {
-        let texel_bytes = self.format.0.get_total_bits() / 8;
-        self.get_texel_count() * (texel_bytes as usize)
+        let block_bytes = self.format.0.get_total_bits() / 8;
+        self.get_block_count(self.format.0.get_block_dim()) * (block_bytes as usize)
+    }
+}
+
+/// A region of a texture, serving as the source or the destination
+/// of a texture copy.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct TextureCopyRegion<T> {
+    /// Texture object to copy from or to.
+    pub texture: T,
+    /// Kind of the texture.
+    pub kind: Kind,
+    /// Face of a cube texture, if any.
+    pub cube_face: Option<CubeFace>,
+    /// Area and mipmap level to copy.
+    pub info: RawImageInfo,
+}
+
+impl<T> TextureCopyRegion<T> {
+    /// Replace the texture object, keeping the region.
+    pub fn convert<U>(&self, texture: U) -> TextureCopyRegion<U> {
+        TextureCopyRegion {
+            texture: texture,
+            kind: self.kind,
+            cube_face: self.cube_face,
+            info: self.info,
+        }
     }
 }
 
@@ -500,6 +537,26 @@ impl Info {
         let format = format::Format(self.format, cty.into());
         self.to_image_info(mip).convert(format)
     }
+
+    /// Estimate the number of bytes taken by all the levels and layers.
+    pub fn get_byte_count(&self) -> usize {
+        use std::cmp::max;
+        let (_, _, layers, aa) = self.kind.get_dimensions();
+        let is_volume = match self.kind {
+            Kind::D3(..) => true,
+            _ => false,
+        };
+        let block_bytes = self.format.get_total_bits() as usize / 8;
+        let blocks: usize = (0 .. self.levels).map(|mip| {
+            let mut info = self.to_image_info(mip);
+            // array layers and cube faces are not reduced by the mipmap chain
+            if !is_volume {
+                info.depth = max(1, layers);
+            }
+            info.get_block_count(self.format.get_block_dim())
+        }).sum();
+        blocks * block_bytes * aa.get_num_fragments() as usize
+    }
 }
 
 /// Texture resource view descriptor.
@@ -524,6 +581,15 @@ pub struct RenderDesc {
     pub layer: Option<Layer>,
 }
 
+/// Texture unordered access view descriptor.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct UnorderedDesc {
+    pub channel: format::ChannelType,
+    pub level: Level,
+}
+
 bitflags!(
     /// Depth-stencil read-only flags
     #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -556,3 +622,62 @@ impl From<RenderDesc> for DepthStencilDesc {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use format::{ChannelType, Format, SurfaceType};
+    use memory::{Bind, Usage};
+    use super::{AaMode, ImageInfoCommon, Info, Kind, RawImageInfo, Size};
+
+    fn image(surface: SurfaceType, width: Size, height: Size, depth: Size) -> RawImageInfo {
+        ImageInfoCommon {
+            xoffset: 0,
+            yoffset: 0,
+            zoffset: 0,
+            width: width,
+            height: height,
+            depth: depth,
+            format: Format(surface, ChannelType::Unorm),
+            mipmap: 0,
+        }
+    }
+
+    #[test]
+    fn test_image_block_count() {
+        assert_eq!(image(SurfaceType::R8_G8_B8_A8, 5, 3, 0).get_block_count((1, 1)), 15);
+        assert_eq!(image(SurfaceType::BC1_R8_G8_B8_A1, 8, 4, 0).get_block_count((4, 4)), 2);
+        // partial blocks are counted whole
+        assert_eq!(image(SurfaceType::BC1_R8_G8_B8_A1, 10, 6, 0).get_block_count((4, 4)), 6);
+        assert_eq!(image(SurfaceType::ASTC_10x8, 25, 17, 1).get_block_count((10, 8)), 9);
+        assert_eq!(image(SurfaceType::BC7_R8_G8_B8_A8, 1, 1, 3).get_block_count((4, 4)), 3);
+    }
+
+    #[test]
+    fn test_image_byte_count() {
+        assert_eq!(image(SurfaceType::R8_G8_B8_A8, 5, 3, 0).get_byte_count(), 60);
+        assert_eq!(image(SurfaceType::BC1_R8_G8_B8_A1, 10, 6, 0).get_byte_count(), 48);
+        assert_eq!(image(SurfaceType::BC3_R8_G8_B8_A8, 2, 2, 0).get_byte_count(), 16);
+        assert_eq!(image(SurfaceType::ETC2_R8_G8_B8, 5, 5, 0).get_byte_count(), 32);
+        assert_eq!(image(SurfaceType::EAC_R11_G11, 1, 1, 0).get_byte_count(), 16);
+        assert_eq!(image(SurfaceType::ASTC_12x10, 12, 10, 0).get_byte_count(), 16);
+        assert_eq!(image(SurfaceType::ASTC_10x8, 25, 17, 0).get_byte_count(), 144);
+    }
+
+    #[test]
+    fn test_texture_byte_count() {
+        let info = |kind, levels, format| Info {
+            kind: kind,
+            levels: levels,
+            format: format,
+            bind: Bind::empty(),
+            usage: Usage::Data,
+        };
+        // 10x6, 5x3 and 2x1 texels, covered by 3x2, 2x1 and 1x1 blocks
+        assert_eq!(info(Kind::D2(10, 6, AaMode::Single), 3, SurfaceType::BC3_R8_G8_B8_A8).get_byte_count(),
+                   9 * 16);
+        // each face of each level
+        assert_eq!(info(Kind::Cube(8), 2, SurfaceType::BC1_R8_G8_B8_A1).get_byte_count(), 30 * 8);
+        assert_eq!(info(Kind::D2Array(13, 13, 2, AaMode::Single), 1, SurfaceType::ASTC_6x6).get_byte_count(),
+                   2 * 9 * 16);
+    }
+}
@@ -91,6 +91,8 @@ impl Drop for Harness {
 
 pub trait Factory<R: gfx::Resources>: gfx::Factory<R> {
     type CommandBuffer: gfx::CommandBuffer<R>;
+    /// Create an encoder with a new command buffer. Encoders can be sent to other threads,
+    /// filled there and appended to the one flushed by the application with `Encoder::append`.
     fn create_encoder(&mut self) -> gfx::Encoder<R, Self::CommandBuffer>;
 }
 
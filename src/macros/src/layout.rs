@@ -0,0 +1,116 @@
+//! Memory layout rules of the GLSL uniform and storage blocks.
+
+/// Constant buffer member, as far as the layout rules are concerned.
+/// Matrices are seen as arrays of their column vectors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Member {
+    /// Scalar of the given size in bytes.
+    Scalar(usize),
+    /// Vector of the given number of scalars of the given size.
+    Vector(usize, usize),
+    /// Array of the given length.
+    Array(Box<Member>, usize),
+}
+
+impl Member {
+    /// Get the alignment and the size of the member in a `#[repr(C)]` structure.
+    pub fn rust_align_size(&self) -> (usize, usize) {
+        match *self {
+            Member::Scalar(size) => (size, size),
+            Member::Vector(count, size) => (size, count * size),
+            Member::Array(ref elem, len) => {
+                let (align, size) = elem.rust_align_size();
+                (align, size * len)
+            },
+        }
+    }
+}
+
+/// Standard block layout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rules {
+    Std140,
+    Std430,
+}
+
+impl Rules {
+    pub fn from_name(name: &str) -> Option<Rules> {
+        match name {
+            "std140" => Some(Rules::Std140),
+            "std430" => Some(Rules::Std430),
+            _ => None,
+        }
+    }
+
+    /// Get the base alignment and the size of the member in a block with this layout.
+    pub fn align_size(&self, member: &Member) -> (usize, usize) {
+        match *member {
+            Member::Scalar(size) => (size, size),
+            Member::Vector(2, size) => (2 * size, 2 * size),
+            Member::Vector(count, size) => (4 * size, count * size),
+            Member::Array(ref elem, len) => {
+                let (mut align, size) = self.align_size(elem);
+                if *self == Rules::Std140 {
+                    align = round_up(align, 16);
+                }
+                (align, round_up(size, align) * len)
+            },
+        }
+    }
+
+    /// Check that the fields of a `#[repr(C)]` structure are placed at the
+    /// same offsets as the block members, describing the first mismatch.
+    pub fn check(&self, fields: &[(String, Member)]) -> Result<(), String> {
+        let (mut rust_offset, mut block_offset) = (0, 0);
+        for &(ref name, ref member) in fields {
+            let (rust_align, rust_size) = member.rust_align_size();
+            let (block_align, block_size) = self.align_size(member);
+            rust_offset = round_up(rust_offset, rust_align);
+            block_offset = round_up(block_offset, block_align);
+            if rust_offset != block_offset {
+                return Err(format!("field `{}` is at offset {} but {:?} places it at {}, padding is needed",
+                    name, rust_offset, self, block_offset));
+            }
+            if rust_size != block_size {
+                return Err(format!("field `{}` takes {} bytes but {} bytes with {:?}, \
+                    the array elements need to be padded", name, rust_size, block_size, self));
+            }
+            rust_offset += rust_size;
+            block_offset += block_size;
+        }
+        Ok(())
+    }
+}
+
+fn round_up(value: usize, align: usize) -> usize {
+    (value + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Member, Rules};
+
+    fn fields(members: &[Member]) -> Vec<(String, Member)> {
+        members.iter().enumerate().map(|(i, m)| (format!("f{}", i), m.clone())).collect()
+    }
+
+    #[test]
+    fn vec3_alignment() {
+        let ok = fields(&[Member::Vector(3, 4), Member::Scalar(4), Member::Vector(2, 4)]);
+        assert_eq!(Rules::Std140.check(&ok), Ok(()));
+        let bad = fields(&[Member::Scalar(4), Member::Vector(3, 4)]);
+        assert!(Rules::Std140.check(&bad).is_err());
+        assert!(Rules::Std430.check(&bad).is_err());
+    }
+
+    #[test]
+    fn scalar_arrays() {
+        let array = fields(&[Member::Array(Box::new(Member::Scalar(4)), 8)]);
+        assert!(Rules::Std140.check(&array).is_err());
+        assert_eq!(Rules::Std430.check(&array), Ok(()));
+        let matrix = fields(&[Member::Array(Box::new(Member::Vector(4, 4)), 4)]);
+        assert_eq!(Rules::Std140.check(&matrix), Ok(()));
+        let mat3 = fields(&[Member::Array(Box::new(Member::Vector(3, 4)), 3)]);
+        assert!(Rules::Std430.check(&mat3).is_err());
+    }
+}
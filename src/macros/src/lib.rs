@@ -6,6 +6,8 @@ extern crate syn;
 
 use proc_macro::TokenStream;
 
+mod layout;
+
 
 #[proc_macro_derive(VertexData)]
 pub fn vertex(input: TokenStream) -> TokenStream {
@@ -15,10 +17,16 @@ pub fn vertex(input: TokenStream) -> TokenStream {
     gen.parse().unwrap()
 }
 
-#[proc_macro_derive(ConstantBuffer)]
+/// Derives `Structure` for a constant buffer. With `#[gfx(layout = "std140")]` or
+/// `#[gfx(layout = "std430")]`, the `#[repr(C)]` structure is also checked to match
+/// the block layout, failing to compile when an explicit padding field is needed.
+#[proc_macro_derive(ConstantBuffer, attributes(gfx))]
 pub fn constant(input: TokenStream) -> TokenStream {
     let s = input.to_string();
     let ast = syn::parse_macro_input(&s).unwrap();
+    if let Some(rules) = find_layout(&ast.attrs) {
+        check_layout(&ast, rules);
+    }
     let gen = structure(ast, quote!(gfx::shade::Formatted), quote!(gfx::shade::ConstFormat));
     gen.parse().unwrap()
 }
@@ -73,3 +81,96 @@ fn structure(ast: syn::DeriveInput, ty_compile: quote::Tokens, ty_run: quote::To
         }
     }
 }
+
+fn find_layout(attrs: &[syn::Attribute]) -> Option<layout::Rules> {
+    for attr in attrs {
+        let items = match attr.value {
+            syn::MetaItem::List(ref ident, ref items) if ident.as_ref() == "gfx" => items,
+            _ => continue,
+        };
+        for item in items {
+            match *item {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref ident, syn::Lit::Str(ref value, _)))
+                    if ident.as_ref() == "layout" => match layout::Rules::from_name(value) {
+                        Some(rules) => return Some(rules),
+                        None => panic!("unknown block layout `{}`, expected `std140` or `std430`", value),
+                    },
+                _ => panic!("unexpected gfx attribute, expected `layout = \"std140\"` or `layout = \"std430\"`"),
+            }
+        }
+    }
+    None
+}
+
+fn is_repr_c(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| match attr.value {
+        syn::MetaItem::List(ref ident, ref items) if ident.as_ref() == "repr" =>
+            items.iter().any(|item| match *item {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref word)) => word.as_ref() == "C",
+                _ => false,
+            }),
+        _ => false,
+    })
+}
+
+fn layout_member(ty: &syn::Ty) -> Result<layout::Member, String> {
+    use layout::Member;
+    let unknown = || Err(format!("unable to find the block layout of `{}`", quote!(#ty)));
+    match *ty {
+        syn::Ty::Array(ref elem, syn::ConstExpr::Lit(syn::Lit::Int(len, _))) => {
+            let len = len as usize;
+            Ok(match try!(layout_member(elem)) {
+                Member::Scalar(size) if len >= 2 && len <= 4 => Member::Vector(len, size),
+                member => Member::Array(Box::new(member), len),
+            })
+        },
+        syn::Ty::Path(None, ref path) => {
+            let segment = path.segments.last().unwrap();
+            // cgmath types are generic over their scalar
+            let scalar = match segment.parameters {
+                syn::PathParameters::AngleBracketed(ref data) => match data.types.first().map(layout_member) {
+                    Some(Ok(Member::Scalar(size))) => Some(size),
+                    Some(Err(e)) => return Err(e),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let column = |count| Member::Vector(count, scalar.unwrap_or(4));
+            match (segment.ident.as_ref(), scalar) {
+                ("f32", None) | ("i32", None) | ("u32", None) => Ok(Member::Scalar(4)),
+                ("f64", None) => Ok(Member::Scalar(8)),
+                ("bool", None) => Err("`bool` is not the size of a GLSL bool, use `u32` instead".to_string()),
+                ("Deg", Some(size)) | ("Rad", Some(size)) => Ok(Member::Scalar(size)),
+                ("Vector2", Some(_)) | ("Point2", Some(_)) => Ok(column(2)),
+                ("Vector3", Some(_)) | ("Point3", Some(_)) => Ok(column(3)),
+                ("Vector4", Some(_)) => Ok(column(4)),
+                ("Matrix2", Some(_)) => Ok(Member::Array(Box::new(column(2)), 2)),
+                ("Matrix3", Some(_)) => Ok(Member::Array(Box::new(column(3)), 3)),
+                ("Matrix4", Some(_)) => Ok(Member::Array(Box::new(column(4)), 4)),
+                _ => unknown(),
+            }
+        },
+        _ => unknown(),
+    }
+}
+
+fn check_layout(ast: &syn::DeriveInput, rules: layout::Rules) {
+    let name = &ast.ident;
+    if !is_repr_c(&ast.attrs) {
+        panic!("`{}` needs to be `#[repr(C)]` to be checked against the {:?} layout", name, rules);
+    }
+    let fields = match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref fields)) => fields,
+        _ => panic!("gfx-rs custom derives can only be casted on structs"),
+    };
+    let members = fields.iter().map(|field| {
+        let field_name = field.ident.as_ref().unwrap().to_string();
+        match layout_member(&field.ty) {
+            Ok(member) => (field_name, member),
+            Err(e) => panic!("`{}::{}`: {}", name, field_name, e),
+        }
+    }).collect::<Vec<_>>();
+    if let Err(e) = rules.check(&members) {
+        panic!("`{}`: {}", name, e);
+    }
+}
@@ -22,7 +22,7 @@ use std::any::Any;
 use std::{fmt, mem};
 
 use core::{Device, SubmissionResult, IndexType, Resources, VertexCount};
-use core::{command, format, handle, texture};
+use core::{command, format, handle, query, texture};
 use core::memory::{self, cast_slice, Typed, Pod, Usage};
 use slice;
 use pso;
@@ -44,6 +44,11 @@ pub enum CopyError<S, D> {
         dst_offset: usize,
         size: usize,
     },
+    SizeMismatch {
+        src: S,
+        dst: D,
+    },
+    Incompatible,
     NoSrcBindFlag,
     NoDstBindFlag,
 }
@@ -57,6 +62,9 @@ pub type CopyBufferTextureResult = Result<(), CopyError<usize, [texture::Size; 3
 /// Result type returned when copying texture data into a buffer.
 pub type CopyTextureBufferResult = Result<(), CopyError<[texture::Size; 3], usize>>;
 
+/// Result type returned when copying texture data into another texture.
+pub type CopyTextureResult = Result<(), CopyError<[texture::Size; 3], [texture::Size; 3]>>;
+
 impl<S, D> fmt::Display for CopyError<S, D>
     where S: fmt::Debug + fmt::Display, D: fmt::Debug + fmt::Display
 {
@@ -72,6 +80,8 @@ impl<S, D> fmt::Display for CopyError<S, D>
                        self.description(),
                        src_offset, src_offset + size,
                        dst_offset, dst_offset + size),
+            SizeMismatch { ref src, ref dst } =>
+                write!(f, "{}: {:?} to {:?}", self.description(), src, dst),
             _ => write!(f, "{}", self.description())
         }
     }
@@ -86,6 +96,8 @@ impl<S, D> Error for CopyError<S, D>
             OutOfSrcBounds {..} => "Copy source is out of bounds",
             OutOfDstBounds {..} => "Copy destination is out of bounds",
             Overlap {..} => "Copy source and destination are overlapping",
+            SizeMismatch {..} => "Copy source and destination have different sizes",
+            Incompatible => "Copy source and destination have incompatible formats or sample counts",
             NoSrcBindFlag => "Copy source is missing `TRANSFER_SRC`",
             NoDstBindFlag => "Copy destination is missing `TRANSFER_DST`",
         }
@@ -107,6 +119,72 @@ pub enum UpdateError<T> {
     InvalidUsage(Usage),
 }
 
+/// An error occuring in indirect draws.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawError {
+    /// The records of the draws are out of the argument buffer.
+    OutOfBounds {
+        /// Number of records in the buffer
+        size: usize,
+        /// End of the records of the draws
+        draw_end: usize,
+    },
+    /// Indexed draws require an index buffer, which `IndexBuffer::Auto` is not.
+    NoIndexBuffer,
+}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DrawError::OutOfBounds { size, draw_end } =>
+                write!(f, "{}: {} / {}", self.description(), draw_end, size),
+            DrawError::NoIndexBuffer => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for DrawError {
+    fn description(&self) -> &str {
+        match *self {
+            DrawError::OutOfBounds {..} => "Indirect draw records are out of the argument buffer",
+            DrawError::NoIndexBuffer => "Indexed indirect draws require an index buffer",
+        }
+    }
+}
+
+/// Check that the records `first .. first + count` are in a buffer of `size` records.
+fn check_indirect_records(size: usize, first: usize, count: usize) -> Result<(), DrawError> {
+    match first.checked_add(count) {
+        Some(end) if end <= size => Ok(()),
+        end => Err(DrawError::OutOfBounds {
+            size: size,
+            draw_end: end.unwrap_or(usize::max_value()),
+        }),
+    }
+}
+
+/// Check that a region fits into the selected mipmap level of a texture,
+/// returning the level dimensions and the region end otherwise.
+fn check_texture_region<R: Resources>(tex: &handle::RawTexture<R>, info: &texture::RawImageInfo)
+                                      -> Result<(), ([texture::Size; 3], [texture::Size; 3])> {
+    let (w, h, d, aa) = tex.get_info().kind.get_level_dimensions(info.mipmap);
+    if info.mipmap < tex.get_info().levels && info.is_inside((w, h, d, aa)) {
+        Ok(())
+    } else {
+        Err(([w, h, d], [info.xoffset + info.width,
+                         info.yoffset + info.height,
+                         info.zoffset + info.depth]))
+    }
+}
+
+fn is_depth_stencil(surface: format::SurfaceType) -> bool {
+    use core::format::SurfaceType::*;
+    match surface {
+        D16 | D24 | D24_S8 | D32 => true,
+        _ => false,
+    }
+}
+
 fn check_update_usage<T>(usage: Usage) -> Result<(), UpdateError<T>> {
     if usage == Usage::Dynamic {
         Ok(())
@@ -158,6 +236,7 @@ pub struct Encoder<R: Resources, C> {
     raw_pso_data: pso::RawDataSet<R>,
     access_info: command::AccessInfo<R>,
     handles: handle::Manager<R>,
+    debug_groups: usize,
 }
 
 impl<R: Resources, C> From<C> for Encoder<R, C> {
@@ -167,6 +246,7 @@ impl<R: Resources, C> From<C> for Encoder<R, C> {
             raw_pso_data: pso::RawDataSet::new(),
             access_info: command::AccessInfo::new(),
             handles: handle::Manager::new(),
+            debug_groups: 0,
         }
     }
 }
@@ -190,6 +270,7 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
     pub fn flush_no_reset<D>(&mut self, device: &mut D) -> SubmissionResult<()>
         where D: Device<Resources=R, CommandBuffer=C>
     {
+        self.close_debug_groups();
         device.pin_submitted_resources(&self.handles);
         device.submit(&mut self.command_buffer, &self.access_info)
     }
@@ -201,6 +282,7 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
                                     -> SubmissionResult<handle::Fence<R>>
         where D: Device<Resources=R, CommandBuffer=C>
     {
+        self.close_debug_groups();
         device.pin_submitted_resources(&self.handles);
         device.fenced_submit(&mut self.command_buffer, &self.access_info, after)
     }
@@ -210,6 +292,31 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         self.command_buffer.reset();
         self.access_info.clear();
         self.handles.clear();
+        self.debug_groups = 0;
+    }
+
+    /// Closes the debug groups left open, so that they don't span several submissions.
+    fn close_debug_groups(&mut self) {
+        for _ in 0 .. self.debug_groups {
+            self.command_buffer.pop_debug_group();
+        }
+        self.debug_groups = 0;
+    }
+
+    /// Moves the commands of another `Encoder` to the end of this one, leaving it empty.
+    ///
+    /// Encoders can be filled on separate threads, then appended in the order their commands
+    /// have to be executed and flushed at once, so that the mapped buffers are only checked for
+    /// overlapping accesses a single time. The Metal and Vulkan backends can't append,
+    /// in which case both encoders are left untouched, and the other one has to be
+    /// flushed on its own.
+    pub fn append(&mut self, other: &mut Encoder<R, C>) -> Result<(), command::AppendError> {
+        try!(self.command_buffer.append(&other.command_buffer));
+        self.access_info.extend(&other.access_info);
+        self.handles.extend(&other.handles);
+        self.debug_groups += other.debug_groups;
+        other.reset();
+        Ok(())
     }
 
     /// Copy part of a buffer to another
@@ -344,6 +451,143 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         Ok(())
     }
 
+    /// Copy part of a texture to another one. The textures need to have
+    /// the same number of bits per texel and of samples, and the regions
+    /// the same dimensions.
+    pub fn copy_texture(
+        &mut self, src: &handle::RawTexture<R>,
+        src_face: Option<texture::CubeFace>, src_info: texture::RawImageInfo,
+        dst: &handle::RawTexture<R>,
+        dst_face: Option<texture::CubeFace>, dst_info: texture::RawImageInfo)
+        -> CopyTextureResult
+    {
+        let (src_desc, dst_desc) = (src.get_info(), dst.get_info());
+        if !src_desc.bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst_desc.bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        if src_desc.format.get_total_bits() != dst_desc.format.get_total_bits() ||
+           src_desc.format.get_block_dim() != dst_desc.format.get_block_dim() ||
+           src_desc.kind.get_dimensions().3 != dst_desc.kind.get_dimensions().3 {
+            return Err(CopyError::Incompatible);
+        }
+        let src_size = [src_info.width, src_info.height, src_info.depth];
+        let dst_size = [dst_info.width, dst_info.height, dst_info.depth];
+        if src_size != dst_size {
+            return Err(CopyError::SizeMismatch {
+                src: src_size,
+                dst: dst_size,
+            });
+        }
+        try!(check_texture_region(src, &src_info).map_err(|(size, copy_end)|
+            CopyError::OutOfSrcBounds { size: size, copy_end: copy_end }));
+        try!(check_texture_region(dst, &dst_info).map_err(|(size, copy_end)|
+            CopyError::OutOfDstBounds { size: size, copy_end: copy_end }));
+
+        let src_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(src).clone(),
+            kind: src_desc.kind,
+            cube_face: src_face,
+            info: src_info,
+        };
+        let dst_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(dst).clone(),
+            kind: dst_desc.kind,
+            cube_face: dst_face,
+            info: dst_info,
+        };
+        self.command_buffer.copy_texture(src_region, dst_region);
+        Ok(())
+    }
+
+    /// Resolve a multisampled texture into a single-sampled one of the same
+    /// format. The region given by `info` is the same in both textures, with
+    /// the mipmap level only applying to `dst`.
+    pub fn resolve(&mut self, src: &handle::RawTexture<R>, dst: &handle::RawTexture<R>,
+                   info: texture::RawImageInfo) -> CopyTextureResult {
+        let (src_desc, dst_desc) = (src.get_info(), dst.get_info());
+        if !src_desc.bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst_desc.bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        if src_desc.format != dst_desc.format ||
+           !src_desc.kind.get_dimensions().3.needs_resolve() ||
+           dst_desc.kind.get_dimensions().3.needs_resolve() {
+            return Err(CopyError::Incompatible);
+        }
+        let src_info = texture::RawImageInfo { mipmap: 0, ..info };
+        try!(check_texture_region(src, &src_info).map_err(|(size, copy_end)|
+            CopyError::OutOfSrcBounds { size: size, copy_end: copy_end }));
+        try!(check_texture_region(dst, &info).map_err(|(size, copy_end)|
+            CopyError::OutOfDstBounds { size: size, copy_end: copy_end }));
+
+        let src_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(src).clone(),
+            kind: src_desc.kind,
+            cube_face: None,
+            info: src_info,
+        };
+        let dst_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(dst).clone(),
+            kind: dst_desc.kind,
+            cube_face: None,
+            info: info,
+        };
+        self.command_buffer.resolve_texture(src_region, dst_region);
+        Ok(())
+    }
+
+    /// Copy part of a single-sampled texture to another one, scaling it to the
+    /// destination region. Only `FilterMethod::Scale`, the nearest texel, and
+    /// `FilterMethod::Bilinear` are meaningful, and depth/stencil textures need
+    /// the same format on both sides and the former filter.
+    pub fn blit(
+        &mut self, src: &handle::RawTexture<R>,
+        src_face: Option<texture::CubeFace>, src_info: texture::RawImageInfo,
+        dst: &handle::RawTexture<R>,
+        dst_face: Option<texture::CubeFace>, dst_info: texture::RawImageInfo,
+        filter: texture::FilterMethod)
+        -> CopyTextureResult
+    {
+        let (src_desc, dst_desc) = (src.get_info(), dst.get_info());
+        if !src_desc.bind.contains(memory::TRANSFER_SRC) {
+            return Err(CopyError::NoSrcBindFlag);
+        }
+        if !dst_desc.bind.contains(memory::TRANSFER_DST) {
+            return Err(CopyError::NoDstBindFlag);
+        }
+        let depth_stencil = is_depth_stencil(src_desc.format) || is_depth_stencil(dst_desc.format);
+        if src_desc.kind.get_dimensions().3.needs_resolve() ||
+           dst_desc.kind.get_dimensions().3.needs_resolve() ||
+           src_desc.format.is_compressed() || dst_desc.format.is_compressed() ||
+           (depth_stencil && (src_desc.format != dst_desc.format || filter != texture::FilterMethod::Scale)) {
+            return Err(CopyError::Incompatible);
+        }
+        try!(check_texture_region(src, &src_info).map_err(|(size, copy_end)|
+            CopyError::OutOfSrcBounds { size: size, copy_end: copy_end }));
+        try!(check_texture_region(dst, &dst_info).map_err(|(size, copy_end)|
+            CopyError::OutOfDstBounds { size: size, copy_end: copy_end }));
+
+        let src_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(src).clone(),
+            kind: src_desc.kind,
+            cube_face: src_face,
+            info: src_info,
+        };
+        let dst_region = texture::TextureCopyRegion {
+            texture: self.handles.ref_texture(dst).clone(),
+            kind: dst_desc.kind,
+            cube_face: dst_face,
+            info: dst_info,
+        };
+        self.command_buffer.blit_texture(src_region, dst_region, filter);
+        Ok(())
+    }
+
     /// Update a buffer with a slice of data.
     pub fn update_buffer<T: Pod>(&mut self, buf: &handle::Buffer<R, T>,
                          data: &[T], offset_elements: usize)
@@ -394,7 +638,7 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         if data.is_empty() { return Ok(()); }
         try!(check_update_usage(tex.raw().get_info().usage));
 
-        let target_count = img.get_texel_count();
+        let target_count = img.get_block_count(S::get_surface_type().get_block_dim());
         if target_count != data.len() {
             return Err(UpdateError::UnitCountMismatch {
                 target: target_count,
@@ -462,9 +706,8 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         self.command_buffer.clear_depth_stencil(target, None, Some(stencil))
     }
 
-    /// Draws a `slice::Slice` using a pipeline state object, and its matching `Data` structure.
-    pub fn draw<D: pso::PipelineData<R>>(&mut self, slice: &slice::Slice<R>,
-                pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+    fn bind_pipeline<D: pso::PipelineData<R>>(&mut self,
+                     pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
     {
         let (pso, _) = self.handles.ref_pso(pipeline.get_handle());
         //TODO: make `raw_data` a member to this struct, to re-use the heap allocation
@@ -473,16 +716,136 @@ impl<R: Resources, C: command::Buffer<R>> Encoder<R, C> {
         self.command_buffer.bind_pixel_targets(self.raw_pso_data.pixel_targets.clone());
         self.command_buffer.bind_pipeline_state(pso.clone());
         self.command_buffer.bind_vertex_buffers(self.raw_pso_data.vertex_buffers.clone());
+        self.command_buffer.bind_stream_output(self.raw_pso_data.stream_output.clone());
         self.command_buffer.set_ref_values(self.raw_pso_data.ref_values);
         self.command_buffer.set_scissor(self.raw_pso_data.scissor);
+        self.command_buffer.set_viewports(&self.raw_pso_data.viewports);
         self.command_buffer.bind_constant_buffers(&self.raw_pso_data.constant_buffers);
-        for &(location, value) in &self.raw_pso_data.global_constants {
-            self.command_buffer.bind_global_constant(location, value);
+        for &(location, ref value) in &self.raw_pso_data.global_constants {
+            self.command_buffer.bind_global_constant(location, value.clone());
         }
         self.command_buffer.bind_unordered_views(&self.raw_pso_data.unordered_views);
         //Note: it's important to bind RTV, DSV, and UAV before SRV
         self.command_buffer.bind_resource_views(&self.raw_pso_data.resource_views);
         self.command_buffer.bind_samplers(&self.raw_pso_data.samplers);
+    }
+
+    /// Draws a `slice::Slice` using a pipeline state object, and its matching `Data` structure.
+    pub fn draw<D: pso::PipelineData<R>>(&mut self, slice: &slice::Slice<R>,
+                pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+    {
+        self.bind_pipeline(pipeline, user_data);
         self.draw_slice(slice, slice.instances);
     }
+
+    /// Draws `count` batches of primitives, with the arguments of each batch read by the GPU
+    /// from the `args` buffer, starting at the record `first`.
+    /// Requires `Capabilities::draw_indirect_supported`.
+    pub fn draw_indirect<D: pso::PipelineData<R>>(&mut self,
+                         args: &handle::Buffer<R, command::DrawIndirectArgs>,
+                         first: usize, count: usize,
+                         pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+                         -> Result<(), DrawError>
+    {
+        try!(check_indirect_records(args.len(), first, count));
+        self.bind_pipeline(pipeline, user_data);
+        self.access_info.buffer_read(args.raw());
+        let offset = first * mem::size_of::<command::DrawIndirectArgs>();
+        self.command_buffer.call_draw_indirect(
+            self.handles.ref_buffer(args.raw()).clone(), offset, count);
+        Ok(())
+    }
+
+    /// Draws `count` batches of indexed primitives, with the arguments of each batch
+    /// read by the GPU from the `args` buffer, starting at the record `first`.
+    /// Requires `Capabilities::draw_indirect_supported`, and an index buffer.
+    pub fn draw_indexed_indirect<D: pso::PipelineData<R>>(&mut self,
+                                 index: &slice::IndexBuffer<R>,
+                                 args: &handle::Buffer<R, command::DrawIndexedIndirectArgs>,
+                                 first: usize, count: usize,
+                                 pipeline: &pso::PipelineState<R, D::Meta>, user_data: &D)
+                                 -> Result<(), DrawError>
+    {
+        try!(check_indirect_records(args.len(), first, count));
+        let (buf, ty) = match *index {
+            slice::IndexBuffer::Auto => return Err(DrawError::NoIndexBuffer),
+            slice::IndexBuffer::Index16(ref buf) => (buf.raw(), IndexType::U16),
+            slice::IndexBuffer::Index32(ref buf) => (buf.raw(), IndexType::U32),
+        };
+        self.bind_pipeline(pipeline, user_data);
+        self.access_info.buffer_read(buf);
+        self.command_buffer.bind_index(self.handles.ref_buffer(buf).clone(), ty);
+        self.access_info.buffer_read(args.raw());
+        let offset = first * mem::size_of::<command::DrawIndexedIndirectArgs>();
+        self.command_buffer.call_draw_indexed_indirect(
+            self.handles.ref_buffer(args.raw()).clone(), offset, count);
+        Ok(())
+    }
+
+    /// Dispatches `x * y * z` compute thread groups using a compute pipeline state object,
+    /// and its matching `Data` structure.
+    pub fn dispatch<D: pso::PipelineData<R>>(&mut self, pipeline: &pso::PipelineState<R, D::Meta>,
+                    user_data: &D, x: command::ThreadGroupCount, y: command::ThreadGroupCount,
+                    z: command::ThreadGroupCount)
+    {
+        let (pso, _) = self.handles.ref_pso(pipeline.get_handle());
+        self.raw_pso_data.clear();
+        user_data.bake_to(&mut self.raw_pso_data, pipeline.get_meta(), &mut self.handles, &mut self.access_info);
+        self.command_buffer.bind_pipeline_state(pso.clone());
+        self.command_buffer.bind_constant_buffers(&self.raw_pso_data.constant_buffers);
+        for &(location, ref value) in &self.raw_pso_data.global_constants {
+            self.command_buffer.bind_global_constant(location, value.clone());
+        }
+        self.command_buffer.bind_unordered_views(&self.raw_pso_data.unordered_views);
+        self.command_buffer.bind_resource_views(&self.raw_pso_data.resource_views);
+        self.command_buffer.bind_samplers(&self.raw_pso_data.samplers);
+        self.command_buffer.call_dispatch(x, y, z);
+    }
+
+    /// Starts counting into an occlusion, pipeline statistics or primitives written query.
+    /// The commands encoded until the matching `end_query` contribute to the result.
+    pub fn begin_query(&mut self, query: &handle::Query<R>) {
+        debug_assert!(query.get_kind().is_ranged(),
+            "Query {:?} can't enclose a range of commands", query.get_kind());
+        let q = *self.handles.ref_query(query);
+        self.command_buffer.begin_query(q);
+    }
+
+    /// Stops counting into an occlusion or pipeline statistics query.
+    pub fn end_query(&mut self, query: &handle::Query<R>) {
+        debug_assert!(query.get_kind().is_ranged(),
+            "Query {:?} can't enclose a range of commands", query.get_kind());
+        let q = *self.handles.ref_query(query);
+        self.command_buffer.end_query(q);
+    }
+
+    /// Writes the GPU time into a timestamp query, once all the previously
+    /// encoded commands are complete.
+    pub fn write_timestamp(&mut self, query: &handle::Query<R>) {
+        debug_assert_eq!(query.get_kind(), query::Kind::Timestamp);
+        let q = *self.handles.ref_query(query);
+        self.command_buffer.write_timestamp(q);
+    }
+
+    /// Opens a named group of commands, shown as a tree node by the graphics
+    /// debugging tools. Groups can be nested, and are closed by `pop_debug_group`,
+    /// or when the encoder is flushed.
+    pub fn push_debug_group(&mut self, name: &str) {
+        self.debug_groups += 1;
+        self.command_buffer.push_debug_group(name);
+    }
+
+    /// Closes the last group opened with `push_debug_group`.
+    /// Does nothing if every group is already closed.
+    pub fn pop_debug_group(&mut self) {
+        if self.debug_groups > 0 {
+            self.debug_groups -= 1;
+            self.command_buffer.pop_debug_group();
+        }
+    }
+
+    /// Inserts a named marker between the commands, for the graphics debugging tools.
+    pub fn insert_debug_marker(&mut self, name: &str) {
+        self.command_buffer.insert_debug_marker(name);
+    }
 }
@@ -175,6 +175,16 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
                            memory::TRANSFER_DST)
     }
 
+    /// Creates a persistently mapped buffer for `num` elements of type `T`,
+    /// to be used for streaming data with the given role.
+    ///
+    /// Requires `Capabilities::persistent_mapping_supported`.
+    fn create_persistent_buffer<T>(&mut self, num: usize, role: buffer::Role, bind: Bind)
+                                   -> Result<handle::Buffer<R, T>, buffer::CreationError>
+    {
+        self.create_buffer(num, role, memory::Usage::Persistent, bind)
+    }
+
     /// Creates a `ShaderSet` from the supplied vertex and pixel shader source code.
     fn create_shader_set(&mut self, vs_code: &[u8], ps_code: &[u8])
                          -> Result<ShaderSet<R>, ProgramError> {
@@ -234,6 +244,34 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
         Ok(ShaderSet::Tessellated(vs, hs, ds, ps))
     }
 
+    /// Creates a compute `ShaderSet` from the supplied compute shader source code.
+    fn create_shader_set_compute(&mut self, cs_code: &[u8])
+                         -> Result<ShaderSet<R>, ProgramError> {
+        let cs = match self.create_shader_compute(cs_code) {
+            Ok(s) => s,
+            Err(e) => return Err(ProgramError::Compute(e)),
+        };
+        Ok(ShaderSet::Compute(cs))
+    }
+
+    /// Creates a transform feedback `ShaderSet` from the supplied vertex and optional geometry
+    /// shader source code, capturing the named outputs into the stream output buffer.
+    fn create_shader_set_transform_feedback(&mut self, vs_code: &[u8], gs_code: Option<&[u8]>,
+                                            outputs: &[&str])
+                                            -> Result<ShaderSet<R>, ProgramError> {
+        let vs = match self.create_shader_vertex(vs_code) {
+            Ok(s) => s,
+            Err(e) => return Err(ProgramError::Vertex(e)),
+        };
+        let gs = match gs_code.map(|code| self.create_shader_geometry(code)) {
+            Some(Ok(s)) => Some(s),
+            Some(Err(e)) => return Err(ProgramError::Geometry(e)),
+            None => None,
+        };
+        let outputs = outputs.iter().map(|&name| name.to_owned()).collect();
+        Ok(ShaderSet::TransformFeedback(vs, gs, outputs))
+    }
+
     /// Creates a basic shader `Program` from the supplied vertex and pixel shader source code.
     fn link_program(&mut self, vs_code: &[u8], ps_code: &[u8])
                     -> Result<handle::Program<R>, ProgramError> {
@@ -265,11 +303,20 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
                                     primitive: Primitive, rasterizer: state::Rasterizer, init: I)
                                     -> Result<pso::PipelineState<R, I::Meta>, PipelineStateError<&'a str>>
     {
-        let mut descriptor = Descriptor::new(primitive, rasterizer);
+        self.create_pipeline_from_descriptor(program, Descriptor::new(primitive, rasterizer), init)
+    }
+
+    /// Creates a strongly typed `PipelineState` from its `Init` structure, a shader `Program` and
+    /// a base `Descriptor`, which carries the fixed-function state not covered by the `Init`, such
+    /// as the multisample controls, depth bias and depth clamp.
+    fn create_pipeline_from_descriptor<'a, I: pso::PipelineInit>(&mut self, program: &'a handle::Program<R>,
+                                       mut descriptor: Descriptor, init: I)
+                                       -> Result<pso::PipelineState<R, I::Meta>, PipelineStateError<&'a str>>
+    {
         let meta = try!(init.link_to(&mut descriptor, program.get_info()));
         let raw = try!(self.create_pipeline_state_raw(program, &descriptor));
 
-        Ok(pso::PipelineState::new(raw, primitive, meta))
+        Ok(pso::PipelineState::new(raw, descriptor.primitive, meta))
     }
 
     /// Creates a strongly typed `PipelineState` from its `Init` structure. Automatically creates a
@@ -283,6 +330,38 @@ pub trait FactoryExt<R: Resources>: Factory<R> {
                                    init)
     }
 
+    /// Creates a strongly typed compute `PipelineState` from its `Init` structure and a compute
+    /// shader `Program`. Only the resource components of the `Init` structure make sense here:
+    /// vertex inputs and pixel targets are never bound for a dispatch.
+    fn create_compute_pipeline_from_program<'a, I: pso::PipelineInit>(&mut self, program: &'a handle::Program<R>, init: I)
+                                            -> Result<pso::PipelineState<R, I::Meta>, PipelineStateError<&'a str>>
+    {
+        // the primitive and rasterizer are ignored by the device when dispatching
+        let primitive = Primitive::PointList;
+        let mut descriptor = Descriptor::new(primitive, state::Rasterizer::new_fill());
+        let meta = try!(init.link_to(&mut descriptor, program.get_info()));
+        let raw = try!(self.create_pipeline_state_raw(program, &descriptor));
+
+        Ok(pso::PipelineState::new(raw, primitive, meta))
+    }
+
+    /// Creates a strongly typed compute `PipelineState` from its `Init` structure, compiling
+    /// and linking the supplied compute shader source.
+    fn create_compute_pipeline<I: pso::PipelineInit>(&mut self, cs: &[u8], init: I)
+                               -> Result<pso::PipelineState<R, I::Meta>, PipelineStateError<String>>
+    {
+        let set = try!(self.create_shader_set_compute(cs));
+        let program = try!(self.create_program(&set).map_err(|e| ProgramError::Link(e)));
+        self.create_compute_pipeline_from_program(&program, init).map_err(|error| {
+            use self::PipelineStateError::*;
+            match error {
+                Program(e) => Program(e),
+                DescriptorInit(e) => DescriptorInit(e.into()),
+                DeviceCreate(e) => DeviceCreate(e),
+            }
+        })
+    }
+
     /// Create a linear sampler with clamping to border.
     fn create_sampler_linear(&mut self) -> handle::Sampler<R> {
         self.create_sampler(texture::SamplerInfo::new(
@@ -40,31 +40,37 @@ pub use draw_state::target::*;
 // public re-exports
 pub use core::{Device, Primitive, Resources, SubmissionError, SubmissionResult};
 pub use core::{VertexCount, InstanceCount};
-pub use core::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader};
-pub use core::{buffer, format, handle, texture, mapping};
+pub use core::{ShaderSet, VertexShader, HullShader, DomainShader, GeometryShader, PixelShader,
+               ComputeShader};
+pub use core::{buffer, command, format, handle, texture, mapping, query};
 pub use core::factory::{Factory, ResourceViewError, TargetViewError, CombinedError};
 pub use core::memory::{self, Bind, TRANSFER_SRC, TRANSFER_DST, RENDER_TARGET,
                        DEPTH_STENCIL, SHADER_RESOURCE, UNORDERED_ACCESS};
-pub use core::command::{Buffer as CommandBuffer, InstanceParams};
+pub use core::command::{Buffer as CommandBuffer, InstanceParams, ThreadGroupCount,
+                        DrawIndirectArgs, DrawIndexedIndirectArgs};
 pub use core::shade::{ProgramInfo, UniformValue};
 
 pub use encoder::{CopyBufferResult, CopyBufferTextureResult, CopyError,
-                  CopyTextureBufferResult, Encoder, UpdateError};
+                  CopyTextureBufferResult, CopyTextureResult, DrawError, Encoder, UpdateError};
 pub use factory::PipelineStateError;
+pub use ring::{RingBuffer, RingError, RingRange};
 pub use slice::{Slice, IntoIndexBuffer, IndexBuffer};
 pub use pso::{PipelineState};
 pub use pso::buffer::{VertexBuffer, InstanceBuffer, RawVertexBuffer,
-                      ConstantBuffer, RawConstantBuffer, Global, RawGlobal};
+                      ConstantBuffer, RingConstantBuffer, RawConstantBuffer,
+                      Global, RawGlobal, StreamOutput};
 pub use pso::resource::{ShaderResource, RawShaderResource, UnorderedAccess,
                         Sampler, TextureSampler};
 pub use pso::target::{DepthStencilTarget, DepthTarget, StencilTarget,
-                      RenderTarget, RawRenderTarget, BlendTarget, BlendRef, Scissor};
+                      RenderTarget, RawRenderTarget, BlendTarget, BlendRef, Scissor, Viewport};
 pub use pso::bundle::{Bundle};
 
 /// Render commands encoder
 mod encoder;
 /// Factory extensions
 mod factory;
+/// Ring buffer for per-frame data
+mod ring;
 /// Slices
 mod slice;
 // Pipeline states
@@ -73,3 +79,5 @@ pub mod pso;
 pub mod shade;
 /// Convenience macros
 pub mod macros;
+/// Texture file loading
+pub mod loader;
@@ -0,0 +1,235 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! DirectDraw Surface container, with the optional DX10 header.
+
+use std::borrow::Cow;
+use std::cmp;
+
+use core::format::{ChannelType as C, Format, SurfaceType as S};
+use core::texture::Level;
+use super::{get_level_size, get_num_slices, make_kind, read_bytes, read_u32};
+use super::{Error, Image, SourceFormat};
+
+pub const MAGIC: [u8; 4] = *b"DDS ";
+
+const HEADER_SIZE: u32 = 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xFC00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+const DIMENSION_TEXTURE1D: u32 = 2;
+const DIMENSION_TEXTURE2D: u32 = 3;
+const DIMENSION_TEXTURE3D: u32 = 4;
+const MISC_TEXTURECUBE: u32 = 0x4;
+
+fn four_cc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+fn map_dxgi_format(format: u32) -> Option<Format> {
+    Some(match format {
+        2 => Format(S::R32_G32_B32_A32, C::Float),
+        3 => Format(S::R32_G32_B32_A32, C::Uint),
+        4 => Format(S::R32_G32_B32_A32, C::Int),
+        6 => Format(S::R32_G32_B32, C::Float),
+        7 => Format(S::R32_G32_B32, C::Uint),
+        8 => Format(S::R32_G32_B32, C::Int),
+        10 => Format(S::R16_G16_B16_A16, C::Float),
+        11 => Format(S::R16_G16_B16_A16, C::Unorm),
+        12 => Format(S::R16_G16_B16_A16, C::Uint),
+        13 => Format(S::R16_G16_B16_A16, C::Inorm),
+        14 => Format(S::R16_G16_B16_A16, C::Int),
+        16 => Format(S::R32_G32, C::Float),
+        17 => Format(S::R32_G32, C::Uint),
+        18 => Format(S::R32_G32, C::Int),
+        24 => Format(S::R10_G10_B10_A2, C::Unorm),
+        25 => Format(S::R10_G10_B10_A2, C::Uint),
+        26 => Format(S::R11_G11_B10, C::Float),
+        28 => Format(S::R8_G8_B8_A8, C::Unorm),
+        29 => Format(S::R8_G8_B8_A8, C::Srgb),
+        30 => Format(S::R8_G8_B8_A8, C::Uint),
+        31 => Format(S::R8_G8_B8_A8, C::Inorm),
+        32 => Format(S::R8_G8_B8_A8, C::Int),
+        34 => Format(S::R16_G16, C::Float),
+        35 => Format(S::R16_G16, C::Unorm),
+        36 => Format(S::R16_G16, C::Uint),
+        37 => Format(S::R16_G16, C::Inorm),
+        38 => Format(S::R16_G16, C::Int),
+        40 => Format(S::D32, C::Float),
+        41 => Format(S::R32, C::Float),
+        42 => Format(S::R32, C::Uint),
+        43 => Format(S::R32, C::Int),
+        45 => Format(S::D24_S8, C::Unorm),
+        49 => Format(S::R8_G8, C::Unorm),
+        50 => Format(S::R8_G8, C::Uint),
+        51 => Format(S::R8_G8, C::Inorm),
+        52 => Format(S::R8_G8, C::Int),
+        54 => Format(S::R16, C::Float),
+        55 => Format(S::D16, C::Unorm),
+        56 => Format(S::R16, C::Unorm),
+        57 => Format(S::R16, C::Uint),
+        58 => Format(S::R16, C::Inorm),
+        59 => Format(S::R16, C::Int),
+        61 => Format(S::R8, C::Unorm),
+        62 => Format(S::R8, C::Uint),
+        63 => Format(S::R8, C::Inorm),
+        64 => Format(S::R8, C::Int),
+        71 => Format(S::BC1_R8_G8_B8_A1, C::Unorm),
+        72 => Format(S::BC1_R8_G8_B8_A1, C::Srgb),
+        74 => Format(S::BC2_R8_G8_B8_A8, C::Unorm),
+        75 => Format(S::BC2_R8_G8_B8_A8, C::Srgb),
+        77 => Format(S::BC3_R8_G8_B8_A8, C::Unorm),
+        78 => Format(S::BC3_R8_G8_B8_A8, C::Srgb),
+        80 => Format(S::BC4_R8, C::Unorm),
+        81 => Format(S::BC4_R8, C::Inorm),
+        83 => Format(S::BC5_R8_G8, C::Unorm),
+        84 => Format(S::BC5_R8_G8, C::Inorm),
+        85 => Format(S::R5_G6_B5, C::Unorm),
+        87 => Format(S::B8_G8_R8_A8, C::Unorm),
+        91 => Format(S::B8_G8_R8_A8, C::Srgb),
+        95 => Format(S::BC6_R16_G16_B16, C::Float),
+        96 => Format(S::BC6S_R16_G16_B16, C::Float),
+        98 => Format(S::BC7_R8_G8_B8_A8, C::Unorm),
+        99 => Format(S::BC7_R8_G8_B8_A8, C::Srgb),
+        _ => return None,
+    })
+}
+
+fn map_pixel_format(flags: u32, code: u32, bit_count: u32, masks: [u32; 4]) -> Option<Format> {
+    if flags & DDPF_FOURCC != 0 {
+        return Some(match code {
+            c if c == four_cc(b"DXT1") => Format(S::BC1_R8_G8_B8_A1, C::Unorm),
+            c if c == four_cc(b"DXT2") || c == four_cc(b"DXT3") => Format(S::BC2_R8_G8_B8_A8, C::Unorm),
+            c if c == four_cc(b"DXT4") || c == four_cc(b"DXT5") => Format(S::BC3_R8_G8_B8_A8, C::Unorm),
+            c if c == four_cc(b"ATI1") || c == four_cc(b"BC4U") => Format(S::BC4_R8, C::Unorm),
+            c if c == four_cc(b"BC4S") => Format(S::BC4_R8, C::Inorm),
+            c if c == four_cc(b"ATI2") || c == four_cc(b"BC5U") => Format(S::BC5_R8_G8, C::Unorm),
+            c if c == four_cc(b"BC5S") => Format(S::BC5_R8_G8, C::Inorm),
+            // `D3DFORMAT` values
+            36 => Format(S::R16_G16_B16_A16, C::Unorm),
+            110 => Format(S::R16_G16_B16_A16, C::Inorm),
+            111 => Format(S::R16, C::Float),
+            112 => Format(S::R16_G16, C::Float),
+            113 => Format(S::R16_G16_B16_A16, C::Float),
+            114 => Format(S::R32, C::Float),
+            115 => Format(S::R32_G32, C::Float),
+            116 => Format(S::R32_G32_B32_A32, C::Float),
+            _ => return None,
+        });
+    }
+    if flags & DDPF_RGB != 0 {
+        return Some(match (bit_count, masks) {
+            (32, [0xFF, 0xFF00, 0xFF_0000, 0xFF00_0000]) => Format(S::R8_G8_B8_A8, C::Unorm),
+            (32, [0xFF_0000, 0xFF00, 0xFF, 0xFF00_0000]) => Format(S::B8_G8_R8_A8, C::Unorm),
+            (32, [0x3FF, 0xF_FC00, 0x3FF0_0000, 0xC000_0000]) => Format(S::R10_G10_B10_A2, C::Unorm),
+            (32, [0xFFFF, 0xFFFF_0000, 0, 0]) => Format(S::R16_G16, C::Unorm),
+            (16, [0xF800, 0x7E0, 0x1F, 0]) => Format(S::R5_G6_B5, C::Unorm),
+            _ => return None,
+        });
+    }
+    if flags & DDPF_LUMINANCE != 0 {
+        return Some(match (bit_count, masks) {
+            (8, [0xFF, 0, 0, 0]) => Format(S::R8, C::Unorm),
+            (16, [0xFFFF, 0, 0, 0]) => Format(S::R16, C::Unorm),
+            (16, [0xFF, 0, 0, 0xFF00]) => Format(S::R8_G8, C::Unorm),
+            _ => return None,
+        });
+    }
+    None
+}
+
+/// Load a texture from a DDS file.
+///
+/// Legacy files can't describe 1D textures nor arrays, which require the DX10 header.
+pub fn load_dds(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(&MAGIC) {
+        return Err(Error::UnknownContainer);
+    }
+    let header = |field: usize| read_u32(data, 4 + field * 4);
+    if try!(header(0)) != HEADER_SIZE {
+        return Err(Error::InvalidHeader("the header size is not 124"));
+    }
+    let height = try!(header(2));
+    let width = try!(header(3));
+    let depth = try!(header(5));
+    let levels = cmp::max(1, try!(header(6)));
+    let pf_flags = try!(header(19));
+    let pf_code = try!(header(20));
+    let bit_count = try!(header(21));
+    let masks = [try!(header(22)), try!(header(23)), try!(header(24)), try!(header(25))];
+    let caps2 = try!(header(27));
+    if levels > 32 {
+        return Err(Error::InvalidHeader("too many mipmap levels"));
+    }
+
+    let mut offset = 4 + HEADER_SIZE as usize;
+    let (format, kind) = if pf_flags & DDPF_FOURCC != 0 && pf_code == four_cc(b"DX10") {
+        let dx10 = |field: usize| read_u32(data, offset + field * 4);
+        let dxgi_format = try!(dx10(0));
+        let dimension = try!(dx10(1));
+        let cube = try!(dx10(2)) & MISC_TEXTURECUBE != 0;
+        let layers = match try!(dx10(3)) {
+            0 | 1 => None,
+            count => Some(count),
+        };
+        offset += DX10_HEADER_SIZE;
+        let format = try!(map_dxgi_format(dxgi_format)
+            .ok_or(Error::UnsupportedFormat(SourceFormat::Dxgi(dxgi_format))));
+        let kind = match dimension {
+            DIMENSION_TEXTURE1D => make_kind(width, 0, 0, layers, false),
+            DIMENSION_TEXTURE2D => make_kind(width, height, 0, layers, cube),
+            DIMENSION_TEXTURE3D => make_kind(width, height, depth, layers, false),
+            _ => Err(Error::InvalidHeader("unknown resource dimension")),
+        };
+        (format, try!(kind))
+    } else {
+        let source = SourceFormat::DdsPixelFormat {
+            flags: pf_flags,
+            four_cc: pf_code,
+            bit_count: bit_count,
+            masks: masks,
+        };
+        let format = try!(map_pixel_format(pf_flags, pf_code, bit_count, masks)
+            .ok_or(Error::UnsupportedFormat(source)));
+        let cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+        if cube && caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+            return Err(Error::UnsupportedKind("cube maps with missing faces"));
+        }
+        let depth = if caps2 & DDSCAPS2_VOLUME != 0 { depth } else { 0 };
+        (format, try!(make_kind(width, height, depth, None, cube)))
+    };
+
+    // each slice holds its whole mipmap chain
+    let mut images = Vec::new();
+    for _ in 0 .. get_num_slices(kind) {
+        for level in 0 .. levels as Level {
+            let size = get_level_size(kind, format.0, level);
+            images.push(Cow::Borrowed(try!(read_bytes(data, offset, size))));
+            offset += size;
+        }
+    }
+
+    Ok(Image {
+        kind: kind,
+        levels: levels as Level,
+        format: format,
+        data: images,
+    })
+}
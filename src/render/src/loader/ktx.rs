@@ -0,0 +1,366 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Khronos texture containers, KTX with the GL formats and KTX2 with the Vulkan ones.
+
+use std::borrow::Cow;
+use std::cmp;
+
+use core::format::{ChannelType as C, Format, SurfaceType as S};
+use core::texture::Level;
+use super::{get_level_rows, get_level_size, get_num_slices, make_kind, read_bytes, read_u32, read_u64,
+            reorder_by_slice};
+use super::{Error, Image, SourceFormat};
+
+pub const IDENTIFIER_1: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+pub const IDENTIFIER_2: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+const ENDIANNESS: u32 = 0x0403_0201;
+const GL_BGRA: u32 = 0x80E1;
+
+fn map_gl_format(internal_format: u32, format: u32) -> Option<Format> {
+    Some(match (internal_format, format) {
+        (0x8058, GL_BGRA) => Format(S::B8_G8_R8_A8, C::Unorm),
+        (0x8C43, GL_BGRA) => Format(S::B8_G8_R8_A8, C::Srgb),
+        (_, GL_BGRA) => return None,
+        (0x8056, _) => Format(S::R4_G4_B4_A4, C::Unorm),
+        (0x8057, _) => Format(S::R5_G5_B5_A1, C::Unorm),
+        (0x8D62, _) => Format(S::R5_G6_B5, C::Unorm),
+        (0x8229, _) => Format(S::R8, C::Unorm),
+        (0x8F94, _) => Format(S::R8, C::Inorm),
+        (0x8232, _) => Format(S::R8, C::Uint),
+        (0x8231, _) => Format(S::R8, C::Int),
+        (0x822B, _) => Format(S::R8_G8, C::Unorm),
+        (0x8F95, _) => Format(S::R8_G8, C::Inorm),
+        (0x8238, _) => Format(S::R8_G8, C::Uint),
+        (0x8237, _) => Format(S::R8_G8, C::Int),
+        (0x8058, _) => Format(S::R8_G8_B8_A8, C::Unorm),
+        (0x8C43, _) => Format(S::R8_G8_B8_A8, C::Srgb),
+        (0x8F97, _) => Format(S::R8_G8_B8_A8, C::Inorm),
+        (0x8D7C, _) => Format(S::R8_G8_B8_A8, C::Uint),
+        (0x8D8E, _) => Format(S::R8_G8_B8_A8, C::Int),
+        (0x8059, _) => Format(S::R10_G10_B10_A2, C::Unorm),
+        (0x906F, _) => Format(S::R10_G10_B10_A2, C::Uint),
+        (0x8C3A, _) => Format(S::R11_G11_B10, C::Float),
+        (0x822A, _) => Format(S::R16, C::Unorm),
+        (0x8F98, _) => Format(S::R16, C::Inorm),
+        (0x8234, _) => Format(S::R16, C::Uint),
+        (0x8233, _) => Format(S::R16, C::Int),
+        (0x822D, _) => Format(S::R16, C::Float),
+        (0x822C, _) => Format(S::R16_G16, C::Unorm),
+        (0x8F99, _) => Format(S::R16_G16, C::Inorm),
+        (0x823A, _) => Format(S::R16_G16, C::Uint),
+        (0x8239, _) => Format(S::R16_G16, C::Int),
+        (0x822F, _) => Format(S::R16_G16, C::Float),
+        (0x8054, _) => Format(S::R16_G16_B16, C::Unorm),
+        (0x8F9A, _) => Format(S::R16_G16_B16, C::Inorm),
+        (0x8D77, _) => Format(S::R16_G16_B16, C::Uint),
+        (0x8D89, _) => Format(S::R16_G16_B16, C::Int),
+        (0x881B, _) => Format(S::R16_G16_B16, C::Float),
+        (0x805B, _) => Format(S::R16_G16_B16_A16, C::Unorm),
+        (0x8F9B, _) => Format(S::R16_G16_B16_A16, C::Inorm),
+        (0x8D76, _) => Format(S::R16_G16_B16_A16, C::Uint),
+        (0x8D88, _) => Format(S::R16_G16_B16_A16, C::Int),
+        (0x881A, _) => Format(S::R16_G16_B16_A16, C::Float),
+        (0x8236, _) => Format(S::R32, C::Uint),
+        (0x8235, _) => Format(S::R32, C::Int),
+        (0x822E, _) => Format(S::R32, C::Float),
+        (0x823C, _) => Format(S::R32_G32, C::Uint),
+        (0x823B, _) => Format(S::R32_G32, C::Int),
+        (0x8230, _) => Format(S::R32_G32, C::Float),
+        (0x8D71, _) => Format(S::R32_G32_B32, C::Uint),
+        (0x8D83, _) => Format(S::R32_G32_B32, C::Int),
+        (0x8815, _) => Format(S::R32_G32_B32, C::Float),
+        (0x8D70, _) => Format(S::R32_G32_B32_A32, C::Uint),
+        (0x8D82, _) => Format(S::R32_G32_B32_A32, C::Int),
+        (0x8814, _) => Format(S::R32_G32_B32_A32, C::Float),
+        (0x81A5, _) => Format(S::D16, C::Unorm),
+        (0x88F0, _) => Format(S::D24_S8, C::Unorm),
+        (0x8CAC, _) => Format(S::D32, C::Float),
+        (0x83F0, _) | (0x83F1, _) => Format(S::BC1_R8_G8_B8_A1, C::Unorm),
+        (0x8C4C, _) | (0x8C4D, _) => Format(S::BC1_R8_G8_B8_A1, C::Srgb),
+        (0x83F2, _) => Format(S::BC2_R8_G8_B8_A8, C::Unorm),
+        (0x8C4E, _) => Format(S::BC2_R8_G8_B8_A8, C::Srgb),
+        (0x83F3, _) => Format(S::BC3_R8_G8_B8_A8, C::Unorm),
+        (0x8C4F, _) => Format(S::BC3_R8_G8_B8_A8, C::Srgb),
+        (0x8DBB, _) => Format(S::BC4_R8, C::Unorm),
+        (0x8DBC, _) => Format(S::BC4_R8, C::Inorm),
+        (0x8DBD, _) => Format(S::BC5_R8_G8, C::Unorm),
+        (0x8DBE, _) => Format(S::BC5_R8_G8, C::Inorm),
+        (0x8E8E, _) => Format(S::BC6S_R16_G16_B16, C::Float),
+        (0x8E8F, _) => Format(S::BC6_R16_G16_B16, C::Float),
+        (0x8E8C, _) => Format(S::BC7_R8_G8_B8_A8, C::Unorm),
+        (0x8E8D, _) => Format(S::BC7_R8_G8_B8_A8, C::Srgb),
+        // ETC1 is a subset of ETC2
+        (0x8D64, _) | (0x9274, _) => Format(S::ETC2_R8_G8_B8, C::Unorm),
+        (0x9275, _) => Format(S::ETC2_R8_G8_B8, C::Srgb),
+        (0x9276, _) => Format(S::ETC2_R8_G8_B8_A1, C::Unorm),
+        (0x9277, _) => Format(S::ETC2_R8_G8_B8_A1, C::Srgb),
+        (0x9278, _) => Format(S::ETC2_R8_G8_B8_A8, C::Unorm),
+        (0x9279, _) => Format(S::ETC2_R8_G8_B8_A8, C::Srgb),
+        (0x9270, _) => Format(S::EAC_R11, C::Unorm),
+        (0x9271, _) => Format(S::EAC_R11, C::Inorm),
+        (0x9272, _) => Format(S::EAC_R11_G11, C::Unorm),
+        (0x9273, _) => Format(S::EAC_R11_G11, C::Inorm),
+        (f @ 0x93B0 ... 0x93BD, _) => Format(astc_surface(f - 0x93B0), C::Unorm),
+        (f @ 0x93D0 ... 0x93DD, _) => Format(astc_surface(f - 0x93D0), C::Srgb),
+        _ => return None,
+    })
+}
+
+fn map_vk_format(format: u32) -> Option<Format> {
+    Some(match format {
+        2 => Format(S::R4_G4_B4_A4, C::Unorm),
+        4 => Format(S::R5_G6_B5, C::Unorm),
+        6 => Format(S::R5_G5_B5_A1, C::Unorm),
+        9 => Format(S::R8, C::Unorm),
+        10 => Format(S::R8, C::Inorm),
+        13 => Format(S::R8, C::Uint),
+        14 => Format(S::R8, C::Int),
+        16 => Format(S::R8_G8, C::Unorm),
+        17 => Format(S::R8_G8, C::Inorm),
+        20 => Format(S::R8_G8, C::Uint),
+        21 => Format(S::R8_G8, C::Int),
+        37 => Format(S::R8_G8_B8_A8, C::Unorm),
+        38 => Format(S::R8_G8_B8_A8, C::Inorm),
+        41 => Format(S::R8_G8_B8_A8, C::Uint),
+        42 => Format(S::R8_G8_B8_A8, C::Int),
+        43 => Format(S::R8_G8_B8_A8, C::Srgb),
+        44 => Format(S::B8_G8_R8_A8, C::Unorm),
+        50 => Format(S::B8_G8_R8_A8, C::Srgb),
+        64 => Format(S::R10_G10_B10_A2, C::Unorm),
+        68 => Format(S::R10_G10_B10_A2, C::Uint),
+        70 => Format(S::R16, C::Unorm),
+        71 => Format(S::R16, C::Inorm),
+        74 => Format(S::R16, C::Uint),
+        75 => Format(S::R16, C::Int),
+        76 => Format(S::R16, C::Float),
+        77 => Format(S::R16_G16, C::Unorm),
+        78 => Format(S::R16_G16, C::Inorm),
+        81 => Format(S::R16_G16, C::Uint),
+        82 => Format(S::R16_G16, C::Int),
+        83 => Format(S::R16_G16, C::Float),
+        84 => Format(S::R16_G16_B16, C::Unorm),
+        85 => Format(S::R16_G16_B16, C::Inorm),
+        88 => Format(S::R16_G16_B16, C::Uint),
+        89 => Format(S::R16_G16_B16, C::Int),
+        90 => Format(S::R16_G16_B16, C::Float),
+        91 => Format(S::R16_G16_B16_A16, C::Unorm),
+        92 => Format(S::R16_G16_B16_A16, C::Inorm),
+        95 => Format(S::R16_G16_B16_A16, C::Uint),
+        96 => Format(S::R16_G16_B16_A16, C::Int),
+        97 => Format(S::R16_G16_B16_A16, C::Float),
+        98 => Format(S::R32, C::Uint),
+        99 => Format(S::R32, C::Int),
+        100 => Format(S::R32, C::Float),
+        101 => Format(S::R32_G32, C::Uint),
+        102 => Format(S::R32_G32, C::Int),
+        103 => Format(S::R32_G32, C::Float),
+        104 => Format(S::R32_G32_B32, C::Uint),
+        105 => Format(S::R32_G32_B32, C::Int),
+        106 => Format(S::R32_G32_B32, C::Float),
+        107 => Format(S::R32_G32_B32_A32, C::Uint),
+        108 => Format(S::R32_G32_B32_A32, C::Int),
+        109 => Format(S::R32_G32_B32_A32, C::Float),
+        122 => Format(S::R11_G11_B10, C::Float),
+        124 => Format(S::D16, C::Unorm),
+        126 => Format(S::D32, C::Float),
+        129 => Format(S::D24_S8, C::Unorm),
+        131 | 133 => Format(S::BC1_R8_G8_B8_A1, C::Unorm),
+        132 | 134 => Format(S::BC1_R8_G8_B8_A1, C::Srgb),
+        135 => Format(S::BC2_R8_G8_B8_A8, C::Unorm),
+        136 => Format(S::BC2_R8_G8_B8_A8, C::Srgb),
+        137 => Format(S::BC3_R8_G8_B8_A8, C::Unorm),
+        138 => Format(S::BC3_R8_G8_B8_A8, C::Srgb),
+        139 => Format(S::BC4_R8, C::Unorm),
+        140 => Format(S::BC4_R8, C::Inorm),
+        141 => Format(S::BC5_R8_G8, C::Unorm),
+        142 => Format(S::BC5_R8_G8, C::Inorm),
+        143 => Format(S::BC6_R16_G16_B16, C::Float),
+        144 => Format(S::BC6S_R16_G16_B16, C::Float),
+        145 => Format(S::BC7_R8_G8_B8_A8, C::Unorm),
+        146 => Format(S::BC7_R8_G8_B8_A8, C::Srgb),
+        147 => Format(S::ETC2_R8_G8_B8, C::Unorm),
+        148 => Format(S::ETC2_R8_G8_B8, C::Srgb),
+        149 => Format(S::ETC2_R8_G8_B8_A1, C::Unorm),
+        150 => Format(S::ETC2_R8_G8_B8_A1, C::Srgb),
+        151 => Format(S::ETC2_R8_G8_B8_A8, C::Unorm),
+        152 => Format(S::ETC2_R8_G8_B8_A8, C::Srgb),
+        153 => Format(S::EAC_R11, C::Unorm),
+        154 => Format(S::EAC_R11, C::Inorm),
+        155 => Format(S::EAC_R11_G11, C::Unorm),
+        156 => Format(S::EAC_R11_G11, C::Inorm),
+        f @ 157 ... 184 => {
+            let channel = if (f - 157) % 2 == 0 { C::Unorm } else { C::Srgb };
+            Format(astc_surface((f - 157) / 2), channel)
+        },
+        _ => return None,
+    })
+}
+
+/// Get the ASTC surface of the given index in the block sizes order, common to GL and Vulkan.
+fn astc_surface(index: u32) -> S {
+    [S::ASTC_4x4, S::ASTC_5x4, S::ASTC_5x5, S::ASTC_6x5, S::ASTC_6x6, S::ASTC_8x5, S::ASTC_8x6,
+     S::ASTC_8x8, S::ASTC_10x5, S::ASTC_10x6, S::ASTC_10x8, S::ASTC_10x10, S::ASTC_12x10,
+     S::ASTC_12x12][index as usize]
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// Load a texture from a KTX file.
+///
+/// Rows of uncompressed images that are padded in the file get repacked,
+/// as well as the data of files with a different endianness.
+pub fn load_ktx(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(&IDENTIFIER_1) {
+        return Err(Error::UnknownContainer);
+    }
+    let swap = match try!(read_u32(data, 12)) {
+        ENDIANNESS => false,
+        e if e == ENDIANNESS.swap_bytes() => true,
+        _ => return Err(Error::InvalidHeader("unknown endianness")),
+    };
+    let header = |field: usize| read_u32(data, 16 + field * 4)
+        .map(|value| if swap { value.swap_bytes() } else { value });
+    let type_size = try!(header(1));
+    let gl_format = try!(header(2));
+    let internal_format = try!(header(3));
+    let width = try!(header(5));
+    let height = try!(header(6));
+    let depth = try!(header(7));
+    let layers = match try!(header(8)) {
+        0 => None,
+        count => Some(count),
+    };
+    let faces = try!(header(9));
+    let levels = cmp::max(1, try!(header(10)));
+    let kv_size = try!(header(11)) as usize;
+    if faces != 1 && faces != 6 {
+        return Err(Error::InvalidHeader("the number of faces is neither 1 nor 6"));
+    }
+    if levels > 32 {
+        return Err(Error::InvalidHeader("too many mipmap levels"));
+    }
+    if kv_size > data.len() {
+        return Err(Error::Truncated {
+            expected: 64 + kv_size,
+            found: data.len(),
+        });
+    }
+    let source = SourceFormat::Gl {
+        internal_format: internal_format,
+        format: gl_format,
+    };
+    let format = try!(map_gl_format(internal_format, gl_format).ok_or(Error::UnsupportedFormat(source)));
+    let kind = try!(make_kind(width, height, depth, layers, faces == 6));
+    // faces of non-array cube maps are padded individually
+    let cube_padding = faces == 6 && layers.is_none();
+
+    let num_slices = get_num_slices(kind);
+    let mut images = Vec::new();
+    let mut offset = 64 + kv_size;
+    for level in 0 .. levels as Level {
+        // skip `imageSize`, the sizes are computed from the format instead
+        offset += 4;
+        let (rows, row_size) = get_level_rows(kind, format.0, level);
+        let row_pitch = align4(row_size);
+        for _ in 0 .. num_slices {
+            let bytes = try!(read_bytes(data, offset, rows * row_pitch));
+            let mut image = if row_pitch == row_size {
+                Cow::Borrowed(bytes)
+            } else {
+                Cow::Owned(bytes.chunks(row_pitch).flat_map(|row| row[.. row_size].iter().cloned()).collect())
+            };
+            if swap && type_size > 1 {
+                for element in image.to_mut().chunks_mut(type_size as usize) {
+                    element.reverse();
+                }
+            }
+            images.push(image);
+            offset += rows * row_pitch;
+            if cube_padding {
+                offset = align4(offset);
+            }
+        }
+        offset = align4(offset);
+    }
+
+    Ok(Image {
+        kind: kind,
+        levels: levels as Level,
+        format: format,
+        data: reorder_by_slice(images, levels as usize),
+    })
+}
+
+/// Load a texture from a KTX2 file.
+///
+/// Supercompressed files are not supported.
+pub fn load_ktx2(data: &[u8]) -> Result<Image, Error> {
+    if !data.starts_with(&IDENTIFIER_2) {
+        return Err(Error::UnknownContainer);
+    }
+    let header = |field: usize| read_u32(data, 12 + field * 4);
+    let vk_format = try!(header(0));
+    let width = try!(header(2));
+    let height = try!(header(3));
+    let depth = try!(header(4));
+    let layers = match try!(header(5)) {
+        0 => None,
+        count => Some(count),
+    };
+    let faces = try!(header(6));
+    let levels = cmp::max(1, try!(header(7)));
+    if try!(header(8)) != 0 {
+        return Err(Error::UnsupportedKind("supercompressed data"));
+    }
+    if faces != 1 && faces != 6 {
+        return Err(Error::InvalidHeader("the number of faces is neither 1 nor 6"));
+    }
+    if levels > 32 {
+        return Err(Error::InvalidHeader("too many mipmap levels"));
+    }
+    let format = try!(map_vk_format(vk_format).ok_or(Error::UnsupportedFormat(SourceFormat::Vulkan(vk_format))));
+    let kind = try!(make_kind(width, height, depth, layers, faces == 6));
+
+    let num_slices = get_num_slices(kind);
+    let mut images = Vec::new();
+    for level in 0 .. levels as Level {
+        // the level index follows the 80 bytes of the header and the section index
+        let index = 80 + level as usize * 24;
+        let offset = try!(read_u64(data, index));
+        let length = try!(read_u64(data, index + 8));
+        let size = get_level_size(kind, format.0, level);
+        if offset > data.len() as u64 {
+            return Err(Error::InvalidHeader("a level starts past the end of the file"));
+        }
+        if length < (size * num_slices) as u64 {
+            return Err(Error::InvalidHeader("a level is smaller than its images"));
+        }
+        let mut offset = offset as usize;
+        for _ in 0 .. num_slices {
+            images.push(Cow::Borrowed(try!(read_bytes(data, offset, size))));
+            offset += size;
+        }
+    }
+
+    Ok(Image {
+        kind: kind,
+        levels: levels as Level,
+        format: format,
+        data: reorder_by_slice(images, levels as usize),
+    })
+}
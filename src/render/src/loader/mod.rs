@@ -0,0 +1,268 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Loading of textures from the DDS, KTX and KTX2 containers.
+//!
+//! The loaded `Image` describes the texture and provides its data in the
+//! order expected by `Factory::create_texture_raw`:
+//!
+//! ```ignore
+//! let image = try!(gfx::loader::load(&bytes));
+//! let info = image.get_info(gfx::memory::SHADER_RESOURCE, gfx::memory::Usage::Data);
+//! let texture = try!(factory.create_texture_raw(info, Some(image.format.1), Some(&image.get_data())));
+//! ```
+
+#![deny(missing_docs)]
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::{cmp, fmt};
+
+use core::format::{Format, SurfaceType};
+use core::memory::{Bind, Usage};
+use core::texture::{AaMode, Info, Kind, Layer, Level, Size};
+
+mod dds;
+mod ktx;
+
+pub use self::dds::load_dds;
+pub use self::ktx::{load_ktx, load_ktx2};
+
+/// The format of a file, as described by its container.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SourceFormat {
+    /// A `DXGI_FORMAT` of a DDS file with the DX10 header.
+    Dxgi(u32),
+    /// A legacy pixel format of a DDS file.
+    DdsPixelFormat {
+        /// Flags of the pixel format
+        flags: u32,
+        /// FourCC code, if the flags have `DDPF_FOURCC`
+        four_cc: u32,
+        /// Number of bits of a pixel
+        bit_count: u32,
+        /// Red, green, blue and alpha masks
+        masks: [u32; 4],
+    },
+    /// The GL formats of a KTX file.
+    Gl {
+        /// `glInternalFormat`
+        internal_format: u32,
+        /// `glFormat`, 0 for compressed formats
+        format: u32,
+    },
+    /// A `VkFormat` of a KTX2 file.
+    Vulkan(u32),
+}
+
+/// An error occuring when loading a texture file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The data doesn't start with the identifier of a supported container.
+    UnknownContainer,
+    /// The header is malformed.
+    InvalidHeader(&'static str),
+    /// The data ends before the images described by the header.
+    Truncated {
+        /// Number of bytes required by the header
+        expected: usize,
+        /// Number of bytes of the data
+        found: usize,
+    },
+    /// The format of the file has no equivalent `format::Format`.
+    UnsupportedFormat(SourceFormat),
+    /// The texture is of a kind that can't be represented by a `texture::Kind`.
+    UnsupportedKind(&'static str),
+    /// A dimension of the texture is larger than `texture::Size`.
+    Size(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownContainer => write!(f, "{}", self.description()),
+            Error::InvalidHeader(reason) |
+            Error::UnsupportedKind(reason) => write!(f, "{}: {}", self.description(), reason),
+            Error::Truncated { expected, found } =>
+                write!(f, "{}: expected {} bytes, found {}", self.description(), expected, found),
+            Error::UnsupportedFormat(ref format) => write!(f, "{}: {:?}", self.description(), format),
+            Error::Size(size) => write!(f, "{}: {}", self.description(), size),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::UnknownContainer => "The data is not a DDS, KTX or KTX2 file",
+            Error::InvalidHeader(_) => "The header of the file is invalid",
+            Error::Truncated { .. } => "The file is truncated",
+            Error::UnsupportedFormat(_) => "The format of the file is not supported",
+            Error::UnsupportedKind(_) => "The kind of texture is not supported",
+            Error::Size(_) => "The texture is too large",
+        }
+    }
+}
+
+/// A texture loaded from a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image<'a> {
+    /// Kind of the texture
+    pub kind: Kind,
+    /// Number of mipmap levels
+    pub levels: Level,
+    /// Format of the texels
+    pub format: Format,
+    /// Data of each slice and level: Slice0.Mip0, Slice0.Mip1, ..., Slice1.Mip0, ...
+    /// The slices are the array layers, and the faces of each layer of a cube map.
+    /// The data is borrowed from the file, unless it had to be repacked.
+    pub data: Vec<Cow<'a, [u8]>>,
+}
+
+impl<'a> Image<'a> {
+    /// Get the texture info to create the texture with.
+    pub fn get_info(&self, bind: Bind, usage: Usage) -> Info {
+        Info {
+            kind: self.kind,
+            levels: self.levels,
+            format: self.format.0,
+            bind: bind,
+            usage: usage,
+        }
+    }
+
+    /// Get the data of each slice and level, as expected by `Factory::create_texture_raw`.
+    pub fn get_data(&self) -> Vec<&[u8]> {
+        self.data.iter().map(|d| &d[..]).collect()
+    }
+}
+
+/// Load a texture from a DDS, KTX or KTX2 file, recognized by its identifier.
+pub fn load(data: &[u8]) -> Result<Image, Error> {
+    if data.starts_with(&dds::MAGIC) {
+        load_dds(data)
+    } else if data.starts_with(&ktx::IDENTIFIER_1) {
+        load_ktx(data)
+    } else if data.starts_with(&ktx::IDENTIFIER_2) {
+        load_ktx2(data)
+    } else {
+        Err(Error::UnknownContainer)
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = try!(read_bytes(data, offset, 4));
+    Ok(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, Error> {
+    let low = try!(read_u32(data, offset));
+    let high = try!(read_u32(data, offset + 4));
+    Ok(low as u64 | (high as u64) << 32)
+}
+
+fn read_bytes(data: &[u8], offset: usize, size: usize) -> Result<&[u8], Error> {
+    match offset.checked_add(size) {
+        Some(end) if end <= data.len() => Ok(&data[offset .. end]),
+        Some(end) => Err(Error::Truncated {
+            expected: end,
+            found: data.len(),
+        }),
+        None => Err(Error::InvalidHeader("the data is out of the addressable range")),
+    }
+}
+
+fn to_size(value: u32) -> Result<Size, Error> {
+    if value > Size::max_value() as u32 {
+        Err(Error::Size(value))
+    } else {
+        Ok(value as Size)
+    }
+}
+
+/// Determine the kind of texture from the dimensions given by a header.
+/// A height of 0 denotes a 1D texture, a depth of 0 a non-volume one,
+/// and no layers a non-array one.
+fn make_kind(width: u32, height: u32, depth: u32, layers: Option<u32>, cube: bool)
+             -> Result<Kind, Error> {
+    let w = try!(to_size(width));
+    let layers = match layers {
+        Some(count) => Some(try!(to_size(count)) as Layer),
+        None => None,
+    };
+    if cube {
+        if width != height || depth > 1 {
+            return Err(Error::InvalidHeader("the faces of a cube map are not square"));
+        }
+        return Ok(match layers {
+            // the faces of all the layers have to be addressable
+            Some(count) if count as usize * 6 > Size::max_value() as usize =>
+                return Err(Error::UnsupportedKind("cube map arrays with too many layers")),
+            Some(count) => Kind::CubeArray(w, count),
+            None => Kind::Cube(w),
+        });
+    }
+    if depth > 1 {
+        if layers.is_some() {
+            return Err(Error::UnsupportedKind("arrays of volume textures"));
+        }
+        return Ok(Kind::D3(w, try!(to_size(height)), try!(to_size(depth))));
+    }
+    Ok(match (height, layers) {
+        (0, None) => Kind::D1(w),
+        (0, Some(count)) => Kind::D1Array(w, count),
+        (h, None) => Kind::D2(w, try!(to_size(h)), AaMode::Single),
+        (h, Some(count)) => Kind::D2Array(w, try!(to_size(h)), count, AaMode::Single),
+    })
+}
+
+/// Get the number of slices of a texture: the layers, times the faces for cube maps.
+fn get_num_slices(kind: Kind) -> usize {
+    let faces = if kind.is_cube() { 6 } else { 1 };
+    kind.get_num_slices().unwrap_or(1) as usize * faces
+}
+
+/// Get the number of block rows of a slice at the given level, and the size of each row.
+/// The rows of all the depth layers of a volume texture are counted.
+fn get_level_rows(kind: Kind, surface: SurfaceType, level: Level) -> (usize, usize) {
+    let (w, h, d, _) = kind.get_level_dimensions(level);
+    let (bw, bh) = surface.get_block_dim();
+    let block_bytes = surface.get_total_bits() as usize / 8;
+    let columns = (w as usize + bw as usize - 1) / bw as usize;
+    let rows = (h as usize + bh as usize - 1) / bh as usize;
+    let depth = match kind {
+        Kind::D3(..) => d as usize,
+        _ => 1,
+    };
+    (cmp::max(1, rows) * cmp::max(1, depth), cmp::max(1, columns) * block_bytes)
+}
+
+/// Get the size of a slice at the given level, tightly packed.
+fn get_level_size(kind: Kind, surface: SurfaceType, level: Level) -> usize {
+    let (rows, row_size) = get_level_rows(kind, surface, level);
+    rows * row_size
+}
+
+/// Reorder the images stored level by level into the slice by slice order.
+fn reorder_by_slice<T>(images: Vec<T>, levels: usize) -> Vec<T> {
+    let slices = images.len() / levels;
+    let mut images = images.into_iter().map(Some).collect::<Vec<_>>();
+    let mut ordered = Vec::with_capacity(images.len());
+    for slice in 0 .. slices {
+        for level in 0 .. levels {
+            ordered.push(images[level * slices + slice].take().unwrap());
+        }
+    }
+    ordered
+}
@@ -114,13 +114,16 @@ macro_rules! gfx_format {
 /// - [Render](pso/target/struct.RenderTarget.html), [blend](pso/target/struct.BlendTarget.html), 
 ///   [depth](pso/target/struct.DepthTarget.html), [stencil](pso/target/struct.StencilTarget.html) targets.
 /// - A [shader resource view](pso/resource/struct.ShaderResource.html) (SRV, DX11)
-/// - An [unordered access view](pso/resource/struct.UnorderedAccess.html) (UAV, DX11, not yet implemented in the OpenGL backend)
+/// - An [unordered access view](pso/resource/struct.UnorderedAccess.html) (UAV, DX11, image or storage buffer in GL 4.2+)
 /// - A [scissor](pso/target/struct.Scissor.html) rectangle value (DX11)
+/// - A list of [viewports](pso/target/struct.Viewport.html) with depth ranges
+/// - A [stream output](pso/buffer/struct.StreamOutput.html) buffer, capturing the varyings
+///   of a transform feedback program (GL 3.0+)
 ///
 /// Structure of a `pipeline state object` can be defined freely.
 ///
 /// It should be noted however, that you can have multiple objects of everything but
-/// depth/stencil and scissor objects in a `pipeline state object`, which is the only
+/// depth/stencil, scissor and stream output objects in a `pipeline state object`, which is the only
 /// restriction in the freedom of defining a `pipeline state object`.
 ///
 /// # `vertex`
@@ -189,6 +189,23 @@ macro_rules! gfx_pipeline_inner {
                         }
                     )*
                 }
+                // stream output
+                for so in &info.stream_outputs {
+                    $(
+                        match meta.$field.link_stream_output(so, &self.$field) {
+                            Some(Ok(d)) => {
+                                assert!(meta.$field.is_active());
+                                desc.stream_output = Some(d);
+                                continue;
+                            },
+                            Some(Err(fm)) => return Err(
+                                InitError::StreamOutput(&so.name, Some(fm))
+                            ),
+                            None => (),
+                        }
+                    )*
+                    return Err(InitError::StreamOutput(&so.name, None));
+                }
                 // depth-stencil, scissor
                 for _ in 0 .. 1 {
                     $(
@@ -20,6 +20,7 @@ use core::{handle, pso, shade};
 use core::memory::Typed;
 use core::format::Format;
 use shade::{ToUniform, Usage};
+use ring::RingRange;
 use super::{DataLink, DataBind, ElementError, RawDataSet, AccessInfo};
 
 pub use core::pso::{BufferIndex, Element, ElemOffset, ElemStride, InstanceRate};
@@ -97,6 +98,14 @@ pub struct ConstantBuffer<T: Structure<shade::ConstFormat>>(
     PhantomData<T>
 );
 
+/// Constant buffer component, bound to a range written into a `RingBuffer`.
+///
+/// - init: `&str` = name of the buffer
+/// - data: `RingRange<T>`
+#[derive(Derivative)]
+#[derivative(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RingConstantBuffer<T: Structure<shade::ConstFormat>>(ConstantBuffer<T>);
+
 /// Raw constant buffer component.
 ///
 /// - init: `&str` = name of the buffer
@@ -125,19 +134,54 @@ pub struct Global<T: ToUniform>(
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct RawGlobal(Option<shade::Location>);
 
+/// Stream output component. Captures the varyings of a transform feedback program
+/// into a buffer, matching them by name with the elements of `T`. The layout of `T`
+/// has to follow the capture order, with the varyings tightly packed.
+///
+/// - init: `()`
+/// - data: `(Buffer<T>, usize)` = buffer, and the index of the element to start writing at
+#[derive(Derivative)]
+#[derivative(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct StreamOutput<T: Structure<Format>>(
+    bool,
+    #[derivative(Hash = "ignore", PartialEq = "ignore")]
+    PhantomData<T>
+);
+
 fn match_attribute(attr: &shade::AttributeVar, fmt: Format) -> bool {
+    match_format(attr.base_type, attr.container, fmt)
+}
+
+fn match_const_format(var: &shade::ConstVar, fmt: shade::ConstFormat) -> bool {
+    use core::shade::{BaseType, ContainerType};
+    let base_match = match (var.base_type, fmt.0) {
+        // booleans are often mirrored by integers
+        (BaseType::Bool, BaseType::I32) |
+        (BaseType::Bool, BaseType::U32) => true,
+        (a, b) => a == b,
+    };
+    // matrix dimensions are reported differently by the backends, so only
+    // the scalars and vectors are compared
+    base_match && match (var.container, fmt.1) {
+        (ContainerType::Matrix(..), _) |
+        (_, ContainerType::Matrix(..)) => true,
+        (a, b) => a == b,
+    }
+}
+
+fn match_format(base_type: shade::BaseType, container: shade::ContainerType, fmt: Format) -> bool {
     use core::shade::{BaseType, ContainerType};
     use core::format::ChannelType;
 
     // TODO: Fill in `_` with compatible `core::format::SurfaceType` variants.
 
-    match (attr.base_type, fmt.1) {
+    match (base_type, fmt.1) {
         (BaseType::I32, ChannelType::Int) |
         (BaseType::F32, ChannelType::Float) |
         (BaseType::F32, ChannelType::Inorm) |
         (BaseType::F32, ChannelType::Srgb) |
         (BaseType::F32, ChannelType::Unorm) |
-        (BaseType::U32, ChannelType::Uint) => match (attr.container, fmt.0) {
+        (BaseType::U32, ChannelType::Uint) => match (container, fmt.0) {
                 (ContainerType::Single, _) |
                 (ContainerType::Vector(2), _) |
                 (ContainerType::Vector(3), _) |
@@ -147,7 +191,7 @@ fn match_attribute(attr: &shade::AttributeVar, fmt: Format) -> bool {
         (BaseType::F64, ChannelType::Float) |
         (BaseType::F64, ChannelType::Inorm) |
         (BaseType::F64, ChannelType::Srgb) |
-        (BaseType::F64, ChannelType::Unorm) => match (attr.container, fmt.0) {
+        (BaseType::F64, ChannelType::Unorm) => match (container, fmt.0) {
                 (ContainerType::Single, _) |
                 (ContainerType::Vector(2), _) |
                 (ContainerType::Vector(3), _) |
@@ -272,8 +316,14 @@ DataLink<'a> for ConstantBuffer<T> {
                             shader_offset: el.location as pso::ElemOffset,
                             code_offset: e.offset,
                         },
+                    Some(e) if !match_const_format(el, e.format) =>
+                        ElementError::Format {
+                            name: el.name.as_str(),
+                            shader_format: (el.base_type, el.container),
+                            code_format: e.format,
+                        },
                     None => ElementError::NotFound(el.name.as_str()),
-                    Some(_) => continue, //TODO: check format
+                    Some(_) => continue,
                 };
                 self.0 = RawConstantBuffer::new();
                 return Some(Err(err));
@@ -295,6 +345,52 @@ DataBind<R> for ConstantBuffer<T> {
     }
 }
 
+impl<'a, T: Structure<shade::ConstFormat>>
+DataLink<'a> for RingConstantBuffer<T> {
+    type Init = &'a str;
+    fn new() -> Self {
+        RingConstantBuffer(ConstantBuffer::new())
+    }
+    fn is_active(&self) -> bool {
+        self.0.is_active()
+    }
+    fn link_constant_buffer<'b>(&mut self, cb: &'b shade::ConstantBufferVar, init: &Self::Init)
+                            -> Option<Result<pso::ConstantBufferDesc, ElementError<&'b str>>> {
+        self.0.link_constant_buffer(cb, init)
+    }
+}
+
+impl<R: Resources, T: Structure<shade::ConstFormat>>
+DataBind<R> for RingConstantBuffer<T> {
+    type Data = RingRange<R, T>;
+    fn bind_to(&self,
+               out: &mut RawDataSet<R>,
+               data: &Self::Data,
+               man: &mut handle::Manager<R>,
+               access: &mut AccessInfo<R>) {
+        let range = pso::BufferRange {
+            offset: data.offset(),
+            size: data.size(),
+        };
+        (self.0).0.bind_range_to(out, data.buffer().raw(), Some(range), man, access)
+    }
+}
+
+impl RawConstantBuffer {
+    fn bind_range_to<R: Resources>(&self,
+                                   out: &mut RawDataSet<R>,
+                                   data: &handle::RawBuffer<R>,
+                                   range: Option<pso::BufferRange>,
+                                   man: &mut handle::Manager<R>,
+                                   access: &mut AccessInfo<R>) {
+        if let Some((usage, slot)) = self.0 {
+            let buf = man.ref_buffer(data).clone();
+            out.constant_buffers.push(pso::ConstantBufferParam(buf, usage, slot, range));
+            access.buffer_read(data)
+        }
+    }
+}
+
 impl<'a> DataLink<'a> for RawConstantBuffer {
     type Init = &'a str;
     fn new() -> Self {
@@ -321,11 +417,7 @@ impl<R: Resources> DataBind<R> for RawConstantBuffer {
                data: &Self::Data,
                man: &mut handle::Manager<R>,
                access: &mut AccessInfo<R>) {
-        if let Some((usage, slot)) = self.0 {
-            let buf = man.ref_buffer(data).clone();
-            out.constant_buffers.push(pso::ConstantBufferParam(buf, usage, slot));
-            access.buffer_read(data)
-        }
+        self.bind_range_to(out, data, None, man, access)
     }
 }
 
@@ -351,7 +443,7 @@ impl<R: Resources, T: ToUniform> DataBind<R> for Global<T> {
                data: &Self::Data,
                man: &mut handle::Manager<R>,
                access: &mut AccessInfo<R>) {
-        let val = data.convert();
+        let val = data.clone().convert();
         self.0.bind_to(out, &val, man, access);
     }
 }
@@ -383,7 +475,46 @@ impl<R: Resources> DataBind<R> for RawGlobal {
                _: &mut handle::Manager<R>,
                _: &mut AccessInfo<R>) {
         if let Some(loc) = self.0 {
-            out.global_constants.push((loc, *data));
+            out.global_constants.push((loc, data.clone()));
+        }
+    }
+}
+
+impl<'a, T: Structure<Format>> DataLink<'a> for StreamOutput<T> {
+    type Init = ();
+    fn new() -> Self {
+        StreamOutput(false, PhantomData)
+    }
+    fn is_active(&self) -> bool {
+        self.0
+    }
+    fn link_stream_output(&mut self, var: &shade::StreamOutputVar, _: &Self::Init) ->
+                          Option<Result<pso::StreamOutputDesc, Format>> {
+        use std::mem;
+        T::query(&var.name).map(|el| {
+            self.0 = true;
+            if el.offset == var.offset && match_format(var.base_type, var.container, el.format) {
+                Ok(mem::size_of::<T>() as ElemStride)
+            } else {
+                Err(el.format)
+            }
+        })
+    }
+}
+
+impl<R: Resources, T: Structure<Format>> DataBind<R> for StreamOutput<T> {
+    type Data = (handle::Buffer<R, T>, usize);
+    fn bind_to(&self,
+               out: &mut RawDataSet<R>,
+               data: &Self::Data,
+               man: &mut handle::Manager<R>,
+               access: &mut AccessInfo<R>) {
+        use std::mem;
+        if self.0 {
+            let buf = man.ref_buffer(data.0.raw()).clone();
+            let offset = data.1 * mem::size_of::<T>();
+            out.stream_output = Some(pso::StreamOutputParam(buf, offset));
+            access.buffer_write(data.0.raw());
         }
     }
 }
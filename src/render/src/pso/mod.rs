@@ -66,8 +66,10 @@ pub struct RawDataSet<R: c::Resources>{
     pub unordered_views: Vec<c::pso::UnorderedViewParam<R>>,
     pub samplers: Vec<c::pso::SamplerParam<R>>,
     pub pixel_targets: c::pso::PixelTargetSet<R>,
+    pub stream_output: Option<c::pso::StreamOutputParam<R>>,
     pub ref_values: c::state::RefValues,
     pub scissor: c::target::Rect,
+    pub viewports: Vec<c::command::Viewport>,
 }
 
 impl<R: c::Resources> RawDataSet<R> {
@@ -81,8 +83,10 @@ impl<R: c::Resources> RawDataSet<R> {
             unordered_views: Vec::new(),
             samplers: Vec::new(),
             pixel_targets: c::pso::PixelTargetSet::new(),
+            stream_output: None,
             ref_values: Default::default(),
             scissor: c::target::Rect{x:0, y:0, w:1, h:1},
+            viewports: Vec::new(),
         }
     }
     /// Clear all contained data.
@@ -94,8 +98,10 @@ impl<R: c::Resources> RawDataSet<R> {
         self.unordered_views.clear();
         self.samplers.clear();
         self.pixel_targets = c::pso::PixelTargetSet::new();
+        self.stream_output = None;
         self.ref_values = Default::default();
         self.scissor = c::target::Rect{x:0, y:0, w:1, h:1};
+        self.viewports.clear();
     }
 }
 
@@ -182,6 +188,8 @@ pub enum InitError<S> {
     Sampler(S, Option<()>),
     /// Pixel target mismatch.
     PixelExport(S, Option<c::format::Format>),
+    /// Stream output varying mismatch.
+    StreamOutput(S, Option<c::format::Format>),
 }
 
 impl<'a> From<InitError<&'a str>> for InitError<String> {
@@ -195,6 +203,7 @@ impl<'a> From<InitError<&'a str>> for InitError<String> {
             UnorderedView(s, v) => UnorderedView(s.to_owned(), v),
             Sampler(s, v) => Sampler(s.to_owned(), v),
             PixelExport(s, v) => PixelExport(s.to_owned(), v),
+            StreamOutput(s, v) => StreamOutput(s.to_owned(), v),
         }
     }
 }
@@ -211,6 +220,7 @@ impl<S: fmt::Debug + fmt::Display> fmt::Display for InitError<S> {
             UnorderedView(ref name, opt) => write!(f, "{}: ({}, {:?})", desc, name, opt),
             Sampler(ref name, opt) => write!(f, "{}: ({}, {:?})", desc, name, opt),
             PixelExport(ref name, format) => write!(f, "{}: ({}, {:?})", desc, name, format),
+            StreamOutput(ref name, format) => write!(f, "{}: ({}, {:?})", desc, name, format),
         }
     }
 }
@@ -233,6 +243,8 @@ impl<S: fmt::Debug + fmt::Display> Error for InitError<S> {
             Sampler(..) => "Sampler mismatch",
             PixelExport(_, None) => "Pixel target not found",
             PixelExport(..) => "Pixel target mismatch",
+            StreamOutput(_, None) => "Stream output varying not found",
+            StreamOutput(..) => "Stream output varying mismatch",
         }
     }
 
@@ -328,6 +340,9 @@ pub trait DataLink<'a>: Sized {
     /// Attempt to link with a sampler.
     fn link_sampler(&mut self, _: &c::shade::SamplerVar, _: &Self::Init)
                     -> Option<c::pso::SamplerDesc> { None }
+    /// Attempt to link with a varying captured into the stream output buffer.
+    fn link_stream_output(&mut self, _: &c::shade::StreamOutputVar, _: &Self::Init) ->
+                          Option<Result<c::pso::StreamOutputDesc, c::format::Format>> { None }
     /// Attempt to enable scissor test.
     fn link_scissor(&mut self) -> bool { false }
 }
@@ -16,7 +16,7 @@
 
 use std::marker::PhantomData;
 use core::{ColorSlot, Resources};
-use core::{format, handle, pso, state, target};
+use core::{command, format, handle, pso, state, target};
 use core::memory::Typed;
 use core::shade::OutputVar;
 use super::{DataLink, DataBind, RawDataSet, AccessInfo};
@@ -92,6 +92,15 @@ pub struct DepthStencilTarget<T>(
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Scissor(bool);
 
+/// Viewport component. Overrides the default viewport, which covers
+/// the whole of the bound targets. Multiple viewports can be selected
+/// from a geometry shader.
+///
+/// - init: `()`
+/// - data: `Vec<command::Viewport>` = target areas and depth ranges
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Viewport;
+
 /// Blend reference component. Sets up the reference color for blending.
 ///
 /// - init: `()`
@@ -280,6 +289,23 @@ impl<R: Resources> DataBind<R> for Scissor {
     }
 }
 
+impl<'a> DataLink<'a> for Viewport {
+    type Init = ();
+    fn new() -> Self { Viewport }
+    fn is_active(&self) -> bool { true }
+}
+
+impl<R: Resources> DataBind<R> for Viewport {
+    type Data = Vec<command::Viewport>;
+    fn bind_to(&self,
+               out: &mut RawDataSet<R>,
+               data: &Self::Data,
+               _: &mut handle::Manager<R>,
+               _: &mut AccessInfo<R>) {
+        out.viewports.extend_from_slice(data);
+    }
+}
+
 impl<'a> DataLink<'a> for BlendRef {
     type Init = ();
     fn new() -> Self { BlendRef }
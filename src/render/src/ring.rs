@@ -0,0 +1,222 @@
+// Copyright 2017 The Gfx-rs Developers.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Ring buffer for streaming per-frame data to the GPU.
+//!
+//! Small pieces of data, like the constants of each draw call, are written
+//! one after the other into a single large buffer, which is mapped instead of
+//! being updated through the command buffer. The space used by a frame is
+//! reclaimed once the fence of that frame is signaled.
+
+#![deny(missing_docs)]
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::{cmp, fmt, mem};
+
+use core::{buffer, handle, mapping, Device, Factory, Resources, VertexCount};
+use core::memory::{self, cast_slice, Pod, Typed};
+
+/// An error occuring when allocating from a `RingBuffer`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RingError {
+    /// The data is larger than the whole ring, in bytes.
+    TooLarge(usize),
+    /// The data doesn't fit in the space left by the current frame.
+    Full,
+    /// The ring couldn't be mapped for writing.
+    Mapping(mapping::Error),
+}
+
+impl fmt::Display for RingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RingError::TooLarge(size) => write!(f, "{}: {} bytes", self.description(), size),
+            RingError::Full => write!(f, "{}", self.description()),
+            RingError::Mapping(ref e) => write!(f, "{}: {}", self.description(), e),
+        }
+    }
+}
+
+impl Error for RingError {
+    fn description(&self) -> &str {
+        match *self {
+            RingError::TooLarge(_) => "The data is larger than the ring buffer",
+            RingError::Full => "The ring buffer is full with the data of the current frame",
+            RingError::Mapping(_) => "The ring buffer could not be mapped",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            RingError::Mapping(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<mapping::Error> for RingError {
+    fn from(e: mapping::Error) -> RingError {
+        RingError::Mapping(e)
+    }
+}
+
+/// A range of a `RingBuffer`, holding the elements written by `RingBuffer::write`.
+///
+/// Constant data is bound with the `RingConstantBuffer` component. Vertex data
+/// is bound with `buffer()`, starting at `start()` as the base vertex of the slice.
+#[derive(Derivative)]
+#[derivative(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct RingRange<R: Resources, T> {
+    buffer: handle::Buffer<R, T>,
+    offset: usize,
+    len: usize,
+}
+
+impl<R: Resources, T> RingRange<R, T> {
+    /// Get the whole buffer of the ring.
+    pub fn buffer(&self) -> &handle::Buffer<R, T> {
+        &self.buffer
+    }
+
+    /// Get the offset of the range in the buffer, in bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Get the size of the range, in bytes.
+    pub fn size(&self) -> usize {
+        self.len * mem::size_of::<T>()
+    }
+
+    /// Get the index of the first element of the range in the buffer.
+    pub fn start(&self) -> VertexCount {
+        (self.offset / cmp::max(1, mem::size_of::<T>())) as VertexCount
+    }
+
+    /// Get the number of elements in the range.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// A buffer sub-allocating the data of each frame in a ring.
+///
+/// Every `write` takes the space following the previous one, wrapping
+/// around at the end of the buffer. Once a frame is submitted, `finish_frame`
+/// associates its space with the fence of the submission. When the ring
+/// runs out of space, `write` waits for the oldest frames to be done with it.
+pub struct RingBuffer<R: Resources> {
+    buffer: handle::Buffer<R, u8>,
+    role: buffer::Role,
+    alignment: usize,
+    /// Position of the next write, counting the bytes since the creation.
+    head: usize,
+    /// Position of the oldest byte possibly in use by the GPU.
+    tail: usize,
+    /// End position of the submitted frames, with the fence of each.
+    frames: VecDeque<(usize, handle::Fence<R>)>,
+}
+
+impl<R: Resources> RingBuffer<R> {
+    /// Create a ring of `size` bytes for data of the given role.
+    ///
+    /// The buffer is persistently mapped if `Capabilities::persistent_mapping_supported`,
+    /// and is an `Upload` buffer otherwise, stalling on every frame.
+    /// Rings of constant data require `Capabilities::constant_buffer_range_supported`.
+    pub fn new<F>(factory: &mut F, role: buffer::Role, size: usize)
+                  -> Result<RingBuffer<R>, buffer::CreationError>
+        where F: Factory<R>
+    {
+        let (usage, alignment) = {
+            let caps = factory.get_capabilities();
+            if role == buffer::Role::Constant && !caps.constant_buffer_range_supported {
+                return Err(buffer::CreationError::UnsupportedRole(role));
+            }
+            let usage = if caps.persistent_mapping_supported {
+                memory::Usage::Persistent
+            } else {
+                memory::Usage::Upload
+            };
+            (usage, caps.limits.constant_buffer_offset_alignment)
+        };
+        let buffer = try!(factory.create_buffer(size, role, usage, memory::Bind::empty()));
+        Ok(RingBuffer {
+            buffer: buffer,
+            role: role,
+            alignment: cmp::max(1, alignment),
+            head: 0,
+            tail: 0,
+            frames: VecDeque::new(),
+        })
+    }
+
+    /// Get the size of the ring, in bytes.
+    pub fn get_size(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Write the data into the ring, returning the range it occupies.
+    ///
+    /// Constant data gets aligned to `Limits::constant_buffer_offset_alignment`,
+    /// any other data to the size of its elements.
+    pub fn write<D, F, T>(&mut self, device: &mut D, factory: &mut F, data: &[T])
+                          -> Result<RingRange<R, T>, RingError>
+        where D: Device<Resources=R>, F: Factory<R>, T: Pod
+    {
+        let capacity = self.get_size();
+        let size = data.len() * mem::size_of::<T>();
+        if size > capacity {
+            return Err(RingError::TooLarge(size));
+        }
+        let alignment = match self.role {
+            buffer::Role::Constant => self.alignment,
+            _ => cmp::max(1, mem::size_of::<T>()),
+        };
+        let lap = self.head - self.head % capacity;
+        let offset = (self.head - lap + alignment - 1) / alignment * alignment;
+        let start = if offset + size > capacity {
+            lap + capacity
+        } else {
+            lap + offset
+        };
+        let end = start + size;
+        while end - self.tail > capacity {
+            let (frame_end, fence) = match self.frames.pop_front() {
+                Some(frame) => frame,
+                None => return Err(RingError::Full),
+            };
+            device.wait_fence(&fence);
+            self.tail = frame_end;
+        }
+
+        let offset = start % capacity;
+        {
+            let mut writer = try!(factory.write_mapping_range(&self.buffer, offset .. offset + size));
+            writer.copy_from_slice(cast_slice(data));
+        }
+        self.head = end;
+        Ok(RingRange {
+            buffer: Typed::new(self.buffer.raw().clone()),
+            offset: offset,
+            len: data.len(),
+        })
+    }
+
+    /// Mark the end of the frame, whose written data is in use by the GPU until
+    /// the fence gets signaled.
+    pub fn finish_frame(&mut self, fence: handle::Fence<R>) {
+        self.frames.push_back((self.head, fence));
+    }
+}
@@ -19,10 +19,11 @@ use cgmath::{Deg, Matrix2, Matrix3, Matrix4, Rad, Point2, Point3, Vector2, Vecto
 
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
 pub use core::shade::{self as core, ConstFormat, Formatted, Usage};
 
 #[allow(missing_docs)]
-pub trait ToUniform: Copy {
+pub trait ToUniform: Clone {
     fn convert(self) -> core::UniformValue;
 }
 
@@ -40,16 +41,45 @@ macro_rules! impl_uniforms {
 
 impl_uniforms! {
     i32 = I32,
+    u32 = U32,
     f32 = F32,
+    f64 = F64,
+    bool = Bool,
     [i32; 2] = I32Vector2,
     [i32; 3] = I32Vector3,
     [i32; 4] = I32Vector4,
+    [u32; 2] = U32Vector2,
+    [u32; 3] = U32Vector3,
+    [u32; 4] = U32Vector4,
     [f32; 2] = F32Vector2,
     [f32; 3] = F32Vector3,
     [f32; 4] = F32Vector4,
+    [f64; 2] = F64Vector2,
+    [f64; 3] = F64Vector3,
+    [f64; 4] = F64Vector4,
     [[f32; 2]; 2] = F32Matrix2,
     [[f32; 3]; 3] = F32Matrix3,
     [[f32; 4]; 4] = F32Matrix4,
+    [[f32; 3]; 2] = F32Matrix2x3,
+    [[f32; 4]; 2] = F32Matrix2x4,
+    [[f32; 2]; 3] = F32Matrix3x2,
+    [[f32; 4]; 3] = F32Matrix3x4,
+    [[f32; 2]; 4] = F32Matrix4x2,
+    [[f32; 3]; 4] = F32Matrix4x3,
+    Vec<i32> = I32Array,
+    Vec<u32> = U32Array,
+    Vec<f32> = F32Array,
+    Vec<[f32; 2]> = F32Vector2Array,
+    Vec<[f32; 3]> = F32Vector3Array,
+    Vec<[f32; 4]> = F32Vector4Array,
+    Vec<[[f32; 4]; 4]> = F32Matrix4Array,
+    Arc<[i32]> = I32Array,
+    Arc<[u32]> = U32Array,
+    Arc<[f32]> = F32Array,
+    Arc<[[f32; 2]]> = F32Vector2Array,
+    Arc<[[f32; 3]]> = F32Vector3Array,
+    Arc<[[f32; 4]]> = F32Vector4Array,
+    Arc<[[[f32; 4]; 4]]> = F32Matrix4Array,
 }
 
 #[cfg(feature = "cgmath-types")]
@@ -91,6 +121,8 @@ pub enum ProgramError {
     Geometry(core::CreateShaderError),
     /// Unable to compile the pixel shader
     Pixel(core::CreateShaderError),
+    /// Unable to compile the compute shader
+    Compute(core::CreateShaderError),
     /// Unable to link
     Link(core::CreateProgramError),
 }
@@ -103,6 +135,7 @@ impl fmt::Display for ProgramError {
             ProgramError::Domain(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Geometry(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Pixel(ref e) => write!(f, "{}: {}", self.description(), e),
+            ProgramError::Compute(ref e) => write!(f, "{}: {}", self.description(), e),
             ProgramError::Link(ref e) => write!(f, "{}: {}", self.description(), e),
         }
     }
@@ -116,6 +149,7 @@ impl Error for ProgramError {
             ProgramError::Domain(_) => "Unable to compile the domain shader",
             ProgramError::Geometry(_) => "Unable to compile the geometry shader",
             ProgramError::Pixel(_) => "Unable to compile the pixel shader",
+            ProgramError::Compute(_) => "Unable to compile the compute shader",
             ProgramError::Link(_) => "Unable to link",
         }
     }
@@ -127,6 +161,7 @@ impl Error for ProgramError {
             ProgramError::Domain(ref e) => Some(e),
             ProgramError::Geometry(ref e) => Some(e),
             ProgramError::Pixel(ref e) => Some(e),
+            ProgramError::Compute(ref e) => Some(e),
             ProgramError::Link(ref e) => Some(e),
         }
     }
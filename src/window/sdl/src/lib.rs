@@ -66,6 +66,12 @@ fn sdl2_pixel_format_from_gfx(format: Format) -> Option<PixelFormatEnum> {
         R4_G4 | R8 | R8_G8 | R11_G11_B10 | R16 | R16_G16 | R16_G16_B16 |
         R16_G16_B16_A16 | R32 | R32_G32 | R32_G32_B32 | R32_G32_B32_A32 | D16 | D24 |
         D24_S8 | D32 => None,
+        // block-compressed surfaces can't be presented
+        BC1_R8_G8_B8_A1 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 | BC4_R8 | BC5_R8_G8 |
+        BC6_R16_G16_B16 | BC6S_R16_G16_B16 | BC7_R8_G8_B8_A8 | ETC2_R8_G8_B8 |
+        ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 | EAC_R11 | EAC_R11_G11 | ASTC_4x4 | ASTC_5x4 |
+        ASTC_5x5 | ASTC_6x5 | ASTC_6x6 | ASTC_8x5 | ASTC_8x6 | ASTC_8x8 | ASTC_10x5 |
+        ASTC_10x6 | ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => None,
     }
 }
 
@@ -10,3 +10,13 @@ struct Vertex {
 struct Constant {
     transform: [[f32; 4]; 4],
 }
+
+#[derive(ConstantBuffer)]
+#[gfx(layout = "std140")]
+#[repr(C)]
+struct Light {
+    pos: [f32; 3],
+    radius: f32,
+    color: [f32; 4],
+    transform: [[f32; 4]; 4],
+}
@@ -0,0 +1,262 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_core;
+
+use gfx::memory::Typed;
+use gfx::{Device, Factory};
+use gfx_core::dummy::{self, DummyCommand, DummyDevice};
+
+fn create() -> (DummyDevice, dummy::DummyFactory) {
+    let mut caps = DummyDevice::new().get_capabilities().clone();
+    caps.copy_buffer_supported = true;
+    caps.max_texture_size = 1 << 10;
+    dummy::create(caps)
+}
+
+#[test]
+fn capabilities() {
+    let (device, factory) = create();
+    assert!(device.get_capabilities().copy_buffer_supported);
+    assert_eq!(factory.get_capabilities().max_texture_size, 1 << 10);
+}
+
+#[test]
+fn limits_and_format_properties() {
+    use gfx::format::{self, Formatted};
+    let mut caps = DummyDevice::new().get_capabilities().clone();
+    caps.limits.max_samples = 8;
+    caps.limits.max_anisotropy = 16;
+    let (device, factory) = dummy::create(caps);
+    assert_eq!(device.get_capabilities().limits.max_anisotropy, 16);
+    let props = factory.get_format_properties(format::Rgba8::get_format());
+    assert_eq!(props.max_samples, 8);
+    assert!(props.support.contains(format::SAMPLE | format::RENDER | format::BLEND));
+    assert!(!props.support.contains(format::UNORDERED));
+}
+
+#[test]
+fn constant_rings_need_ranges() {
+    use gfx::buffer::{CreationError, Role};
+    let (_, mut factory) = create();
+    match gfx::RingBuffer::new(&mut factory, Role::Constant, 256) {
+        Err(CreationError::UnsupportedRole(Role::Constant)) => (),
+        other => panic!("unexpected result {:?}", other.err()),
+    }
+    assert!(gfx::RingBuffer::new(&mut factory, Role::Vertex, 256).is_ok());
+}
+
+#[test]
+fn buffer_update_and_copy() {
+    let (mut device, mut factory) = create();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic,
+                                              gfx::memory::TRANSFER_SRC).unwrap();
+    let download = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging, gfx::memory::Usage::Download,
+                                                gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.update_buffer(&buffer, &[1, 2, 3, 4], 0).unwrap();
+    encoder.copy_buffer(&buffer, &download, 1, 0, 3).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(&factory.read_mapping(&download).unwrap()[..], &[2, 3, 4, 0]);
+
+    let raw = *buffer.raw().resource();
+    assert_eq!(device.get_log()[0], DummyCommand::UpdateBuffer(raw, vec![1, 0, 0, 0, 2, 0, 0, 0,
+                                                                          3, 0, 0, 0, 4, 0, 0, 0], 0));
+    device.clear_log();
+    assert!(device.get_log().is_empty());
+}
+
+#[test]
+fn mapping_ranges() {
+    use gfx::mapping::Error;
+    let (mut device, mut factory) = create();
+    let upload = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging, gfx::memory::Usage::Upload,
+                                              gfx::memory::TRANSFER_SRC).unwrap();
+    let download = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging, gfx::memory::Usage::Download,
+                                                gfx::memory::TRANSFER_DST).unwrap();
+
+    factory.write_mapping_range(&upload, 1..3).unwrap().copy_from_slice(&[5, 6]);
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.copy_buffer(&upload, &download, 0, 0, 4).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(&factory.read_mapping_range(&download, 1..4).unwrap()[..], &[5, 6, 0]);
+    assert!(factory.read_mapping_range(&download, 2..2).unwrap().is_empty());
+
+    assert_eq!(factory.read_mapping_range(&download, 2..5).err(), Some(Error::OutOfBounds));
+    assert_eq!(factory.read_mapping_range(&download, 3..1).err(), Some(Error::OutOfBounds));
+    assert_eq!(factory.write_mapping_range(&upload, 4..5).err(), Some(Error::OutOfBounds));
+    assert_eq!(factory.read_mapping_range(&upload, 0..1).err(),
+               Some(Error::InvalidAccess(gfx::memory::READ, gfx::memory::Usage::Upload)));
+}
+
+#[test]
+fn mappings_are_aligned() {
+    use std::mem;
+    let (_, mut factory) = create();
+    let upload = factory.create_buffer::<f64>(3, gfx::buffer::Role::Staging, gfx::memory::Usage::Upload,
+                                              gfx::memory::TRANSFER_SRC).unwrap();
+    let mut writer = factory.write_mapping(&upload).unwrap();
+    assert_eq!(writer.as_ptr() as usize % mem::align_of::<f64>(), 0);
+    writer.copy_from_slice(&[1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn texture_upload() {
+    let (mut device, mut factory) = create();
+    let kind = gfx::texture::Kind::D2(2, 2, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::TRANSFER_SRC, gfx::memory::Usage::Dynamic,
+        Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(4, gfx::buffer::Role::Staging,
+        gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    let mut img = texture.get_info().to_image_info(0);
+    img.xoffset = 1;
+    img.width = 1;
+    encoder.update_texture::<_, gfx::format::Rgba8>(&texture, None, img, &[[1, 2, 3, 4], [5, 6, 7, 8]]).unwrap();
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(&factory.read_mapping(&download).unwrap()[..],
+               &[[0, 0, 0, 0], [1, 2, 3, 4], [0, 0, 0, 0], [5, 6, 7, 8]]);
+}
+
+#[test]
+fn texture_copy_and_blit() {
+    use gfx::format::{ChannelType, R8_G8_B8_A8, Rgba8};
+    use gfx::texture::{AaMode, FilterMethod, Kind};
+    let (mut device, mut factory) = create();
+    let bind = gfx::memory::TRANSFER_SRC | gfx::memory::TRANSFER_DST;
+    let src = factory.create_texture::<R8_G8_B8_A8>(Kind::D2(2, 2, AaMode::Single), 1, bind,
+        gfx::memory::Usage::Dynamic, Some(ChannelType::Unorm)).unwrap();
+    let dst = factory.create_texture::<R8_G8_B8_A8>(Kind::D2(4, 4, AaMode::Single), 1, bind,
+        gfx::memory::Usage::Dynamic, Some(ChannelType::Unorm)).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(16, gfx::buffer::Role::Staging,
+        gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    let img = src.get_info().to_image_info(0);
+    encoder.update_texture::<_, Rgba8>(&src, None, img, &[[1; 4], [2; 4], [3; 4], [4; 4]]).unwrap();
+    let src_info = src.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    let dst_info = dst.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    encoder.blit(src.raw(), None, src_info, dst.raw(), None, dst_info, FilterMethod::Scale).unwrap();
+    let mut corner = dst_info;
+    corner.width = 2;
+    corner.height = 2;
+    assert_eq!(encoder.copy_texture(src.raw(), None, src_info, dst.raw(), None, dst_info),
+               Err(gfx::CopyError::SizeMismatch { src: [2, 2, 0], dst: [4, 4, 0] }));
+    assert_eq!(encoder.resolve(src.raw(), dst.raw(), corner), Err(gfx::CopyError::Incompatible));
+    encoder.copy_texture(src.raw(), None, src_info, dst.raw(), None, corner).unwrap();
+    encoder.copy_texture_to_buffer_raw(dst.raw(), None, dst_info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(&factory.read_mapping(&download).unwrap()[..],
+               &[[1; 4], [2; 4], [2; 4], [2; 4],
+                 [3; 4], [4; 4], [2; 4], [2; 4],
+                 [3; 4], [3; 4], [4; 4], [4; 4],
+                 [3; 4], [3; 4], [4; 4], [4; 4]]);
+}
+
+#[test]
+fn debug_groups_and_names() {
+    let (mut device, mut factory) = create();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic,
+                                              gfx::memory::Bind::empty()).unwrap();
+    factory.set_debug_name(&buffer, "vertices");
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.push_debug_group("shadows");
+    encoder.insert_debug_marker("cascade 0");
+    encoder.pop_debug_group();
+    encoder.flush(&mut device);
+    assert_eq!(device.get_log(), &[DummyCommand::PushDebugGroup("shadows".to_string()),
+                                   DummyCommand::InsertDebugMarker("cascade 0".to_string()),
+                                   DummyCommand::PopDebugGroup]);
+}
+
+#[test]
+fn statistics_and_live_resources() {
+    use gfx::format::{ChannelType, R8_G8_B8_A8};
+    use gfx::handle::ResourceType;
+    use gfx::texture::{AaMode, Kind};
+    let (mut device, mut factory) = create();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic,
+                                              gfx::memory::Bind::empty()).unwrap();
+    let _texture = factory.create_texture::<R8_G8_B8_A8>(Kind::D2Array(4, 4, 3, AaMode::Single), 3,
+        gfx::memory::SHADER_RESOURCE, gfx::memory::Usage::Dynamic, Some(ChannelType::Unorm)).unwrap();
+    let stats = factory.get_statistics();
+    assert_eq!((stats.buffers, stats.buffer_bytes), (1, 16));
+    // 3 layers of 4x4, 2x2 and 1x1 texels
+    assert_eq!((stats.textures, stats.texture_bytes), (1, 3 * (16 + 4 + 1) * 4));
+
+    drop(buffer);
+    let live = factory.get_live_resources();
+    assert_eq!(live.len(), 1);
+    assert_eq!((live[0].ty, live[0].bytes), (ResourceType::Texture, 252));
+    device.cleanup();
+    assert_eq!(factory.get_statistics().buffers, 0);
+}
+
+#[test]
+fn parallel_encoders() {
+    use std::thread;
+    let (mut device, mut factory) = create();
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    let workers = (0 .. 3).map(|i| {
+        let mut worker: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+        thread::spawn(move || {
+            worker.insert_debug_marker(&format!("worker {}", i));
+            worker
+        })
+    }).collect::<Vec<_>>();
+    encoder.insert_debug_marker("main");
+    for worker in workers {
+        encoder.append(&mut worker.join().unwrap()).unwrap();
+    }
+    encoder.flush(&mut device);
+    assert_eq!(device.get_log(), &[DummyCommand::InsertDebugMarker("main".to_string()),
+                                   DummyCommand::InsertDebugMarker("worker 0".to_string()),
+                                   DummyCommand::InsertDebugMarker("worker 1".to_string()),
+                                   DummyCommand::InsertDebugMarker("worker 2".to_string())]);
+}
+
+#[test]
+fn invalid_commands_are_skipped() {
+    use gfx::command::Buffer;
+    use gfx::format::{ChannelType, R8_G8_B8_A8};
+    use gfx::texture::Kind;
+    let (mut device, mut factory) = create();
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging, gfx::memory::Usage::Download,
+                                              gfx::memory::TRANSFER_DST).unwrap();
+    let cube = factory.create_texture::<R8_G8_B8_A8>(Kind::Cube(2), 1, gfx::memory::SHADER_RESOURCE,
+        gfx::memory::Usage::Dynamic, Some(ChannelType::Unorm)).unwrap();
+
+    // the encoder checks these, so they are recorded on the command buffer directly
+    let mut cb = factory.create_command_buffer();
+    let raw = *buffer.raw().resource();
+    cb.update_buffer(raw, &[1; 8], 12);
+    let img = cube.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+    cb.update_texture(*cube.raw().resource(), Kind::Cube(2), None, &[0; 16], img);
+    cb.update_buffer(raw, &[2; 4], 0);
+    device.submit(&mut cb, &gfx::command::AccessInfo::new()).unwrap();
+    assert_eq!(&factory.read_mapping(&buffer).unwrap()[..], &[0x02020202, 0, 0, 0]);
+}
+
+gfx_defines!{
+    pipeline compute_pipe {
+        scale: gfx::Global<f32> = "u_Scale",
+    }
+}
+
+#[test]
+fn compute_dispatch() {
+    use gfx::traits::FactoryExt;
+    let (mut device, mut factory) = create();
+    let pso = factory.create_compute_pipeline(b"", compute_pipe::new()).unwrap();
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.dispatch(&pso, &compute_pipe::Data { scale: 2.0 }, 4, 2, 1);
+    encoder.flush(&mut device);
+    let log = device.get_log();
+    assert_eq!(log.first(), Some(&DummyCommand::BindPipelineState));
+    assert_eq!(log.last(), Some(&DummyCommand::Dispatch(4, 2, 1)));
+}
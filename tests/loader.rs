@@ -0,0 +1,117 @@
+extern crate gfx;
+
+use gfx::format::{ChannelType, Format, SurfaceType};
+use gfx::loader::{self, Error, SourceFormat};
+use gfx::texture::{AaMode, Kind};
+
+fn push_u32s(out: &mut Vec<u8>, values: &[u32]) {
+    for &v in values {
+        out.extend_from_slice(&[v as u8, (v >> 8) as u8, (v >> 16) as u8, (v >> 24) as u8]);
+    }
+}
+
+/// DDS header with the given height, width, mipmap count, pixel format and caps2.
+fn dds_header(width: u32, height: u32, levels: u32, pixel_format: [u32; 8], caps2: u32) -> Vec<u8> {
+    let mut out = b"DDS ".to_vec();
+    push_u32s(&mut out, &[124, 0, height, width, 0, 0, levels]);
+    push_u32s(&mut out, &[0; 11]);
+    push_u32s(&mut out, &pixel_format);
+    push_u32s(&mut out, &[0, caps2, 0, 0, 0]);
+    out
+}
+
+const DX10: u32 = 0x30315844;
+
+#[test]
+fn dds_mip_chain() {
+    let rgba = [32, 0x41, 0, 32, 0xFF, 0xFF00, 0xFF0000, 0xFF000000];
+    let mut file = dds_header(4, 4, 3, rgba, 0);
+    file.extend((0 .. 84).map(|i| i as u8));
+    let image = loader::load(&file).unwrap();
+    assert_eq!(image.kind, Kind::D2(4, 4, AaMode::Single));
+    assert_eq!(image.levels, 3);
+    assert_eq!(image.format, Format(SurfaceType::R8_G8_B8_A8, ChannelType::Unorm));
+    let data = image.get_data();
+    assert_eq!(data.iter().map(|d| d.len()).collect::<Vec<_>>(), vec![64, 16, 4]);
+    assert_eq!(data[2], &[80, 81, 82, 83]);
+}
+
+#[test]
+fn dds_cube_array() {
+    let mut file = dds_header(8, 8, 2, [32, 0x4, DX10, 0, 0, 0, 0, 0], 0);
+    // BC1 cube map array of 2 layers
+    push_u32s(&mut file, &[71, 3, 0x4, 2, 0]);
+    file.extend((0 .. 12 * (32 + 8)).map(|i| (i / 8) as u8));
+    let image = loader::load_dds(&file).unwrap();
+    assert_eq!(image.kind, Kind::CubeArray(8, 2));
+    assert_eq!(image.format, Format(SurfaceType::BC1_R8_G8_B8_A1, ChannelType::Unorm));
+    assert_eq!(image.data.len(), 24);
+    // each face is followed by its mipmap
+    assert_eq!(image.data[1][0], 4);
+    assert_eq!(image.data[2][0], 5);
+    assert_eq!(image.data[23].len(), 8);
+}
+
+#[test]
+fn ktx_padded_rows() {
+    let mut file = vec![0xAB, b'K', b'T', b'X', b' ', b'1', b'1', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // R8 of 3x2 texels, with 2 levels and some key-value data
+    push_u32s(&mut file, &[0x04030201, 0x1401, 1, 0x1903, 0x8229, 0x1903, 3, 2, 0, 0, 1, 2, 4]);
+    push_u32s(&mut file, &[0]);
+    push_u32s(&mut file, &[8]);
+    file.extend_from_slice(&[1, 2, 3, 0, 4, 5, 6, 0]);
+    push_u32s(&mut file, &[4]);
+    file.extend_from_slice(&[7, 0, 0, 0]);
+    let image = loader::load(&file).unwrap();
+    assert_eq!(image.kind, Kind::D2(3, 2, AaMode::Single));
+    assert_eq!(image.get_data(), vec![&[1, 2, 3, 4, 5, 6][..], &[7][..]]);
+}
+
+#[test]
+fn ktx2_array_order() {
+    let mut file = vec![0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // 1D R8 array of 2 layers of 2 texels, with 2 levels
+    push_u32s(&mut file, &[9, 1, 2, 0, 0, 2, 1, 2, 0]);
+    push_u32s(&mut file, &[0; 8]);
+    push_u32s(&mut file, &[128, 0, 4, 0, 4, 0]);
+    push_u32s(&mut file, &[132, 0, 2, 0, 2, 0]);
+    file.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+    let image = loader::load(&file).unwrap();
+    assert_eq!(image.kind, Kind::D1Array(2, 2));
+    assert_eq!(image.get_data(), vec![&[1, 2][..], &[5][..], &[3, 4][..], &[6][..]]);
+}
+
+#[test]
+fn ktx2_level_out_of_range() {
+    let mut file = vec![0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+    // 1D R8 texture of 2 texels
+    push_u32s(&mut file, &[9, 1, 2, 0, 0, 0, 1, 1, 0]);
+    push_u32s(&mut file, &[0; 8]);
+    // the level starts way past the end of the file
+    push_u32s(&mut file, &[0, 0x100, 2, 0, 2, 0]);
+    file.extend_from_slice(&[1, 2]);
+    assert_eq!(loader::load(&file), Err(Error::InvalidHeader("a level starts past the end of the file")));
+    // the level ends past the end of the file
+    file[80] = 105;
+    file[85] = 0;
+    assert_eq!(loader::load(&file), Err(Error::Truncated { expected: 107, found: 106 }));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(loader::load(b"not a texture"), Err(Error::UnknownContainer));
+    let mut file = dds_header(4, 4, 1, [32, 0x4, DX10, 0, 0, 0, 0, 0], 0);
+    push_u32s(&mut file, &[97, 3, 0, 1, 0]);
+    assert_eq!(loader::load(&file), Err(Error::UnsupportedFormat(SourceFormat::Dxgi(97))));
+    let mut file = dds_header(4, 4, 1, [32, 0x4, DX10, 0, 0, 0, 0, 0], 0);
+    push_u32s(&mut file, &[28, 3, 0, 1, 0]);
+    file.extend_from_slice(&[0; 60]);
+    assert_eq!(loader::load(&file), Err(Error::Truncated { expected: 212, found: 208 }));
+    // a huge cube map array without any data
+    let mut file = dds_header(8, 8, 1, [32, 0x4, DX10, 0, 0, 0, 0, 0], 0);
+    push_u32s(&mut file, &[71, 3, 0x4, 0x2000, 0]);
+    assert_eq!(loader::load(&file), Err(Error::Truncated { expected: 180, found: 148 }));
+    file[140] = 0xFF;
+    file[141] = 0xFF;
+    assert_eq!(loader::load(&file), Err(Error::UnsupportedKind("cube map arrays with too many layers")));
+}
@@ -41,6 +41,21 @@ gfx_defines!{
         blend_ref: gfx::BlendRef = (),
         scissor: gfx::Scissor = (),
     }
+
+    pipeline computepipe {
+        const_locals: gfx::ConstantBuffer<Local> = "Locals",
+        buf_particles: gfx::UnorderedAccess<[f32; 4]> = "Particles",
+    }
+
+    pipeline globalpipe {
+        lights: gfx::Global<Vec<[f32; 4]>> = "u_Lights",
+        mask: gfx::Global<[u32; 2]> = "u_Mask",
+    }
+
+    pipeline capturepipe {
+        vertex: gfx::VertexBuffer<Vertex> = (),
+        captured: gfx::StreamOutput<Instance> = (),
+    }
 }
 
 fn _test_pso<R, F>(factory: &mut F) -> gfx::PipelineState<R, testpipe::Meta> where
@@ -50,6 +65,154 @@ fn _test_pso<R, F>(factory: &mut F) -> gfx::PipelineState<R, testpipe::Meta> whe
     factory.create_pipeline_simple(&[], &[], testpipe::new()).unwrap()
 }
 
+fn _test_compute_pso<R, F>(factory: &mut F) -> gfx::PipelineState<R, computepipe::Meta> where
+    R: gfx::Resources,
+    F: gfx::traits::FactoryExt<R>,
+{
+    factory.create_compute_pipeline(&[], computepipe::new()).unwrap()
+}
+
+fn _test_capture_pso<R, F>(factory: &mut F) -> gfx::PipelineState<R, capturepipe::Meta> where
+    R: gfx::Resources,
+    F: gfx::traits::FactoryExt<R>,
+{
+    let set = factory.create_shader_set_transform_feedback(&[], None, &["pos", "color"]).unwrap();
+    factory.create_pipeline_state(&set, gfx::Primitive::PointList,
+                                  gfx::state::Rasterizer::new_fill(), capturepipe::new()).unwrap()
+}
+
+/// Reflection of a program without any resources, to be filled by the tests.
+fn empty_info() -> gfx::ProgramInfo {
+    gfx::ProgramInfo {
+        vertex_attributes: Vec::new(),
+        globals: Vec::new(),
+        constant_buffers: Vec::new(),
+        textures: Vec::new(),
+        unordereds: Vec::new(),
+        samplers: Vec::new(),
+        outputs: Vec::new(),
+        stream_outputs: Vec::new(),
+        output_depth: false,
+        knows_outputs: true,
+    }
+}
+
+#[test]
+fn stream_output_link() {
+    use gfx::pso::PipelineInit;
+    use gfx::shade::core::{BaseType, ContainerType, StreamOutputVar};
+    let varying = |name: &str, offset, size| StreamOutputVar {
+        name: name.to_owned(),
+        offset: offset,
+        base_type: BaseType::F32,
+        container: ContainerType::Vector(size),
+    };
+    let mut info = empty_info();
+    info.stream_outputs = vec![varying("pos", 0, 2), varying("color", 8, 3)];
+    let mut desc = gfx::pso::Descriptor::new(gfx::Primitive::PointList,
+                                             gfx::state::Rasterizer::new_fill());
+    assert!(capturepipe::new().link_to(&mut desc, &info).is_ok());
+    assert_eq!(desc.stream_output, Some(20));
+
+    info.stream_outputs[1].offset = 12;
+    assert_eq!(capturepipe::new().link_to(&mut desc, &info).err(),
+               Some(gfx::pso::InitError::StreamOutput("color", Some(fm::Format(fm::SurfaceType::R32_G32_B32,
+                                                                                fm::ChannelType::Float)))));
+}
+
+#[test]
+fn constant_buffer_link() {
+    use gfx::pso::{ElementError, PipelineInit};
+    use gfx::shade::core::{BaseType, ConstVar, ConstantBufferVar, ContainerType};
+    let mut info = empty_info();
+    info.constant_buffers = vec![ConstantBufferVar {
+        name: "Locals".to_owned(),
+        slot: 0,
+        size: 16,
+        usage: gfx::shade::core::VERTEX,
+        elements: vec![ConstVar {
+            name: "pos".to_owned(),
+            location: 0,
+            count: 1,
+            base_type: BaseType::U32,
+            container: ContainerType::Vector(4),
+            layout: None,
+        }],
+    }];
+    let mut desc = gfx::pso::Descriptor::new(gfx::Primitive::PointList,
+                                             gfx::state::Rasterizer::new_fill());
+    assert!(computepipe::new().link_to(&mut desc, &info).is_ok());
+
+    info.constant_buffers[0].elements[0].base_type = BaseType::F32;
+    assert_eq!(computepipe::new().link_to(&mut desc, &info).err(),
+               Some(gfx::pso::InitError::ConstantBuffer("Locals", Some(ElementError::Format {
+                   name: "pos",
+                   shader_format: (BaseType::F32, ContainerType::Vector(4)),
+                   code_format: (BaseType::U32, ContainerType::Vector(4)),
+               }))));
+}
+
+#[test]
+fn constant_buffer_members() {
+    use gfx::shade::core::{BaseType, ConstVar, ConstantBufferVar, ConstMemberContent, ContainerType};
+    let element = |name: &str, offset| ConstVar {
+        name: name.to_owned(),
+        location: offset,
+        count: 1,
+        base_type: BaseType::F32,
+        container: ContainerType::Vector(4),
+        layout: None,
+    };
+    let cb = ConstantBufferVar {
+        name: "Lights".to_owned(),
+        slot: 0,
+        size: 80,
+        usage: gfx::shade::core::PIXEL,
+        elements: vec![
+            element("ambient", 0),
+            element("lights[0].pos", 16),
+            element("lights[0].color", 32),
+            element("lights[1].pos", 48),
+            element("lights[1].color", 64),
+        ],
+    };
+    let members = cb.members();
+    assert_eq!(members.len(), 3);
+    assert_eq!(members[0].content, ConstMemberContent::Element(0));
+    assert_eq!((members[2].name.as_str(), members[2].index), ("lights", Some(1)));
+    match members[2].content {
+        ConstMemberContent::Struct(ref fields) => {
+            assert_eq!(fields[1].name, "color");
+            assert_eq!(fields[1].content, ConstMemberContent::Element(4));
+        },
+        ConstMemberContent::Element(_) => panic!("lights[1] is not a structure"),
+    }
+}
+
+#[test]
+fn global_link() {
+    use gfx::pso::PipelineInit;
+    use gfx::shade::core::{BaseType, ConstVar, ContainerType, CompatibilityError};
+    let global = |name: &str, count, base_type, size| ConstVar {
+        name: name.to_owned(),
+        location: 0,
+        count: count,
+        base_type: base_type,
+        container: ContainerType::Vector(size),
+        layout: None,
+    };
+    let mut info = empty_info();
+    info.globals = vec![global("u_Lights", 8, BaseType::F32, 4), global("u_Mask", 1, BaseType::U32, 2)];
+    let mut desc = gfx::pso::Descriptor::new(gfx::Primitive::PointList,
+                                             gfx::state::Rasterizer::new_fill());
+    assert!(globalpipe::new().link_to(&mut desc, &info).is_ok());
+
+    info.globals[1].base_type = BaseType::I32;
+    assert_eq!(globalpipe::new().link_to(&mut desc, &info).err(),
+               Some(gfx::pso::InitError::GlobalConstant("u_Mask", Some(CompatibilityError::ErrorBaseType))));
+    assert_eq!(info.globals[0].is_compatible(&gfx::UniformValue::F32Vector4Array(vec![[0.0; 4]; 9].into())),
+               Err(CompatibilityError::ErrorArraySize));
+}
 
 gfx_pipeline_base!( testraw {
     vertex: gfx::RawVertexBuffer,
@@ -0,0 +1,600 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_device_software;
+
+use gfx::traits::FactoryExt;
+use gfx::memory::Typed;
+use gfx::{Factory, Device};
+
+pub type ColorFormat = gfx::format::Rgba8;
+
+const WIDTH: u16 = 16;
+const HEIGHT: u16 = 16;
+
+const VERTEX_SHADER: &'static [u8] = b"
+    attribute vec2 a_Pos
+    attribute vec3 a_Color
+    varying vec3 v_Color
+    mov position a_Pos
+    mov v_Color a_Color
+";
+
+const PIXEL_SHADER: &'static [u8] = b"
+    varying vec3 v_Color
+    output vec4 Target0
+    mov Target0.xyz v_Color
+    mov Target0.w 1
+";
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+        color: [f32; 3] = "a_Color",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+        viewports: gfx::Viewport = (),
+    }
+}
+
+const COLOR_PIXEL_SHADER: &'static [u8] = b"
+    constant Locals vec4 u_Color
+    output vec4 Target0
+    mov Target0 u_Color
+";
+
+gfx_defines!{
+    constant Locals {
+        color: [f32; 4] = "u_Color",
+    }
+
+    pipeline ring_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        locals: gfx::RingConstantBuffer<Locals> = "Locals",
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+fn render(vertices: &[Vertex]) -> Vec<[u8; 4]> {
+    render_viewports(vertices, Vec::new())
+}
+
+fn render_viewports(vertices: &[Vertex], viewports: Vec<gfx::command::Viewport>) -> Vec<[u8; 4]> {
+    let (mut device, mut factory) = gfx_device_software::create();
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET | gfx::memory::TRANSFER_SRC,
+        gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(WIDTH as usize * HEIGHT as usize,
+        gfx::buffer::Role::Staging, gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(vertices, ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: target,
+        viewports: viewports,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.clear(&data.out, [0.0, 0.0, 0.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+    device.cleanup();
+
+    let reader = factory.read_mapping(&download).unwrap();
+    reader.to_vec()
+}
+
+fn pixel(image: &[[u8; 4]], x: u16, y: u16) -> [u8; 4] {
+    image[(y * WIDTH + x) as usize]
+}
+
+#[test]
+fn triangle_coverage() {
+    let image = render(&[
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  1.0], color: [1.0, 0.0, 0.0] },
+    ]);
+    // the lower-left half is covered, with the diagonal excluded
+    assert_eq!(pixel(&image, 0, 0), [0xFF, 0, 0, 0xFF]);
+    assert_eq!(pixel(&image, 3, 11), [0xFF, 0, 0, 0xFF]);
+    assert_eq!(pixel(&image, 8, 8), [0, 0, 0, 0xFF]);
+    assert_eq!(pixel(&image, WIDTH - 1, HEIGHT - 1), [0, 0, 0, 0xFF]);
+    let covered = image.iter().filter(|p| p[0] != 0).count();
+    assert_eq!(covered, (WIDTH as usize * (HEIGHT as usize - 1)) / 2);
+}
+
+#[test]
+fn varying_interpolation() {
+    let image = render(&[
+        Vertex { pos: [-1.0, -1.0], color: [0.0, 0.0, 0.0] },
+        Vertex { pos: [ 3.0, -1.0], color: [2.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  3.0], color: [0.0, 2.0, 0.0] },
+    ]);
+    // the color at a pixel center is equal to its texture coordinates
+    for &(x, y) in [(0, 0), (5, 9), (15, 15)].iter() {
+        let expected = [((x as f32 + 0.5) / WIDTH as f32 * 255.0).round() as u8,
+                        ((y as f32 + 0.5) / HEIGHT as f32 * 255.0).round() as u8, 0, 0xFF];
+        let p = pixel(&image, x, y);
+        for i in 0 .. 4 {
+            assert!((p[i] as i32 - expected[i] as i32).abs() <= 1, "{:?} != {:?}", p, expected);
+        }
+    }
+}
+
+#[test]
+fn viewport_area() {
+    let rect = gfx::Rect { x: 4, y: 8, w: 8, h: 4 };
+    let image = render_viewports(&[
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 3.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  3.0], color: [1.0, 0.0, 0.0] },
+    ], vec![gfx::command::Viewport::new(rect)]);
+    for y in 0 .. HEIGHT {
+        for x in 0 .. WIDTH {
+            let inside = x >= rect.x && x < rect.x + rect.w && y >= rect.y && y < rect.y + rect.h;
+            let expected = if inside { [0xFF, 0, 0, 0xFF] } else { [0, 0, 0, 0xFF] };
+            assert_eq!(pixel(&image, x, y), expected, "at ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn unsupported_multisample() {
+    let (_, mut factory) = gfx_device_software::create();
+    let program = factory.link_program(VERTEX_SHADER, PIXEL_SHADER).unwrap();
+    let mut desc = gfx::pso::Descriptor::new(gfx::Primitive::TriangleList, gfx::state::Rasterizer::new_fill());
+    assert!(factory.create_pipeline_from_descriptor(&program, desc, pipe::new()).is_ok());
+    desc.multisample.alpha_to_coverage = true;
+    assert!(factory.create_pipeline_from_descriptor(&program, desc, pipe::new()).is_err());
+}
+
+#[test]
+fn appended_updates() {
+    let (mut device, mut factory) = gfx_device_software::create();
+    let bind = gfx::memory::TRANSFER_SRC | gfx::memory::TRANSFER_DST;
+    let buffer = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging,
+        gfx::memory::Usage::Dynamic, bind).unwrap();
+    let download = factory.create_buffer::<u32>(4, gfx::buffer::Role::Staging,
+        gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    let mut other: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.update_buffer(&buffer, &[1, 2], 0).unwrap();
+    other.update_buffer(&buffer, &[3, 4], 2).unwrap();
+    other.copy_buffer(&buffer, &download, 0, 0, 4).unwrap();
+    encoder.append(&mut other).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(&factory.read_mapping(&download).unwrap()[..], &[1, 2, 3, 4]);
+}
+
+#[test]
+fn ring_constants() {
+    let (mut device, mut factory) = gfx_device_software::create();
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET | gfx::memory::TRANSFER_SRC,
+        gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(WIDTH as usize * HEIGHT as usize,
+        gfx::buffer::Role::Staging, gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut ring = gfx::RingBuffer::new(&mut factory, gfx::buffer::Role::Constant, 64).unwrap();
+    let red = ring.write(&mut device, &mut factory, &[Locals { color: [1.0, 0.0, 0.0, 1.0] }]).unwrap();
+    let green = ring.write(&mut device, &mut factory, &[Locals { color: [0.0, 1.0, 0.0, 1.0] }]).unwrap();
+    assert_eq!((red.offset(), green.offset()), (0, 16));
+    assert_eq!(ring.write(&mut device, &mut factory, &[0u8; 65]), Err(gfx::RingError::TooLarge(65)));
+
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, COLOR_PIXEL_SHADER, ring_pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&[
+        Vertex { pos: [-1.0, -1.0], color: [0.0; 3] },
+        Vertex { pos: [ 3.0, -1.0], color: [0.0; 3] },
+        Vertex { pos: [-1.0,  3.0], color: [0.0; 3] },
+    ], ());
+    let data = ring_pipe::Data {
+        vbuf: vbuf,
+        locals: green,
+        out: target,
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.draw(&slice, &pso, &data);
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    let fence = encoder.fenced_flush_no_reset(&mut device, None).unwrap();
+    ring.finish_frame(fence);
+    device.cleanup();
+    assert_eq!(pixel(&factory.read_mapping(&download).unwrap(), 5, 5), [0, 0xFF, 0, 0xFF]);
+
+    // the space of the finished frame is reclaimed, but not the one of the current frame
+    for _ in 0 .. 4 {
+        ring.write(&mut device, &mut factory, &[0u8; 16]).unwrap();
+    }
+    assert_eq!(ring.write(&mut device, &mut factory, &[0u8; 16]), Err(gfx::RingError::Full));
+}
+
+#[test]
+fn indirect_draws() {
+    use gfx::{DrawError, DrawIndirectArgs, IndexBuffer};
+    let (mut device, mut factory) = gfx_device_software::create();
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET | gfx::memory::TRANSFER_SRC,
+        gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(WIDTH as usize * HEIGHT as usize,
+        gfx::buffer::Role::Staging, gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let vbuf = factory.create_vertex_buffer(&[
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 3.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  3.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0, -1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [ 3.0, -1.0], color: [0.0, 1.0, 0.0] },
+        Vertex { pos: [-1.0,  3.0], color: [0.0, 1.0, 0.0] },
+    ]);
+    let args = factory.create_buffer_immutable(&[DrawIndirectArgs {
+        vertex_count: 3,
+        instance_count: 1,
+        first_vertex: 3,
+        first_instance: 0,
+    }], gfx::buffer::Role::Indirect, gfx::memory::Bind::empty()).unwrap();
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: target,
+        viewports: Vec::new(),
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    assert_eq!(encoder.draw_indirect(&args, 1, 1, &pso, &data),
+               Err(DrawError::OutOfBounds { size: 1, draw_end: 2 }));
+    let indexed_args = factory.create_buffer::<gfx::DrawIndexedIndirectArgs>(1, gfx::buffer::Role::Indirect,
+        gfx::memory::Usage::Data, gfx::memory::Bind::empty()).unwrap();
+    assert_eq!(encoder.draw_indexed_indirect(&IndexBuffer::Auto, &indexed_args, 0, 1, &pso, &data),
+               Err(DrawError::NoIndexBuffer));
+    encoder.draw_indirect(&args, 0, 1, &pso, &data).unwrap();
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+    assert_eq!(pixel(&factory.read_mapping(&download).unwrap(), 5, 5), [0, 0xFF, 0, 0xFF]);
+}
+
+type Encoder = gfx::Encoder<gfx_device_software::Resources, gfx_device_software::CommandBuffer>;
+type TargetView = gfx::handle::RenderTargetView<gfx_device_software::Resources, ColorFormat>;
+
+/// Render into a cleared black target with the given function, and read the result back.
+fn render_with<F>(fun: F) -> Vec<[u8; 4]>
+    where F: FnOnce(&mut gfx_device_software::Factory, &mut Encoder, TargetView)
+{
+    let (mut device, mut factory) = gfx_device_software::create();
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET | gfx::memory::TRANSFER_SRC,
+        gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(WIDTH as usize * HEIGHT as usize,
+        gfx::buffer::Role::Staging, gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+
+    let mut encoder: Encoder = factory.create_command_buffer().into();
+    encoder.clear(&target, [0.0, 0.0, 0.0, 1.0]);
+    fun(&mut factory, &mut encoder, target);
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+
+    let reader = factory.read_mapping(&download).unwrap();
+    reader.to_vec()
+}
+
+/// A triangle covering the whole target.
+fn fullscreen(color: [f32; 3]) -> [Vertex; 3] {
+    [Vertex { pos: [-1.0, -1.0], color: color },
+     Vertex { pos: [ 3.0, -1.0], color: color },
+     Vertex { pos: [-1.0,  3.0], color: color }]
+}
+
+/// Get the normalized device coordinate of the center of a pixel.
+fn center(pixel: u16, size: u16) -> f32 {
+    (pixel as f32 + 0.5) / size as f32 * 2.0 - 1.0
+}
+
+fn assert_near(actual: [u8; 4], expected: [u8; 4]) {
+    for i in 0 .. 4 {
+        assert!((actual[i] as i32 - expected[i] as i32).abs() <= 1, "{:?} != {:?}", actual, expected);
+    }
+}
+
+const DEPTH_VERTEX_SHADER: &'static [u8] = b"
+    attribute vec3 a_Pos
+    attribute vec3 a_Color
+    varying vec3 v_Color
+    mov position a_Pos
+    mov v_Color a_Color
+";
+
+gfx_defines!{
+    vertex DepthVertex {
+        pos: [f32; 3] = "a_Pos",
+        color: [f32; 3] = "a_Color",
+    }
+
+    pipeline depth_pipe {
+        vbuf: gfx::VertexBuffer<DepthVertex> = (),
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+        depth: gfx::DepthStencilTarget<gfx::format::DepthStencil> = (
+            gfx::preset::depth::LESS_EQUAL_WRITE,
+            gfx::state::Stencil::new(gfx::state::Comparison::NotEqual, 0xFF, (
+                gfx::state::StencilOp::Keep, gfx::state::StencilOp::Keep, gfx::state::StencilOp::Replace))),
+    }
+}
+
+/// A rectangle from `x0` to `x1` in normalized device coordinates, covering the whole height.
+fn rectangle(x0: f32, x1: f32, z: f32, color: [f32; 3]) -> Vec<DepthVertex> {
+    [(x0, -1.0), (x1, -1.0), (x0, 1.0), (x0, 1.0), (x1, -1.0), (x1, 1.0)].iter().map(|&(x, y)| {
+        DepthVertex { pos: [x, y, z], color: color }
+    }).collect()
+}
+
+#[test]
+fn depth_stencil_tests() {
+    let image = render_with(|factory, encoder, target| {
+        let pso = factory.create_pipeline_simple(DEPTH_VERTEX_SHADER, PIXEL_SHADER, depth_pipe::new()).unwrap();
+        let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+        let texture = factory.create_texture::<gfx::format::D24_S8>(kind, 1, gfx::memory::DEPTH_STENCIL,
+            gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+        let depth = factory.view_texture_as_depth_stencil_trivial(&texture).unwrap();
+        encoder.clear_depth(&depth, 1.0);
+        encoder.clear_stencil(&depth, 0);
+        // every draw passes the stencil test only where the stencil differs from
+        // its reference, which it writes when also passing the depth test
+        let draws = [
+            (rectangle(-1.0, 1.0, 0.0, [0.0, 1.0, 0.0]), 1),
+            (rectangle(-1.0, 1.0, 0.5, [1.0, 0.0, 0.0]), 2),
+            (rectangle(-1.0, 0.0, -0.5, [0.0, 0.0, 1.0]), 1),
+            (rectangle(0.0, 1.0, -0.5, [0.0, 0.0, 1.0]), 2),
+        ];
+        for &(ref vertices, stencil) in draws.iter() {
+            let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices[..], ());
+            let data = depth_pipe::Data {
+                vbuf: vbuf,
+                out: target.clone(),
+                depth: (depth.clone(), (stencil, stencil)),
+            };
+            encoder.draw(&slice, &pso, &data);
+        }
+    });
+    for y in 0 .. HEIGHT {
+        for x in 0 .. WIDTH {
+            let expected = if x < WIDTH / 2 { [0, 0xFF, 0, 0xFF] } else { [0, 0, 0xFF, 0xFF] };
+            assert_eq!(pixel(&image, x, y), expected, "at ({}, {})", x, y);
+        }
+    }
+}
+
+const HALF_ALPHA_PIXEL_SHADER: &'static [u8] = b"
+    varying vec3 v_Color
+    output vec4 Target0
+    mov Target0.xyz v_Color
+    mov Target0.w 0.5
+";
+
+gfx_defines!{
+    pipeline blend_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::BlendTarget<ColorFormat> = ("Target0", gfx::state::RED | gfx::state::BLUE,
+                                              gfx::preset::blend::ALPHA),
+    }
+}
+
+#[test]
+fn alpha_blending() {
+    let image = render_with(|factory, encoder, target| {
+        let pso = factory.create_pipeline_simple(VERTEX_SHADER, HALF_ALPHA_PIXEL_SHADER, blend_pipe::new()).unwrap();
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&fullscreen([1.0, 1.0, 0.0]), ());
+        encoder.clear(&target, [0.0, 0.0, 1.0, 1.0]);
+        encoder.draw(&slice, &pso, &blend_pipe::Data {
+            vbuf: vbuf,
+            out: target,
+        });
+    });
+    // the green channel is masked out
+    assert_near(pixel(&image, 0, 0), [0x80, 0, 0x80, 0xFF]);
+    assert_near(pixel(&image, 9, 12), [0x80, 0, 0x80, 0xFF]);
+}
+
+#[test]
+fn line_rasterization() {
+    let image = render_with(|factory, encoder, target| {
+        let set = factory.create_shader_set(VERTEX_SHADER, PIXEL_SHADER).unwrap();
+        let pso = factory.create_pipeline_state(&set, gfx::Primitive::LineList,
+            gfx::state::Rasterizer::new_fill(), pipe::new()).unwrap();
+        let (row, column) = (center(4, HEIGHT), center(10, WIDTH));
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&[
+            Vertex { pos: [-1.0, row], color: [1.0, 0.0, 0.0] },
+            Vertex { pos: [ 1.0, row], color: [1.0, 0.0, 0.0] },
+            Vertex { pos: [column, -1.0], color: [1.0, 0.0, 0.0] },
+            Vertex { pos: [column,  1.0], color: [1.0, 0.0, 0.0] },
+        ], ());
+        encoder.draw(&slice, &pso, &pipe::Data {
+            vbuf: vbuf,
+            out: target,
+            viewports: Vec::new(),
+        });
+    });
+    for y in 0 .. HEIGHT {
+        for x in 0 .. WIDTH {
+            let expected = if y == 4 || x == 10 { [0xFF, 0, 0, 0xFF] } else { [0, 0, 0, 0xFF] };
+            assert_eq!(pixel(&image, x, y), expected, "at ({}, {})", x, y);
+        }
+    }
+}
+
+#[test]
+fn point_rasterization() {
+    let points = [(0, 0), (7, 3), (15, 15)];
+    let image = render_with(|factory, encoder, target| {
+        let set = factory.create_shader_set(VERTEX_SHADER, PIXEL_SHADER).unwrap();
+        let pso = factory.create_pipeline_state(&set, gfx::Primitive::PointList,
+            gfx::state::Rasterizer::new_fill(), pipe::new()).unwrap();
+        let vertices: Vec<_> = points.iter().map(|&(x, y)| {
+            Vertex { pos: [center(x, WIDTH), center(y, HEIGHT)], color: [0.0, 1.0, 0.0] }
+        }).collect();
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertices[..], ());
+        encoder.draw(&slice, &pso, &pipe::Data {
+            vbuf: vbuf,
+            out: target,
+            viewports: Vec::new(),
+        });
+    });
+    for y in 0 .. HEIGHT {
+        for x in 0 .. WIDTH {
+            let expected = if points.contains(&(x, y)) { [0, 0xFF, 0, 0xFF] } else { [0, 0, 0, 0xFF] };
+            assert_eq!(pixel(&image, x, y), expected, "at ({}, {})", x, y);
+        }
+    }
+}
+
+const TEXTURE_VERTEX_SHADER: &'static [u8] = b"
+    attribute vec2 a_Pos
+    attribute vec3 a_Color
+    varying vec2 v_Uv
+    mov position a_Pos
+    mad v_Uv a_Pos 0.5 0.5
+";
+
+const TEXTURE_PIXEL_SHADER: &'static [u8] = b"
+    varying vec2 v_Uv
+    texture 2d t_Color
+    sampler t_Color
+    output vec4 Target0
+    tex Target0 t_Color t_Color v_Uv
+";
+
+gfx_defines!{
+    pipeline texture_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        color: gfx::TextureSampler<[f32; 4]> = "t_Color",
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+/// Render a 2x2 texture over the whole target with the given filter.
+fn render_texture(filter: gfx::texture::FilterMethod) -> Vec<[u8; 4]> {
+    render_with(|factory, encoder, target| {
+        let pso = factory.create_pipeline_simple(TEXTURE_VERTEX_SHADER, TEXTURE_PIXEL_SHADER,
+                                                 texture_pipe::new()).unwrap();
+        let kind = gfx::texture::Kind::D2(2, 2, gfx::texture::AaMode::Single);
+        let texels = [[0xFF, 0, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF], [0, 0xFF, 0, 0xFF], [0, 0, 0xFF, 0xFF]];
+        let (_, view) = factory.create_texture_immutable::<ColorFormat>(kind, &[&texels]).unwrap();
+        let sampler = factory.create_sampler(gfx::texture::SamplerInfo::new(filter, gfx::texture::WrapMode::Clamp));
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&fullscreen([0.0; 3]), ());
+        encoder.draw(&slice, &pso, &texture_pipe::Data {
+            vbuf: vbuf,
+            color: (view, sampler),
+            out: target,
+        });
+    })
+}
+
+#[test]
+fn texture_sampling() {
+    let image = render_texture(gfx::texture::FilterMethod::Scale);
+    // every quarter of the target gets the color of a texel
+    assert_eq!(pixel(&image, 3, 2), [0xFF, 0, 0, 0xFF]);
+    assert_eq!(pixel(&image, 12, 7), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(pixel(&image, 0, 15), [0, 0xFF, 0, 0xFF]);
+    assert_eq!(pixel(&image, 8, 8), [0, 0, 0xFF, 0xFF]);
+
+    let image = render_texture(gfx::texture::FilterMethod::Bilinear);
+    // the corners are clamped to the texels, the center is their average
+    assert_near(pixel(&image, 0, 0), [0xFF, 0, 0, 0xFF]);
+    assert_near(pixel(&image, 15, 15), [0, 0, 0xFF, 0xFF]);
+    let center = |a: u8, b: u8, c: u8, d: u8| {
+        // the pixel center (7.5, 7.5) is at 0.4375 of the distance between the texel centers
+        let (near, far) = (1.0 - 0.4375, 0.4375);
+        let row0 = a as f32 * near + b as f32 * far;
+        let row1 = c as f32 * near + d as f32 * far;
+        (row0 * near + row1 * far).round() as u8
+    };
+    assert_near(pixel(&image, 7, 7), [center(0xFF, 0xFF, 0, 0), center(0, 0xFF, 0xFF, 0),
+                                      center(0, 0xFF, 0, 0xFF), 0xFF]);
+}
+
+#[test]
+fn occlusion_and_timestamp_queries() {
+    use gfx::query::{CreationError, Kind};
+    let (mut device, mut factory) = gfx_device_software::create();
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET, gfx::memory::Usage::Data,
+        Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    assert_eq!(factory.create_query(Kind::PrimitivesWritten),
+               Err(CreationError::Unsupported(Kind::PrimitivesWritten)));
+    let occlusion = factory.create_query(Kind::Occlusion).unwrap();
+    let start = factory.create_query(Kind::Timestamp).unwrap();
+    let end = factory.create_query(Kind::Timestamp).unwrap();
+
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&[
+        Vertex { pos: [-1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [ 1.0, -1.0], color: [1.0, 0.0, 0.0] },
+        Vertex { pos: [-1.0,  1.0], color: [1.0, 0.0, 0.0] },
+    ], ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: target,
+        viewports: Vec::new(),
+    };
+
+    let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
+    encoder.write_timestamp(&start);
+    encoder.draw(&slice, &pso, &data);
+    encoder.begin_query(&occlusion);
+    encoder.draw(&slice, &pso, &data);
+    encoder.end_query(&occlusion);
+    encoder.write_timestamp(&end);
+    assert_eq!(device.get_query_result(&occlusion), None);
+    encoder.flush(&mut device);
+    // only the draw enclosed by the query is counted
+    assert_eq!(device.get_query_result(&occlusion), Some((WIDTH as u64 * (HEIGHT as u64 - 1)) / 2));
+    let (start, end) = (device.get_query_result(&start).unwrap(), device.get_query_result(&end).unwrap());
+    assert!(start <= end, "{} > {}", start, end);
+}
+
+const ARRAY_PIXEL_SHADER: &'static [u8] = b"
+    uniform vec4 u_Colors[3]
+    output vec4 Target0
+    mov Target0 u_Colors[2]
+";
+
+gfx_defines!{
+    pipeline array_pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        colors: gfx::Global<Vec<[f32; 4]>> = "u_Colors",
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+#[test]
+fn global_arrays() {
+    let image = render_with(|factory, encoder, target| {
+        let pso = factory.create_pipeline_simple(VERTEX_SHADER, ARRAY_PIXEL_SHADER, array_pipe::new()).unwrap();
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&fullscreen([0.0; 3]), ());
+        let data = array_pipe::Data {
+            vbuf: vbuf,
+            colors: vec![[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0]],
+            out: target,
+        };
+        encoder.draw(&slice, &pso, &data);
+    });
+    assert_eq!(pixel(&image, 5, 5), [0, 0, 0xFF, 0xFF]);
+}
@@ -0,0 +1,101 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_device_software;
+extern crate gfx_device_trace;
+
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+use gfx::traits::FactoryExt;
+use gfx::memory::Typed;
+use gfx::{Factory, Device};
+
+pub type ColorFormat = gfx::format::Rgba8;
+
+const WIDTH: u16 = 8;
+const HEIGHT: u16 = 8;
+
+const VERTEX_SHADER: &'static [u8] = b"
+    attribute vec2 a_Pos
+    mov position a_Pos
+";
+
+const PIXEL_SHADER: &'static [u8] = b"
+    output vec4 Target0
+    mov Target0 1
+";
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+/// Trace output shared with the test.
+#[derive(Clone)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl io::Write for Output {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn capture_and_replay() {
+    let output = Output(Rc::new(RefCell::new(Vec::new())));
+    let (device, factory) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_trace::create(device, factory, output.clone());
+
+    let kind = gfx::texture::Kind::D2(WIDTH, HEIGHT, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET | gfx::memory::TRANSFER_SRC,
+        gfx::memory::Usage::Data, Some(gfx::format::ChannelType::Unorm)).unwrap();
+    let target = factory.view_texture_as_render_target::<ColorFormat>(&texture, 0, None).unwrap();
+    let download = factory.create_buffer::<[u8; 4]>(WIDTH as usize * HEIGHT as usize,
+        gfx::buffer::Role::Staging, gfx::memory::Usage::Download, gfx::memory::TRANSFER_DST).unwrap();
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&[
+        Vertex { pos: [-1.0, -1.0] },
+        Vertex { pos: [ 1.0, -1.0] },
+        Vertex { pos: [-1.0,  1.0] },
+    ], ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: target,
+    };
+
+    let cb = factory.create_command_buffer(|f| f.create_command_buffer());
+    let mut encoder: gfx::Encoder<_, _> = cb.into();
+    encoder.clear(&data.out, [0.0, 0.0, 0.0, 1.0]);
+    encoder.draw(&slice, &pso, &data);
+    let info = texture.get_info().to_raw_image_info(gfx::format::ChannelType::Unorm, 0);
+    encoder.copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0).unwrap();
+    encoder.flush(&mut device);
+    device.cleanup();
+    let captured = factory.read_mapping(&download).unwrap().to_vec();
+    assert_eq!(captured[0], [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(captured[captured.len() - 1], [0, 0, 0, 0xFF]);
+
+    let (mut device, mut factory) = gfx_device_software::create();
+    let mut cb = factory.create_command_buffer();
+    let mut replayer = gfx_device_trace::Replayer::new();
+    let trace = output.0.borrow();
+    for event in gfx_device_trace::read(&trace[..]) {
+        replayer.replay(event.unwrap(), &mut device, &mut factory, &mut cb).unwrap();
+    }
+    let id = download.raw().resource().get_id();
+    let buffer = replayer.objects().buffer(id).unwrap().clone();
+    let replayed = factory.read_mapping(&gfx::handle::Buffer::<_, [u8; 4]>::new(buffer)).unwrap().to_vec();
+    assert_eq!(captured, replayed);
+}
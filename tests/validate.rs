@@ -0,0 +1,163 @@
+#[macro_use]
+extern crate gfx;
+extern crate gfx_device_software;
+extern crate gfx_device_validate;
+
+use gfx::traits::FactoryExt;
+use gfx::memory::Typed;
+use gfx::{Device, Factory};
+use gfx_device_validate::Error;
+
+pub type ColorFormat = gfx::format::Rgba8;
+
+const VERTEX_SHADER: &'static [u8] = b"
+    attribute vec2 a_Pos
+    mov position a_Pos
+";
+
+const PIXEL_SHADER: &'static [u8] = b"
+    output vec4 Target0
+    mov Target0 1
+";
+
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    pipeline pipe {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        out: gfx::RenderTarget<ColorFormat> = "Target0",
+    }
+}
+
+const TRIANGLE: [Vertex; 3] = [
+    Vertex { pos: [-1.0, -1.0] },
+    Vertex { pos: [ 1.0, -1.0] },
+    Vertex { pos: [-1.0,  1.0] },
+];
+
+#[test]
+fn vertex_buffer_bounds() {
+    let (device, factory) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_validate::create(device, factory);
+    let (_, _, target) = factory.create_render_target::<ColorFormat>(4, 4).unwrap();
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, mut slice) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    let data = pipe::Data {
+        vbuf: vbuf.clone(),
+        out: target,
+    };
+
+    let cb = factory.create_command_buffer(|f| f.create_command_buffer());
+    let mut encoder: gfx::Encoder<_, _> = cb.into();
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.flush_no_reset(&mut device), Ok(()));
+    encoder.reset();
+
+    slice.end = 4;
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.flush_no_reset(&mut device), Err(gfx::SubmissionError::InvalidCommands));
+    assert_eq!(device.take_errors(), vec![Error::VertexBufferOutOfBounds {
+        buffer: *vbuf.raw().resource(),
+        slot: 0,
+        end: 32,
+        size: 24,
+    }]);
+}
+
+#[test]
+fn color_target_format() {
+    let (device, factory) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_validate::create(device, factory);
+    let kind = gfx::texture::Kind::D2(4, 4, gfx::texture::AaMode::Single);
+    let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+        kind, 1, gfx::memory::RENDER_TARGET, gfx::memory::Usage::Data,
+        Some(gfx::format::ChannelType::Srgb)).unwrap();
+    let desc = gfx::texture::RenderDesc {
+        channel: gfx::format::ChannelType::Srgb,
+        level: 0,
+        layer: None,
+    };
+    let view = factory.view_texture_as_render_target_raw(texture.raw(), desc).unwrap();
+    let pso = factory.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: Typed::new(view.clone()),
+    };
+
+    let cb = factory.create_command_buffer(|f| f.create_command_buffer());
+    let mut encoder: gfx::Encoder<_, _> = cb.into();
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.flush_no_reset(&mut device), Err(gfx::SubmissionError::InvalidCommands));
+    let mut handles = gfx::handle::Manager::new();
+    assert_eq!(device.take_errors(), vec![Error::ColorTargetFormat {
+        view: *handles.ref_rtv(&view),
+        slot: 0,
+        expected: gfx::format::Format(gfx::format::SurfaceType::R8_G8_B8_A8, gfx::format::ChannelType::Unorm),
+        found: gfx::format::Format(gfx::format::SurfaceType::R8_G8_B8_A8, gfx::format::ChannelType::Srgb),
+    }]);
+}
+
+#[test]
+fn unknown_pipeline_state() {
+    let (device, mut inner) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_validate::create(device, inner.clone());
+    let (_, _, target) = factory.create_render_target::<ColorFormat>(4, 4).unwrap();
+    // created behind the back of the validating factory
+    let pso = inner.create_pipeline_simple(VERTEX_SHADER, PIXEL_SHADER, pipe::new()).unwrap();
+    let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    let data = pipe::Data {
+        vbuf: vbuf,
+        out: target,
+    };
+
+    let cb = factory.create_command_buffer(|f| f.create_command_buffer());
+    let mut encoder: gfx::Encoder<_, _> = cb.into();
+    encoder.draw(&slice, &pso, &data);
+    assert_eq!(encoder.flush_no_reset(&mut device), Err(gfx::SubmissionError::InvalidCommands));
+    let mut handles = gfx::handle::Manager::new();
+    let raw = *handles.ref_pso(pso.get_handle()).0;
+    assert_eq!(device.take_errors(), vec![Error::UnknownPipelineState(raw)]);
+    encoder.reset();
+
+    // dispatches are checked against the pipeline state as well
+    encoder.dispatch(&pso, &data, 1, 1, 1);
+    assert_eq!(encoder.flush_no_reset(&mut device), Err(gfx::SubmissionError::InvalidCommands));
+    assert_eq!(device.take_errors(), vec![Error::UnknownPipelineState(raw)]);
+}
+
+#[test]
+fn released_resources() {
+    let (device, factory) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_validate::create(device, factory);
+    let (vbuf, _) = factory.create_vertex_buffer_with_slice(&TRIANGLE, ());
+    device.cleanup();
+    assert_eq!(factory.get_statistics().buffers, 1);
+    // the registry lets the backend free the buffer once it is dropped
+    drop(vbuf);
+    device.cleanup();
+    assert_eq!(factory.get_statistics().buffers, 0);
+}
+
+#[test]
+fn unbalanced_debug_group() {
+    let (device, factory) = gfx_device_software::create();
+    let (mut device, mut factory) = gfx_device_validate::create(device, factory);
+    let mut cb = factory.create_command_buffer(|f| f.create_command_buffer());
+    // the encoder ignores the extra pops, so they have to go to the command buffer
+    gfx::CommandBuffer::pop_debug_group(&mut cb);
+    let mut encoder: gfx::Encoder<_, _> = cb.into();
+    assert_eq!(encoder.flush_no_reset(&mut device), Err(gfx::SubmissionError::InvalidCommands));
+    assert_eq!(device.take_errors(), vec![Error::UnbalancedDebugGroup]);
+    encoder.reset();
+
+    // the groups left open are closed by the flush
+    encoder.push_debug_group("frame");
+    encoder.pop_debug_group();
+    encoder.pop_debug_group();
+    encoder.push_debug_group("shadows");
+    assert_eq!(encoder.flush_no_reset(&mut device), Ok(()));
+    assert_eq!(device.take_errors(), vec![]);
+}